@@ -0,0 +1,261 @@
+use crate::jsonc::mask_jsonc;
+use crate::project::{find_project_file, is_toml_file, parse_edited_project, Library, Project};
+use crate::result::{BargeError, Result};
+use crate::toml::{parse_toml_document, to_toml_string};
+use crate::{color_eprintln, color_println, GREEN, NO_COLOR, RED};
+use serde::Serialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use toml_edit::{ArrayOfTables, Item, Table, Value as TomlValue};
+
+const LIBRARIES_KEY: &str = "\"external_libraries\"";
+const MALFORMED_PROJECT_FILE: &str = "Could not locate the fields of the project file";
+
+fn check_pkg_config_package(name: &str) -> Result<()> {
+    let status = Command::new("pkg-config")
+        .args(["--exists", name])
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => {
+            color_eprintln!("The package {} was not found by pkg-config", name);
+            Err(BargeError::InvalidValue("Unknown pkg-config package"))
+        }
+        Err(error) => {
+            color_eprintln!("Could not execute pkg-config");
+            Err(error.into())
+        }
+    }
+}
+
+// Returns the index of the bracket closing the one at the given index, while
+// skipping the contents of strings.
+fn find_closing_bracket(json: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, character) in json.char_indices().skip_while(|(i, _)| *i < open) {
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match character {
+            '"' => in_string = true,
+            '[' | '{' => depth += 1,
+            ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn get_line_indentation(json: &str, index: usize) -> &str {
+    let start = json[..index].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = &json[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn serialize_entry(library: &Library, unit: &str, indentation: &str) -> Result<String> {
+    let mut buffer = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+    library.serialize(&mut serde_json::Serializer::with_formatter(
+        &mut buffer,
+        formatter,
+    ))?;
+    let entry = String::from_utf8(buffer)?;
+    Ok(entry.replace('\n', &format!("\n{}", indentation)))
+}
+
+fn find_libraries_key(json: &str) -> Option<usize> {
+    json.match_indices(LIBRARIES_KEY)
+        .map(|(index, _)| index)
+        .find(|&index| {
+            json[index + LIBRARIES_KEY.len()..]
+                .trim_start()
+                .starts_with(':')
+        })
+}
+
+fn insert_library_inline(
+    json: &str,
+    text: &str,
+    library: &Library,
+    root_end: usize,
+) -> Result<String> {
+    let entry = serde_json::to_string(library)?;
+    let malformed = || BargeError::InvalidValue(MALFORMED_PROJECT_FILE);
+    let (index, insertion) = match find_libraries_key(text) {
+        Some(key) => {
+            let open = key + text[key..].find('[').ok_or_else(malformed)?;
+            let close = find_closing_bracket(text, open).ok_or_else(malformed)?;
+            if text[open + 1..close].trim().is_empty() {
+                (close, entry)
+            } else {
+                (text[..close].trim_end().len(), format!(", {}", entry))
+            }
+        }
+        None => (
+            text[..root_end].trim_end().len(),
+            format!(", {}: [{}]", LIBRARIES_KEY, entry),
+        ),
+    };
+    Ok(format!("{}{}{}", &json[..index], insertion, &json[index..]))
+}
+
+// Returns the index after the last value before the given closing bracket,
+// and the index after the comments following it. The separator is empty if
+// the value is already followed by a (trailing) comma.
+fn find_insertion_point<'a>(json: &'a str, text: &str, close: usize) -> (usize, usize, &'a str) {
+    let last = text[..close].trim_end().len();
+    let end = json[..close].trim_end().len();
+    let separator = if json[last..end].trim_start().starts_with(',') {
+        ""
+    } else {
+        ","
+    };
+    (last, end, separator)
+}
+
+// Inserts the library into the project file as text, so the formatting of the
+// rest of the file (key order, indentation, spacing, comments) is left
+// untouched. The positions are looked up in the masked text, where comments
+// and trailing commas are replaced by spaces.
+fn insert_library(json: &str, library: &Library) -> Result<String> {
+    let malformed = || BargeError::InvalidValue(MALFORMED_PROJECT_FILE);
+    let text = mask_jsonc(json);
+    let root = text.find('{').ok_or_else(malformed)?;
+    let root_end = find_closing_bracket(&text, root).ok_or_else(malformed)?;
+    if !text[root..root_end].contains('\n') {
+        return insert_library_inline(json, &text, library, root_end);
+    }
+
+    let first_key = root + text[root..].find('"').ok_or_else(malformed)?;
+    let unit = match get_line_indentation(json, first_key) {
+        "" => "    ",
+        unit => unit,
+    };
+
+    if let Some(key) = find_libraries_key(&text) {
+        let indentation = get_line_indentation(json, key);
+        let entry_indentation = format!("{}{}", indentation, unit);
+        let entry = serialize_entry(library, unit, &entry_indentation)?;
+        let open = key + text[key..].find('[').ok_or_else(malformed)?;
+        let close = find_closing_bracket(&text, open).ok_or_else(malformed)?;
+        let (last, end, separator) = find_insertion_point(json, &text, close);
+        let separator = if last == open + 1 { "" } else { separator };
+        return Ok(format!(
+            "{}{}{}\n{}{}\n{}{}",
+            &json[..last],
+            separator,
+            &json[last..end],
+            entry_indentation,
+            entry,
+            indentation,
+            &json[close..]
+        ));
+    }
+
+    let entry_indentation = format!("{}{}", unit, unit);
+    let entry = serialize_entry(library, unit, &entry_indentation)?;
+    let (last, end, separator) = find_insertion_point(json, &text, root_end);
+    Ok(format!(
+        "{}{}{}\n{}{}: [\n{}{}\n{}]\n{}",
+        &json[..last],
+        separator,
+        &json[last..end],
+        unit,
+        LIBRARIES_KEY,
+        entry_indentation,
+        entry,
+        unit,
+        &json[root_end..]
+    ))
+}
+
+// Appends the library to the external_libraries of the TOML project file,
+// either as a new [[external_libraries]] table, or into the inline array,
+// keeping the comments and formatting of the rest of the file.
+fn append_library_table(toml: &str, library: &Library) -> Result<String> {
+    let mut document = parse_toml_document(toml)?;
+    let mut table = Table::new();
+    for (key, item) in parse_toml_document(&to_toml_string(library)?)?.iter() {
+        table.insert(key, item.clone());
+    }
+    match document.get_mut("external_libraries") {
+        None => {
+            let mut tables = ArrayOfTables::new();
+            tables.push(table);
+            document.insert("external_libraries", Item::ArrayOfTables(tables));
+        }
+        Some(Item::ArrayOfTables(tables)) => tables.push(table),
+        Some(Item::Value(TomlValue::Array(libraries))) => {
+            // The new entry is laid out like the last one.
+            let mut entry = TomlValue::InlineTable(table.into_inline_table());
+            if let Some(last) = libraries.iter().last() {
+                *entry.decor_mut() = last.decor().clone();
+            }
+            libraries.push_formatted(entry);
+        }
+        Some(_) => return Err(BargeError::InvalidValue(MALFORMED_PROJECT_FILE)),
+    }
+    Ok(document.to_string())
+}
+
+pub(crate) fn add_library(
+    project: &Project,
+    name: Option<&String>,
+    cflags: Option<&String>,
+    ldflags: Option<&String>,
+) -> Result<()> {
+    let library = match name {
+        Some(name) => {
+            let exists = project.external_libraries.iter().flatten().any(|library| {
+                matches!(library, Library::PkgConfig { name: existing } if existing == name)
+            });
+            if exists {
+                return Err(BargeError::InvalidValue(
+                    "The library is already used by the project",
+                ));
+            }
+            check_pkg_config_package(name)?;
+            Library::PkgConfig { name: name.clone() }
+        }
+        None => Library::Manual {
+            cflags: cflags.cloned().unwrap_or_default(),
+            ldflags: ldflags.cloned().unwrap_or_default(),
+        },
+    };
+
+    let path = find_project_file(Path::new(""))
+        .ok_or(BargeError::ProjectNotFound("Project file not found."))?;
+    let path = path.to_string_lossy();
+    let original = std::fs::read_to_string(path.as_ref())?;
+    let edited = if is_toml_file(&path) {
+        append_library_table(&original, &library)?
+    } else {
+        insert_library(&original, &library)?
+    };
+    parse_edited_project(&path, &edited)?;
+    std::fs::write(path.as_ref(), edited)?;
+
+    match library {
+        Library::PkgConfig { name } => {
+            color_println!(GREEN, "Added pkg-config library {} to the project", name)
+        }
+        Library::Manual { .. } => {
+            color_println!(GREEN, "Added manually specified library to the project")
+        }
+    }
+    Ok(())
+}
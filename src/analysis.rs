@@ -0,0 +1,466 @@
+use crate::annotations::{get_annotations, AnnotationFormat};
+use crate::clangtidy::{has_warnings_as_errors, update_clang_tidy};
+use crate::commandlog::{continue_command_log, record_and_output};
+use crate::dependencies::{get_dependencies_for_project, parse_dependencies};
+use crate::languages::{get_language, get_source_language, LanguageKind};
+use crate::makefile::{get_source_flags, resolve_build_flags, BuildFlags, BuildTarget};
+use crate::project::{
+    collect_source_files, CollectSourceFilesMode, Project, SourceKind, StaticAnalyzer,
+};
+use crate::result::{BargeError, Result};
+use crate::sarif::write_sarif_report;
+use crate::utilities::{get_changed_files, get_content_hash};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE, YELLOW};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Output;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub(crate) const ANALYSIS_CACHE: &str = "build/analysis.json";
+const ANALYSIS_MODULE_DIRECTORY: &str = "build/analysis-modules";
+// The compile flags passed on to cppcheck, which only needs the preprocessor
+// flags.
+const CPPCHECK_FLAG_PREFIXES: &[&str] = &["-I", "-D", "-U"];
+
+// How the fixes suggested by clang-tidy are applied. By default, they are not
+// applied if the source has compile errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FixMode {
+    Fix,
+    FixErrors,
+}
+
+impl FixMode {
+    fn flag(&self) -> &'static str {
+        match self {
+            FixMode::Fix => "-fix",
+            FixMode::FixErrors => "-fix-errors",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AnalysisTool {
+    Analyzer(StaticAnalyzer),
+    FortranSyntax,
+    CobolSyntax,
+}
+
+struct AnalysisJob {
+    tool: AnalysisTool,
+    source: String,
+    arguments: Vec<String>,
+    hash: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct CachedAnalysis {
+    pub hash: String,
+    pub diagnostics: String,
+}
+
+impl AnalysisTool {
+    fn executable(&self, project: &Project) -> String {
+        match self {
+            AnalysisTool::Analyzer(StaticAnalyzer::ClangTidy) => String::from("clang-tidy"),
+            AnalysisTool::Analyzer(StaticAnalyzer::Cppcheck) => String::from("cppcheck"),
+            AnalysisTool::FortranSyntax => project.compiler(LanguageKind::Fortran),
+            AnalysisTool::CobolSyntax => String::from("cobc"),
+        }
+    }
+
+    fn configuration_file(&self) -> Option<&'static str> {
+        match self {
+            AnalysisTool::Analyzer(StaticAnalyzer::ClangTidy) => Some(".clang-tidy"),
+            _ => None,
+        }
+    }
+}
+
+impl AnalysisJob {
+    fn cache_key(&self, project: &Project) -> String {
+        format!("{} {}", self.tool.executable(project), self.source)
+    }
+}
+
+// The standard of the source, in the form of cppcheck, like `--std=c++17`.
+fn get_source_standard(project: &Project, source: &str) -> String {
+    let kind = match get_source_language(project, source) {
+        Some(LanguageKind::C) => LanguageKind::C,
+        _ => LanguageKind::Cpp,
+    };
+    get_language(kind)
+        .standard(project)
+        .map(|standard| format!("--std={}", standard))
+        .unwrap_or_default()
+}
+
+// The compile flags of the source, the same as in the build of the project and
+// in the compilation database.
+fn get_compile_flags(project: &Project, flags: &BuildFlags, source: &str) -> Result<String> {
+    let base = match get_source_language(project, source) {
+        Some(LanguageKind::C) => flags.compile_flags(LanguageKind::C),
+        _ => flags.compile_flags(LanguageKind::Cpp),
+    };
+    let source_flags = get_source_flags(project, BuildTarget::Debug, source, base)?;
+    Ok(source_flags.unwrap_or_else(|| base.clone()))
+}
+
+fn get_analysis_arguments(
+    project: &Project,
+    tool: AnalysisTool,
+    source: &str,
+    flags: &BuildFlags,
+    fix: Option<FixMode>,
+) -> Result<Vec<String>> {
+    let compile_flags = match tool {
+        AnalysisTool::Analyzer(_) => get_compile_flags(project, flags, source)?,
+        _ => String::new(),
+    };
+    let standard = get_source_standard(project, source);
+    let fortran_standard = get_language(LanguageKind::Fortran)
+        .standard_flag(project)
+        .unwrap_or_default();
+    let cobol_standard = get_language(LanguageKind::Cobol)
+        .standard_flag(project)
+        .unwrap_or_default();
+    let module_directory = format!("-J{}", ANALYSIS_MODULE_DIRECTORY);
+
+    let arguments: Vec<&str> = match tool {
+        AnalysisTool::FortranSyntax => vec![
+            "-fsyntax-only",
+            &fortran_standard,
+            "-Wall",
+            "-Wextra",
+            &module_directory,
+            source,
+        ],
+        AnalysisTool::CobolSyntax => vec!["-fsyntax-only", &cobol_standard, "-Wall", source],
+        AnalysisTool::Analyzer(StaticAnalyzer::ClangTidy) => ["--quiet"]
+            .into_iter()
+            .chain(fix.map(|fix| fix.flag()))
+            .chain([source, "--"])
+            .chain(compile_flags.split_whitespace())
+            .collect(),
+        AnalysisTool::Analyzer(StaticAnalyzer::Cppcheck) => [
+            "--quiet",
+            "--enable=warning,style,performance,portability",
+            "--template=gcc",
+            &standard,
+        ]
+        .into_iter()
+        .chain(compile_flags.split_whitespace().filter(|flag| {
+            CPPCHECK_FLAG_PREFIXES
+                .iter()
+                .any(|prefix| flag.starts_with(prefix))
+        }))
+        .chain([source])
+        .collect(),
+    };
+    Ok(arguments
+        .iter()
+        .map(|argument| argument.to_string())
+        .collect())
+}
+
+fn get_analysis_hash(
+    tool: AnalysisTool,
+    arguments: &[String],
+    inputs: Option<&Vec<String>>,
+) -> Option<String> {
+    let mut contents = vec![arguments.join(" ").into_bytes()];
+    if let Some(configuration) = tool.configuration_file() {
+        contents.push(std::fs::read(configuration).unwrap_or_default());
+    }
+    for input in inputs? {
+        contents.push(std::fs::read(input).ok()?);
+    }
+    let parts: Vec<&[u8]> = contents.iter().map(|part| part.as_slice()).collect();
+    Some(get_content_hash(&parts))
+}
+
+fn get_object(build_directory: &str, source: &str) -> String {
+    let name = source.strip_prefix("src/").unwrap_or(source);
+    format!("{}/obj/{}.o", build_directory, name)
+}
+
+// Returns whether the source is changed, or it includes a changed header,
+// according to the dependencies of its object.
+fn is_affected(
+    source: &str,
+    changed: &[String],
+    dependencies: &HashMap<String, Vec<String>>,
+    build_directory: &str,
+) -> bool {
+    let is_changed = |path: &str| {
+        let path = path.strip_prefix("./").unwrap_or(path);
+        changed.iter().any(|file| file == path)
+    };
+    is_changed(source)
+        || dependencies
+            .get(&get_object(build_directory, source))
+            .is_some_and(|inputs| inputs.iter().any(|input| is_changed(input)))
+}
+
+pub(crate) fn load_analysis_cache() -> HashMap<String, CachedAnalysis> {
+    std::fs::read_to_string(ANALYSIS_CACHE)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn run_analysis_job(project: &Project, job: &AnalysisJob) -> Result<Output> {
+    Ok(record_and_output(
+        project
+            .command(&job.tool.executable(project))
+            .args(&job.arguments),
+    )?)
+}
+
+fn get_diagnostics(output: &Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .filter(|line| !line.trim().is_empty())
+        .filter(|line| !line.ends_with("generated."))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn run_static_analysis(
+    project: &Project,
+    jobs: Option<usize>,
+    annotations: Option<AnnotationFormat>,
+    sarif: Option<&str>,
+    fix: Option<FixMode>,
+    changed_since: Option<&str>,
+) -> Result<()> {
+    let analyzers = project
+        .static_analyzers
+        .clone()
+        .unwrap_or(vec![StaticAnalyzer::ClangTidy]);
+    if fix.is_some() && !analyzers.contains(&StaticAnalyzer::ClangTidy) {
+        return Err(BargeError::FailedOperation(
+            "Fixes can only be applied by clang-tidy, which is not a static analyzer of the project",
+        ));
+    }
+    let sources: Vec<String> =
+        collect_source_files(project, CollectSourceFilesMode::CCppSourcesOnly)?
+            .into_iter()
+            .filter(|source| project.source_kind(source) != Some(SourceKind::Header))
+            .collect();
+
+    if analyzers.contains(&StaticAnalyzer::ClangTidy) {
+        update_clang_tidy(project)?;
+    }
+
+    continue_command_log()?;
+    let flags = resolve_build_flags(project, BuildTarget::Debug)?;
+    let build_directory = project.build_directory(BuildTarget::Debug);
+    let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+    for kind in [LanguageKind::C, LanguageKind::Cpp] {
+        let rules = get_dependencies_for_project(project, &build_directory, kind)?;
+        dependencies.extend(parse_dependencies(&rules));
+    }
+
+    let mut runs: Vec<(AnalysisTool, &String)> = analyzers
+        .iter()
+        .flat_map(|analyzer| {
+            sources
+                .iter()
+                .map(|source| (AnalysisTool::Analyzer(*analyzer), source))
+        })
+        .collect();
+    // Fortran modules and Cobol copybooks are not tracked, so these are never cached.
+    let all_sources = collect_source_files(project, CollectSourceFilesMode::All)?;
+    for source in &all_sources {
+        match get_source_language(project, source) {
+            Some(LanguageKind::Fortran) => runs.push((AnalysisTool::FortranSyntax, source)),
+            Some(LanguageKind::Cobol) => runs.push((AnalysisTool::CobolSyntax, source)),
+            _ => {}
+        }
+    }
+    if let Some(revision) = changed_since {
+        let changed = get_changed_files(revision)?;
+        runs.retain(|(_, source)| is_affected(source, &changed, &dependencies, &build_directory));
+        if runs.is_empty() {
+            color_println!(BLUE, "No source files were changed since {}", revision);
+            if let Some(path) = sarif {
+                write_sarif_report(path, &[])?;
+            }
+            return Ok(());
+        }
+    }
+    if runs
+        .iter()
+        .any(|(tool, _)| *tool == AnalysisTool::FortranSyntax)
+    {
+        std::fs::create_dir_all(ANALYSIS_MODULE_DIRECTORY)?;
+    }
+
+    let queue: Vec<AnalysisJob> = runs
+        .into_iter()
+        .map(|(tool, source)| {
+            let object = get_object(&build_directory, source);
+            let arguments = get_analysis_arguments(project, tool, source, &flags, fix)?;
+            let inputs = match tool {
+                AnalysisTool::Analyzer(_) => dependencies.get(&object),
+                _ => None,
+            };
+            // The sources are changed by the fixes, so the results are not cached.
+            let hash = match fix {
+                Some(_) => None,
+                None => get_analysis_hash(tool, &arguments, inputs),
+            };
+            Ok(AnalysisJob {
+                tool,
+                source: source.clone(),
+                hash,
+                arguments,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    // The fixes of the headers included by more sources would be applied
+    // concurrently, so fixes are applied by a single job.
+    let threads = match fix {
+        Some(_) => 1,
+        None => jobs
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .clamp(1, queue.len().max(1)),
+    };
+    color_println!(
+        BLUE,
+        "Running static analysis on project ({} job(s))",
+        threads
+    );
+
+    // The results of the sources left out by --changed are kept, the stale ones
+    // are only dropped when every source is analyzed.
+    let previous = load_analysis_cache();
+    let keys: Vec<String> = queue.iter().map(|job| job.cache_key(project)).collect();
+    let cache: Mutex<HashMap<String, CachedAnalysis>> = Mutex::new(
+        previous
+            .iter()
+            .filter(|(key, _)| changed_since.is_some() || keys.contains(key))
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect(),
+    );
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let flagged = AtomicUsize::new(0);
+    let errors = AtomicUsize::new(0);
+    let reported: Mutex<Vec<(usize, String)>> = Mutex::new(Vec::new());
+    let failure: Mutex<Option<BargeError>> = Mutex::new(None);
+    let console = Mutex::new(());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let job = match queue.get(index) {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                let key = job.cache_key(project);
+                let cached = previous
+                    .get(&key)
+                    .filter(|entry| Some(&entry.hash) == job.hash.as_ref());
+                let result = match cached {
+                    Some(entry) => Ok(entry.diagnostics.clone()),
+                    None => run_analysis_job(project, job).map(|output| get_diagnostics(&output)),
+                };
+
+                let _guard = console.lock().unwrap_or_else(|e| e.into_inner());
+                let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                color_println!(
+                    WHITE,
+                    "[{}/{}] {} {}{}",
+                    done,
+                    queue.len(),
+                    job.tool.executable(project),
+                    job.source,
+                    if cached.is_some() { " (cached)" } else { "" }
+                );
+                match result {
+                    Ok(diagnostics) => {
+                        if !diagnostics.is_empty() {
+                            flagged.fetch_add(1, Ordering::SeqCst);
+                            if job.tool == AnalysisTool::Analyzer(StaticAnalyzer::ClangTidy)
+                                && has_warnings_as_errors(&diagnostics)
+                            {
+                                errors.fetch_add(1, Ordering::SeqCst);
+                            }
+                            let mut reported = reported.lock().unwrap_or_else(|e| e.into_inner());
+                            reported.push((index, diagnostics.clone()));
+                            match annotations {
+                                Some(format) => get_annotations(&diagnostics, format)
+                                    .iter()
+                                    .for_each(|annotation| println!("{}", annotation)),
+                                None => println!("{}", diagnostics),
+                            }
+                        }
+                        if let Some(hash) = &job.hash {
+                            let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+                            cache.insert(
+                                key,
+                                CachedAnalysis {
+                                    hash: hash.clone(),
+                                    diagnostics,
+                                },
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        color_eprintln!("Could not execute {}", job.tool.executable(project));
+                        let mut failure = failure.lock().unwrap_or_else(|e| e.into_inner());
+                        failure.get_or_insert(error);
+                        next.store(queue.len(), Ordering::SeqCst);
+                    }
+                }
+            });
+        }
+    });
+
+    let cache = cache.into_inner().unwrap_or_else(|e| e.into_inner());
+    if !cache.is_empty() {
+        std::fs::create_dir_all("build")?;
+        std::fs::write(ANALYSIS_CACHE, serde_json::to_string(&cache)?)?;
+    }
+
+    if let Some(error) = failure.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        return Err(error);
+    }
+
+    if let Some(path) = sarif {
+        let mut reported = reported.into_inner().unwrap_or_else(|e| e.into_inner());
+        reported.sort_by_key(|(index, _)| *index);
+        let diagnostics: Vec<(String, String)> = reported
+            .into_iter()
+            .map(|(index, diagnostics)| (queue[index].tool.executable(project), diagnostics))
+            .collect();
+        write_sarif_report(path, &diagnostics)?;
+    }
+
+    match flagged.into_inner() {
+        0 => color_println!(GREEN, "No diagnostics were reported"),
+        count => color_println!(
+            YELLOW,
+            "{} of {} analyzer run(s) reported diagnostics",
+            count,
+            queue.len()
+        ),
+    }
+    if errors.into_inner() > 0 {
+        return Err(BargeError::FailedOperation(
+            "Static analysis reported warnings treated as errors",
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use crate::result::{BargeError, Result};
+use std::process::Command;
+
+const ANNOTATIONS_VARIABLE: &str = "BARGE_ANNOTATIONS";
+const SEVERITIES: &[&str] = &[
+    "fatal error",
+    "error",
+    "warning",
+    "note",
+    "remark",
+    "style",
+    "performance",
+    "portability",
+    "information",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AnnotationFormat {
+    Github,
+    Generic,
+}
+
+impl TryFrom<&str> for AnnotationFormat {
+    type Error = BargeError;
+
+    fn try_from(string: &str) -> Result<AnnotationFormat> {
+        match string {
+            "github" => Ok(AnnotationFormat::Github),
+            "generic" => Ok(AnnotationFormat::Generic),
+            _ => Err(BargeError::InvalidValue(
+                "Invalid annotation format, valid choices are: github, generic",
+            )),
+        }
+    }
+}
+
+impl AnnotationFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            AnnotationFormat::Github => "github",
+            AnnotationFormat::Generic => "generic",
+        }
+    }
+}
+
+pub(crate) struct Diagnostic<'a> {
+    pub file: &'a str,
+    pub line: &'a str,
+    pub column: Option<&'a str>,
+    pub severity: &'a str,
+    pub message: &'a str,
+}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+// Parses the file:line[:column]: severity: message format of compilers and analyzers.
+pub(crate) fn parse_diagnostic(line: &str) -> Option<Diagnostic<'_>> {
+    let (file, rest) = line.split_once(':')?;
+    let (line_number, mut rest) = rest.split_once(':')?;
+    if file.is_empty() || !is_number(line_number) {
+        return None;
+    }
+
+    let mut column = None;
+    if let Some((value, remaining)) = rest.split_once(':') {
+        if is_number(value) {
+            column = Some(value);
+            rest = remaining;
+        }
+    }
+
+    let (severity, message) = rest.split_once(':')?;
+    let (severity, message) = (severity.trim(), message.trim());
+    if !SEVERITIES.contains(&severity) || message.is_empty() {
+        return None;
+    }
+
+    Some(Diagnostic {
+        file,
+        line: line_number,
+        column,
+        severity,
+        message,
+    })
+}
+
+fn get_github_severity(severity: &str) -> &'static str {
+    match severity {
+        "error" | "fatal error" => "error",
+        "warning" => "warning",
+        _ => "notice",
+    }
+}
+
+fn escape_github_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+fn format_annotation(diagnostic: &Diagnostic, format: AnnotationFormat) -> String {
+    match format {
+        AnnotationFormat::Github => {
+            let mut properties = format!(
+                "file={},line={}",
+                escape_github_property(diagnostic.file),
+                diagnostic.line
+            );
+            if let Some(column) = diagnostic.column {
+                properties += &format!(",col={}", column);
+            }
+            format!(
+                "::{} {}::{}",
+                get_github_severity(diagnostic.severity),
+                properties,
+                escape_github_data(diagnostic.message)
+            )
+        }
+        AnnotationFormat::Generic => format!(
+            "{}:{}:{}: {}: {}",
+            diagnostic.file,
+            diagnostic.line,
+            diagnostic.column.unwrap_or("1"),
+            diagnostic.severity,
+            diagnostic.message
+        ),
+    }
+}
+
+pub(crate) fn is_diagnostic(line: &str) -> bool {
+    parse_diagnostic(line).is_some()
+}
+
+pub(crate) fn get_annotations(output: &str, format: AnnotationFormat) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(parse_diagnostic)
+        .map(|diagnostic| format_annotation(&diagnostic, format))
+        .collect()
+}
+
+pub(crate) fn enable_annotations(command: &mut Command, format: Option<AnnotationFormat>) {
+    if let Some(format) = format {
+        command.env(ANNOTATIONS_VARIABLE, format.name());
+    }
+}
+
+pub(crate) fn get_requested_annotations() -> Option<AnnotationFormat> {
+    let format = std::env::var(ANNOTATIONS_VARIABLE).ok()?;
+    AnnotationFormat::try_from(format.as_str()).ok()
+}
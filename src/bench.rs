@@ -0,0 +1,91 @@
+use crate::languages::LanguageKind;
+use crate::makefile::{call_pkg_config, resolve_build_flags, BuildTarget};
+use crate::project::{collect_harness_files, get_harness_binaries, Library, Project};
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+const GOOGLE_BENCHMARK: &str = "benchmark";
+const GOOGLE_BENCHMARK_MAIN: &str = "benchmark_main";
+
+fn uses_google_benchmark(project: &Project) -> bool {
+    project
+        .external_libraries
+        .iter()
+        .flatten()
+        .any(|library| matches!(library, Library::PkgConfig { name } if name == GOOGLE_BENCHMARK))
+}
+
+fn has_pkg_config_package(name: &str) -> bool {
+    Command::new("pkg-config")
+        .args(["--exists", name])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Builds the sources of the bench directory in release mode, and runs each of
+// them with the given arguments, measuring their wall-clock time.
+pub(crate) fn run_benchmarks(
+    project: &Project,
+    filter: Option<&String>,
+    arguments: &[String],
+) -> Result<()> {
+    let target = BuildTarget::Release;
+    let mut sources = collect_harness_files(project, "bench")?;
+    if let Some(filter) = filter {
+        sources.retain(|source| source.contains(filter.as_str()));
+    }
+    if sources.is_empty() {
+        color_println!(BLUE, "No benchmarks were found in the bench directory");
+        return Ok(());
+    }
+
+    project.check_host_target()?;
+    project.build(target)?;
+    color_println!(
+        BLUE,
+        "Building benchmarks with {} configuration",
+        target.to_string()
+    );
+
+    let mut flags = resolve_build_flags(project, target)?;
+    for kind in [LanguageKind::C, LanguageKind::Cpp] {
+        *flags.compile_flags_mut(kind) += " -DBARGE_BENCHMARK";
+    }
+    // Google Benchmark is linked by the project itself, its main function is
+    // only provided for the benchmarks, so that BENCHMARK_MAIN() is optional.
+    if uses_google_benchmark(project) && has_pkg_config_package(GOOGLE_BENCHMARK_MAIN) {
+        flags.ldflags += &format!(" {}", call_pkg_config(GOOGLE_BENCHMARK_MAIN, "--libs")?);
+    }
+
+    let build_directory = project.build_directory(target);
+    let benchmarks = get_harness_binaries(&build_directory, "bench", &sources);
+    if !project.build_harness(target, &flags, "bench", &benchmarks)? {
+        color_eprintln!("Failed to build benchmarks");
+        return Err(BargeError::FailedOperation(
+            "One or more benchmarks failed to build",
+        ));
+    }
+
+    let mut failed = 0;
+    for benchmark in &benchmarks {
+        color_println!(BLUE, "Running benchmark {}", benchmark.source);
+        let start_time = Instant::now();
+        let status = Command::new(&benchmark.binary).args(arguments).status()?;
+        let duration = start_time.elapsed().as_secs_f64();
+        if status.success() {
+            color_println!(GREEN, "DONE {} ({:.3} s)", benchmark.source, duration);
+        } else {
+            color_eprintln!("FAIL {} ({:.3} s)", benchmark.source, duration);
+            failed += 1;
+        }
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(BargeError::FailedOperation("One or more benchmarks failed"))
+    }
+}
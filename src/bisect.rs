@@ -0,0 +1,100 @@
+use crate::makefile::BuildTarget;
+use crate::project::Project;
+use crate::result::{print_error, BargeError, Result};
+use crate::utilities::has_uncommitted_changes;
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED};
+use std::process::Command;
+
+// The exit codes understood by `git bisect run`.
+const BISECT_GOOD: i32 = 0;
+const BISECT_BAD: i32 = 1;
+const BISECT_SKIP: i32 = 125;
+
+// Runs `git bisect` between the given revisions, letting barge build (and
+// optionally test) each visited commit through the hidden bisect-step
+// subcommand. The bisection is always reset afterwards, so that the
+// repository is left at the commit it was on before.
+pub(crate) fn bisect(
+    good: &str,
+    bad: Option<&String>,
+    target: BuildTarget,
+    test: bool,
+    jobs: Option<usize>,
+) -> Result<()> {
+    if has_uncommitted_changes()? {
+        color_eprintln!("Commit or stash the changes of the working tree before bisecting");
+        return Err(BargeError::FailedOperation(
+            "The working tree has uncommitted changes",
+        ));
+    }
+
+    let bad = bad.map(String::as_str).unwrap_or("HEAD");
+    color_println!(
+        BLUE,
+        "Bisecting between {} (good) and {} (bad), {} each commit in {} mode",
+        good,
+        bad,
+        if test { "testing" } else { "building" },
+        target
+    );
+    let start = Command::new("git")
+        .args(["bisect", "start", bad, good])
+        .status()?;
+    if !start.success() {
+        return Err(BargeError::FailedOperation("Could not start bisecting"));
+    }
+
+    let mut step = Command::new("git");
+    step.args(["bisect", "run"])
+        .arg(std::env::current_exe()?)
+        .arg("bisect-step")
+        .arg(target.to_string());
+    if test {
+        step.arg("--test");
+    }
+    if let Some(jobs) = jobs {
+        step.arg("--jobs").arg(jobs.to_string());
+    }
+    // The root check has already passed for the bisect subcommand itself.
+    let status = step.env("BARGE_ALLOW_ROOT", "1").status();
+    let reset = Command::new("git").args(["bisect", "reset"]).status();
+
+    if !status?.success() {
+        return Err(BargeError::FailedOperation("Bisecting failed"));
+    }
+    if !reset?.success() {
+        return Err(BargeError::FailedOperation("Could not reset the bisection"));
+    }
+    Ok(())
+}
+
+// Classifies the current commit for `git bisect run`. Commits where the
+// project file can not be loaded are skipped, as are commits where the build
+// (or the tests) can not be built when looking for a test regression.
+pub(crate) fn run_bisect_step<F>(target: BuildTarget, test: bool, load: F) -> i32
+where
+    F: Fn() -> Result<Project>,
+{
+    let project = match load() {
+        Ok(project) => project,
+        Err(error) => {
+            print_error(&error);
+            return BISECT_SKIP;
+        }
+    };
+
+    if let Err(error) = project.build(target) {
+        print_error(&error);
+        return if test { BISECT_SKIP } else { BISECT_BAD };
+    }
+    if test {
+        if let Err(error) = project.test(target, None) {
+            print_error(&error);
+            return match error {
+                BargeError::FailedOperation("One or more tests failed to build") => BISECT_SKIP,
+                _ => BISECT_BAD,
+            };
+        }
+    }
+    BISECT_GOOD
+}
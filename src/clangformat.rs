@@ -0,0 +1,125 @@
+use crate::project::Project;
+use crate::result::Result;
+use crate::{color_println, BLUE, NO_COLOR, YELLOW};
+use std::path::Path;
+
+const CLANG_FORMAT_FILE: &str = ".clang-format";
+const GENERATED_HEADER: &str =
+    "# Generated by barge from the format_style and format_options fields of the\n\
+     # project file, edit those instead.\n";
+const DEFAULT_STYLE: &str = "Google";
+
+// Whether the style is the name of a predefined style, instead of `file`, or a
+// style given inline, like `{BasedOnStyle: LLVM, IndentWidth: 4}`.
+fn is_named_style(style: &str) -> bool {
+    style != "file" && !style.starts_with('{')
+}
+
+// Whether the style of the project is given in the project file, and so the
+// .clang-format file is generated from it.
+fn is_style_configured(project: &Project) -> bool {
+    let named = project.format_style.as_deref().is_none_or(is_named_style);
+    named && (project.format_style.is_some() || project.format_options.is_some())
+}
+
+// Writes the value as YAML, in the flow style for nested options, like
+// `BraceWrapping: {AfterClass: true}`. JSON scalars are valid YAML, so only the
+// strings which can be written without quotes are treated specially, for the
+// sake of readability.
+fn get_yaml_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, get_yaml_value(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        serde_json::Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(get_yaml_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        serde_json::Value::String(text)
+            if text.starts_with(|c: char| c.is_ascii_alphabetic())
+                && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !["true", "false", "yes", "no", "on", "off", "null"]
+                    .contains(&text.to_lowercase().as_str()) =>
+        {
+            text.clone()
+        }
+        value => value.to_string(),
+    }
+}
+
+fn get_style_entries(project: &Project) -> Vec<(String, String)> {
+    let style = project.format_style.as_deref().unwrap_or(DEFAULT_STYLE);
+    let mut entries = vec![(String::from("BasedOnStyle"), style.to_string())];
+    for (name, value) in project.format_options.iter().flatten() {
+        entries.push((name.clone(), get_yaml_value(value)));
+    }
+    entries
+}
+
+fn generate_clang_format(project: &Project) -> String {
+    let mut content = format!("{}---\n", GENERATED_HEADER);
+    for (name, value) in get_style_entries(project) {
+        content += &format!("{}: {}\n", name, value);
+    }
+    content + "...\n"
+}
+
+fn is_generated(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|content| content.starts_with(GENERATED_HEADER))
+}
+
+// Returns the --style argument of clang-format. The style configured in the
+// project file is passed inline, so that it is used even if the .clang-format
+// file is not up to date. Otherwise an existing .clang-format file is used, so
+// that barge and the editors agree on the style.
+pub(crate) fn get_style_arg(project: &Project) -> String {
+    match project.format_style.as_deref() {
+        Some(style) if !is_named_style(style) => format!("--style={}", style),
+        _ if is_style_configured(project) => {
+            let entries: Vec<String> = get_style_entries(project)
+                .into_iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .collect();
+            format!("--style={{{}}}", entries.join(", "))
+        }
+        _ if Path::new(CLANG_FORMAT_FILE).exists() => String::from("--style=file"),
+        _ => format!("--style={}", DEFAULT_STYLE),
+    }
+}
+
+// Writes the .clang-format file of the project into the directory, only if its
+// contents change. Returns whether the file was written.
+pub(crate) fn write_clang_format(project: &Project, directory: &Path) -> Result<bool> {
+    let path = directory.join(CLANG_FORMAT_FILE);
+    let content = generate_clang_format(project);
+    if std::fs::read_to_string(&path).unwrap_or_default() == content {
+        return Ok(false);
+    }
+    std::fs::write(&path, content)?;
+    Ok(true)
+}
+
+// Keeps the .clang-format file in sync with the style configured in the
+// project file. A .clang-format file not generated by barge is left unchanged.
+pub(crate) fn update_clang_format(project: &Project) -> Result<()> {
+    if !is_style_configured(project) {
+        return Ok(());
+    }
+    let path = Path::new(CLANG_FORMAT_FILE);
+    if path.exists() && !is_generated(path) {
+        color_println!(
+            YELLOW,
+            "The {} file was not generated by barge, it is left unchanged",
+            CLANG_FORMAT_FILE
+        );
+        return Ok(());
+    }
+    if write_clang_format(project, Path::new(""))? {
+        color_println!(BLUE, "Updated {} from the project file", CLANG_FORMAT_FILE);
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use crate::project::Project;
+use crate::result::Result;
+use crate::{color_println, BLUE, NO_COLOR, YELLOW};
+
+const CLANG_TIDY_FILE: &str = ".clang-tidy";
+const GENERATED_HEADER: &str =
+    "# Generated by barge from the clang_tidy field of the project file, edit that\n\
+     # instead.\n";
+
+// The marker clang-tidy appends to the name of the checks which are reported
+// as errors, like `[bugprone-use-after-move,-warnings-as-errors]`.
+const WARNINGS_AS_ERRORS_MARKER: &str = "-warnings-as-errors]";
+
+fn generate_clang_tidy(project: &Project) -> Option<String> {
+    let configuration = project.clang_tidy.as_ref()?;
+    let checks: Vec<String> = configuration
+        .checks
+        .iter()
+        .flatten()
+        .cloned()
+        .chain(
+            configuration
+                .disabled_checks
+                .iter()
+                .flatten()
+                .map(|check| format!("-{}", check)),
+        )
+        .collect();
+
+    let mut content = format!("{}---\n", GENERATED_HEADER);
+    if !checks.is_empty() {
+        content += &format!("Checks: '{}'\n", checks.join(","));
+    }
+    if let Some(errors) = &configuration.warnings_as_errors {
+        content += &format!("WarningsAsErrors: '{}'\n", errors.join(","));
+    }
+    Some(content + "...\n")
+}
+
+// Keeps the .clang-tidy file in sync with the clang_tidy field of the project
+// file. If the field is missing, or the .clang-tidy file was not generated by
+// barge, the file is left unchanged, and clang-tidy reads it as usual.
+pub(crate) fn update_clang_tidy(project: &Project) -> Result<()> {
+    let Some(content) = generate_clang_tidy(project) else {
+        return Ok(());
+    };
+    let previous = std::fs::read_to_string(CLANG_TIDY_FILE).ok();
+    match previous {
+        Some(previous) if previous == content => {}
+        Some(previous) if !previous.starts_with(GENERATED_HEADER) => color_println!(
+            YELLOW,
+            "The {} file was not generated by barge, it is left unchanged",
+            CLANG_TIDY_FILE
+        ),
+        _ => {
+            std::fs::write(CLANG_TIDY_FILE, content)?;
+            color_println!(BLUE, "Updated {} from the project file", CLANG_TIDY_FILE);
+        }
+    }
+    Ok(())
+}
+
+// Returns whether the diagnostics of clang-tidy contain warnings which are
+// treated as errors.
+pub(crate) fn has_warnings_as_errors(diagnostics: &str) -> bool {
+    diagnostics
+        .lines()
+        .any(|line| line.contains(WARNINGS_AS_ERRORS_MARKER))
+}
@@ -0,0 +1,346 @@
+use crate::annotations::{get_annotations, get_requested_annotations};
+use crate::objecthash::ObjectHash;
+use crate::result::{BargeError, Result};
+use crate::scheduling::{acquire_memory_heavy_slot, wait_with_peak_rss};
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED, WHITE};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+
+pub(crate) const COMMAND_LOG: &str = "build/commands.ndjson";
+const COMMAND_ENVIRONMENT: &str = ".commands-environment.json";
+const COMMAND_LOG_VARIABLE: &str = "BARGE_COMMAND_LOG";
+const IGNORED_VARIABLES: &[&str] = &[
+    "MAKEFLAGS",
+    "MAKELEVEL",
+    "MFLAGS",
+    "MAKE_TERMOUT",
+    "MAKE_TERMERR",
+];
+
+// Logs already started by this process, as multilib variants and external
+// projects are built by the same invocation.
+static STARTED_COMMAND_LOGS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RecordedCommand {
+    pub cwd: String,
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_kb: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unset: Vec<String>,
+}
+
+fn get_environment() -> BTreeMap<String, String> {
+    std::env::vars()
+        .filter(|(name, _)| !IGNORED_VARIABLES.contains(&name.as_str()))
+        .filter(|(name, _)| !name.starts_with("BARGE_"))
+        .collect()
+}
+
+fn get_environment_path(log: &Path) -> PathBuf {
+    log.with_file_name(COMMAND_ENVIRONMENT)
+}
+
+fn quote_argument(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+pub(crate) fn start_command_log() -> Result<()> {
+    open_command_log(true)
+}
+
+// Records the commands after the ones of the last build, for the commands run
+// outside of builds, like the static analyzers.
+pub(crate) fn continue_command_log() -> Result<()> {
+    open_command_log(false)
+}
+
+fn open_command_log(truncate: bool) -> Result<()> {
+    let log = std::env::current_dir()?.join(COMMAND_LOG);
+    {
+        let mut started = STARTED_COMMAND_LOGS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if started.contains(&log) {
+            return Ok(());
+        }
+        started.push(log.clone());
+    }
+
+    if !truncate && log.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = log.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&log, "")?;
+    std::fs::write(
+        get_environment_path(&log),
+        serde_json::to_string(&get_environment())?,
+    )?;
+    Ok(())
+}
+
+pub(crate) fn get_recorder_prefix() -> Result<String> {
+    let executable = std::env::current_exe()?;
+    Ok(format!(
+        "{} record-command",
+        quote_argument(&executable.to_string_lossy())
+    ))
+}
+
+pub(crate) fn enable_command_recording(command: &mut Command) -> Result<()> {
+    let log = std::env::current_dir()?.join(COMMAND_LOG);
+    command.env(COMMAND_LOG_VARIABLE, log);
+    Ok(())
+}
+
+fn create_record(
+    log: &Path,
+    arguments: Vec<String>,
+    cwd: &Path,
+    environment: BTreeMap<String, String>,
+) -> RecordedCommand {
+    let baseline: BTreeMap<String, String> = std::fs::read_to_string(get_environment_path(log))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    RecordedCommand {
+        cwd: cwd.to_string_lossy().to_string(),
+        command: arguments,
+        source: None,
+        peak_rss_kb: None,
+        set: environment
+            .iter()
+            .filter(|(name, value)| baseline.get(*name) != Some(*value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        unset: baseline
+            .keys()
+            .filter(|name| !environment.contains_key(*name))
+            .cloned()
+            .collect(),
+    }
+}
+
+fn write_record(log: &Path, record: &RecordedCommand) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new().append(true).open(log)?;
+    file.write_all((serde_json::to_string(record)? + "\n").as_bytes())?;
+    Ok(())
+}
+
+fn append_to_log(
+    log: &Path,
+    arguments: &[String],
+    source: Option<&String>,
+    peak_rss_kb: Option<u64>,
+) -> Result<()> {
+    let mut record = create_record(
+        log,
+        arguments.to_vec(),
+        &std::env::current_dir()?,
+        get_environment(),
+    );
+    record.source = source.cloned();
+    record.peak_rss_kb = peak_rss_kb;
+    write_record(log, &record)
+}
+
+// Records a command run by barge itself, instead of the makefile, like the
+// queries of pkg-config, or the builds of the Rust crates, if the command log
+// of the project in the current directory was started by this process.
+fn record_command(command: &Command) -> Result<()> {
+    let log = std::env::current_dir()?.join(COMMAND_LOG);
+    let started = STARTED_COMMAND_LOGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(&log);
+    if !started {
+        return Ok(());
+    }
+
+    let arguments = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|argument| argument.to_string_lossy().to_string())
+        .collect();
+    let cwd = match command.get_current_dir() {
+        Some(directory) => std::env::current_dir()?.join(directory),
+        None => std::env::current_dir()?,
+    };
+    let mut environment = get_environment();
+    for (name, value) in command.get_envs() {
+        let name = name.to_string_lossy().to_string();
+        match value {
+            Some(value) => environment.insert(name, value.to_string_lossy().to_string()),
+            None => environment.remove(&name),
+        };
+    }
+    write_record(&log, &create_record(&log, arguments, &cwd, environment))
+}
+
+fn record_or_warn(command: &Command) {
+    if record_command(command).is_err() {
+        color_eprintln!(
+            "Could not record command {}",
+            command.get_program().to_string_lossy()
+        );
+    }
+}
+
+// Runs the command like Command::output, recording it in the command log.
+pub(crate) fn record_and_output(command: &mut Command) -> std::io::Result<Output> {
+    record_or_warn(command);
+    command.output()
+}
+
+// Runs the command like Command::status, recording it in the command log.
+pub(crate) fn record_and_status(command: &mut Command) -> std::io::Result<ExitStatus> {
+    record_or_warn(command);
+    command.status()
+}
+
+pub(crate) fn record_and_execute(
+    arguments: &[String],
+    source: Option<&String>,
+    message: Option<&String>,
+    object_hash: Option<ObjectHash>,
+) -> Result<i32> {
+    let (program, rest) = arguments
+        .split_first()
+        .ok_or(BargeError::InvalidValue("No command was given to record"))?;
+    if object_hash.as_ref().is_some_and(|hash| hash.is_unchanged()) {
+        return Ok(0);
+    }
+    if let Some(message) = message {
+        println!("{}", message);
+    }
+
+    let _slot = match source {
+        Some(source) => acquire_memory_heavy_slot(source)?,
+        None => None,
+    };
+    let annotations = get_requested_annotations();
+    let mut command = Command::new(program);
+    command.args(rest).env_remove(COMMAND_LOG_VARIABLE);
+    if annotations.is_some() {
+        command.stderr(Stdio::piped());
+    }
+    let mut child = command.spawn()?;
+
+    // The diagnostics are read on a separate thread, so the pipe never fills up.
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            stderr.read_to_string(&mut output).map(|_| output)
+        })
+    });
+    let (code, peak_rss_kb) = wait_with_peak_rss(&mut child)?;
+    if let Some(object_hash) = object_hash {
+        match code {
+            0 => object_hash.store(),
+            _ => object_hash.discard(),
+        }
+    }
+
+    if let (Some(format), Some(stderr)) = (annotations, stderr) {
+        let output = stderr
+            .join()
+            .map_err(|_| BargeError::FailedOperation("Could not read the command output"))??;
+        std::io::stderr().write_all(output.as_bytes())?;
+        for annotation in get_annotations(&output, format) {
+            println!("{}", annotation);
+        }
+    }
+
+    if let Some(log) = std::env::var_os(COMMAND_LOG_VARIABLE) {
+        if append_to_log(Path::new(&log), arguments, source, peak_rss_kb).is_err() {
+            color_eprintln!("Could not record command {}", program);
+        }
+    }
+    Ok(code)
+}
+
+fn load_command_log() -> Result<Vec<RecordedCommand>> {
+    let log = std::fs::read_to_string(COMMAND_LOG).map_err(|_| {
+        BargeError::FailedOperation("No commands were recorded, build the project first")
+    })?;
+    log.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+fn print_recorded_commands(commands: &[(usize, &RecordedCommand)]) {
+    for (index, command) in commands {
+        color_println!(WHITE, "{:>4}: {}", index, command.command.join(" "));
+    }
+}
+
+pub(crate) fn replay_command(selector: Option<&String>) -> Result<()> {
+    let commands = load_command_log()?;
+    let indexed: Vec<(usize, &RecordedCommand)> = commands.iter().enumerate().collect();
+
+    let selector = match selector {
+        Some(selector) => selector,
+        None => {
+            print_recorded_commands(&indexed);
+            return Ok(());
+        }
+    };
+
+    let selected: Vec<(usize, &RecordedCommand)> = match selector.parse::<usize>() {
+        Ok(index) => indexed.into_iter().filter(|(i, _)| *i == index).collect(),
+        Err(_) => indexed
+            .into_iter()
+            .filter(|(_, command)| command.command.join(" ").contains(selector.as_str()))
+            .collect(),
+    };
+
+    let (index, command) = match selected.as_slice() {
+        [] => {
+            return Err(BargeError::InvalidValue(
+                "No recorded command matches the given index or filter",
+            ))
+        }
+        [selected] => *selected,
+        _ => {
+            print_recorded_commands(&selected);
+            return Err(BargeError::InvalidValue(
+                "More than one recorded command matches the given filter",
+            ));
+        }
+    };
+
+    color_println!(BLUE, "Replaying command {}", index);
+    color_println!(WHITE, "{}", command.command.join(" "));
+    let (program, arguments) = command
+        .command
+        .split_first()
+        .ok_or(BargeError::InvalidValue("The recorded command is empty"))?;
+    let mut replay = Command::new(program);
+    replay
+        .args(arguments)
+        .current_dir(&command.cwd)
+        .envs(&command.set);
+    for name in &command.unset {
+        replay.env_remove(name);
+    }
+
+    if replay.status()?.success() {
+        Ok(())
+    } else {
+        color_eprintln!("The replayed command failed");
+        Err(BargeError::FailedOperation("Failed to replay command"))
+    }
+}
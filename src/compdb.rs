@@ -0,0 +1,59 @@
+use crate::makefile::{get_source_flags, resolve_build_flags, BuildTarget};
+use crate::project::{collect_source_files, CollectSourceFilesMode, Project, SourceKind};
+use crate::result::Result;
+use crate::{color_println, GREEN, NO_COLOR};
+use serde::Serialize;
+
+const COMPILATION_DATABASE: &str = "compile_commands.json";
+
+#[derive(Serialize)]
+struct CompileCommand {
+    directory: String,
+    file: String,
+    arguments: Vec<String>,
+    output: String,
+}
+
+pub(crate) fn write_compilation_database(project: &Project, target: BuildTarget) -> Result<()> {
+    let flags = resolve_build_flags(project, target)?;
+    let build_directory = project.build_directory(target);
+    let directory = std::env::current_dir()?.to_string_lossy().to_string();
+
+    let mut commands = Vec::new();
+    for source in collect_source_files(project, CollectSourceFilesMode::CCppSourcesOnly)? {
+        let Some(SourceKind::Language(kind)) = project.source_kind(&source) else {
+            continue;
+        };
+        let compiler = project.compiler(kind);
+        let flags = flags.compile_flags(kind);
+
+        let source_flags = get_source_flags(project, target, &source, flags)?;
+        let flags = source_flags.as_ref().unwrap_or(flags);
+        let name = source.strip_prefix("src/").unwrap_or(&source);
+        let output = format!("{}/obj/{}.o", build_directory, name);
+        let arguments = std::iter::once(compiler.as_str())
+            .chain(flags.split_whitespace())
+            .chain(["-c", &source, "-o", &output])
+            .map(|argument| argument.to_string())
+            .collect();
+        commands.push(CompileCommand {
+            directory: directory.clone(),
+            file: source.clone(),
+            arguments,
+            output,
+        });
+    }
+
+    std::fs::write(
+        COMPILATION_DATABASE,
+        serde_json::to_string_pretty(&commands)? + "\n",
+    )?;
+    color_println!(
+        GREEN,
+        "Wrote {} with {} entries for the {} configuration",
+        COMPILATION_DATABASE,
+        commands.len(),
+        target.to_string()
+    );
+    Ok(())
+}
@@ -0,0 +1,557 @@
+use crate::errorcodes::get_error_code_names;
+use crate::project::{find_project_file, read_project_value};
+use crate::result::{BargeError, Result};
+use crate::utilities::look_for_project_directory;
+use std::fmt::Write;
+
+const BINARY_NAME: &str = env!("CARGO_PKG_NAME");
+
+// The kinds of names which are read from the project file while completing,
+// with `barge complete-values <KIND>`.
+pub(crate) const PROJECT_VALUE_KINDS: [&str; 4] = ["bins", "artifacts", "members", "externals"];
+
+// The values an argument can be completed with.
+enum Values {
+    Text,
+    Files,
+    Directories,
+    List(Vec<String>),
+    Project(&'static str),
+}
+
+fn get_values(subcommand: &str, arg: &clap::Arg) -> Values {
+    match (subcommand, arg.get_id().as_str()) {
+        (_, "TARGET") => Values::List(vec![String::from("debug"), String::from("release")]),
+        (_, "project-dir") | ("install", "prefix") => Values::Directories,
+        ("new-file", "PATH") | ("size", "diff") => Values::Files,
+        (_, "bin") => Values::Project("bins"),
+        (_, "member") => Values::Project("members"),
+        ("artifact", "NAME") | ("package", "output") => Values::Project("artifacts"),
+        ("update", "NAME") => Values::Project("externals"),
+        ("init", "TYPE") => Values::List(
+            ["executable", "shared-lib", "static-lib"]
+                .map(String::from)
+                .to_vec(),
+        ),
+        ("explain", "CODE") => Values::List(
+            get_error_code_names()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        ),
+        _ => {
+            let values: Vec<String> = arg
+                .get_possible_values()
+                .iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| value.get_name().to_string())
+                .collect();
+            if values.is_empty() {
+                Values::Text
+            } else {
+                Values::List(values)
+            }
+        }
+    }
+}
+
+pub(crate) fn get_subcommands(command: &clap::Command) -> Vec<&clap::Command> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .collect()
+}
+
+// The name of the subcommand, followed by all of its aliases.
+fn get_names(subcommand: &clap::Command) -> Vec<&str> {
+    std::iter::once(subcommand.get_name())
+        .chain(subcommand.get_all_aliases())
+        .collect()
+}
+
+pub(crate) fn get_about(command: &clap::Command) -> String {
+    command
+        .get_about()
+        .map(|about| about.to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn get_options(command: &clap::Command) -> Vec<&clap::Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .collect()
+}
+
+pub(crate) fn get_positionals(command: &clap::Command) -> Vec<&clap::Arg> {
+    command
+        .get_positionals()
+        .filter(|arg| !arg.is_last_set() && !arg.is_hide_set())
+        .collect()
+}
+
+pub(crate) fn get_help(arg: &clap::Arg) -> String {
+    arg.get_help()
+        .map(|help| help.to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn takes_value(arg: &clap::Arg) -> bool {
+    arg.get_num_args().is_some_and(|range| range.takes_values())
+}
+
+pub(crate) fn get_shorts(arg: &clap::Arg) -> Vec<char> {
+    let mut shorts: Vec<char> = arg.get_short().into_iter().collect();
+    shorts.extend(arg.get_visible_short_aliases().into_iter().flatten());
+    shorts
+}
+
+pub(crate) fn get_longs(arg: &clap::Arg) -> Vec<&str> {
+    let mut longs: Vec<&str> = arg.get_long().into_iter().collect();
+    longs.extend(arg.get_visible_aliases().into_iter().flatten());
+    longs
+}
+
+// Every spelling of the option, for example, -j and --jobs.
+fn get_switches(arg: &clap::Arg) -> Vec<String> {
+    get_shorts(arg)
+        .into_iter()
+        .map(|short| format!("-{}", short))
+        .chain(get_longs(arg).into_iter().map(|long| format!("--{}", long)))
+        .collect()
+}
+
+fn get_bash_reply(values: &Values) -> String {
+    let words = match values {
+        Values::Text => return String::from("COMPREPLY=()"),
+        Values::Files => return String::from("COMPREPLY=($(compgen -f -- \"$current\"))"),
+        Values::Directories => return String::from("COMPREPLY=($(compgen -d -- \"$current\"))"),
+        Values::List(values) => values.join(" "),
+        Values::Project(kind) => format!("$(_barge_values {})", kind),
+    };
+    format!("COMPREPLY=($(compgen -W \"{}\" -- \"$current\"))", words)
+}
+
+fn write_bash_arguments(script: &mut String, name: &str, command: &clap::Command) {
+    let options = get_options(command);
+    let _ = writeln!(script, "            case \"$previous\" in");
+    for option in options.iter().filter(|option| takes_value(option)) {
+        let _ = writeln!(
+            script,
+            "                {}) {}; return ;;",
+            get_switches(option).join("|"),
+            get_bash_reply(&get_values(name, option))
+        );
+    }
+    let _ = writeln!(script, "            esac");
+
+    let switches: Vec<String> = options
+        .iter()
+        .flat_map(|option| get_switches(option))
+        .collect();
+    let _ = writeln!(script, "            if [[ \"$current\" == -* ]]; then");
+    let _ = writeln!(
+        script,
+        "                COMPREPLY=($(compgen -W \"{}\" -- \"$current\"))",
+        switches.join(" ")
+    );
+    if name.is_empty() {
+        let subcommands: Vec<&str> = get_subcommands(command)
+            .into_iter()
+            .flat_map(|subcommand| {
+                std::iter::once(subcommand.get_name()).chain(subcommand.get_visible_aliases())
+            })
+            .collect();
+        let _ = writeln!(script, "            else");
+        let _ = writeln!(
+            script,
+            "                COMPREPLY=($(compgen -W \"{}\" -- \"$current\"))",
+            subcommands.join(" ")
+        );
+    } else {
+        for (index, positional) in get_positionals(command).into_iter().enumerate() {
+            let _ = writeln!(
+                script,
+                "            elif [[ $positional -eq {} ]]; then",
+                index
+            );
+            let _ = writeln!(
+                script,
+                "                {}",
+                get_bash_reply(&get_values(name, positional))
+            );
+        }
+    }
+    let _ = writeln!(script, "            fi");
+}
+
+fn generate_bash(command: &clap::Command) -> String {
+    // The values of the options are skipped while looking for the subcommand
+    // and counting the positional arguments.
+    let mut value_switches: Vec<String> = Vec::new();
+    for arg in get_subcommands(command)
+        .into_iter()
+        .chain([command])
+        .flat_map(get_options)
+        .filter(|option| takes_value(option))
+    {
+        for switch in get_switches(arg) {
+            if !value_switches.contains(&switch) {
+                value_switches.push(switch);
+            }
+        }
+    }
+
+    let mut script = String::new();
+    let _ = writeln!(script, "_barge_values() {{");
+    let _ = writeln!(
+        script,
+        "    {} complete-values \"$1\" 2>/dev/null",
+        BINARY_NAME
+    );
+    let _ = writeln!(script, "}}\n");
+    let _ = writeln!(script, "_barge() {{");
+    let _ = writeln!(script, "    local current=\"${{COMP_WORDS[COMP_CWORD]}}\"");
+    let _ = writeln!(
+        script,
+        "    local previous=\"${{COMP_WORDS[COMP_CWORD-1]}}\""
+    );
+    let _ = writeln!(script, "    local subcommand=\"\" positional=0 index");
+    let _ = writeln!(
+        script,
+        "    for ((index = 1; index < COMP_CWORD; index++)); do"
+    );
+    let _ = writeln!(script, "        case \"${{COMP_WORDS[index]}}\" in");
+    let _ = writeln!(
+        script,
+        "            {}) ((index++)) ;;",
+        value_switches.join("|")
+    );
+    let _ = writeln!(script, "            -*) ;;");
+    let _ = writeln!(script, "            *)");
+    let _ = writeln!(script, "                if [[ -z \"$subcommand\" ]]; then");
+    let _ = writeln!(
+        script,
+        "                    subcommand=\"${{COMP_WORDS[index]}}\""
+    );
+    let _ = writeln!(script, "                else");
+    let _ = writeln!(script, "                    ((positional++))");
+    let _ = writeln!(script, "                fi");
+    let _ = writeln!(script, "                ;;");
+    let _ = writeln!(script, "        esac");
+    let _ = writeln!(script, "    done\n");
+    let _ = writeln!(script, "    case \"$subcommand\" in");
+    let _ = writeln!(script, "        \"\")");
+    write_bash_arguments(&mut script, "", command);
+    let _ = writeln!(script, "            ;;");
+    for subcommand in get_subcommands(command) {
+        let _ = writeln!(script, "        {})", get_names(subcommand).join("|"));
+        write_bash_arguments(&mut script, subcommand.get_name(), subcommand);
+        let _ = writeln!(script, "            ;;");
+    }
+    let _ = writeln!(script, "    esac");
+    let _ = writeln!(script, "}}\n");
+    let _ = writeln!(script, "complete -F _barge {}", BINARY_NAME);
+    script
+}
+
+// Escapes the text for a single-quoted string of zsh.
+fn escape_zsh(text: &str) -> String {
+    text.replace('\'', "'\\''")
+}
+
+// Escapes the description of an option for the specifications of _arguments.
+fn escape_zsh_description(text: &str) -> String {
+    escape_zsh(
+        &text
+            .replace('\\', "\\\\")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:"),
+    )
+}
+
+fn get_zsh_action(values: &Values) -> String {
+    match values {
+        Values::Text => String::new(),
+        Values::Files => String::from("_files"),
+        Values::Directories => String::from("_files -/"),
+        Values::List(values) => format!("({})", values.join(" ")),
+        Values::Project(kind) => format!("{{_barge_values {}}}", kind),
+    }
+}
+
+fn get_zsh_specifications(name: &str, command: &clap::Command) -> Vec<String> {
+    let mut specifications = Vec::new();
+    for option in get_options(command) {
+        let repeated = matches!(
+            option.get_action(),
+            clap::ArgAction::Append | clap::ArgAction::Count
+        );
+        let description = escape_zsh_description(&get_help(option));
+        let value = takes_value(option).then(|| {
+            let value_name = option
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| option.get_id().to_string());
+            format!(
+                ":{}:{}",
+                escape_zsh_description(&value_name),
+                escape_zsh(&get_zsh_action(&get_values(name, option)))
+            )
+        });
+        for short in get_shorts(option) {
+            let suffix = if value.is_some() { "+" } else { "" };
+            specifications.push(format!(
+                "'{}-{}{}[{}]{}'",
+                if repeated { "*" } else { "" },
+                short,
+                suffix,
+                description,
+                value.clone().unwrap_or_default()
+            ));
+        }
+        for long in get_longs(option) {
+            let suffix = if value.is_some() { "=" } else { "" };
+            specifications.push(format!(
+                "'{}--{}{}[{}]{}'",
+                if repeated { "*" } else { "" },
+                long,
+                suffix,
+                description,
+                value.clone().unwrap_or_default()
+            ));
+        }
+    }
+    for (index, positional) in get_positionals(command).into_iter().enumerate() {
+        specifications.push(format!(
+            "'{}:{}{}:{}'",
+            index + 1,
+            if positional.is_required_set() {
+                ""
+            } else {
+                ":"
+            },
+            escape_zsh_description(positional.get_id().as_str()),
+            escape_zsh(&get_zsh_action(&get_values(name, positional)))
+        ));
+    }
+    specifications
+}
+
+fn generate_zsh(command: &clap::Command) -> String {
+    let mut script = String::new();
+    let _ = writeln!(script, "#compdef {}\n", BINARY_NAME);
+    let _ = writeln!(script, "_barge_values() {{");
+    let _ = writeln!(script, "    local -a values");
+    let _ = writeln!(
+        script,
+        "    values=(${{(f)\"$({} complete-values \"$1\" 2>/dev/null)\"}})",
+        BINARY_NAME
+    );
+    let _ = writeln!(script, "    compadd -a values");
+    let _ = writeln!(script, "}}\n");
+    let _ = writeln!(script, "_barge() {{");
+    let _ = writeln!(script, "    local line state");
+    let _ = writeln!(script, "    _arguments -C \\");
+    for specification in get_zsh_specifications("", command) {
+        let _ = writeln!(script, "        {} \\", specification);
+    }
+    let _ = writeln!(script, "        '1: :->subcommand' \\");
+    let _ = writeln!(script, "        '*:: :->argument'\n");
+    let _ = writeln!(script, "    case $state in");
+    let _ = writeln!(script, "        subcommand)");
+    let _ = writeln!(script, "            local -a subcommands");
+    let _ = writeln!(script, "            subcommands=(");
+    for subcommand in get_subcommands(command) {
+        let about = escape_zsh(&get_about(subcommand).replace(':', "\\:"));
+        for name in std::iter::once(subcommand.get_name()).chain(subcommand.get_visible_aliases()) {
+            let _ = writeln!(script, "                '{}:{}'", name, about);
+        }
+    }
+    let _ = writeln!(script, "            )");
+    let _ = writeln!(script, "            _describe 'subcommand' subcommands");
+    let _ = writeln!(script, "            ;;");
+    let _ = writeln!(script, "        argument)");
+    let _ = writeln!(script, "            case $line[1] in");
+    for subcommand in get_subcommands(command) {
+        let _ = writeln!(
+            script,
+            "                {})",
+            get_names(subcommand).join("|")
+        );
+        let specifications = get_zsh_specifications(subcommand.get_name(), subcommand);
+        let _ = writeln!(script, "                    _arguments \\");
+        let _ = writeln!(
+            script,
+            "                        {}",
+            specifications.join(" \\\n                        ")
+        );
+        let _ = writeln!(script, "                    ;;");
+    }
+    let _ = writeln!(script, "            esac");
+    let _ = writeln!(script, "            ;;");
+    let _ = writeln!(script, "    esac");
+    let _ = writeln!(script, "}}\n");
+    // Works both from the function path of zsh, and when sourced.
+    let _ = writeln!(script, "if [ \"$funcstack[1]\" = \"_barge\" ]; then");
+    let _ = writeln!(script, "    _barge \"$@\"");
+    let _ = writeln!(script, "else");
+    let _ = writeln!(script, "    compdef _barge {}", BINARY_NAME);
+    let _ = writeln!(script, "fi");
+    script
+}
+
+// Escapes the text for a single-quoted string of fish.
+fn escape_fish(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn get_fish_values(values: &Values) -> String {
+    match values {
+        Values::Text => String::new(),
+        Values::Files => String::from(" -F"),
+        Values::Directories => String::from(" -a '(__fish_complete_directories)'"),
+        Values::List(values) => format!(" -a '{}'", escape_fish(&values.join(" "))),
+        Values::Project(kind) => format!(" -a '(__barge_values {})'", kind),
+    }
+}
+
+fn write_fish_options(script: &mut String, name: &str, command: &clap::Command, condition: &str) {
+    for option in get_options(command) {
+        // The global options are completed everywhere, without a condition.
+        let global = option.is_global_set();
+        if global && !name.is_empty() {
+            continue;
+        }
+        let mut line = format!("complete -c {}", BINARY_NAME);
+        if !global {
+            let _ = write!(line, " -n '{}'", condition);
+        }
+        for short in get_shorts(option) {
+            let _ = write!(line, " -s {}", short);
+        }
+        for long in get_longs(option) {
+            let _ = write!(line, " -l {}", long);
+        }
+        if takes_value(option) {
+            let values = get_values(name, option);
+            // Only the free-form values fall back to the names of files.
+            line += if matches!(values, Values::Files) {
+                " -r"
+            } else {
+                " -x"
+            };
+            line += &get_fish_values(&values);
+        }
+        let _ = writeln!(script, "{} -d '{}'", line, escape_fish(&get_help(option)));
+    }
+}
+
+fn generate_fish(command: &clap::Command) -> String {
+    let mut script = String::new();
+    let _ = writeln!(script, "function __barge_values");
+    let _ = writeln!(
+        script,
+        "    {} complete-values $argv[1] 2>/dev/null",
+        BINARY_NAME
+    );
+    let _ = writeln!(script, "end\n");
+    let _ = writeln!(script, "complete -c {} -f", BINARY_NAME);
+    write_fish_options(&mut script, "", command, "__fish_use_subcommand");
+    for subcommand in get_subcommands(command) {
+        for name in std::iter::once(subcommand.get_name()).chain(subcommand.get_visible_aliases()) {
+            let _ = writeln!(
+                script,
+                "complete -c {} -n __fish_use_subcommand -a {} -d '{}'",
+                BINARY_NAME,
+                name,
+                escape_fish(&get_about(subcommand))
+            );
+        }
+    }
+    for subcommand in get_subcommands(command) {
+        let condition = format!(
+            "__fish_seen_subcommand_from {}",
+            get_names(subcommand).join(" ")
+        );
+        write_fish_options(&mut script, subcommand.get_name(), subcommand, &condition);
+        for positional in get_positionals(subcommand) {
+            let values = get_values(subcommand.get_name(), positional);
+            if matches!(values, Values::Text) {
+                continue;
+            }
+            let _ = writeln!(
+                script,
+                "complete -c {} -n '{}'{}",
+                BINARY_NAME,
+                condition,
+                get_fish_values(&values)
+            );
+        }
+    }
+    script
+}
+
+// Prints the completion script of the given shell (bash, zsh, or fish) for
+// the command line interface.
+pub(crate) fn print_completions(mut command: clap::Command, shell: &str) -> Result<()> {
+    // Building the command propagates the global options to the subcommands,
+    // and adds the help and version options.
+    command.build();
+    let script = match shell {
+        "bash" => generate_bash(&command),
+        "zsh" => generate_zsh(&command),
+        "fish" => generate_fish(&command),
+        _ => return Err(BargeError::InvalidValue("Unsupported shell")),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+fn get_names_of(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item["name"].as_str())
+        .map(String::from)
+        .collect()
+}
+
+// Prints the names of the given kind from the project file, one per line, for
+// the completion scripts. Outside of a project, nothing is printed.
+pub(crate) fn print_completion_values(kind: &str) -> Result<()> {
+    let Some(path) = look_for_project_directory()
+        .ok()
+        .and_then(|directory| find_project_file(&directory))
+    else {
+        return Ok(());
+    };
+    let project = read_project_value(&path.to_string_lossy())?;
+    let names = match kind {
+        "bins" => get_names_of(&project["bins"]),
+        "externals" => get_names_of(&project["external_projects"]),
+        "artifacts" => project["artifacts"]
+            .as_object()
+            .map(|artifacts| artifacts.keys().cloned().collect())
+            .unwrap_or_default(),
+        "members" => project["workspace_members"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|member| member.as_str())
+            .map(String::from)
+            .collect(),
+        _ => {
+            return Err(BargeError::InvalidValue(
+                "Unknown kind of completion values",
+            ))
+        }
+    };
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::dependencies::get_stored_compiler_identity;
+use crate::languages::LanguageKind;
+use crate::makefile::{get_target_flags, BuildFlags};
+use crate::project::{ConfigureChecks, Project};
+use crate::result::{BargeError, Result};
+use crate::selfprofile::ProfileSection;
+use crate::utilities::{get_content_hash, parallel_map};
+use crate::{color_println, BLUE, NO_COLOR};
+use std::collections::HashMap;
+use std::path::Path;
+
+const GENERATED_DIRECTORY: &str = "generated";
+const CONFIG_HEADER: &str = "config.h";
+const CONFIGURE_STAMP: &str = ".configure";
+const MAX_TYPE_SIZE: u64 = 1024;
+
+enum Probe<'a> {
+    Header(&'a str),
+    Function(&'a str),
+    Symbol(&'a str, &'a str),
+    Size(&'a str),
+}
+
+// The architectures recognized from the predefined macros of the compiler,
+// which tell the actual target even if the triple of the compiler is not
+// accurate, like the one of GCC when compiling with -m32.
+const ARCHITECTURE_MACROS: [(&str, &str); 12] = [
+    ("__x86_64__", "x86_64"),
+    ("__i386__", "i386"),
+    ("__aarch64__", "aarch64"),
+    ("__arm__", "arm"),
+    ("__riscv", "riscv"),
+    ("__powerpc64__", "powerpc64"),
+    ("__powerpc__", "powerpc"),
+    ("__mips64", "mips64"),
+    ("__mips__", "mips"),
+    ("__s390x__", "s390x"),
+    ("__wasm32__", "wasm32"),
+    ("__AVR__", "avr"),
+];
+
+#[derive(Clone)]
+pub(crate) struct TargetProperties {
+    pub triple: String,
+    pub architecture: String,
+    pub pointer_size: u32,
+    pub big_endian: bool,
+}
+
+fn get_predefined_macros(project: &Project, compiler: &str) -> Result<HashMap<String, String>> {
+    let output = project
+        .command(compiler)
+        .args(get_target_flags(project).split_whitespace())
+        .args(["-dM", "-E", "-x", "c", "/dev/null"])
+        .output()?;
+    if !output.status.success() {
+        return Err(BargeError::FailedOperation(
+            "Could not query the predefined macros of the compiler",
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.strip_prefix("#define "))
+        .map(|line| match line.split_once(' ') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect())
+}
+
+// Queries the target of the compiler, with the flags of the project selecting
+// it, so that the properties describe the target even when cross-compiling.
+pub(crate) fn get_target_properties(project: &Project) -> Result<TargetProperties> {
+    let compiler = project.compiler(LanguageKind::C);
+    let output = project
+        .command(&compiler)
+        .args(get_target_flags(project).split_whitespace())
+        .arg("-dumpmachine")
+        .output()?;
+    let triple = String::from_utf8(output.stdout)?.trim().to_string();
+    let macros = get_predefined_macros(project, &compiler)?;
+
+    let pointer_size = macros
+        .get("__SIZEOF_POINTER__")
+        .and_then(|size| size.parse().ok())
+        .ok_or(BargeError::FailedOperation(
+            "Could not determine the pointer size of the target",
+        ))?;
+    let architecture = match ARCHITECTURE_MACROS
+        .iter()
+        .find(|(name, _)| macros.contains_key(*name))
+    {
+        Some((_, "riscv")) => format!("riscv{}", pointer_size * 8),
+        Some((_, architecture)) => architecture.to_string(),
+        None => triple.split('-').next().unwrap_or_default().to_string(),
+    };
+    let big_endian =
+        macros.get("__BYTE_ORDER__").map(String::as_str) == Some("__ORDER_BIG_ENDIAN__");
+
+    Ok(TargetProperties {
+        triple,
+        architecture,
+        pointer_size,
+        big_endian,
+    })
+}
+
+fn get_target_definitions(properties: &TargetProperties) -> String {
+    let endianness = match properties.big_endian {
+        true => "#define BARGE_TARGET_BIG_ENDIAN 1\n/* #undef BARGE_TARGET_LITTLE_ENDIAN */",
+        false => "/* #undef BARGE_TARGET_BIG_ENDIAN */\n#define BARGE_TARGET_LITTLE_ENDIAN 1",
+    };
+    format!(
+        "#define BARGE_TARGET_ARCH \"{}\"\n#define BARGE_TARGET_TRIPLE \"{}\"\n\
+         #define BARGE_TARGET_POINTER_SIZE {}\n{}",
+        properties.architecture, properties.triple, properties.pointer_size, endianness
+    )
+}
+
+pub(crate) fn get_generated_directory(build_directory: &str) -> String {
+    format!("{}/{}", build_directory, GENERATED_DIRECTORY)
+}
+
+pub(crate) fn get_config_header(project: &Project, build_directory: &str) -> Option<String> {
+    project.configure_checks.as_ref().map(|_| {
+        format!(
+            "{}/{}",
+            get_generated_directory(build_directory),
+            CONFIG_HEADER
+        )
+    })
+}
+
+// Turns a header, function, or type name into the name of a macro, in the
+// style of autoconf, for example, sys/types.h becomes SYS_TYPES_H, and void *
+// becomes VOID_P.
+fn get_macro_name(prefix: &str, name: &str) -> String {
+    let mut macro_name = String::from(prefix);
+    for c in name.chars() {
+        let c = match c {
+            '*' => 'P',
+            c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+            _ => '_',
+        };
+        if c != '_' || !macro_name.ends_with('_') {
+            macro_name.push(c);
+        }
+    }
+    macro_name.trim_end_matches('_').to_string()
+}
+
+// The linker flags of the probes, without the linker scripts of the project,
+// which describe the memory layout of the project, not of the probes.
+fn get_probe_ldflags(ldflags: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut arguments = ldflags.split_whitespace();
+    while let Some(argument) = arguments.next() {
+        match argument {
+            "-T" => {
+                arguments.next();
+            }
+            _ if argument.starts_with("-T") => {}
+            _ => result.push(argument),
+        }
+    }
+    result
+}
+
+struct Prober<'a> {
+    project: &'a Project,
+    compiler: String,
+    cflags: Vec<&'a str>,
+    ldflags: Vec<&'a str>,
+    directory: String,
+}
+
+impl Prober<'_> {
+    // Compiles (and optionally links) the probe source, returning whether it
+    // succeeded. Warnings are disabled, as they are irrelevant to the probes.
+    fn try_compile(&self, name: &str, source: &str, link: bool) -> bool {
+        let path = format!("{}/{}.c", self.directory, name);
+        let output = format!("{}/{}.out", self.directory, name);
+        if std::fs::write(&path, source).is_err() {
+            return false;
+        }
+
+        let mut command = self.project.command(&self.compiler);
+        command.args(&self.cflags).arg("-w");
+        if !link {
+            command.arg("-c");
+        }
+        command.arg(&path).arg("-o").arg(&output);
+        if link {
+            command.args(&self.ldflags);
+        }
+        let success = command
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&output);
+        success
+    }
+
+    // The size of a type is found without running anything, so that it works
+    // for cross targets, by compiling assertions on the size with a binary
+    // search.
+    fn find_type_size(&self, name: &str, type_name: &str) -> Option<u64> {
+        let fits = |size: u64| {
+            let source = format!(
+                "#include <stddef.h>\n#include <stdint.h>\n\
+                 typedef char barge_check[(sizeof({}) <= {}) ? 1 : -1];\n",
+                type_name, size
+            );
+            self.try_compile(name, &source, false)
+        };
+
+        let mut upper = 1;
+        while !fits(upper) {
+            upper *= 2;
+            if upper > MAX_TYPE_SIZE {
+                return None;
+            }
+        }
+        let mut lower = upper / 2;
+        while upper - lower > 1 {
+            let middle = (lower + upper) / 2;
+            if fits(middle) {
+                upper = middle;
+            } else {
+                lower = middle;
+            }
+        }
+        Some(upper)
+    }
+
+    fn run(&self, index: usize, probe: &Probe) -> String {
+        let name = format!("probe{}", index);
+        match probe {
+            Probe::Header(header) => {
+                let source = format!("#include <{}>\n", header);
+                let found = self.try_compile(&name, &source, false);
+                get_definition(&get_macro_name("HAVE_", header), found.then_some("1"))
+            }
+            Probe::Function(function) => {
+                let source = format!(
+                    "char {}(void);\nint main(void) {{ return (int) {}(); }}\n",
+                    function, function
+                );
+                let found = self.try_compile(&name, &source, true);
+                get_definition(&get_macro_name("HAVE_", function), found.then_some("1"))
+            }
+            Probe::Symbol(symbol, header) => {
+                let source = format!(
+                    "#include <{}>\nint main(void) {{ (void) {}; return 0; }}\n",
+                    header, symbol
+                );
+                let found = self.try_compile(&name, &source, false);
+                let value = if found { "1" } else { "0" };
+                get_definition(&get_macro_name("HAVE_DECL_", symbol), Some(value))
+            }
+            Probe::Size(type_name) => {
+                let size = self.find_type_size(&name, type_name).map(|s| s.to_string());
+                get_definition(&get_macro_name("SIZEOF_", type_name), size.as_deref())
+            }
+        }
+    }
+}
+
+fn get_definition(name: &str, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("#define {} {}", name, value),
+        None => format!("/* #undef {} */", name),
+    }
+}
+
+fn get_probes(checks: &ConfigureChecks) -> Vec<Probe<'_>> {
+    let headers = checks.headers.iter().flatten().map(|h| Probe::Header(h));
+    let functions = checks
+        .functions
+        .iter()
+        .flatten()
+        .map(|f| Probe::Function(f));
+    let symbols = checks
+        .symbols
+        .iter()
+        .flatten()
+        .map(|s| Probe::Symbol(&s.name, &s.header));
+    let sizes = checks.sizeof.iter().flatten().map(|t| Probe::Size(t));
+    headers
+        .chain(functions)
+        .chain(symbols)
+        .chain(sizes)
+        .collect()
+}
+
+// Evaluates the configure checks of the project with small compilation and
+// linking probes, and writes the results into the generated config.h. The
+// probes only run again if the checks, the flags, or the compiler changed,
+// and the header is only rewritten when its contents change, so that the
+// objects are not rebuilt needlessly.
+pub(crate) fn write_config_header(
+    project: &Project,
+    build_directory: &str,
+    flags: &BuildFlags,
+) -> Result<()> {
+    let (Some(checks), Some(path)) = (
+        &project.configure_checks,
+        get_config_header(project, build_directory),
+    ) else {
+        return Ok(());
+    };
+
+    let directory = get_generated_directory(build_directory);
+    let stamp_path = format!("{}/{}", directory, CONFIGURE_STAMP);
+    let stamp = get_content_hash(&[
+        serde_json::to_string(checks)?.as_bytes(),
+        flags.compile_flags(LanguageKind::C).as_bytes(),
+        flags.ldflags.as_bytes(),
+        get_stored_compiler_identity(build_directory)
+            .unwrap_or_default()
+            .as_bytes(),
+    ]);
+    if Path::new(&path).is_file()
+        && std::fs::read_to_string(&stamp_path).ok() == Some(stamp.clone())
+    {
+        return Ok(());
+    }
+
+    let _section = ProfileSection::start("configure checks");
+    let probes = get_probes(checks);
+    color_println!(BLUE, "Running {} configure check(s)", probes.len());
+    let probe_directory = format!("{}/probes", directory);
+    std::fs::create_dir_all(&probe_directory)?;
+    let compiler = project.compiler(LanguageKind::C);
+    let prober = Prober {
+        project,
+        compiler,
+        cflags: flags
+            .compile_flags(LanguageKind::C)
+            .split_whitespace()
+            .collect(),
+        ldflags: get_probe_ldflags(&flags.ldflags),
+        directory: probe_directory.clone(),
+    };
+    let indices: Vec<usize> = (0..probes.len()).collect();
+    let definitions = parallel_map(&indices, |&index| prober.run(index, &probes[index]));
+    let _ = std::fs::remove_dir(&probe_directory);
+    let target = get_target_definitions(&get_target_properties(project)?);
+
+    let header = format!(
+        "/* Generated by barge from the configure checks of the project file. */\n\
+         #ifndef BARGE_CONFIG_H\n#define BARGE_CONFIG_H\n\n{}\n\n{}\n\n#endif\n",
+        target,
+        definitions.join("\n")
+    );
+    if std::fs::read_to_string(&path).ok().as_ref() != Some(&header) {
+        std::fs::write(&path, header)?;
+    }
+    std::fs::write(stamp_path, stamp)?;
+    Ok(())
+}
@@ -0,0 +1,365 @@
+use crate::languages::LanguageKind;
+use crate::makefile::BuildTarget;
+use crate::project::{
+    collect_source_files, collect_test_files, CollectSourceFilesMode, Project, ProjectType,
+    Toolset, DEFAULT_TOOLSET,
+};
+use crate::result::{BargeError, Result};
+use crate::utilities::attempt_remove_directory;
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE, YELLOW};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const COVERAGE_DIRECTORY: &str = "build/coverage";
+const LLVM_PROFILE_VARIABLE: &str = "LLVM_PROFILE_FILE";
+
+struct FileCoverage {
+    file: String,
+    percent: f64,
+    lines: u64,
+}
+
+fn get_coverage_flags(toolset: &Toolset) -> (&'static str, &'static str) {
+    match toolset {
+        Toolset::Gnu => ("--coverage", "--coverage"),
+        Toolset::Llvm => (
+            "-fprofile-instr-generate -fcoverage-mapping",
+            "-fprofile-instr-generate",
+        ),
+    }
+}
+
+fn get_instrumented_variant(project: &Project) -> Project {
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let (cflags, ldflags) = get_coverage_flags(toolset);
+    let mut variant = project.clone();
+    variant.build_variant = Some(String::from("coverage"));
+    for kind in [LanguageKind::C, LanguageKind::Cpp] {
+        variant.append_custom_flags(kind, cflags);
+    }
+    variant.custom_ldflags =
+        Some(variant.custom_ldflags.clone().unwrap_or_default() + " " + ldflags);
+    variant
+}
+
+fn collect_files(directory: &Path, extension: &str, files: &mut Vec<PathBuf>) {
+    for path in std::fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            collect_files(&path, extension, files);
+        } else if path.extension().is_some_and(|e| e == extension) {
+            files.push(path);
+        }
+    }
+}
+
+// Runs the tests of the project if there are any, or the executable otherwise.
+fn run_instrumented_variant(
+    variant: &Project,
+    target: BuildTarget,
+    arguments: &[String],
+) -> Result<Result<()>> {
+    if !collect_test_files(variant)?.is_empty() {
+        return Ok(variant.test(target, None));
+    }
+    if variant.project_type != ProjectType::Executable {
+        return Err(BargeError::InvalidValue(
+            "Coverage of libraries can only be measured with tests",
+        ));
+    }
+
+    variant.build(target)?;
+    let path = format!("{}/{}", variant.build_directory(target), variant.name);
+    color_println!(BLUE, "Running executable {}", path);
+    let status = Command::new(&path).args(arguments).status()?;
+    if status.success() {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(BargeError::FailedOperation(
+            "The executable exited with an error",
+        )))
+    }
+}
+
+fn parse_gcov_summary(output: &str) -> Vec<FileCoverage> {
+    let mut results = Vec::new();
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        let Some(file) = line
+            .strip_prefix("File '")
+            .and_then(|rest| rest.strip_suffix('\''))
+        else {
+            continue;
+        };
+        let Some(summary) = lines.next().and_then(|l| l.strip_prefix("Lines executed:")) else {
+            continue;
+        };
+        let Some((percent, total)) = summary.split_once("% of ") else {
+            continue;
+        };
+        if let (Ok(percent), Ok(lines)) = (percent.parse(), total.trim().parse()) {
+            results.push(FileCoverage {
+                file: file.to_string(),
+                percent,
+                lines,
+            });
+        }
+    }
+    results
+}
+
+// The sources are recorded relative to the project root in the coverage notes,
+// so gcov runs there, and the annotated sources are moved afterwards.
+fn report_gcov_coverage(variant: &Project, target: BuildTarget) -> Result<Vec<FileCoverage>> {
+    let objects = PathBuf::from(variant.build_directory(target)).join("obj");
+    let mut results = Vec::new();
+    for source in collect_source_files(variant, CollectSourceFilesMode::CCppSourcesOnly)? {
+        let relative = source.strip_prefix("src/").unwrap_or(&source);
+        let output = Command::new("gcov")
+            .args(["--preserve-paths", "--relative-only", "--object-file"])
+            .arg(objects.join(format!("{}.o", relative)))
+            .arg(&source)
+            .stderr(Stdio::null())
+            .output()?;
+        let output = String::from_utf8(output.stdout)?;
+        for created in output.lines().filter_map(|line| {
+            line.strip_prefix("Creating '")
+                .and_then(|rest| rest.strip_suffix('\''))
+        }) {
+            std::fs::rename(created, Path::new(COVERAGE_DIRECTORY).join(created))?;
+        }
+        results.extend(
+            parse_gcov_summary(&output)
+                .into_iter()
+                .filter(|coverage| coverage.file == source),
+        );
+    }
+    Ok(results)
+}
+
+fn get_llvm_binaries(variant: &Project, target: BuildTarget) -> Vec<PathBuf> {
+    let directory = PathBuf::from(variant.build_directory(target));
+    let mut binaries = Vec::new();
+    if variant.project_type == ProjectType::Executable {
+        binaries.push(directory.join(&variant.name));
+    }
+    let tests = std::fs::read_dir(directory.join("tests"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_none());
+    binaries.extend(tests);
+    binaries
+}
+
+fn run_llvm_tool(command: &mut Command, tool: &'static str) -> Result<Vec<u8>> {
+    match command.output() {
+        Ok(output) if output.status.success() => Ok(output.stdout),
+        Ok(output) => {
+            color_eprintln!("{}", String::from_utf8_lossy(&output.stderr).trim());
+            Err(BargeError::FailedOperation(
+                "Failed to process the coverage data",
+            ))
+        }
+        Err(error) => {
+            color_eprintln!("Could not execute {}", tool);
+            Err(error.into())
+        }
+    }
+}
+
+fn report_llvm_coverage(variant: &Project, target: BuildTarget) -> Result<Vec<FileCoverage>> {
+    let mut profiles = Vec::new();
+    collect_files(
+        &Path::new(COVERAGE_DIRECTORY).join("profiles"),
+        "profraw",
+        &mut profiles,
+    );
+    let profdata = Path::new(COVERAGE_DIRECTORY).join("coverage.profdata");
+    run_llvm_tool(
+        Command::new("llvm-profdata")
+            .args(["merge", "-sparse"])
+            .args(&profiles)
+            .arg("-o")
+            .arg(&profdata),
+        "llvm-profdata",
+    )?;
+
+    let binaries = get_llvm_binaries(variant, target);
+    let (first, rest) = binaries.split_first().ok_or(BargeError::NoneOption(
+        "No instrumented binaries were found",
+    ))?;
+    let cov = |subcommand: &str| {
+        let mut command = Command::new("llvm-cov");
+        command.arg(subcommand).arg(first);
+        for binary in rest {
+            command.arg("-object").arg(binary);
+        }
+        command.arg(format!("-instr-profile={}", profdata.display()));
+        command.arg("src");
+        command
+    };
+
+    let text = run_llvm_tool(&mut cov("show"), "llvm-cov")?;
+    std::fs::write(Path::new(COVERAGE_DIRECTORY).join("coverage.txt"), text)?;
+    run_llvm_tool(
+        cov("show")
+            .arg("-format=html")
+            .arg(format!("-output-dir={}/html", COVERAGE_DIRECTORY)),
+        "llvm-cov",
+    )?;
+
+    let summary = run_llvm_tool(cov("export").arg("-summary-only"), "llvm-cov")?;
+    let summary: serde_json::Value = serde_json::from_slice(&summary)?;
+    let files = summary["data"][0]["files"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    Ok(files
+        .iter()
+        .map(|file| FileCoverage {
+            file: file["filename"].as_str().unwrap_or_default().to_string(),
+            percent: file["summary"]["lines"]["percent"].as_f64().unwrap_or(0.0),
+            lines: file["summary"]["lines"]["count"].as_u64().unwrap_or(0),
+        })
+        .collect())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Writes a summary table and the annotated sources produced by gcov as HTML.
+fn write_gcov_html(results: &[FileCoverage]) -> Result<()> {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Coverage</title>\n\
+         <style>body{font-family:sans-serif}pre{background:#f6f6f6}\
+         .missed{background:#fcc}.hit{background:#cfc}</style></head><body>\n\
+         <h1>Coverage</h1>\n<table>\n<tr><th>File</th><th>Lines</th><th>Coverage</th></tr>\n",
+    );
+    for result in results {
+        html += &format!(
+            "<tr><td><a href=\"#{0}\">{0}</a></td><td>{1}</td><td>{2:.2}%</td></tr>\n",
+            escape_html(&result.file),
+            result.lines,
+            result.percent
+        );
+    }
+    html += "</table>\n";
+
+    let mut reports = Vec::new();
+    collect_files(Path::new(COVERAGE_DIRECTORY), "gcov", &mut reports);
+    reports.sort();
+    for report in reports {
+        let content = std::fs::read_to_string(&report)?;
+        let source = content
+            .lines()
+            .next()
+            .and_then(|line| line.split_once(":Source:"))
+            .map_or(String::new(), |(_, source)| source.to_string());
+        if !results.iter().any(|result| result.file == source) {
+            continue;
+        }
+        html += &format!("<h2 id=\"{0}\">{0}</h2>\n<pre>", escape_html(&source));
+        for line in content.lines() {
+            let count = line.split(':').next().unwrap_or_default().trim();
+            let class = match count {
+                "#####" | "=====" => "missed",
+                "-" => "",
+                _ => "hit",
+            };
+            html += &format!("<span class=\"{}\">{}</span>\n", class, escape_html(line));
+        }
+        html += "</pre>\n";
+    }
+    html += "</body></html>\n";
+    std::fs::write(Path::new(COVERAGE_DIRECTORY).join("index.html"), html)?;
+    Ok(())
+}
+
+fn write_text_summary(results: &[FileCoverage]) -> Result<()> {
+    let summary: String = results
+        .iter()
+        .map(|result| {
+            format!(
+                "{:>7.2}% {:>6} {}\n",
+                result.percent, result.lines, result.file
+            )
+        })
+        .collect();
+    std::fs::write(Path::new(COVERAGE_DIRECTORY).join("summary.txt"), summary)?;
+    Ok(())
+}
+
+pub(crate) fn report_coverage(
+    project: &Project,
+    target: BuildTarget,
+    arguments: &[String],
+) -> Result<()> {
+    project.check_host_target()?;
+    let variant = get_instrumented_variant(project);
+    let toolset = *variant.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+
+    // Stale counters of previous runs would be merged into the results.
+    attempt_remove_directory(COVERAGE_DIRECTORY)?;
+    std::fs::create_dir_all(COVERAGE_DIRECTORY)?;
+    let mut counters = Vec::new();
+    collect_files(
+        Path::new(&variant.build_directory(target)),
+        "gcda",
+        &mut counters,
+    );
+    for counter in counters {
+        std::fs::remove_file(counter)?;
+    }
+    let profiles = std::env::current_dir()?
+        .join(COVERAGE_DIRECTORY)
+        .join("profiles/%p.profraw");
+    std::env::set_var(LLVM_PROFILE_VARIABLE, profiles);
+
+    let run_result = run_instrumented_variant(&variant, target, arguments)?;
+    color_println!(BLUE, "Collecting coverage data");
+    let mut results = match toolset {
+        Toolset::Gnu => report_gcov_coverage(&variant, target)?,
+        Toolset::Llvm => report_llvm_coverage(&variant, target)?,
+    };
+    results.sort_by(|a, b| a.file.cmp(&b.file));
+    if toolset == Toolset::Gnu {
+        write_gcov_html(&results)?;
+    }
+    write_text_summary(&results)?;
+
+    let root = std::env::current_dir()?;
+    let (mut covered, mut total) = (0.0, 0);
+    for result in &results {
+        let file = Path::new(&result.file);
+        let file = file.strip_prefix(&root).unwrap_or(file);
+        let style = if result.percent >= 80.0 {
+            *GREEN
+        } else if result.percent >= 50.0 {
+            *YELLOW
+        } else {
+            *RED
+        };
+        color_println!(style, "{:>7.2}% {}", result.percent, file.display());
+        covered += result.percent * result.lines as f64 / 100.0;
+        total += result.lines;
+    }
+    if total > 0 {
+        color_println!(
+            WHITE,
+            "{:>7.2}% total ({} lines)",
+            covered * 100.0 / total as f64,
+            total
+        );
+    }
+    color_println!(BLUE, "Coverage report written to {}", COVERAGE_DIRECTORY);
+    run_result
+}
@@ -0,0 +1,285 @@
+use crate::analysis::load_analysis_cache;
+use crate::project::Project;
+use crate::records::{load_build_record, load_test_results};
+use crate::result::{print_error, Result};
+use crate::size::{SizeManifest, SIZE_MANIFEST};
+use crate::{color_println, BLUE, NO_COLOR, WHITE};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+const DASHBOARD_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1 { border-bottom: 2px solid #36c; }
+h2 { color: #36c; margin-top: 1.5em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.25em 0.75em; border-bottom: 1px solid #ddd; }
+td.number { text-align: right; font-family: monospace; }
+pre { background: #f4f4f4; padding: 0.5em; overflow-x: auto; }
+.success { color: #282; font-weight: bold; }
+.failure { color: #c22; font-weight: bold; }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn get_build_directories() -> Vec<String> {
+    let mut directories: Vec<String> = std::fs::read_dir("build")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.path().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    directories.sort();
+    directories
+}
+
+fn collect_report_files(directory: &Path, files: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if name != "obj" && !name.ends_with("-worktree") {
+                collect_report_files(&path, files);
+            }
+        } else if name.ends_with(".json") || name.ends_with(".html") || name.ends_with(".txt") {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+fn render_builds(html: &mut String) {
+    html.push_str("<h2>Builds</h2>\n<table>\n");
+    html.push_str("<tr><th>Directory</th><th>Status</th><th>Duration</th><th>Finished</th><th>Commit</th></tr>\n");
+    for directory in get_build_directories() {
+        if let Some(record) = load_build_record(&directory) {
+            let (class, status) = if record.success {
+                ("success", "succeeded")
+            } else {
+                ("failure", "failed")
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td class=\"number\">{:.2} s</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&directory),
+                class,
+                status,
+                record.duration,
+                escape_html(&record.timestamp),
+                escape_html(record.commit.as_deref().unwrap_or("-")),
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+}
+
+fn render_tests(html: &mut String) {
+    for directory in get_build_directories() {
+        let results = match load_test_results(&directory) {
+            Some(results) => results,
+            None => continue,
+        };
+
+        let passed = results.iter().filter(|result| result.passed).count();
+        html.push_str(&format!(
+            "<h2>Tests of {}</h2>\n<p>{} of {} test(s) passed.</p>\n<table>\n<tr><th>Test</th><th>Result</th><th>Duration</th></tr>\n",
+            escape_html(&directory),
+            passed,
+            results.len()
+        ));
+        for result in results {
+            let (class, status) = if result.passed {
+                ("success", "passed")
+            } else {
+                ("failure", "failed")
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td class=\"number\">{:.2} s</td></tr>\n",
+                escape_html(&result.source),
+                class,
+                status,
+                result.duration
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+fn render_sizes(html: &mut String) {
+    for directory in get_build_directories() {
+        let manifest: SizeManifest =
+            match std::fs::read_to_string(format!("{}/{}", directory, SIZE_MANIFEST))
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+            {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+
+        let (text, data, bss) = manifest
+            .objects
+            .values()
+            .fold((0, 0, 0), |(text, data, bss), object| {
+                (text + object.text, data + object.data, bss + object.bss)
+            });
+        html.push_str(&format!(
+            "<h2>Size of {}</h2>\n<p>text: {}, data: {}, bss: {}</p>\n<table>\n<tr><th>Symbol</th><th>Size</th></tr>\n",
+            escape_html(&directory),
+            text,
+            data,
+            bss
+        ));
+
+        let mut symbols: Vec<(&String, &u64)> = manifest.symbols.iter().collect();
+        symbols.sort_by(|(_, a), (_, b)| b.cmp(a));
+        for (symbol, size) in symbols.iter().take(10) {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td></tr>\n",
+                escape_html(symbol),
+                size
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+fn render_analysis(html: &mut String) {
+    let cache = load_analysis_cache();
+    if cache.is_empty() {
+        return;
+    }
+
+    let mut flagged: Vec<_> = cache
+        .iter()
+        .filter(|(_, entry)| !entry.diagnostics.is_empty())
+        .collect();
+    flagged.sort_by_key(|(key, _)| *key);
+    html.push_str(&format!(
+        "<h2>Static Analysis</h2>\n<p>{} of {} analyzed file(s) have diagnostics.</p>\n",
+        flagged.len(),
+        cache.len()
+    ));
+    for (key, entry) in flagged {
+        html.push_str(&format!(
+            "<h3>{}</h3>\n<pre>{}</pre>\n",
+            escape_html(key),
+            escape_html(&entry.diagnostics)
+        ));
+    }
+}
+
+fn render_files(html: &mut String) {
+    let mut files = Vec::new();
+    collect_report_files(Path::new("build"), &mut files);
+    if files.is_empty() {
+        return;
+    }
+
+    files.sort();
+    html.push_str("<h2>Reports</h2>\n<ul>\n");
+    for file in files {
+        html.push_str(&format!(
+            "<li><a href=\"/{}\">{}</a></li>\n",
+            escape_html(&file),
+            escape_html(&file)
+        ));
+    }
+    html.push_str("</ul>\n");
+}
+
+fn render_dashboard(project: &Project) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} dashboard</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{} {}</h1>\n",
+        escape_html(&project.name),
+        DASHBOARD_STYLE,
+        escape_html(&project.name),
+        escape_html(&project.version)
+    );
+    render_builds(&mut html);
+    render_tests(&mut html);
+    render_sizes(&mut html);
+    render_analysis(&mut html);
+    render_files(&mut html);
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+pub(crate) fn get_content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next().unwrap_or_default() {
+        "html" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+pub(crate) fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<()> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)?;
+    Ok(())
+}
+
+fn handle_connection(project: &Project, mut stream: TcpStream) -> Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path);
+
+    if path == "/" {
+        let html = render_dashboard(project);
+        return respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            html.as_bytes(),
+        );
+    }
+
+    let file = path.trim_start_matches('/');
+    let readable = file.starts_with("build/") && !file.split('/').any(|part| part == "..");
+    match std::fs::read(file).ok().filter(|_| readable) {
+        Some(contents) => respond(&mut stream, "200 OK", get_content_type(file), &contents),
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+pub(crate) fn serve_dashboard(project: &Project, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    color_println!(
+        BLUE,
+        "Serving the project dashboard at http://127.0.0.1:{}/",
+        port
+    );
+    color_println!(WHITE, "Press Ctrl+C to stop");
+
+    for stream in listener.incoming() {
+        if let Err(error) = handle_connection(project, stream?) {
+            print_error(&error);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use crate::languages::LanguageKind;
+use crate::makefile::{resolve_build_flags, BuildTarget};
+use crate::project::{Project, ProjectType};
+use crate::result::{BargeError, Result};
+use crate::{color_println, BLUE, GREEN, NO_COLOR, WHITE};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SECTION_FLAGS: &str = "-ffunction-sections -fdata-sections";
+const GC_SECTIONS_FLAGS: &str = "-Wl,--gc-sections";
+
+fn parse_removed_section(line: &str) -> Option<(String, String)> {
+    if let Some(rest) = line.split("removing unused section '").nth(1) {
+        let (section, rest) = rest.split_once('\'')?;
+        let file = rest.split("in file '").nth(1)?.trim_end_matches('\'');
+        Some((file.to_string(), section.to_string()))
+    } else {
+        let rest = line.split("removing unused section ").nth(1)?;
+        let (file, section) = rest.rsplit_once(":(")?;
+        Some((file.to_string(), section.trim_end_matches(')').to_string()))
+    }
+}
+
+fn classify_section(section: &str) -> Option<(&'static str, &str)> {
+    [
+        (".text.", "function"),
+        (".data.rel.ro.", "object"),
+        (".data.", "object"),
+        (".rodata.", "constant"),
+        (".bss.", "object"),
+    ]
+    .iter()
+    .find_map(|(prefix, kind)| section.strip_prefix(prefix).map(|name| (*kind, name)))
+}
+
+fn demangle(symbols: &[String]) -> Vec<String> {
+    let filter = Command::new("c++filt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn();
+    let mut filter = match filter {
+        Ok(filter) => filter,
+        Err(_) => return symbols.to_vec(),
+    };
+
+    if let Some(stdin) = filter.stdin.as_mut() {
+        let _ = stdin.write_all((symbols.join("\n") + "\n").as_bytes());
+    }
+
+    match filter.wait_with_output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect(),
+        _ => symbols.to_vec(),
+    }
+}
+
+pub(crate) fn report_dead_code(project: &Project, target: BuildTarget) -> Result<()> {
+    if project.project_type == ProjectType::StaticLibrary {
+        return Err(BargeError::InvalidValue(
+            "Dead code reports are only available for executables and shared libraries",
+        ));
+    }
+
+    let mut variant = project.clone();
+    variant.build_variant = Some(String::from("gc"));
+    for kind in [LanguageKind::C, LanguageKind::Cpp] {
+        variant.append_custom_flags(kind, SECTION_FLAGS);
+    }
+    variant.custom_ldflags =
+        Some(variant.custom_ldflags.clone().unwrap_or_default() + " " + GC_SECTIONS_FLAGS);
+    variant.build(target)?;
+
+    color_println!(BLUE, "Collecting unreferenced sections");
+    let build_directory = variant.build_directory(target);
+    let objects = Command::new("find")
+        .arg(format!("{}/obj", build_directory))
+        .args(["-type", "f", "-name", "*.o"])
+        .output()?
+        .stdout;
+    let objects: Vec<&str> = std::str::from_utf8(&objects)?.lines().collect();
+
+    let cpp_compiler = variant.compiler(LanguageKind::Cpp);
+    let shared_flag = if variant.project_type == ProjectType::SharedLibrary {
+        vec!["-shared"]
+    } else {
+        vec![]
+    };
+    let ldflags = resolve_build_flags(&variant, target)?.ldflags;
+    let link = variant
+        .command(&cpp_compiler)
+        .args(shared_flag)
+        .args(&objects)
+        .args(["-o", "/dev/null"])
+        .args(ldflags.split_whitespace())
+        .arg("-Wl,--print-gc-sections")
+        .output()?;
+    let log = String::from_utf8(link.stderr)?;
+
+    let mut removed: BTreeMap<String, Vec<(&str, String)>> = BTreeMap::new();
+    for (file, section) in log.lines().filter_map(parse_removed_section) {
+        if !objects.contains(&file.as_str()) {
+            continue;
+        }
+        if let Some((kind, name)) = classify_section(&section) {
+            removed
+                .entry(file)
+                .or_default()
+                .push((kind, name.to_string()));
+        }
+    }
+
+    if removed.is_empty() {
+        color_println!(GREEN, "No unreferenced functions or objects were found");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    for (file, symbols) in &removed {
+        color_println!(WHITE, "{}", file);
+        let names: Vec<String> = symbols.iter().map(|(_, name)| name.clone()).collect();
+        for ((kind, _), name) in symbols.iter().zip(demangle(&names)) {
+            println!("    {} {}", kind, name);
+        }
+        total += symbols.len();
+    }
+    color_println!(
+        BLUE,
+        "Found {} unreferenced function(s) and object(s)",
+        total
+    );
+    Ok(())
+}
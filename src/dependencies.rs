@@ -0,0 +1,316 @@
+use crate::languages::{get_language, LanguageKind};
+use crate::project::{get_find_name_arguments, Project};
+use crate::result::Result;
+use crate::selfprofile::ProfileSection;
+use crate::utilities::parallel_map;
+use crate::{color_println, BLUE, NO_COLOR};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const DEPENDENCY_CACHE: &str = ".dependencies";
+const COMPILER_STAMP: &str = ".compiler";
+
+pub(crate) fn parse_dependencies(rules: &str) -> Vec<(String, Vec<String>)> {
+    rules
+        .replace("\\\n", " ")
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(target, prerequisites)| {
+            (
+                target.trim().to_string(),
+                prerequisites
+                    .split_whitespace()
+                    .map(|prerequisite| prerequisite.to_string())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+const INCLUDE_DIRECTORIES: [&str; 2] = ["include", "src"];
+
+// Returns the header named by an #include directive, along with whether it
+// was written with quotes (searched next to the including file first).
+fn parse_include_directive(line: &str) -> Option<(&str, bool)> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let header = directive.strip_prefix("include")?.trim_start();
+    if let Some(header) = header.strip_prefix('"') {
+        header.split_once('"').map(|(name, _)| (name, true))
+    } else if let Some(header) = header.strip_prefix('<') {
+        header.split_once('>').map(|(name, _)| (name, false))
+    } else {
+        None
+    }
+}
+
+// Removes the . and .. components of a relative path without touching the
+// file system, so that every header is recorded under a single name.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Collects the headers included by the sources of the project, without
+// invoking the compiler. Headers that are not found in the directory of the
+// including file or in the include directories of the project are system
+// headers, and are not tracked.
+#[derive(Default)]
+struct IncludeScanner {
+    headers: Mutex<HashMap<PathBuf, Vec<PathBuf>>>,
+}
+
+impl IncludeScanner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<PathBuf>>> {
+        self.headers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn resolve(file: &Path, header: &str, quoted: bool) -> Option<PathBuf> {
+        let local = file
+            .parent()
+            .filter(|_| quoted)
+            .map(|parent| parent.join(header));
+        local
+            .into_iter()
+            .chain(
+                INCLUDE_DIRECTORIES
+                    .iter()
+                    .map(|dir| Path::new(dir).join(header)),
+            )
+            .find(|candidate| candidate.is_file())
+            .map(|candidate| normalize_path(&candidate))
+    }
+
+    fn get_direct_includes(file: &Path) -> Vec<PathBuf> {
+        let contents = std::fs::read(file).unwrap_or_default();
+        String::from_utf8_lossy(&contents)
+            .lines()
+            .filter_map(parse_include_directive)
+            .filter_map(|(header, quoted)| IncludeScanner::resolve(file, header, quoted))
+            .collect()
+    }
+
+    // Returns every header reachable from the file, in a stable order.
+    fn scan(&self, file: &Path) -> Vec<String> {
+        let mut visited = BTreeSet::new();
+        let mut pending = IncludeScanner::get_direct_includes(file);
+        while let Some(header) = pending.pop() {
+            if !visited.insert(header.clone()) {
+                continue;
+            }
+            let known = self.lock().get(&header).cloned();
+            let includes = known.unwrap_or_else(|| {
+                let includes = IncludeScanner::get_direct_includes(&header);
+                self.lock().insert(header.clone(), includes.clone());
+                includes
+            });
+            pending.extend(includes);
+        }
+        visited
+            .into_iter()
+            .map(|header| header.to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+fn is_cached_rule_valid(rule: &str, cache_time: SystemTime) -> bool {
+    parse_dependencies(rule)
+        .iter()
+        .flat_map(|(_, prerequisites)| prerequisites)
+        .all(|prerequisite| {
+            std::fs::metadata(prerequisite)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|time| time < cache_time)
+        })
+}
+
+fn load_dependency_cache(path: &str) -> (HashMap<String, String>, Option<SystemTime>) {
+    let cache_time = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let cache = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    (cache, cache_time)
+}
+
+pub(crate) fn get_dependencies_for_project(
+    project: &Project,
+    build_directory: &str,
+    kind: LanguageKind,
+) -> Result<String> {
+    let _section = ProfileSection::start("dependency scan");
+    let sources = Command::new("find")
+        .arg("src")
+        .args(vec!["-type", "f"])
+        .args(get_find_name_arguments(
+            &get_language(kind).extensions(project),
+        ))
+        .output()?
+        .stdout;
+    let mut sources: Vec<&str> = std::str::from_utf8(&sources)?.split('\n').collect();
+    sources.retain(|source| !source.is_empty());
+
+    let cache_path = format!("{}/{}", build_directory, DEPENDENCY_CACHE);
+    let (mut cache, cache_time) = load_dependency_cache(&cache_path);
+    let mut cache_changed = false;
+
+    // The sources are scanned in parallel, sharing the headers scanned so far.
+    let scanner = IncludeScanner::default();
+    let rules = parallel_map(&sources, |file| {
+        if let (Some(rule), Some(cache_time)) = (cache.get(*file), cache_time) {
+            if is_cached_rule_valid(rule, cache_time) {
+                return (rule.clone(), false);
+            }
+        }
+
+        let object = if let Some(name) = file.strip_prefix("src/") {
+            format!("{}/obj/{}.o", build_directory, name)
+        } else {
+            String::from("")
+        };
+
+        let mut prerequisites = vec![file.to_string()];
+        prerequisites.extend(scanner.scan(Path::new(file)));
+        (format!("{}: {}\n", object, prerequisites.join(" ")), true)
+    });
+
+    let mut dependencies = Vec::new();
+    for (file, (rule, scanned)) in sources.iter().zip(rules) {
+        if scanned {
+            cache.insert(file.to_string(), rule.clone());
+            cache_changed = true;
+        }
+        dependencies.push(rule);
+    }
+
+    if cache_changed {
+        std::fs::create_dir_all(build_directory)?;
+        std::fs::write(&cache_path, serde_json::to_string(&cache)?)?;
+    }
+
+    Ok(dependencies.join("").trim_end().to_string())
+}
+
+// Removes the cached dependencies only, the compiler stamp is kept, as every
+// object depends on it.
+pub(crate) fn discard_dependency_cache(build_directory: &str) -> Result<()> {
+    let path = format!("{}/{}", build_directory, DEPENDENCY_CACHE);
+    if std::path::Path::new(&path).exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn get_compiler_identity(project: &Project) -> Result<String> {
+    let cpp_compiler = project.compiler(LanguageKind::Cpp);
+    let output = project.command(&cpp_compiler).arg("--version").output()?;
+    let version = String::from_utf8(output.stdout)?;
+    Ok(version.lines().next().unwrap_or_default().to_string())
+}
+
+pub(crate) fn get_stored_compiler_identity(build_directory: &str) -> Option<String> {
+    std::fs::read_to_string(format!("{}/{}", build_directory, COMPILER_STAMP)).ok()
+}
+
+pub(crate) fn check_compiler_identity(project: &Project, build_directory: &str) -> Result<()> {
+    let _section = ProfileSection::start("compiler identity");
+    let identity = get_compiler_identity(project)?;
+    let stored = get_stored_compiler_identity(build_directory);
+    if stored.as_ref() == Some(&identity) {
+        return Ok(());
+    }
+
+    if stored.is_some() {
+        color_println!(
+            BLUE,
+            "The compiler has changed, dependency information will be regenerated"
+        );
+    }
+    discard_dependency_cache(build_directory)?;
+    std::fs::create_dir_all(build_directory)?;
+    std::fs::write(format!("{}/{}", build_directory, COMPILER_STAMP), identity)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_directives_are_parsed() {
+        assert_eq!(
+            parse_include_directive("#include \"a/b.h\""),
+            Some(("a/b.h", true))
+        );
+        assert_eq!(
+            parse_include_directive("  #  include <vector> // comment"),
+            Some(("vector", false))
+        );
+        assert_eq!(parse_include_directive("#include HEADER"), None);
+        assert_eq!(parse_include_directive("#include \"unterminated.h"), None);
+        assert_eq!(parse_include_directive("#define include \"a.h\""), None);
+        assert_eq!(parse_include_directive("// #include \"a.h\""), None);
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        assert_eq!(
+            normalize_path(Path::new("src/./a/../b.h")),
+            PathBuf::from("src/b.h")
+        );
+        assert_eq!(
+            normalize_path(Path::new("src/../../a.h")),
+            PathBuf::from("../a.h")
+        );
+        assert_eq!(
+            normalize_path(Path::new("../include/a.h")),
+            PathBuf::from("../include/a.h")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/tmp/src/../include/a.h")),
+            PathBuf::from("/tmp/include/a.h")
+        );
+    }
+
+    #[test]
+    fn included_headers_are_scanned() {
+        let root = std::env::temp_dir().join(format!("barge-includes-{}", std::process::id()));
+        let files = [
+            (
+                "app/main.c",
+                "#include \"local.h\"\n#include <beside.h>\n#include \"missing.h\"\n#include \"../shared/common.h\"\n",
+            ),
+            ("app/local.h", "#include \"local.h\"\n"),
+            ("app/beside.h", ""),
+            ("shared/common.h", "#include \"extra.h\"\n"),
+            ("shared/extra.h", "#include \"../shared/common.h\"\n"),
+        ];
+        for (path, contents) in files {
+            std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            std::fs::write(root.join(path), contents).unwrap();
+        }
+
+        // The headers included with angle brackets are not looked up next to
+        // the including file, and the missing headers are left out.
+        let headers = IncludeScanner::default().scan(&root.join("app/main.c"));
+        let expected: Vec<String> = ["app/local.h", "shared/common.h", "shared/extra.h"]
+            .iter()
+            .map(|path| root.join(path).to_string_lossy().into_owned())
+            .collect();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(headers, expected);
+    }
+}
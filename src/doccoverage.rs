@@ -0,0 +1,221 @@
+use crate::project::{Project, SourceKind};
+use crate::result::{BargeError, Result};
+use crate::utilities::attempt_remove_directory;
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE, YELLOW};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const COVERAGE_DIRECTORY: &str = "build/doc-coverage";
+const COVERAGE_OVERRIDES: &str = "
+GENERATE_XML=YES
+XML_OUTPUT=xml
+GENERATE_HTML=NO
+GENERATE_LATEX=NO
+EXTRACT_ALL=YES
+EXTRACT_PRIVATE=NO
+WARN_IF_UNDOCUMENTED=NO
+QUIET=YES
+";
+
+struct Entity {
+    header: String,
+    documented: bool,
+}
+
+fn get_attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let length = tag[start..].find('"')?;
+    Some(tag[start..start + length].to_string())
+}
+
+fn get_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}", name), format!("</{}>", name));
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        if !after.starts_with([' ', '>', '/']) {
+            rest = after;
+            continue;
+        }
+        let end = match after.find(&close) {
+            Some(end) => start + open.len() + end + close.len(),
+            None => break,
+        };
+        elements.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    elements
+}
+
+fn has_description(element: &str) -> bool {
+    ["briefdescription", "detaileddescription"]
+        .iter()
+        .flat_map(|name| get_elements(element, name))
+        .any(|description| {
+            let mut text = String::new();
+            let mut in_tag = false;
+            for c in description.chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    c if !in_tag => text.push(c),
+                    _ => {}
+                }
+            }
+            !text.trim().is_empty()
+        })
+}
+
+fn get_location(element: &str) -> Option<String> {
+    let location = get_elements(element, "location")
+        .into_iter()
+        .next()
+        .or_else(|| {
+            let start = element.find("<location ")?;
+            let end = element[start..].find("/>")?;
+            Some(&element[start..start + end])
+        })?;
+    get_attribute(location, "declfile").or_else(|| get_attribute(location, "file"))
+}
+
+fn get_own_content(compound: &str) -> String {
+    let mut own = compound.to_string();
+    for member in get_elements(compound, "memberdef") {
+        own = own.replacen(member, "", 1);
+    }
+    own
+}
+
+fn collect_entities(project: &Project, xml: &str, entities: &mut HashMap<String, Entity>) {
+    let mut record = |id: Option<String>, element: &str| {
+        let (id, header) = match (id, get_location(element)) {
+            (Some(id), Some(header)) => (id, header),
+            _ => return,
+        };
+        if project.source_kind(&header) != Some(SourceKind::Header) {
+            return;
+        }
+        let documented = has_description(element);
+        entities
+            .entry(id)
+            .and_modify(|entity| entity.documented |= documented)
+            .or_insert(Entity { header, documented });
+    };
+
+    for compound in get_elements(xml, "compounddef") {
+        let tag = &compound[..compound.find('>').unwrap_or(compound.len())];
+        let kind = get_attribute(tag, "kind").unwrap_or_default();
+        let public = get_attribute(tag, "prot").is_none_or(|prot| prot == "public");
+        if (kind == "class" || kind == "struct") && public {
+            record(get_attribute(tag, "id"), &get_own_content(compound));
+        }
+
+        for member in get_elements(compound, "memberdef") {
+            let tag = &member[..member.find('>').unwrap_or(member.len())];
+            let function = get_attribute(tag, "kind").as_deref() == Some("function");
+            let public = get_attribute(tag, "prot").as_deref() == Some("public");
+            if function && public {
+                record(get_attribute(tag, "id"), member);
+            }
+        }
+    }
+}
+
+fn generate_xml(project: &Project) -> Result<()> {
+    if !Path::new("Doxyfile").exists() {
+        return Err(BargeError::FailedOperation(
+            "Doxyfile is missing from the project directory",
+        ));
+    }
+
+    attempt_remove_directory(COVERAGE_DIRECTORY)?;
+    std::fs::create_dir_all(COVERAGE_DIRECTORY)?;
+    let configuration = std::fs::read_to_string("Doxyfile")?
+        + COVERAGE_OVERRIDES
+        + &format!("OUTPUT_DIRECTORY={}\n", COVERAGE_DIRECTORY);
+
+    let mut doxygen = Command::new("doxygen")
+        .arg("-")
+        .env("BARGE_PROJECT_NAME", &project.name)
+        .env("BARGE_PROJECT_VERSION", &project.version)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    doxygen
+        .stdin
+        .as_mut()
+        .ok_or(BargeError::NoneOption("Could not interact with doxygen"))?
+        .write_all(configuration.as_bytes())?;
+
+    if doxygen.wait()?.success() {
+        Ok(())
+    } else {
+        Err(BargeError::FailedOperation(
+            "Failed to generate documentation using doxygen",
+        ))
+    }
+}
+
+pub(crate) fn report_doc_coverage(project: &Project, fail_under: Option<f64>) -> Result<()> {
+    color_println!(BLUE, "Measuring documentation coverage of project headers");
+    generate_xml(project)?;
+
+    let mut entities = HashMap::new();
+    for entry in std::fs::read_dir(format!("{}/xml", COVERAGE_DIRECTORY))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "xml") {
+            collect_entities(project, &std::fs::read_to_string(path)?, &mut entities);
+        }
+    }
+
+    let mut headers: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for entity in entities.values() {
+        let (documented, total) = headers.entry(entity.header.clone()).or_default();
+        *documented += usize::from(entity.documented);
+        *total += 1;
+    }
+
+    for (header, (documented, total)) in &headers {
+        color_println!(
+            WHITE,
+            "{:>6.1}% {} ({}/{})",
+            100.0 * *documented as f64 / *total as f64,
+            header,
+            documented,
+            total
+        );
+    }
+
+    let documented: usize = headers.values().map(|(documented, _)| documented).sum();
+    let total: usize = headers.values().map(|(_, total)| total).sum();
+    if total == 0 {
+        color_println!(YELLOW, "No public functions or classes were found");
+        return Ok(());
+    }
+
+    let coverage = 100.0 * documented as f64 / total as f64;
+    match fail_under {
+        Some(threshold) if coverage < threshold => {
+            color_eprintln!(
+                "Documentation coverage is {:.1}%, below the threshold of {:.1}%",
+                coverage,
+                threshold
+            );
+            Err(BargeError::FailedOperation(
+                "Documentation coverage is below the threshold",
+            ))
+        }
+        _ => {
+            color_println!(
+                GREEN,
+                "Documentation coverage is {:.1}% ({}/{})",
+                coverage,
+                documented,
+                total
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::dashboard::{get_content_type, respond};
+use crate::fastpath::get_source_fingerprint;
+use crate::project::Project;
+use crate::result::{print_error, BargeError, Result};
+use crate::watch::wait_for_changes;
+use crate::{color_println, BLUE, NO_COLOR, WHITE, YELLOW};
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(target_os = "macos")]
+const DEFAULT_BROWSER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_BROWSER: &str = "xdg-open";
+
+// Returns the value of the option in the Doxyfile, where the last assignment of
+// an option takes effect.
+fn get_doxygen_option(configuration: &str, name: &str) -> Option<String> {
+    configuration
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(option, _)| option.trim() == name)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .next_back()
+}
+
+// The directory of the HTML documentation, as configured in the Doxyfile.
+fn get_html_directory() -> Result<PathBuf> {
+    let configuration = std::fs::read_to_string("Doxyfile")?;
+    let output = get_doxygen_option(&configuration, "OUTPUT_DIRECTORY").unwrap_or_default();
+    let html = get_doxygen_option(&configuration, "HTML_OUTPUT")
+        .filter(|html| !html.is_empty())
+        .unwrap_or(String::from("html"));
+    Ok(Path::new(&output).join(html))
+}
+
+// Opens the page in the browser given by the BROWSER environment variable, or
+// in the default browser of the desktop.
+fn open_in_browser(page: &str) -> Result<()> {
+    let browser = std::env::var("BROWSER").unwrap_or(String::from(DEFAULT_BROWSER));
+    color_println!(BLUE, "Opening {}", page);
+    let status = Command::new(&browser).arg(page).status();
+    if !status.is_ok_and(|status| status.success()) {
+        color_println!(YELLOW, "Could not open the page using {}", browser);
+    }
+    Ok(())
+}
+
+pub(crate) fn open_documentation() -> Result<()> {
+    let index = get_html_directory()?.join("index.html");
+    if !index.exists() {
+        return Err(BargeError::FailedOperation(
+            "The HTML documentation was not generated",
+        ));
+    }
+    open_in_browser(&index.canonicalize()?.to_string_lossy())
+}
+
+fn handle_connection(directory: &Path, mut stream: TcpStream) -> Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let file = path.trim_start_matches('/');
+    let mut file = directory.join(file);
+    if file.is_dir() {
+        file = file.join("index.html");
+    }
+    let readable = !path.split('/').any(|part| part == "..");
+    match std::fs::read(&file).ok().filter(|_| readable) {
+        Some(contents) => respond(
+            &mut stream,
+            "200 OK",
+            get_content_type(&file.to_string_lossy()),
+            &contents,
+        ),
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+// Serves the HTML documentation locally. If watching, the documentation is
+// generated again whenever the sources change.
+pub(crate) fn serve_documentation(
+    project: &Project,
+    port: u16,
+    open: bool,
+    watch: bool,
+) -> Result<()> {
+    let directory = get_html_directory()?;
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let address = format!("http://127.0.0.1:{}/", port);
+    color_println!(
+        BLUE,
+        "Serving the documentation of {} at {}",
+        project.name,
+        address
+    );
+    color_println!(WHITE, "Press Ctrl+C to stop");
+    if open {
+        open_in_browser(&address)?;
+    }
+
+    std::thread::scope(|scope| {
+        if watch {
+            scope.spawn(|| {
+                let mut fingerprint = get_source_fingerprint(&[]);
+                loop {
+                    fingerprint = match wait_for_changes(&fingerprint, &[]) {
+                        Ok(fingerprint) => fingerprint,
+                        Err(error) => return print_error(&error),
+                    };
+                    if let Err(error) = project.document() {
+                        print_error(&error);
+                    }
+                }
+            });
+        }
+        for stream in listener.incoming() {
+            if let Err(error) = stream
+                .map_err(BargeError::from)
+                .and_then(|stream| handle_connection(&directory, stream))
+            {
+                print_error(&error);
+            }
+        }
+    });
+    Ok(())
+}
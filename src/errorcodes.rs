@@ -0,0 +1,342 @@
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED, WHITE};
+
+pub(crate) struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "B0001",
+        title: "Project file not found",
+        explanation: "\
+barge looks for barge.json or barge.toml in the current directory, and then in
+each of its parent directories, up to the root of the filesystem. This error
+means that none of them contains a project file.
+
+Possible fixes:
+- Change to the directory of the project (or one of its subdirectories).
+- Create a new project with `barge init <NAME>`, or a project file in an
+  existing directory with `barge init --json <NAME>`.",
+    },
+    ErrorCode {
+        code: "B0002",
+        title: "Malformed JSON or TOML file",
+        explanation: "\
+A file read by barge (most likely barge.json or barge.toml, but also barge.lock
+or one of the caches in the build directory) could not be parsed, or one of its
+fields has a value of the wrong type. The message shows the line (and for JSON
+files, the column) of the problem. Dates and times are not supported in
+barge.toml, as no field of the project file uses them.
+
+Possible fixes:
+- Check the project file for syntax errors, like missing commas or quotes.
+  Comments and trailing commas are only accepted in barge.json, not in the
+  files barge writes.
+- Compare the fields of the project file with the list in the README, for
+  example, booleans must be written as true or false, not as strings.
+- Remove the build directory with `barge clean` if a cache is corrupted.",
+    },
+    ErrorCode {
+        code: "B0003",
+        title: "Project file schema violation",
+        explanation: "\
+The project file is valid JSON, but it does not follow the schema of barge
+project files: the root of the file is not an object, or the schema_version
+field is not a non-negative integer.
+
+Possible fixes:
+- Make sure the whole project file is a single JSON object.
+- Remove the schema_version field, and run `barge upgrade` to set it.",
+    },
+    ErrorCode {
+        code: "B0004",
+        title: "Project file from a newer version",
+        explanation: "\
+The schema_version of the project file is newer than the schemas known by this
+version of barge, so the meaning of some of its fields may be unknown.
+
+Possible fixes:
+- Update barge to the version used by the other contributors of the project.",
+    },
+    ErrorCode {
+        code: "B0005",
+        title: "Invalid build profile",
+        explanation: "\
+The build target given on the command line (or in BARGE_PROFILE) is not one of
+the build profiles of barge. Only debug and release are supported.
+
+Possible fixes:
+- Use `barge build debug` or `barge build release`.
+- Check the value of the BARGE_PROFILE environment variable.",
+    },
+    ErrorCode {
+        code: "B0006",
+        title: "Missing tool or file",
+        explanation: "\
+A program that barge had to run (like make, the compilers, pkg-config, or git)
+or a file that it had to read could not be found.
+
+Possible fixes:
+- Install the tools required by the subcommand, the README lists them.
+- Check that the tools are in one of the directories of PATH.
+- Check that the cross-compilation toolchain of the target is installed.",
+    },
+    ErrorCode {
+        code: "B0007",
+        title: "Compiler launcher not found",
+        explanation: "\
+The compiler_launcher of the project file (or BARGE_COMPILER_LAUNCHER) names a
+program that is not in any of the directories of PATH.
+
+Possible fixes:
+- Install the launcher, for example, ccache or sccache.
+- Set BARGE_COMPILER_LAUNCHER to an empty value to build without it.",
+    },
+    ErrorCode {
+        code: "B0008",
+        title: "Building as root",
+        explanation: "\
+Subcommands that build the project refuse to run as root, as the build
+artifacts would be owned by root, and the user could not remove them later.
+
+Possible fixes:
+- Run barge as a regular user.
+- In containers, where this is intended, pass --allow-root or set
+  BARGE_ALLOW_ROOT=1.",
+    },
+    ErrorCode {
+        code: "B0009",
+        title: "Lockfile out of date",
+        explanation: "\
+The build was started with --locked or --frozen, but barge.lock would have to
+be updated (because an external project is not locked yet, its repository has
+changed, or it is no longer used), or something would have to be fetched over
+the network.
+
+Possible fixes:
+- Build once without --locked, and commit the updated barge.lock.
+- Run `barge update` to lock the latest commits of the external projects.",
+    },
+    ErrorCode {
+        code: "B0010",
+        title: "Locked external project changed",
+        explanation: "\
+The contents of an external project checked out at its locked commit do not
+match the checksum recorded in barge.lock. Either the lockfile was edited by
+hand, or the history of the repository of the external project was rewritten.
+
+Possible fixes:
+- Verify that the repository of the external project is trustworthy.
+- Run `barge update <NAME>` to lock the current state of the project.",
+    },
+    ErrorCode {
+        code: "B0011",
+        title: "Missing libraries for the bitness",
+        explanation: "\
+The project is built for a bitness (with the bits field or --bits) that the
+installed C and C++ runtime libraries do not support.
+
+Possible fixes:
+- Install the multilib packages of the compiler, for example, gcc-multilib
+  and g++-multilib on Debian-based systems.",
+    },
+    ErrorCode {
+        code: "B0012",
+        title: "Running a cross-compiled executable",
+        explanation: "\
+The project is built for another target than the host, so its executables can
+not be run or tested directly.
+
+Possible fixes:
+- Build for the host by removing the target field, or by passing another
+  --target option.
+- Run the executable in an emulator or on the target device.",
+    },
+    ErrorCode {
+        code: "B0013",
+        title: "Invalid command template",
+        explanation: "\
+One of the command_templates of the project file uses a placeholder that is
+not known, or contains a { without a matching }.
+
+Possible fixes:
+- Use only the placeholders listed for command_templates in the README.",
+    },
+    ErrorCode {
+        code: "B0014",
+        title: "Invalid environment override",
+        explanation: "\
+One of the BARGE_ environment variables overriding the project file has an
+invalid value.
+
+Possible fixes:
+- BARGE_TOOLSET must be gnu or llvm.
+- BARGE_JOBS must be a positive integer.",
+    },
+    ErrorCode {
+        code: "B0015",
+        title: "Undefined variable in the project file",
+        explanation: "\
+One of the flags of the project file refers to a ${NAME} variable that is
+neither built-in (PROJECT_NAME, VERSION, BUILD_TARGET, BUILD_DIR, or
+PROJECT_DIR), nor set in the environment, or a ${ is not closed with a }.
+
+Possible fixes:
+- Set the environment variable before running barge, for example, in the
+  profile of the shell, or in the environment of the CI job.
+- Check the name of the variable for typos.",
+    },
+];
+
+// The messages of the errors with a code, which are matched exactly, so that
+// the errors do not need to carry their codes.
+const CODED_MESSAGES: &[(&str, &str)] = &[
+    ("Malformed TOML file", "B0002"),
+    ("The project file is not valid", "B0002"),
+    ("The project file must contain an object", "B0003"),
+    (
+        "The schema_version field must be a non-negative integer",
+        "B0003",
+    ),
+    (
+        "The project file was written by a newer version of barge",
+        "B0004",
+    ),
+    ("Invalid target specified", "B0005"),
+    ("Compiler launcher not found", "B0007"),
+    ("Refusing to build as root", "B0008"),
+    (
+        "The lockfile needs to be updated, but it is locked",
+        "B0009",
+    ),
+    (
+        "External project not fetched, and --frozen forbids network access",
+        "B0009",
+    ),
+    (
+        "Locked commit not fetched, and --frozen forbids network access",
+        "B0009",
+    ),
+    ("Checksum mismatch for a locked external project", "B0010"),
+    (
+        "The libraries required for the selected bitness are missing",
+        "B0011",
+    ),
+    (
+        "Cross-compiled executables can not be run on the host",
+        "B0012",
+    ),
+    ("Unknown placeholder in command template", "B0013"),
+    ("Unterminated placeholder in command template", "B0013"),
+    (
+        "Invalid BARGE_JOBS, a positive integer is expected",
+        "B0014",
+    ),
+    (
+        "Invalid BARGE_TOOLSET, valid choices are: gnu, llvm",
+        "B0014",
+    ),
+    ("Undefined variable in the project file", "B0015"),
+    ("Unterminated variable in the project file", "B0015"),
+];
+
+fn get_code(error: &BargeError) -> Option<&'static str> {
+    match error {
+        BargeError::ProjectNotFound(_) => Some("B0001"),
+        BargeError::SerdeJsonError(_) => Some("B0002"),
+        BargeError::StdIoError(e) if e.kind() == std::io::ErrorKind::NotFound => Some("B0006"),
+        BargeError::InvalidValue(message) | BargeError::FailedOperation(message) => CODED_MESSAGES
+            .iter()
+            .find(|(coded, _)| coded == message)
+            .map(|(_, code)| *code),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_error_code_names() -> Vec<&'static str> {
+    ERROR_CODES.iter().map(|error| error.code).collect()
+}
+
+pub(crate) fn get_error_code(error: &BargeError) -> Option<&'static ErrorCode> {
+    let code = get_code(error)?;
+    ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code == code)
+}
+
+pub(crate) fn explain_error_code(code: Option<&String>) -> Result<()> {
+    let Some(code) = code else {
+        color_println!(BLUE, "Error codes of barge");
+        for error_code in ERROR_CODES {
+            println!("{}  {}", error_code.code, error_code.title);
+        }
+        return Ok(());
+    };
+
+    match ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+    {
+        Some(error_code) => {
+            color_println!(WHITE, "{}: {}", error_code.code, error_code.title);
+            println!();
+            println!("{}", error_code.explanation);
+            Ok(())
+        }
+        None => {
+            color_eprintln!("{} is not an error code of barge", code);
+            Err(BargeError::InvalidValue("Unknown error code"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every coded message must still be produced somewhere, as the codes are
+    // lost silently when a message is reworded.
+    #[test]
+    fn coded_messages_are_produced() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let sources: String = std::fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| !path.ends_with("errorcodes.rs"))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .collect();
+        for (message, code) in CODED_MESSAGES {
+            assert!(
+                sources.contains(&format!("\"{}\"", message)),
+                "The message of {} is not produced: {}",
+                code,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn every_code_is_reachable() {
+        let errors = [
+            BargeError::ProjectNotFound("Project file not found."),
+            BargeError::SerdeJsonError(serde_json::from_str::<u8>("").unwrap_err()),
+            BargeError::StdIoError(std::io::ErrorKind::NotFound.into()),
+        ];
+        let mut codes: Vec<&str> = errors.iter().filter_map(get_code).collect();
+        codes.extend(
+            CODED_MESSAGES
+                .iter()
+                .filter_map(|(message, _)| get_code(&BargeError::InvalidValue(message))),
+        );
+        for error_code in ERROR_CODES {
+            assert!(
+                codes.contains(&error_code.code),
+                "{} is never reported",
+                error_code.code
+            );
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::dependencies::{
+    get_compiler_identity, get_dependencies_for_project, get_stored_compiler_identity,
+    parse_dependencies,
+};
+use crate::languages::{get_language, get_source_language, LanguageKind};
+use crate::makefile::{get_source_flag_stamp, resolve_build_flags, BuildFlags, BuildTarget};
+use crate::project::{collect_source_files, CollectSourceFilesMode, Project};
+use crate::result::Result;
+use crate::{color_println, BLUE, GREEN, NO_COLOR, WHITE};
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+fn get_modification_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Whether the overridden flags of the object differ from the stamped ones,
+// including when the override is added or removed.
+fn is_source_flag_stamp_changed(build_directory: &str, flags: &BuildFlags, name: &str) -> bool {
+    let stored = std::fs::read_to_string(get_source_flag_stamp(build_directory, name)).ok();
+    let current = flags
+        .source_stamps
+        .iter()
+        .find(|(source, _)| source == name)
+        .map(|(_, value)| value);
+    stored.as_ref() != current
+}
+
+fn explain_object(
+    source: &str,
+    object: &str,
+    flags_changed: bool,
+    compiler_changed: bool,
+    dependencies: Option<&Vec<String>>,
+) -> Option<String> {
+    let object_time = match get_modification_time(object) {
+        Some(time) => time,
+        None => return Some(String::from("missing output")),
+    };
+
+    if compiler_changed {
+        return Some(String::from("compiler changed"));
+    }
+
+    if flags_changed {
+        return Some(String::from("flags changed"));
+    }
+
+    if get_modification_time(source).is_some_and(|time| time > object_time) {
+        return Some(format!("source newer ({})", source));
+    }
+
+    dependencies?
+        .iter()
+        .filter(|dependency| *dependency != source)
+        .find(|dependency| get_modification_time(dependency).is_some_and(|t| t > object_time))
+        .map(|header| format!("header changed ({})", header))
+}
+
+pub(crate) fn explain_build(project: &Project, target: BuildTarget) -> Result<()> {
+    color_println!(
+        BLUE,
+        "Explaining build with {} configuration",
+        target.to_string()
+    );
+
+    let build_directory = project.build_directory(target);
+    let flags = resolve_build_flags(project, target)?;
+    let changed_stamps: Vec<&str> = flags
+        .stamps()
+        .iter()
+        .filter(|(stamp, value)| {
+            let path = format!("{}/{}", build_directory, stamp);
+            std::fs::read_to_string(path).ok().as_ref() != Some(*value)
+        })
+        .map(|(stamp, _)| *stamp)
+        .collect();
+
+    let compiler_changed =
+        get_stored_compiler_identity(&build_directory) != Some(get_compiler_identity(project)?);
+
+    let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+    for kind in [LanguageKind::C, LanguageKind::Cpp] {
+        let rules = get_dependencies_for_project(project, &build_directory, kind)?;
+        dependencies.extend(parse_dependencies(&rules));
+    }
+
+    let mut outdated = 0;
+    let mut objects = Vec::new();
+    for source in collect_source_files(project, CollectSourceFilesMode::All)? {
+        let language = match get_source_language(project, &source) {
+            Some(kind) => get_language(kind),
+            None => continue,
+        };
+
+        let name = source.strip_prefix("src/").unwrap_or(&source);
+        let object = format!("{}/obj/{}.o", build_directory, name);
+        let flags_changed = changed_stamps.contains(&language.stamp())
+            || is_source_flag_stamp_changed(&build_directory, &flags, name);
+        let recorded_dependencies = language.recorded_dependencies(&object);
+        if let Some(reason) = explain_object(
+            &source,
+            &object,
+            flags_changed,
+            compiler_changed,
+            dependencies.get(&object).or(recorded_dependencies.as_ref()),
+        ) {
+            color_println!(WHITE, "{}: {}", object, reason);
+            outdated += 1;
+        }
+        objects.push(object);
+    }
+
+    let artifact = format!("{}/{}", build_directory, project.artifact_name());
+    let link_reason = match get_modification_time(&artifact) {
+        None => Some(String::from("missing output")),
+        Some(_) if changed_stamps.contains(&".ldflags") => Some(String::from("flags changed")),
+        Some(_) if outdated > 0 => Some(format!("{} object(s) out of date", outdated)),
+        Some(artifact_time) => objects
+            .iter()
+            .chain(collect_source_files(project, CollectSourceFilesMode::LinkerScriptsOnly)?.iter())
+            .find(|input| get_modification_time(input).is_some_and(|t| t > artifact_time))
+            .map(|input| format!("input newer ({})", input)),
+    };
+
+    if let Some(reason) = link_reason {
+        color_println!(WHITE, "{}: {}", artifact, reason);
+    } else {
+        color_println!(GREEN, "Everything is up to date");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,457 @@
+use crate::commandlog::record_and_status;
+use crate::lockfile::{get_lock_mode, LockMode, LockedProject, Lockfile};
+use crate::makefile::{build_library_flags, BuildTarget};
+use crate::project::{find_project_file, ExternalProject, Library, Project, ProjectType};
+use crate::result::{BargeError, Result};
+use crate::selfprofile::ProfileSection;
+use crate::utilities::attempt_remove_directory;
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+pub(crate) const EXTERNAL_PROJECTS_DIRECTORY: &str = "deps";
+
+const BUILD_STACK_VARIABLE: &str = "BARGE_EXTERNAL_BUILD_STACK";
+
+pub(crate) fn get_external_project_directory(external: &ExternalProject) -> PathBuf {
+    Path::new(EXTERNAL_PROJECTS_DIRECTORY).join(&external.name)
+}
+
+// The external projects are fetched into the directory named after them, so
+// their names have to name a single directory inside deps.
+pub(crate) fn is_valid_external_project_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+pub(crate) fn check_external_project_names(project: &Project) -> Result<()> {
+    for external in project.external_projects.iter().flatten() {
+        if !is_valid_external_project_name(&external.name) {
+            color_eprintln!("Invalid external project name {:?}", external.name);
+            return Err(BargeError::InvalidValue(
+                "The names of external projects must not be empty, . or .., or contain slashes",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Removes the working copy of an external project, refusing to remove anything
+// but a directory directly inside deps.
+fn remove_external_project_directory(directory: &Path) -> Result<()> {
+    let inside = directory
+        .strip_prefix(EXTERNAL_PROJECTS_DIRECTORY)
+        .is_ok_and(|name| {
+            let mut components = name.components();
+            matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+        });
+    if !inside {
+        return Err(BargeError::FailedOperation(
+            "Refusing to remove a directory outside of deps",
+        ));
+    }
+    attempt_remove_directory(&directory.to_string_lossy())
+}
+
+fn run_git(directory: &Path, arguments: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(arguments)
+        .output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if !error.trim().is_empty() {
+            color_eprintln!("{}", error.trim());
+        }
+        return Err(BargeError::FailedOperation(
+            "Failed to execute git on an external project",
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn fetch_external_project(
+    external: &ExternalProject,
+    directory: &Path,
+    locked: Option<&LockedProject>,
+) -> Result<()> {
+    if get_lock_mode() == LockMode::Frozen {
+        color_eprintln!("The external project {} is missing", external.name);
+        return Err(BargeError::FailedOperation(
+            "External project not fetched, and --frozen forbids network access",
+        ));
+    }
+    color_println!(BLUE, "Fetching external project {}", external.name);
+    let mut git = Command::new("git");
+    git.arg("clone").arg("--quiet");
+    if locked.is_none() {
+        if let Some(reference) = external.tag.as_ref().or(external.branch.as_ref()) {
+            git.args(["--branch", reference]);
+        }
+    }
+    if !git.arg(&external.git).arg(directory).status()?.success() {
+        color_eprintln!("Could not clone {}", external.git);
+        return Err(BargeError::FailedOperation(
+            "Failed to fetch external project",
+        ));
+    }
+    Ok(())
+}
+
+// Checks out the locked commit, and makes sure that its contents match the
+// checksum recorded in the lockfile.
+fn checkout_locked_commit(directory: &Path, locked: &LockedProject) -> Result<()> {
+    let commit = format!("{}^{{commit}}", locked.commit);
+    if run_git(directory, &["rev-parse", "HEAD"])? != locked.commit
+        && run_git(directory, &["rev-parse", "--quiet", "--verify", &commit]).is_err()
+    {
+        if get_lock_mode() == LockMode::Frozen {
+            color_eprintln!("The locked commit of {} is not available", locked.name);
+            return Err(BargeError::FailedOperation(
+                "Locked commit not fetched, and --frozen forbids network access",
+            ));
+        }
+        run_git(directory, &["fetch", "--quiet", "--tags", "origin"])?;
+    }
+    run_git(directory, &["checkout", "--quiet", &locked.commit])?;
+
+    if run_git(directory, &["rev-parse", "HEAD^{tree}"])? != locked.checksum {
+        color_eprintln!("The contents of {} do not match barge.lock", locked.name);
+        return Err(BargeError::InvalidValue(
+            "Checksum mismatch for a locked external project",
+        ));
+    }
+    Ok(())
+}
+
+fn resolve_external_project(external: &ExternalProject, directory: &Path) -> Result<LockedProject> {
+    Ok(LockedProject {
+        name: external.name.clone(),
+        git: external.git.clone(),
+        commit: run_git(directory, &["rev-parse", "HEAD"])?,
+        checksum: run_git(directory, &["rev-parse", "HEAD^{tree}"])?,
+    })
+}
+
+// Makes the working copy of the external project match the lockfile, and
+// records the resolved commit of projects that are not locked yet.
+fn lock_external_project(
+    external: &ExternalProject,
+    directory: &Path,
+    lockfile: &mut Lockfile,
+) -> Result<()> {
+    let locked = lockfile
+        .get(&external.name)
+        .filter(|locked| locked.git == external.git)
+        .cloned();
+    let fetched = find_project_file(directory).is_some();
+    if fetched && locked.is_none() && lockfile.get(&external.name).is_some() {
+        // The repository of the external project has changed since locking it.
+        remove_external_project_directory(directory)?;
+    }
+
+    if find_project_file(directory).is_none() {
+        fetch_external_project(external, directory, locked.as_ref())?;
+    }
+    match locked {
+        Some(locked) => checkout_locked_commit(directory, &locked),
+        None => {
+            lockfile.set(resolve_external_project(external, directory)?);
+            Ok(())
+        }
+    }
+}
+
+fn load_external_project(project: &Project, directory: &Path) -> Result<Project> {
+    let mut external = Project::load_from_directory(directory)?;
+    if external.project_type == ProjectType::Executable {
+        return Err(BargeError::InvalidValue(
+            "External projects must be static or shared libraries",
+        ));
+    }
+
+    // The dependencies have to be ABI compatible with the project using them.
+    external.bits = project.bits;
+    external.target = project.target.clone();
+    external.toolset = project.toolset.or(external.toolset);
+    external.jobs = project.jobs;
+    Ok(external)
+}
+
+// Builds the external project by running barge in its directory, with the
+// settings the dependencies have to share with the project using them to be
+// ABI compatible. The directories of the projects being built are passed down
+// to detect circular dependencies.
+fn build_external_project(project: &Project, directory: &Path, target: BuildTarget) -> Result<()> {
+    let directory = directory.canonicalize()?;
+    load_external_project(project, &directory)?;
+    let mut stack: Vec<PathBuf> = std::env::var_os(BUILD_STACK_VARIABLE)
+        .map(|stack| std::env::split_paths(&stack).collect())
+        .unwrap_or_default();
+    if stack.contains(&directory) {
+        return Err(BargeError::InvalidValue(
+            "The external projects depend on each other circularly",
+        ));
+    }
+    stack.push(directory.clone());
+    let stack = std::env::join_paths(stack)
+        .map_err(|_| BargeError::InvalidValue("Invalid path of an external project"))?;
+
+    let mut build = Command::new(std::env::current_exe()?);
+    build
+        .arg("build")
+        .arg(target.to_string())
+        .current_dir(&directory)
+        .env(BUILD_STACK_VARIABLE, stack)
+        // The root check has already passed for the project using it.
+        .env("BARGE_ALLOW_ROOT", "1");
+    if let Some(bits) = project.bits {
+        build.arg("--bits").arg(bits.to_string());
+    }
+    if let Some(triple) = &project.target {
+        build.arg("--target").arg(triple);
+    }
+    if let Some(jobs) = project.jobs {
+        build.arg("--jobs").arg(jobs.to_string());
+    }
+    if let Some(toolset) = project.toolset {
+        build.env(
+            "BARGE_TOOLSET",
+            serde_json::to_value(toolset)?.as_str().unwrap_or_default(),
+        );
+    }
+    match get_lock_mode() {
+        LockMode::Unlocked => {}
+        LockMode::Locked => {
+            build.arg("--locked");
+        }
+        LockMode::Frozen => {
+            build.arg("--frozen");
+        }
+    }
+    if !record_and_status(&mut build)?.success() {
+        return Err(BargeError::FailedOperation(
+            "Failed to build the external project",
+        ));
+    }
+    Ok(())
+}
+
+// With --locked or --frozen, every external project has to be locked already,
+// and the lockfile must not list any other projects, so it is never changed.
+fn check_lockfile_current(project: &Project, lockfile: &Lockfile) -> Result<()> {
+    let externals = project.external_projects.as_deref().unwrap_or_default();
+    for external in externals {
+        if lockfile
+            .get(&external.name)
+            .is_none_or(|locked| locked.git != external.git)
+        {
+            color_eprintln!("The external project {} is not locked", external.name);
+            return Err(BargeError::InvalidValue(
+                "The lockfile needs to be updated, but it is locked",
+            ));
+        }
+    }
+    for locked in &lockfile.projects {
+        if !externals
+            .iter()
+            .any(|external| external.name == locked.name)
+        {
+            color_eprintln!("The locked project {} is no longer used", locked.name);
+            return Err(BargeError::InvalidValue(
+                "The lockfile needs to be updated, but it is locked",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn retain_listed_projects(project: &Project, lockfile: &mut Lockfile) {
+    let externals = project.external_projects.as_deref().unwrap_or_default();
+    lockfile
+        .projects
+        .retain(|locked| externals.iter().any(|e| e.name == locked.name));
+}
+
+pub(crate) fn prepare_external_projects(project: &Project, target: BuildTarget) -> Result<()> {
+    let _section = ProfileSection::start("external projects");
+    if project.external_projects.is_none() {
+        return Ok(());
+    }
+
+    let mut lockfile = Lockfile::load()?;
+    if get_lock_mode() != LockMode::Unlocked {
+        check_lockfile_current(project, &lockfile)?;
+    }
+    let previous = lockfile.clone();
+    retain_listed_projects(project, &mut lockfile);
+    for external in project.external_projects.iter().flatten() {
+        let directory = get_external_project_directory(external);
+        lock_external_project(external, &directory, &mut lockfile)?;
+        color_println!(BLUE, "Building external project {}", external.name);
+        build_external_project(project, &directory, target)?;
+    }
+    if lockfile != previous {
+        lockfile.save()?;
+    }
+    Ok(())
+}
+
+fn get_requested_reference(external: &ExternalProject) -> String {
+    if let Some(tag) = &external.tag {
+        format!("tags/{}", tag)
+    } else if let Some(branch) = &external.branch {
+        format!("origin/{}", branch)
+    } else {
+        String::from("origin/HEAD")
+    }
+}
+
+pub(crate) fn update_external_projects(project: &Project, name: Option<&String>) -> Result<()> {
+    let externals: Vec<&ExternalProject> = project
+        .external_projects
+        .iter()
+        .flatten()
+        .filter(|external| name.is_none_or(|name| external.name == *name))
+        .collect();
+    if externals.is_empty() {
+        return Err(BargeError::InvalidValue(
+            "No matching external projects to update",
+        ));
+    }
+
+    let mut lockfile = Lockfile::load()?;
+    retain_listed_projects(project, &mut lockfile);
+    for external in externals {
+        let directory = get_external_project_directory(external);
+        if lockfile
+            .get(&external.name)
+            .is_some_and(|locked| locked.git != external.git)
+        {
+            remove_external_project_directory(&directory)?;
+        }
+        if find_project_file(&directory).is_some() {
+            color_println!(BLUE, "Updating external project {}", external.name);
+            run_git(
+                &directory,
+                &["fetch", "--quiet", "--tags", "--force", "origin"],
+            )?;
+            let reference = get_requested_reference(external);
+            run_git(&directory, &["checkout", "--quiet", "--detach", &reference])?;
+        } else {
+            fetch_external_project(external, &directory, None)?;
+        }
+
+        let resolved = resolve_external_project(external, &directory)?;
+        match lockfile.get(&external.name) {
+            Some(locked) if locked.commit == resolved.commit => {
+                color_println!(GREEN, "{} is up to date", external.name);
+            }
+            Some(locked) => color_println!(
+                GREEN,
+                "Updated {} from {} to {}",
+                external.name,
+                &locked.commit[..12.min(locked.commit.len())],
+                &resolved.commit[..12]
+            ),
+            None => color_println!(
+                GREEN,
+                "Locked {} at {}",
+                external.name,
+                &resolved.commit[..12]
+            ),
+        }
+        lockfile.set(resolved);
+    }
+    lockfile.save()
+}
+
+#[derive(Default)]
+struct ExternalProjectFlags {
+    cflags: String,
+    ldflags: String,
+    artifacts: Vec<String>,
+    libraries: Vec<Library>,
+    visited: Vec<PathBuf>,
+}
+
+// Collects the exports of the external projects of the dependent project, and
+// of their own external projects, recursively. The artifacts are listed before
+// the artifacts of their dependencies, as static libraries have to be linked
+// in that order.
+fn collect_external_project_flags(
+    project: &Project,
+    dependent: &Project,
+    root: &Path,
+    target: BuildTarget,
+    flags: &mut ExternalProjectFlags,
+) -> Result<()> {
+    for external in dependent.external_projects.iter().flatten() {
+        let directory = root.join(get_external_project_directory(external));
+        // The external projects are only fetched by building the project, or
+        // by update, until then their flags are left out.
+        if find_project_file(&directory).is_none() {
+            continue;
+        }
+        let canonical = directory.canonicalize()?;
+        if flags.visited.contains(&canonical) {
+            continue;
+        }
+        flags.visited.push(canonical);
+
+        let loaded = load_external_project(project, &directory)?;
+        let artifact = directory
+            .join(loaded.build_directory(target))
+            .join(loaded.artifact_name());
+        let exports = loaded.exports.as_ref();
+        let default_include_dirs = vec![String::from("include")];
+        let include_dirs = exports
+            .and_then(|exports| exports.include_dirs.as_ref())
+            .unwrap_or(&default_include_dirs);
+        for include_dir in include_dirs {
+            flags.cflags += &format!(" -I{}", directory.join(include_dir).display());
+        }
+        for define in exports
+            .and_then(|e| e.defines.as_ref())
+            .iter()
+            .flat_map(|d| d.iter())
+        {
+            flags.cflags += &format!(" -D{}", define);
+        }
+        if exports.and_then(|exports| exports.external_libraries) != Some(false) {
+            for library in loaded.external_libraries.iter().flatten() {
+                if !flags.libraries.contains(library) {
+                    flags.libraries.push(library.clone());
+                }
+            }
+        }
+
+        flags.ldflags += &format!(" {}", artifact.display());
+        if loaded.project_type == ProjectType::SharedLibrary {
+            let absolute = std::env::current_dir()?.join(artifact.parent().unwrap_or(&directory));
+            flags.ldflags += &format!(" -Wl,-rpath,{}", absolute.display());
+        }
+        flags.artifacts.push(artifact.to_string_lossy().to_string());
+        collect_external_project_flags(project, &loaded, &directory, target, flags)?;
+    }
+    Ok(())
+}
+
+// Returns the compiler flags, the linker flags, and the artifacts of the external projects.
+pub(crate) fn get_external_project_flags(
+    project: &Project,
+    target: BuildTarget,
+) -> Result<(String, String, Vec<String>)> {
+    let mut flags = ExternalProjectFlags::default();
+    collect_external_project_flags(project, project, Path::new(""), target, &mut flags)?;
+    if flags.libraries.is_empty() {
+        return Ok((flags.cflags, flags.ldflags, flags.artifacts));
+    }
+
+    let (library_cflags, library_ldflags) = build_library_flags(&Some(flags.libraries))?;
+    Ok((
+        format!("{} {}", flags.cflags, library_cflags.trim_end()),
+        format!("{} {}", flags.ldflags, library_ldflags.trim_end()),
+        flags.artifacts,
+    ))
+}
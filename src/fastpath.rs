@@ -0,0 +1,243 @@
+use crate::commandlog::record_and_output;
+use crate::languages::LanguageKind;
+use crate::lockfile::LOCKFILE;
+use crate::makefile::{get_referenced_variables, BuildTarget};
+use crate::project::{Library, Project, PROJECT_FILE_NAMES};
+use crate::result::Result;
+use crate::selfprofile::ProfileSection;
+use crate::utilities::{find_executable, get_content_hash};
+use std::path::Path;
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+const BUILD_STATE: &str = ".build-state";
+pub(crate) const WATCHED_DIRECTORIES: &[&str] = &["src", "include"];
+const WATCHED_VARIABLES: &[&str] = &[
+    "PATH",
+    "PKG_CONFIG_PATH",
+    "PKG_CONFIG_LIBDIR",
+    "CPPFLAGS",
+    "CFLAGS",
+    "CXXFLAGS",
+    "FFLAGS",
+    "LDFLAGS",
+];
+
+fn get_file_stamp(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!(
+        "{} {} {}",
+        path.display(),
+        modified.as_nanos(),
+        metadata.len()
+    ))
+}
+
+fn collect_file_stamps(directory: &Path, stamps: &mut Vec<String>) {
+    let mut entries: Vec<_> = match std::fs::read_dir(directory) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_file_stamps(&path, stamps);
+        } else {
+            stamps.extend(get_file_stamp(&path));
+        }
+    }
+}
+
+fn find_in_path(program: &str) -> Option<String> {
+    find_executable(program).and_then(|candidate| get_file_stamp(&candidate))
+}
+
+fn hash_stamps(stamps: &[String]) -> String {
+    let parts: Vec<&[u8]> = stamps.iter().map(|stamp| stamp.as_bytes()).collect();
+    get_content_hash(&parts)
+}
+
+pub(crate) fn get_input_fingerprint(project: &Project, target: BuildTarget) -> Result<String> {
+    let _section = ProfileSection::start("input fingerprint");
+    let json = serde_json::to_string(project)?;
+    let mut stamps = vec![target.to_string(), json.clone()];
+    stamps.extend(get_file_stamp(&std::env::current_exe()?));
+    stamps.extend(
+        WATCHED_VARIABLES
+            .iter()
+            .copied()
+            .chain(get_referenced_variables(&json))
+            .map(|name| format!("{}={}", name, std::env::var(name).unwrap_or_default())),
+    );
+
+    stamps.extend(
+        [LanguageKind::C, LanguageKind::Cpp]
+            .iter()
+            .filter_map(|kind| find_in_path(&project.compiler(*kind))),
+    );
+
+    collect_source_stamps(&mut stamps);
+    stamps.extend(get_file_stamp(Path::new(LOCKFILE)));
+    let assembly_include_dirs = project
+        .assembly
+        .as_ref()
+        .and_then(|assembly| assembly.include_dirs.as_ref());
+    for directory in assembly_include_dirs.into_iter().flatten() {
+        collect_file_stamps(Path::new(directory), &mut stamps);
+    }
+    if let Some(layout) = &project.memory_layout {
+        for input in layout
+            .template
+            .iter()
+            .chain(layout.fragments.iter().flatten())
+        {
+            stamps.extend(get_file_stamp(Path::new(input)));
+        }
+    }
+    Ok(hash_stamps(&stamps))
+}
+
+fn collect_source_stamps(stamps: &mut Vec<String>) {
+    for directory in WATCHED_DIRECTORIES {
+        collect_file_stamps(Path::new(directory), stamps);
+    }
+}
+
+// Fingerprints the project file and the sources, along with the files of the
+// given additional directories, like the tests.
+pub(crate) fn get_source_fingerprint(directories: &[&str]) -> String {
+    let mut stamps: Vec<String> = PROJECT_FILE_NAMES
+        .iter()
+        .filter_map(|name| get_file_stamp(Path::new(name)))
+        .collect();
+    collect_source_stamps(&mut stamps);
+    for directory in directories {
+        collect_file_stamps(Path::new(directory), &mut stamps);
+    }
+    hash_stamps(&stamps)
+}
+
+fn get_output_fingerprint(project: &Project, target: BuildTarget) -> Option<String> {
+    let artifact = project.artifact_name();
+    let binaries: Vec<&String> = project.bins.iter().flatten().map(|bin| &bin.name).collect();
+    let mut outputs: Vec<_> = std::fs::read_dir(project.build_directory(target))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                name.starts_with(&artifact) || binaries.iter().any(|binary| name == **binary)
+            })
+        })
+        .collect();
+    outputs.sort();
+
+    let stamps: Vec<String> = outputs
+        .iter()
+        .filter_map(|path| get_file_stamp(path))
+        .collect();
+    if stamps.is_empty() {
+        None
+    } else {
+        Some(hash_stamps(&stamps))
+    }
+}
+
+fn get_build_state_path(project: &Project, target: BuildTarget) -> String {
+    format!("{}/{}", project.build_directory(target), BUILD_STATE)
+}
+
+// Resolves the .pc files of the pkg-config libraries of the project, so that
+// the fast path can notice changes of the installed libraries without running
+// pkg-config. Returns None if one of them can not be resolved.
+fn find_package_files(project: &Project) -> Option<Vec<String>> {
+    project
+        .external_libraries
+        .iter()
+        .flatten()
+        .filter_map(|library| match library {
+            Library::PkgConfig { name } => Some(name),
+            Library::Manual { .. } => None,
+        })
+        .map(|name| {
+            let output =
+                record_and_output(Command::new("pkg-config").args(["--path", name])).ok()?;
+            let path = String::from_utf8(output.stdout).ok()?.trim().to_string();
+            (output.status.success() && Path::new(&path).is_file()).then_some(path)
+        })
+        .collect()
+}
+
+fn get_package_fingerprint(files: &[String]) -> Option<String> {
+    let stamps: Vec<String> = files
+        .iter()
+        .map(|file| get_file_stamp(Path::new(file)))
+        .collect::<Option<_>>()?;
+    Some(hash_stamps(&stamps))
+}
+
+pub(crate) fn is_build_up_to_date(project: &Project, target: BuildTarget) -> Result<bool> {
+    let _section = ProfileSection::start("up-to-date check");
+    // Build steps may have side effects, and external projects and Rust crates
+    // have their own sources, so these projects always go through make.
+    if project.pre_build_steps.is_some()
+        || project.post_build_steps.is_some()
+        || project.external_projects.is_some()
+        || project.rust_crates.is_some()
+    {
+        return Ok(false);
+    }
+
+    // The build state holds the fingerprints of the inputs, the outputs, and
+    // the .pc files of the pkg-config libraries, followed by the .pc files.
+    let stored = match std::fs::read_to_string(get_build_state_path(project, target)) {
+        Ok(stored) => stored,
+        Err(_) => return Ok(false),
+    };
+    let mut lines = stored.lines();
+    let (Some(inputs), Some(outputs), Some(packages)) = (lines.next(), lines.next(), lines.next())
+    else {
+        return Ok(false);
+    };
+    let files: Vec<String> = lines.map(String::from).collect();
+    Ok(
+        get_output_fingerprint(project, target).as_deref() == Some(outputs)
+            && get_package_fingerprint(&files).as_deref() == Some(packages)
+            && get_input_fingerprint(project, target)? == inputs,
+    )
+}
+
+pub(crate) fn write_build_state(
+    project: &Project,
+    target: BuildTarget,
+    inputs: &str,
+) -> Result<()> {
+    // The inputs are fingerprinted before the build, so changes made while
+    // make was running are not mistaken for built ones.
+    let path = get_build_state_path(project, target);
+    let outputs = get_output_fingerprint(project, target);
+    let files = find_package_files(project);
+    let packages = files.as_deref().and_then(get_package_fingerprint);
+    match (outputs, files, packages) {
+        (Some(outputs), Some(files), Some(packages))
+            if get_input_fingerprint(project, target)? == inputs =>
+        {
+            let mut lines = vec![inputs.to_string(), outputs, packages];
+            lines.extend(files);
+            std::fs::write(path, lines.join("\n"))?;
+        }
+        _ => discard_build_state(&project.build_directory(target))?,
+    }
+    Ok(())
+}
+
+pub(crate) fn discard_build_state(build_directory: &str) -> Result<()> {
+    let path = format!("{}/{}", build_directory, BUILD_STATE);
+    if Path::new(&path).exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
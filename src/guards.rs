@@ -0,0 +1,254 @@
+use crate::project::{get_find_name_arguments, GuardStyle, Project, SourceKind};
+use crate::result::{BargeError, Result};
+use crate::scaffold::get_include_guard;
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use std::process::Command;
+
+enum Guard {
+    Missing,
+    PragmaOnce(usize),
+    Macro {
+        name: String,
+        ifndef: usize,
+        define: usize,
+        endif: usize,
+    },
+}
+
+fn get_directive(line: &str) -> Option<(&str, &str)> {
+    let directive = line.trim().strip_prefix('#')?.trim_start();
+    let (name, argument) = directive
+        .split_once(char::is_whitespace)
+        .unwrap_or((directive, ""));
+    Some((name, argument.trim()))
+}
+
+// Returns the lines without their comments, where block comments may start and
+// end anywhere, even in the middle of a line, like `/* x */ #ifndef FOO`.
+fn strip_comments(lines: &[String]) -> Vec<String> {
+    let mut in_comment = false;
+    let mut result = Vec::new();
+    for line in lines {
+        let mut code = String::new();
+        let mut rest = line.as_str();
+        loop {
+            if in_comment {
+                let Some(end) = rest.find("*/") else {
+                    break;
+                };
+                rest = &rest[end + 2..];
+                in_comment = false;
+                code.push(' ');
+                continue;
+            }
+            let line_comment = rest.find("//");
+            match rest.find("/*") {
+                Some(start) if line_comment.is_none_or(|comment| start < comment) => {
+                    code += &rest[..start];
+                    rest = &rest[start + 2..];
+                    in_comment = true;
+                }
+                _ => {
+                    code += &rest[..line_comment.unwrap_or(rest.len())];
+                    break;
+                }
+            }
+        }
+        result.push(code);
+    }
+    result
+}
+
+fn get_code_lines(lines: &[String]) -> Vec<usize> {
+    strip_comments(lines)
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn detect_guard(lines: &[String]) -> Guard {
+    let lines = &strip_comments(lines);
+    let code = get_code_lines(lines);
+    let (first, second, last) = match (code.first(), code.get(1), code.last()) {
+        (Some(first), second, Some(last)) => (*first, second.copied(), *last),
+        _ => return Guard::Missing,
+    };
+
+    match get_directive(&lines[first]) {
+        Some(("pragma", "once")) => Guard::PragmaOnce(first),
+        Some(("ifndef", name)) => {
+            let defined = second.and_then(|second| get_directive(&lines[second]));
+            let closed = matches!(get_directive(&lines[last]), Some(("endif", _)));
+            match (defined, second) {
+                (Some(("define", defined)), Some(define)) if defined == name && closed => {
+                    Guard::Macro {
+                        name: name.to_string(),
+                        ifndef: first,
+                        define,
+                        endif: last,
+                    }
+                }
+                _ => Guard::Missing,
+            }
+        }
+        _ => Guard::Missing,
+    }
+}
+
+fn fix_guard(lines: &mut Vec<String>, guard: Guard, style: GuardStyle, expected: &str) {
+    let insert_at = get_code_lines(lines)
+        .first()
+        .copied()
+        .unwrap_or(lines.len());
+    match (guard, style) {
+        (
+            Guard::Macro {
+                ifndef,
+                define,
+                endif,
+                ..
+            },
+            GuardStyle::PragmaOnce,
+        ) => {
+            lines.remove(endif);
+            lines.remove(define);
+            lines[ifndef] = String::from("#pragma once");
+        }
+        (
+            Guard::Macro {
+                ifndef,
+                define,
+                endif,
+                ..
+            },
+            GuardStyle::IncludeGuard,
+        ) => {
+            lines[ifndef] = format!("#ifndef {}", expected);
+            lines[define] = format!("#define {}", expected);
+            lines[endif] = format!("#endif // {}", expected);
+        }
+        (Guard::PragmaOnce(index), GuardStyle::IncludeGuard) => {
+            lines[index] = format!("#ifndef {}", expected);
+            lines.insert(index + 1, format!("#define {}", expected));
+            lines.push(String::new());
+            lines.push(format!("#endif // {}", expected));
+        }
+        (Guard::Missing, GuardStyle::PragmaOnce) => {
+            lines.splice(
+                insert_at..insert_at,
+                [String::from("#pragma once"), String::new()],
+            );
+        }
+        (Guard::Missing, GuardStyle::IncludeGuard) => {
+            lines.splice(
+                insert_at..insert_at,
+                [
+                    format!("#ifndef {}", expected),
+                    format!("#define {}", expected),
+                    String::new(),
+                ],
+            );
+            lines.push(String::new());
+            lines.push(format!("#endif // {}", expected));
+        }
+        (Guard::PragmaOnce(_), GuardStyle::PragmaOnce) => {}
+    }
+}
+
+pub(crate) fn collect_headers(project: &Project) -> Result<Vec<String>> {
+    let mut headers = Vec::new();
+    for directory in ["include", "src"] {
+        if !std::path::Path::new(directory).is_dir() {
+            continue;
+        }
+
+        let find = Command::new("find")
+            .arg(directory)
+            .args(["-type", "f"])
+            .args(get_find_name_arguments(
+                &project.extensions(SourceKind::Header),
+            ))
+            .output()?
+            .stdout;
+        headers.extend(
+            std::str::from_utf8(&find)?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string()),
+        );
+    }
+    headers.sort();
+    Ok(headers)
+}
+
+pub(crate) fn check_include_guards(project: &Project, fix: bool) -> Result<()> {
+    color_println!(BLUE, "Checking include guards of project headers");
+
+    let settings = project.scaffolding.as_ref();
+    let style = settings
+        .and_then(|s| s.guard_style)
+        .unwrap_or(GuardStyle::PragmaOnce);
+
+    let mut nonconforming = 0;
+    for header in collect_headers(project)? {
+        let relative = header
+            .strip_prefix("include/")
+            .or_else(|| header.strip_prefix("src/"))
+            .unwrap_or(&header);
+        let expected = get_include_guard(project, relative);
+
+        let content = std::fs::read_to_string(&header)?;
+        let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+        let guard = detect_guard(&lines);
+        let problem = match (&guard, style) {
+            (Guard::Missing, _) => Some(String::from("missing include guard")),
+            (Guard::PragmaOnce(_), GuardStyle::PragmaOnce) => None,
+            (Guard::PragmaOnce(_), GuardStyle::IncludeGuard) => Some(format!(
+                "expected include guard {}, found #pragma once",
+                expected
+            )),
+            (Guard::Macro { .. }, GuardStyle::PragmaOnce) => {
+                Some(String::from("expected #pragma once, found include guard"))
+            }
+            (Guard::Macro { name, .. }, GuardStyle::IncludeGuard) => {
+                if *name == expected {
+                    None
+                } else {
+                    Some(format!(
+                        "expected include guard {}, found {}",
+                        expected, name
+                    ))
+                }
+            }
+        };
+
+        if let Some(problem) = problem {
+            nonconforming += 1;
+            if fix {
+                fix_guard(&mut lines, guard, style, &expected);
+                std::fs::write(&header, lines.join("\n") + "\n")?;
+                color_println!(GREEN, "{}: fixed ({})", header, problem);
+            } else {
+                color_eprintln!("{}: {}", header, problem);
+            }
+        }
+    }
+
+    if nonconforming == 0 {
+        color_println!(GREEN, "All headers have conforming include guards");
+        Ok(())
+    } else if fix {
+        color_println!(
+            GREEN,
+            "Fixed the include guards of {} header(s)",
+            nonconforming
+        );
+        Ok(())
+    } else {
+        Err(BargeError::FailedOperation(
+            "One or more headers have nonconforming include guards",
+        ))
+    }
+}
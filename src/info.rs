@@ -0,0 +1,151 @@
+use crate::guards::collect_headers;
+use crate::languages::{detect_languages, languages};
+use crate::makefile::{resolve_build_flags, BuildTarget};
+use crate::project::{
+    collect_source_files, get_launcher_arguments, CollectSourceFilesMode, Project, SourceKind,
+    DEFAULT_TOOLSET,
+};
+use crate::result::Result;
+use crate::utilities::find_executable;
+use crate::{color_println, BLUE, NO_COLOR, WHITE, YELLOW};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+fn get_serialized_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(|name| name.to_string()))
+        .unwrap_or_default()
+}
+
+// The resolved properties of the project for a build target, for editors, CI
+// jobs, and scripts.
+#[derive(Serialize)]
+struct ProjectMetadata {
+    name: String,
+    version: String,
+    description: String,
+    project_type: String,
+    profile: String,
+    toolset: String,
+    target: Option<String>,
+    compilers: BTreeMap<&'static str, String>,
+    flags: BTreeMap<&'static str, String>,
+    sources: Vec<String>,
+    headers: Vec<String>,
+    build_directory: String,
+    artifact: String,
+}
+
+// The flags are resolved with extra whitespace in them.
+fn normalize_flags(flags: &str) -> String {
+    flags.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub(crate) fn print_project_metadata(project: &Project, target: BuildTarget) -> Result<()> {
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let flags = resolve_build_flags(project, target)?;
+    let sources = collect_source_files(project, CollectSourceFilesMode::All)?
+        .into_iter()
+        .filter(|path| !matches!(project.source_kind(path), Some(SourceKind::Header)))
+        .collect();
+
+    let metadata = ProjectMetadata {
+        name: project.name.clone(),
+        version: project.version.clone(),
+        description: project.description.clone(),
+        project_type: get_serialized_name(&project.project_type),
+        profile: target.to_string(),
+        toolset: get_serialized_name(toolset),
+        target: project.target.clone(),
+        compilers: languages()
+            .iter()
+            .map(|language| (language.key(), project.compiler(language.kind())))
+            .collect(),
+        flags: languages()
+            .iter()
+            .map(|language| {
+                let flags = flags.compile_flags(language.kind());
+                (language.key(), normalize_flags(flags))
+            })
+            .chain([("ld", normalize_flags(&flags.ldflags))])
+            .collect(),
+        sources,
+        headers: collect_headers(project)?,
+        build_directory: project.build_directory(target),
+        artifact: project.artifact_path(target),
+    };
+    println!("{}", serde_json::to_string_pretty(&metadata)?);
+    Ok(())
+}
+
+pub(crate) fn print_project_info(project: &Project) -> Result<()> {
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let mix = detect_languages(project)?;
+
+    color_println!(BLUE, "{} {}", project.name, project.version);
+    if !project.description.is_empty() {
+        color_println!(WHITE, "{}", project.description);
+    }
+    color_println!(
+        WHITE,
+        "Type: {}",
+        get_serialized_name(&project.project_type)
+    );
+    color_println!(WHITE, "Toolset: {}", get_serialized_name(toolset));
+    if let Some(triple) = &project.target {
+        color_println!(WHITE, "Target: {}", triple);
+    }
+    if let Some(launcher) = get_launcher_arguments(project.compiler_launcher.as_deref()).first() {
+        match find_executable(launcher) {
+            Some(path) => color_println!(
+                WHITE,
+                "Compiler launcher: {} ({})",
+                launcher,
+                path.display()
+            ),
+            None => color_println!(YELLOW, "Compiler launcher: {} (not found)", launcher),
+        }
+    }
+    if let Some(link) = &project.link {
+        let mut options = Vec::new();
+        if let Some(entry) = &link.entry {
+            options.push(format!("entry {}", entry));
+        }
+        if link.no_startup_files == Some(true) {
+            options.push(String::from("no startup files"));
+        }
+        if let Some(subsystem) = &link.subsystem {
+            options.push(format!("{} subsystem", get_serialized_name(subsystem)));
+        }
+        if !options.is_empty() {
+            color_println!(WHITE, "Link options: {}", options.join(", "));
+        }
+    }
+
+    let counts: Vec<String> = mix
+        .languages
+        .iter()
+        .map(|(language, count)| format!("{} {}", count, language))
+        .collect();
+    if counts.is_empty() {
+        color_println!(WHITE, "Languages: {}", mix.describe());
+    } else {
+        color_println!(
+            WHITE,
+            "Languages: {} ({} source file(s))",
+            mix.describe(),
+            counts.join(", ")
+        );
+    }
+
+    color_println!(WHITE, "Linker driver: {}", project.compiler(mix.linker()));
+
+    for language in languages().iter().filter(|l| mix.contains(l.kind())) {
+        if let Some(standard) = language.standard(project) {
+            color_println!(WHITE, "{} standard: {}", language.name(), standard);
+        }
+    }
+
+    Ok(())
+}
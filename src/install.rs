@@ -0,0 +1,113 @@
+use crate::makefile::BuildTarget;
+use crate::project::{Project, ProjectType};
+use crate::result::Result;
+use crate::{color_println, BLUE, GREEN, NO_COLOR, WHITE};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+pub(crate) const DEFAULT_INSTALL_PREFIX: &str = "/usr/local";
+
+fn copy_file(source: &Path, destination: &Path, mode: u32) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(source, destination)?;
+    // Other hosts have no permission bits, the copy keeps the read-only flag.
+    #[cfg(unix)]
+    std::fs::set_permissions(destination, std::fs::Permissions::from_mode(mode))?;
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(())
+}
+
+fn collect_headers(directory: &Path, headers: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_headers(&path, headers)?;
+        } else {
+            headers.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Copies the artifact (and the public headers of libraries) under root, laid
+// out like the given prefix, and returns the paths of the copied files.
+pub(crate) fn stage_install_tree(
+    project: &Project,
+    target: BuildTarget,
+    root: &Path,
+    prefix: &str,
+) -> Result<Vec<PathBuf>> {
+    let destination = root.join(prefix.trim_start_matches('/'));
+    let built = PathBuf::from(project.artifact_path(target));
+    let converted = project.converted_artifact_path(target);
+    let artifact = match &converted {
+        Some(converted) => Path::new(converted).file_name().map_or_else(
+            || project.artifact_name(),
+            |name| name.to_string_lossy().to_string(),
+        ),
+        None => project.artifact_name(),
+    };
+
+    let (directory, mode) = match project.project_type {
+        // The converted images are not executable on the host.
+        ProjectType::Executable if converted.is_some() => ("bin", 0o644),
+        ProjectType::Executable => ("bin", 0o755),
+        ProjectType::SharedLibrary => ("lib", 0o755),
+        ProjectType::StaticLibrary => ("lib", 0o644),
+    };
+    let installed = destination.join(directory).join(&artifact);
+    copy_file(&built, &installed, mode)?;
+    let mut files = vec![installed];
+
+    let build_directory = PathBuf::from(project.build_directory(target));
+    for binary in project.bins.iter().flatten() {
+        let installed = destination.join("bin").join(&binary.name);
+        copy_file(&build_directory.join(&binary.name), &installed, 0o755)?;
+        files.push(installed);
+    }
+
+    let include = Path::new("include");
+    if project.project_type != ProjectType::Executable && include.is_dir() {
+        let mut headers = Vec::new();
+        collect_headers(include, &mut headers)?;
+        for header in headers {
+            let relative = header.strip_prefix(include).unwrap_or(&header);
+            let installed = destination.join("include").join(relative);
+            copy_file(&header, &installed, 0o644)?;
+            files.push(installed);
+        }
+    }
+
+    Ok(files)
+}
+
+pub(crate) fn install_project(project: &Project, target: BuildTarget, prefix: &str) -> Result<()> {
+    project.build(target)?;
+
+    let destdir = std::env::var("DESTDIR").unwrap_or_default();
+    let root = if destdir.is_empty() {
+        PathBuf::from("/")
+    } else {
+        PathBuf::from(destdir)
+    };
+
+    color_println!(
+        BLUE,
+        "Installing project to {}",
+        root.join(prefix.trim_start_matches('/')).display()
+    );
+    for file in stage_install_tree(project, target, &root, prefix)? {
+        color_println!(WHITE, "Installed {}", file.display());
+    }
+    color_println!(GREEN, "Project {} successfully installed", project.name);
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use crate::result::Result;
+use serde::de::DeserializeOwned;
+use std::ops::Range;
+
+// Calls the action with the byte index and the character of everything in the
+// text outside of strings.
+fn for_each_outside_strings(text: &str, mut action: impl FnMut(usize, char) -> usize) {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut skip_until = 0;
+    for (index, character) in text.char_indices() {
+        if index < skip_until {
+            continue;
+        }
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if character == '"' {
+            in_string = true;
+        } else {
+            skip_until = action(index, character);
+        }
+    }
+}
+
+fn blank_out(text: &mut [u8], start: usize, end: usize) {
+    for byte in &mut text[start..end] {
+        if *byte != b'\n' {
+            *byte = b' ';
+        }
+    }
+}
+
+// Replaces the comments (both // and /* */ style) and the trailing commas of
+// arrays and objects with spaces. The byte offsets of everything else stay
+// the same, so that the positions of the errors of serde_json are accurate,
+// and that the text can be edited at the offsets found in the masked text.
+pub(crate) fn mask_jsonc(text: &str) -> String {
+    let mut masked = text.as_bytes().to_vec();
+    for_each_outside_strings(text, |index, character| {
+        let rest = &text[index..];
+        let end = if character != '/' {
+            return index;
+        } else if rest.starts_with("//") {
+            index + rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            index + comment.find("*/").map_or(rest.len(), |end| end + 4)
+        } else {
+            return index;
+        };
+        blank_out(&mut masked, index, end);
+        end
+    });
+
+    let without_comments = String::from_utf8_lossy(&masked).to_string();
+    for_each_outside_strings(&without_comments, |index, character| {
+        if character != ',' {
+            return index;
+        }
+        let next = without_comments[index + character.len_utf8()..]
+            .trim_start()
+            .chars()
+            .next();
+        if matches!(next, Some(']' | '}')) {
+            masked[index] = b' ';
+        }
+        index
+    });
+    String::from_utf8_lossy(&masked).to_string()
+}
+
+// Returns the end of the string starting at the index, after its closing quote.
+fn find_string_end(text: &str, start: usize) -> Option<usize> {
+    let mut escaped = false;
+    for (index, character) in text[start + 1..].char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(start + 1 + index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Returns the byte range of the value of the field in the root object of the
+// JSON text, so that it can be replaced without touching the rest of the text.
+// The fields of nested objects are skipped.
+pub(crate) fn find_root_field(text: &str, name: &str) -> Option<Range<usize>> {
+    let masked = mask_jsonc(text);
+    let mut depth = 0;
+    let mut expecting_key = false;
+    let mut index = 0;
+    while let Some(character) = masked[index..].chars().next() {
+        match character {
+            '{' | '[' => {
+                depth += 1;
+                expecting_key = character == '{' && depth == 1;
+            }
+            '}' | ']' => depth -= 1,
+            ',' => expecting_key = depth == 1,
+            '"' => {
+                let end = find_string_end(&masked, index)?;
+                if expecting_key {
+                    let key: String = serde_json::from_str(&masked[index..end]).ok()?;
+                    let rest = masked[end..].trim_start().strip_prefix(':')?;
+                    let start = masked.len() - rest.trim_start().len();
+                    if key == name {
+                        return Some(start..find_value_end(&masked, start)?);
+                    }
+                    expecting_key = false;
+                    index = start;
+                    continue;
+                }
+                index = end;
+                continue;
+            }
+            _ => {}
+        }
+        index += character.len_utf8();
+    }
+    None
+}
+
+// Returns the end of the JSON value starting at the index.
+fn find_value_end(masked: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut index = start;
+    while let Some(character) = masked[index..].chars().next() {
+        match character {
+            '"' => {
+                index = find_string_end(masked, index)?;
+                if depth == 0 {
+                    return Some(index);
+                }
+                continue;
+            }
+            '{' | '[' => depth += 1,
+            '}' | ']' if depth == 0 => break,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            ',' if depth == 0 => break,
+            _ => {}
+        }
+        index += character.len_utf8();
+    }
+    Some(start + masked[start..index].trim_end().len())
+}
+
+// Deserializes JSON with comments and trailing commas, which are allowed in
+// the project file, so that the custom flags can be annotated.
+pub(crate) fn from_jsonc_str<T: DeserializeOwned>(text: &str) -> Result<T> {
+    Ok(serde_json::from_str(&mask_jsonc(text))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_and_trailing_commas_are_masked() {
+        let text = "{\"a\": [1, 2,], // comment\n\"b\": \"x, ]\", /* é */}";
+        let masked = mask_jsonc(text);
+        assert_eq!(masked.len(), text.len());
+        let value: serde_json::Value = serde_json::from_str(&masked).unwrap();
+        assert_eq!(value["a"], serde_json::json!([1, 2]));
+        assert_eq!(value["b"], "x, ]");
+    }
+
+    #[test]
+    fn root_fields_are_found_at_the_top_level_only() {
+        let text = "{\n  \"nested\": {\"version\": \"1.0\"}, // \"version\": 0\n  \"list\": [{\"a\": 1}, \"b\"],\n  \"version\": \"1.0\",\n  \"bits\": 32\n}";
+        let range = find_root_field(text, "version").unwrap();
+        assert_eq!(&text[range.clone()], "\"1.0\"");
+        assert!(range.start > text.find("list").unwrap());
+        assert_eq!(&text[find_root_field(text, "bits").unwrap()], "32");
+        assert_eq!(
+            &text[find_root_field(text, "list").unwrap()],
+            "[{\"a\": 1}, \"b\"]"
+        );
+        assert_eq!(find_root_field(text, "a"), None);
+        assert_eq!(find_root_field(text, "missing"), None);
+    }
+
+    #[test]
+    fn non_ascii_text_outside_strings_is_kept() {
+        let text = "\u{feff}{\"name\": \"árvíztűrő\"}";
+        assert_eq!(mask_jsonc(text), text);
+        assert!(from_jsonc_str::<serde_json::Value>(text).is_err());
+        assert_eq!(mask_jsonc("[1,\u{a0}]"), "[1 \u{a0}]");
+    }
+}
@@ -0,0 +1,721 @@
+use crate::dependencies::{get_dependencies_for_project, parse_dependencies};
+use crate::makefile::{get_command, get_find_expression, BuildFlags, SharedFlags};
+use crate::project::{
+    collect_source_files, merge_extensions, CollectSourceFilesMode, CommandTemplates, Formatter,
+    Project, SourceKind, Toolset, DEFAULT_COBOL_STANDARD, DEFAULT_CPP_STANDARD, DEFAULT_C_STANDARD,
+    DEFAULT_FORTRAN_STANDARD, DEFAULT_TOOLSET,
+};
+use crate::result::Result;
+use crate::validate::{report_invalid_field, report_leftover_fields};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub(crate) enum LanguageKind {
+    Cpp,
+    C,
+    Fortran,
+    Cobol,
+    Assembly,
+}
+
+impl Display for LanguageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", get_language(*self).name())
+    }
+}
+
+// Everything barge needs to know about a source language: which files belong
+// to it, how they are compiled, formatted, and what they need when linking.
+// The build makefile is generated from the registered languages, in the same
+// way for each of them: the sources of a language are found into
+// {VARIABLE}SRC, and compiled into {VARIABLE}OBJ with {COMPILER} and
+// {VARIABLE}FLAGS.
+pub(crate) trait Language: Sync {
+    fn kind(&self) -> LanguageKind;
+
+    fn name(&self) -> &'static str;
+
+    // The name of the language in the settings of the project file, like the
+    // fields of extra_extensions, or the compile_{key} command template.
+    fn key(&self) -> &'static str;
+
+    fn default_extensions(&self) -> &'static [&'static str];
+
+    fn extensions(&self, project: &Project) -> Vec<String> {
+        let extra = project
+            .extra_extensions
+            .as_ref()
+            .and_then(|extra| extra.languages.get(&self.kind()));
+        merge_extensions(self.default_extensions(), extra)
+    }
+
+    // The prefix of the make variables of the language.
+    fn variable(&self) -> &'static str;
+
+    fn compiler_variable(&self) -> &'static str {
+        self.variable()
+    }
+
+    // The name of the flags in the command templates and the project file,
+    // like {cflags} and custom_cflags.
+    fn flags_name(&self) -> String {
+        format!("{}flags", self.variable().to_lowercase())
+    }
+
+    // The compiler of the language in the toolset, without a target prefix.
+    fn executable(&self, toolset: &Toolset) -> &'static str;
+
+    fn compiler(&self, project: &Project) -> String {
+        project.cross_tool(self.executable(project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET)))
+    }
+
+    // The flags of the compiler, from the flags shared by the languages, and
+    // the custom flags of the project.
+    fn compile_flags(&self, project: &Project, _shared: &SharedFlags, custom: &str) -> String {
+        self.standard_flag(project).unwrap_or_default() + " " + custom
+    }
+
+    // The environment variables appended to the flags of the compiler, if the
+    // project respects them.
+    fn environment_flags(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    // The file in the build directory storing the flags of the language, so
+    // that its objects are rebuilt when they change.
+    fn stamp(&self) -> &'static str;
+
+    fn standard(&self, _project: &Project) -> Option<String> {
+        None
+    }
+
+    fn standard_flag(&self, project: &Project) -> Option<String> {
+        self.standard(project)
+            .map(|standard| format!("-std={}", standard))
+    }
+
+    // The flags required to link objects of the language, if it has sources.
+    fn extra_ldflags(&self) -> Result<String> {
+        Ok(String::new())
+    }
+
+    // Whether the objects have to be linked by the compiler of the language,
+    // if it has sources, so that its runtime is linked as well.
+    fn links_objects(&self) -> bool {
+        false
+    }
+
+    fn default_compile_command(&self) -> &'static str;
+
+    // The formatter of the sources, unless the project configures another one.
+    fn formatter(&self) -> Option<Formatter> {
+        None
+    }
+
+    // Prerequisites of the objects besides the source, the flag stamp, and the
+    // compiler identity.
+    fn extra_prerequisites(&self) -> &'static str {
+        ""
+    }
+
+    // The makefile lines describing the dependencies of the objects.
+    fn dependencies(&self, _project: &Project, _build_directory: &str) -> Result<String> {
+        Ok(String::new())
+    }
+
+    // The prerequisites of an object written by the compiler while building
+    // it, for the languages whose dependencies are not scanned by barge.
+    fn recorded_dependencies(&self, _object: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    fn variables(&self, project: &Project, flags: &BuildFlags) -> String {
+        let variable = self.variable();
+        format!(
+            "{compiler_variable}={compiler}\n\
+             {variable}FLAGS={flags}\n\
+             {variable}SRC=$(shell find src -type f {sources})\n\
+             {variable}OBJ=$(patsubst src/%,$(BUILDDIR)/obj/%.o,$({variable}SRC))\n",
+            compiler_variable = self.compiler_variable(),
+            compiler = self.compiler(project),
+            flags = flags.compile_flags(self.kind()),
+            sources = get_find_expression(&self.extensions(project)),
+        )
+    }
+
+    fn compile_rule(&self, templates: &CommandTemplates) -> Result<String> {
+        let command = get_command(
+            templates.compile.get(&self.kind()),
+            self.default_compile_command(),
+        )?;
+        Ok(format!(
+            "$({variable}OBJ): $(BUILDDIR)/obj/%.o: src/% $(BUILDDIR)/{stamp} $(BUILDDIR)/.compiler{prerequisites}\n\
+             \t@mkdir -p $(shell dirname $@)\n\
+             \t@$(RECORD_OBJECT) --source $< --message \"$(GREEN)$(DIM)Building {name} object $@.$(RESET)\" -- $(LAUNCHER) {command}\n",
+            variable = self.variable(),
+            stamp = self.stamp(),
+            prerequisites = self.extra_prerequisites(),
+            name = self.name(),
+        ))
+    }
+}
+
+struct C;
+struct Cpp;
+struct Assembly;
+struct Fortran;
+struct Cobol;
+
+// The flags of the C and C++ compilers, which only differ in their standard
+// and their custom flags.
+fn get_c_family_flags(
+    language: &dyn Language,
+    project: &Project,
+    shared: &SharedFlags,
+    custom: &str,
+) -> String {
+    language.standard_flag(project).unwrap_or_default()
+        + " "
+        + &shared.leading
+        + " "
+        + custom
+        + &shared.trailing
+}
+
+impl Language for C {
+    fn kind(&self) -> LanguageKind {
+        LanguageKind::C
+    }
+
+    fn name(&self) -> &'static str {
+        "C"
+    }
+
+    fn key(&self) -> &'static str {
+        "c"
+    }
+
+    fn default_extensions(&self) -> &'static [&'static str] {
+        &["c"]
+    }
+
+    fn variable(&self) -> &'static str {
+        "C"
+    }
+
+    fn compiler_variable(&self) -> &'static str {
+        "CC"
+    }
+
+    fn executable(&self, toolset: &Toolset) -> &'static str {
+        match toolset {
+            Toolset::Gnu => "gcc",
+            Toolset::Llvm => "clang",
+        }
+    }
+
+    fn compile_flags(&self, project: &Project, shared: &SharedFlags, custom: &str) -> String {
+        get_c_family_flags(self, project, shared, custom)
+    }
+
+    fn environment_flags(&self) -> &'static [&'static str] {
+        &["CPPFLAGS", "CFLAGS"]
+    }
+
+    fn stamp(&self) -> &'static str {
+        ".cflags"
+    }
+
+    fn standard(&self, project: &Project) -> Option<String> {
+        Some(
+            project
+                .c_standard
+                .clone()
+                .unwrap_or(DEFAULT_C_STANDARD.to_string()),
+        )
+    }
+
+    fn default_compile_command(&self) -> &'static str {
+        "$(CC) $(CFLAGS) -c $< -o $@"
+    }
+
+    fn formatter(&self) -> Option<Formatter> {
+        Some(Formatter::ClangFormat)
+    }
+
+    fn extra_prerequisites(&self) -> &'static str {
+        " $(CONFIG_HEADER)"
+    }
+
+    fn dependencies(&self, project: &Project, build_directory: &str) -> Result<String> {
+        get_dependencies_for_project(project, build_directory, self.kind())
+    }
+}
+
+impl Language for Cpp {
+    fn kind(&self) -> LanguageKind {
+        LanguageKind::Cpp
+    }
+
+    fn name(&self) -> &'static str {
+        "C++"
+    }
+
+    fn key(&self) -> &'static str {
+        "cpp"
+    }
+
+    fn default_extensions(&self) -> &'static [&'static str] {
+        &["cpp", "cc", "cxx"]
+    }
+
+    fn variable(&self) -> &'static str {
+        "CXX"
+    }
+
+    fn executable(&self, toolset: &Toolset) -> &'static str {
+        match toolset {
+            Toolset::Gnu => "g++",
+            Toolset::Llvm => "clang++",
+        }
+    }
+
+    fn compile_flags(&self, project: &Project, shared: &SharedFlags, custom: &str) -> String {
+        get_c_family_flags(self, project, shared, custom)
+    }
+
+    fn environment_flags(&self) -> &'static [&'static str] {
+        &["CPPFLAGS", "CXXFLAGS"]
+    }
+
+    fn stamp(&self) -> &'static str {
+        ".cxxflags"
+    }
+
+    fn standard(&self, project: &Project) -> Option<String> {
+        Some(
+            project
+                .cpp_standard
+                .clone()
+                .unwrap_or(DEFAULT_CPP_STANDARD.to_string()),
+        )
+    }
+
+    fn links_objects(&self) -> bool {
+        true
+    }
+
+    fn default_compile_command(&self) -> &'static str {
+        "$(CXX) $(CXXFLAGS) -c $< -o $@"
+    }
+
+    fn formatter(&self) -> Option<Formatter> {
+        Some(Formatter::ClangFormat)
+    }
+
+    fn extra_prerequisites(&self) -> &'static str {
+        " $(CONFIG_HEADER)"
+    }
+
+    fn dependencies(&self, project: &Project, build_directory: &str) -> Result<String> {
+        get_dependencies_for_project(project, build_directory, self.kind())
+    }
+}
+
+impl Language for Assembly {
+    fn kind(&self) -> LanguageKind {
+        LanguageKind::Assembly
+    }
+
+    fn name(&self) -> &'static str {
+        "assembly"
+    }
+
+    fn key(&self) -> &'static str {
+        "asm"
+    }
+
+    fn default_extensions(&self) -> &'static [&'static str] {
+        &["s", "asm"]
+    }
+
+    fn variable(&self) -> &'static str {
+        "ASM"
+    }
+
+    fn executable(&self, _toolset: &Toolset) -> &'static str {
+        "nasm"
+    }
+
+    fn compiler(&self, _project: &Project) -> String {
+        String::from("nasm")
+    }
+
+    fn compile_flags(&self, project: &Project, _shared: &SharedFlags, custom: &str) -> String {
+        let format = match project.bits {
+            Some(bits) if bits != 64 => "-f elf32",
+            _ => "-f elf64",
+        };
+        let include_flags: String = project
+            .assembly
+            .as_ref()
+            .and_then(|assembly| assembly.include_dirs.as_ref())
+            .iter()
+            .flat_map(|directories| directories.iter())
+            .map(|directory| format!(" -I{}/", directory.trim_end_matches('/')))
+            .collect();
+        match custom {
+            "" => format!("{}{}", format, include_flags),
+            custom => format!("{}{} {}", format, include_flags, custom),
+        }
+    }
+
+    fn stamp(&self) -> &'static str {
+        ".asmflags"
+    }
+
+    fn default_compile_command(&self) -> &'static str {
+        "$(ASM) $(ASMFLAGS) -MD $(@:.o=.d) -MP $< -o $@"
+    }
+
+    // The dependencies of assembly sources are written by nasm while assembling.
+    fn dependencies(&self, _project: &Project, _build_directory: &str) -> Result<String> {
+        Ok(String::from("-include $(ASMOBJ:.o=.d)"))
+    }
+
+    fn recorded_dependencies(&self, object: &str) -> Option<Vec<String>> {
+        let path = format!("{}.d", object.strip_suffix(".o").unwrap_or(object));
+        let rules = std::fs::read_to_string(path).ok()?;
+        parse_dependencies(&rules)
+            .into_iter()
+            .find(|(target, _)| target == object)
+            .map(|(_, prerequisites)| prerequisites)
+    }
+}
+
+impl Language for Fortran {
+    fn kind(&self) -> LanguageKind {
+        LanguageKind::Fortran
+    }
+
+    fn name(&self) -> &'static str {
+        "FORTRAN"
+    }
+
+    fn key(&self) -> &'static str {
+        "fortran"
+    }
+
+    fn default_extensions(&self) -> &'static [&'static str] {
+        &["f90"]
+    }
+
+    fn variable(&self) -> &'static str {
+        "FORTRAN"
+    }
+
+    fn executable(&self, _toolset: &Toolset) -> &'static str {
+        "gfortran"
+    }
+
+    // LLVM has no FORTRAN compiler, so gfortran is prefixed by the target with
+    // either toolset.
+    fn compiler(&self, project: &Project) -> String {
+        match &project.target {
+            Some(triple) => format!("{}-gfortran", triple),
+            None => String::from("gfortran"),
+        }
+    }
+
+    fn compile_flags(&self, project: &Project, shared: &SharedFlags, custom: &str) -> String {
+        self.standard_flag(project).unwrap_or_default() + " " + custom + &shared.bits
+    }
+
+    fn environment_flags(&self) -> &'static [&'static str] {
+        &["FFLAGS"]
+    }
+
+    fn stamp(&self) -> &'static str {
+        ".fortranflags"
+    }
+
+    fn standard(&self, project: &Project) -> Option<String> {
+        Some(
+            project
+                .fortran_standard
+                .clone()
+                .unwrap_or(DEFAULT_FORTRAN_STANDARD.to_string()),
+        )
+    }
+
+    fn extra_ldflags(&self) -> Result<String> {
+        Ok(String::from("-lgfortran"))
+    }
+
+    fn links_objects(&self) -> bool {
+        true
+    }
+
+    fn default_compile_command(&self) -> &'static str {
+        "$(FORTRAN) $(FORTRANFLAGS) -J$(BUILDDIR) -c $< -o $@"
+    }
+
+    fn formatter(&self) -> Option<Formatter> {
+        Some(Formatter::Program(self.kind(), "fprettify"))
+    }
+}
+
+impl Language for Cobol {
+    fn kind(&self) -> LanguageKind {
+        LanguageKind::Cobol
+    }
+
+    fn name(&self) -> &'static str {
+        "Cobol"
+    }
+
+    fn key(&self) -> &'static str {
+        "cobol"
+    }
+
+    fn default_extensions(&self) -> &'static [&'static str] {
+        &["cob"]
+    }
+
+    fn variable(&self) -> &'static str {
+        "COBOL"
+    }
+
+    fn executable(&self, _toolset: &Toolset) -> &'static str {
+        "cobc"
+    }
+
+    fn compiler(&self, _project: &Project) -> String {
+        String::from("cobc")
+    }
+
+    fn stamp(&self) -> &'static str {
+        ".cobolflags"
+    }
+
+    fn standard(&self, project: &Project) -> Option<String> {
+        Some(
+            project
+                .cobol_standard
+                .clone()
+                .unwrap_or(DEFAULT_COBOL_STANDARD.to_string()),
+        )
+    }
+
+    fn extra_ldflags(&self) -> Result<String> {
+        let result = Command::new("cob-config").arg("--libs").output()?.stdout;
+        Ok(String::from_utf8(result)?.trim().to_string())
+    }
+
+    fn default_compile_command(&self) -> &'static str {
+        "$(COBOL) $(COBOLFLAGS) -c $< -o $@"
+    }
+}
+
+// Every supported language, in the order their sections appear in the build
+// makefile. Supporting a new language only requires implementing the trait
+// and adding it here.
+static LANGUAGES: [&dyn Language; 5] = [&C, &Cpp, &Assembly, &Fortran, &Cobol];
+
+pub(crate) fn languages() -> &'static [&'static dyn Language] {
+    &LANGUAGES
+}
+
+pub(crate) fn get_language(kind: LanguageKind) -> &'static dyn Language {
+    *LANGUAGES
+        .iter()
+        .find(|language| language.kind() == kind)
+        .expect("every language kind is registered")
+}
+
+// The settings of the languages are stored in the project file as fields of
+// the enclosing object, named after the languages, like "fortran" or
+// "compile_fortran". The fields naming no language are unknown to barge, as
+// the other fields of the object are taken before.
+fn serialize_language_fields<T: Serialize, S: Serializer>(
+    fields: &BTreeMap<LanguageKind, T>,
+    name: fn(&dyn Language) -> String,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        fields
+            .iter()
+            .map(|(kind, value)| (name(get_language(*kind)), value)),
+    )
+}
+
+fn deserialize_language_fields<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+    deserializer: D,
+    name: fn(&dyn Language) -> String,
+) -> std::result::Result<BTreeMap<LanguageKind, T>, D::Error> {
+    let mut fields = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
+    let values: Vec<_> = LANGUAGES
+        .iter()
+        .filter_map(|language| Some((language, fields.remove(&name(*language))?)))
+        .collect();
+    report_leftover_fields(fields.into_keys());
+
+    let mut result = BTreeMap::new();
+    for (language, value) in values.into_iter().filter(|(_, value)| !value.is_null()) {
+        let value = serde_json::from_value(value).map_err(|error| {
+            let field = name(*language);
+            report_invalid_field(&field);
+            D::Error::custom(format!("{}: {}", field, error))
+        })?;
+        result.insert(language.kind(), value);
+    }
+    Ok(result)
+}
+
+// The fields named after the key of the language, like "fortran".
+pub(crate) mod language_fields {
+    use super::*;
+
+    fn name(language: &dyn Language) -> String {
+        language.key().to_string()
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        fields: &BTreeMap<LanguageKind, T>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serialize_language_fields(fields, name, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<BTreeMap<LanguageKind, T>, D::Error> {
+        deserialize_language_fields(deserializer, name)
+    }
+}
+
+// The command templates of the compilers, like "compile_fortran".
+pub(crate) mod compile_fields {
+    use super::*;
+
+    fn name(language: &dyn Language) -> String {
+        format!("compile_{}", language.key())
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        fields: &BTreeMap<LanguageKind, T>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serialize_language_fields(fields, name, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<BTreeMap<LanguageKind, T>, D::Error> {
+        deserialize_language_fields(deserializer, name)
+    }
+}
+
+// The custom flags of the compilers, like "custom_fortranflags".
+pub(crate) mod custom_flags_fields {
+    use super::*;
+
+    fn name(language: &dyn Language) -> String {
+        format!("custom_{}", language.flags_name())
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        fields: &BTreeMap<LanguageKind, T>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serialize_language_fields(fields, name, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<BTreeMap<LanguageKind, T>, D::Error> {
+        deserialize_language_fields(deserializer, name)
+    }
+}
+
+pub(crate) struct LanguageMix {
+    pub languages: Vec<(LanguageKind, usize)>,
+}
+
+pub(crate) fn get_source_language(project: &Project, path: &str) -> Option<LanguageKind> {
+    match project.source_kind(path)? {
+        SourceKind::Language(kind) => Some(kind),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_all_extensions(project: &Project) -> Vec<String> {
+    LANGUAGES
+        .iter()
+        .flat_map(|language| language.extensions(project))
+        .collect()
+}
+
+pub(crate) fn detect_languages(project: &Project) -> Result<LanguageMix> {
+    let mut languages: Vec<(LanguageKind, usize)> = Vec::new();
+    for source in collect_source_files(project, CollectSourceFilesMode::All)? {
+        let language = match get_source_language(project, &source) {
+            Some(language) => language,
+            None => continue,
+        };
+        match languages.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += 1,
+            None => languages.push((language, 1)),
+        }
+    }
+
+    languages.sort_by(|(l1, c1), (l2, c2)| c2.cmp(c1).then(l1.cmp(l2)));
+    Ok(LanguageMix { languages })
+}
+
+impl LanguageMix {
+    pub(crate) fn contains(&self, language: LanguageKind) -> bool {
+        self.languages.iter().any(|(l, _)| *l == language)
+    }
+
+    // The language whose compiler links the objects, the first one needing
+    // its own runtime, or the C compiler otherwise.
+    pub(crate) fn linker(&self) -> LanguageKind {
+        if self.languages.is_empty() {
+            return LanguageKind::Cpp;
+        }
+        LANGUAGES
+            .iter()
+            .find(|language| language.links_objects() && self.contains(language.kind()))
+            .map_or(LanguageKind::C, |language| language.kind())
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        let (assembly, compiled): (Vec<LanguageKind>, Vec<LanguageKind>) = self
+            .languages
+            .iter()
+            .map(|(language, _)| *language)
+            .partition(|language| *language == LanguageKind::Assembly);
+
+        let description = match compiled.as_slice() {
+            [] if assembly.is_empty() => return String::from("no sources"),
+            [] => return String::from("assembly only"),
+            [language] if assembly.is_empty() => return format!("{} only", language),
+            [language] => language.to_string(),
+            languages => {
+                let names: Vec<String> = languages.iter().map(|l| l.to_string()).collect();
+                format!("mixed {}", names.join("/"))
+            }
+        };
+
+        if assembly.is_empty() {
+            description
+        } else {
+            description + " with assembly"
+        }
+    }
+}
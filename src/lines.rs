@@ -0,0 +1,332 @@
+use crate::guards::collect_headers;
+use crate::languages::{detect_languages, get_language, get_source_language};
+use crate::project::{
+    collect_source_files, get_find_name_arguments, CollectSourceFilesMode, Project, SourceKind,
+};
+use crate::result::Result;
+use crate::utilities::matches_glob;
+use crate::{color_println, BLUE, NO_COLOR, WHITE};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+// The categories of the files, in the order they are listed in the table.
+const CATEGORIES: [&str; 7] = [
+    "C",
+    "C++",
+    "headers",
+    "assembly",
+    "FORTRAN",
+    "Cobol",
+    "linker scripts",
+];
+
+#[derive(Clone, Copy, Default)]
+struct LineCount {
+    files: usize,
+    code: usize,
+    comments: usize,
+    blank: usize,
+}
+
+impl LineCount {
+    fn lines(&self) -> usize {
+        self.code + self.comments + self.blank
+    }
+
+    fn add(&mut self, count: &LineCount) {
+        self.files += count.files;
+        self.code += count.code;
+        self.comments += count.comments;
+        self.blank += count.blank;
+    }
+}
+
+// The comments and the string literals of a language, as much as needed to
+// tell code and comments apart.
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    escapes: bool,
+    // The column (starting from zero) which marks the whole line as a comment
+    // if it holds one of the characters, like in fixed-format COBOL sources.
+    comment_column: Option<(usize, &'static [char])>,
+}
+
+const C_SYNTAX: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    escapes: true,
+    comment_column: None,
+};
+
+const LINKER_SCRIPT_SYNTAX: Syntax = Syntax {
+    line_comments: &[],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+    escapes: false,
+    comment_column: None,
+};
+
+const ASSEMBLY_SYNTAX: Syntax = Syntax {
+    line_comments: &[";"],
+    block_comment: None,
+    quotes: &['"', '\'', '`'],
+    escapes: false,
+    comment_column: None,
+};
+
+const FORTRAN_SYNTAX: Syntax = Syntax {
+    line_comments: &["!"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    escapes: false,
+    comment_column: None,
+};
+
+const COBOL_SYNTAX: Syntax = Syntax {
+    line_comments: &["*>"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    escapes: false,
+    comment_column: Some((6, &['*', '/'])),
+};
+
+fn get_syntax(category: &str) -> &'static Syntax {
+    match category {
+        "assembly" => &ASSEMBLY_SYNTAX,
+        "FORTRAN" => &FORTRAN_SYNTAX,
+        "Cobol" => &COBOL_SYNTAX,
+        "linker scripts" => &LINKER_SCRIPT_SYNTAX,
+        _ => &C_SYNTAX,
+    }
+}
+
+// Sorts the lines of the source into code (lines with anything besides
+// comments), comments, and blank lines. Blank lines inside block comments are
+// counted as blank. String literals do not continue on the next line.
+fn count_line_kinds(content: &str, syntax: &Syntax) -> LineCount {
+    let mut count = LineCount {
+        files: 1,
+        ..LineCount::default()
+    };
+    let mut in_block = false;
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            count.blank += 1;
+            continue;
+        }
+        if let Some((column, markers)) = syntax.comment_column {
+            if !in_block
+                && line
+                    .chars()
+                    .nth(column)
+                    .is_some_and(|c| markers.contains(&c))
+            {
+                count.comments += 1;
+                continue;
+            }
+        }
+
+        let (mut code, mut comment) = (false, false);
+        let mut quote = None;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if in_block {
+                comment = true;
+                let (_, end) = syntax.block_comment.unwrap_or_default();
+                match rest.find(end) {
+                    Some(index) => {
+                        in_block = false;
+                        rest = &rest[index + end.len()..];
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+            if let Some(open) = quote {
+                if syntax.escapes && c == '\\' {
+                    // The escaped character can not close the literal.
+                    let mut characters = rest.chars();
+                    characters.nth(1);
+                    rest = characters.as_str();
+                    continue;
+                }
+                if c == open {
+                    quote = None;
+                }
+            } else if syntax
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker))
+            {
+                comment = true;
+                break;
+            } else if let Some((start, _)) = syntax
+                .block_comment
+                .filter(|(start, _)| rest.starts_with(start))
+            {
+                in_block = true;
+                comment = true;
+                rest = &rest[start.len()..];
+                continue;
+            } else if syntax.quotes.contains(&c) {
+                quote = Some(c);
+                code = true;
+            } else if !c.is_whitespace() {
+                code = true;
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+
+        if code {
+            count.code += 1;
+        } else if comment {
+            count.comments += 1;
+        } else {
+            count.blank += 1;
+        }
+    }
+    count
+}
+
+fn get_category(project: &Project, path: &str) -> Option<&'static str> {
+    match project.source_kind(path) {
+        Some(SourceKind::Header) => return Some("headers"),
+        Some(SourceKind::LinkerScript) => return Some("linker scripts"),
+        _ => {}
+    }
+    if path.ends_with(".inc") {
+        return Some("assembly");
+    }
+    get_source_language(project, path).map(|kind| get_language(kind).name())
+}
+
+// Returns whether the file is left out of the line counts and the statistics,
+// because it matches one of the metrics_exclude patterns of the project.
+pub(crate) fn is_excluded_from_metrics(project: &Project, path: &str) -> bool {
+    project
+        .metrics_exclude
+        .iter()
+        .flatten()
+        .any(|pattern| matches_glob(pattern, path))
+}
+
+// The assembly include files, which are not sources on their own.
+fn collect_assembly_includes() -> Result<Vec<String>> {
+    let find = Command::new("find")
+        .arg("src")
+        .args(["-type", "f"])
+        .args(get_find_name_arguments(&[String::from("inc")]))
+        .output()?
+        .stdout;
+    Ok(std::str::from_utf8(&find)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+// The sources, the assembly include files, the linker scripts, and the headers
+// (including the ones in the include directory, unless headers are not
+// counted).
+fn collect_counted_files(project: &Project, headers: bool) -> Result<Vec<String>> {
+    let mut files = collect_source_files(project, CollectSourceFilesMode::All)?;
+    files.extend(collect_assembly_includes()?);
+    if headers {
+        for header in collect_headers(project)? {
+            if !files.contains(&header) {
+                files.push(header);
+            }
+        }
+    } else {
+        files.retain(|file| project.source_kind(file) != Some(SourceKind::Header));
+    }
+    files.retain(|file| !is_excluded_from_metrics(project, file));
+    files.sort();
+    Ok(files)
+}
+
+fn print_table(title: &str, rows: &[(String, LineCount)], total: LineCount) {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain([title.len(), "Total".len()])
+        .max()
+        .unwrap_or_default();
+    color_println!(
+        BLUE,
+        "{:<width$}  {:>6}  {:>8}  {:>8}  {:>8}  {:>8}",
+        title,
+        "Files",
+        "Code",
+        "Comments",
+        "Blank",
+        "Lines"
+    );
+    for (name, count) in rows {
+        color_println!(
+            WHITE,
+            "{:<width$}  {:>6}  {:>8}  {:>8}  {:>8}  {:>8}",
+            name,
+            count.files,
+            count.code,
+            count.comments,
+            count.blank,
+            count.lines()
+        );
+    }
+    color_println!(
+        BLUE,
+        "{:<width$}  {:>6}  {:>8}  {:>8}  {:>8}  {:>8}",
+        "Total",
+        total.files,
+        total.code,
+        total.comments,
+        total.blank,
+        total.lines()
+    );
+}
+
+// Counts the code, comment, and blank lines of the files of the project, and
+// prints them per language and per directory.
+pub(crate) fn print_line_counts(project: &Project, headers: bool) -> Result<()> {
+    let mut languages: BTreeMap<&str, LineCount> = BTreeMap::new();
+    let mut directories: BTreeMap<String, LineCount> = BTreeMap::new();
+    let mut total = LineCount::default();
+    for file in collect_counted_files(project, headers)? {
+        let Some(category) = get_category(project, &file) else {
+            continue;
+        };
+        let content = std::fs::read(&file)?;
+        let count = count_line_kinds(&String::from_utf8_lossy(&content), get_syntax(category));
+        let directory = Path::new(&file)
+            .parent()
+            .map(|parent| parent.to_string_lossy().to_string())
+            .unwrap_or_default();
+        languages.entry(category).or_default().add(&count);
+        directories.entry(directory).or_default().add(&count);
+        total.add(&count);
+    }
+
+    let languages: Vec<(String, LineCount)> = CATEGORIES
+        .iter()
+        .filter_map(|category| Some((category.to_string(), *languages.get(category)?)))
+        .collect();
+    let directories: Vec<(String, LineCount)> = directories.into_iter().collect();
+    print_table("Language", &languages, total);
+    println!();
+    print_table("Directory", &directories, total);
+    println!();
+
+    color_println!(
+        BLUE,
+        "The project contains {} lines of code, {} lines of comments ({})",
+        total.code,
+        total.comments,
+        detect_languages(project)?.describe()
+    );
+    Ok(())
+}
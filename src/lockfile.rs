@@ -0,0 +1,70 @@
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub(crate) const LOCKFILE: &str = "barge.lock";
+const LOCKFILE_VERSION: u64 = 1;
+
+static LOCK_MODE: AtomicU8 = AtomicU8::new(LockMode::Unlocked as u8);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum LockMode {
+    Unlocked,
+    // The lockfile must not change.
+    Locked,
+    // The lockfile must not change, and the network must not be accessed.
+    Frozen,
+}
+
+pub(crate) fn set_lock_mode(mode: LockMode) {
+    LOCK_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn get_lock_mode() -> LockMode {
+    match LOCK_MODE.load(Ordering::Relaxed) {
+        mode if mode == LockMode::Frozen as u8 => LockMode::Frozen,
+        mode if mode == LockMode::Locked as u8 => LockMode::Locked,
+        _ => LockMode::Unlocked,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LockedProject {
+    pub name: String,
+    pub git: String,
+    pub commit: String,
+    pub checksum: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Lockfile {
+    pub version: u64,
+    pub projects: Vec<LockedProject>,
+}
+
+impl Lockfile {
+    pub(crate) fn load() -> Result<Lockfile> {
+        match std::fs::read_to_string(LOCKFILE) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(_) => Ok(Lockfile {
+                version: LOCKFILE_VERSION,
+                projects: Vec::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        std::fs::write(LOCKFILE, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&LockedProject> {
+        self.projects.iter().find(|locked| locked.name == name)
+    }
+
+    pub(crate) fn set(&mut self, locked: LockedProject) {
+        self.projects.retain(|project| project.name != locked.name);
+        self.projects.push(locked);
+        self.projects.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
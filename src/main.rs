@@ -1,33 +1,139 @@
+use crate::add::add_library;
+use crate::analysis::{run_static_analysis, FixMode};
+use crate::annotations::AnnotationFormat;
+use crate::bench::run_benchmarks;
+use crate::bisect::{bisect, run_bisect_step};
+use crate::clangformat::write_clang_format;
+use crate::commandlog::{record_and_execute, replay_command};
+use crate::compdb::write_compilation_database;
+use crate::completions::{print_completion_values, print_completions, PROJECT_VALUE_KINDS};
+use crate::coverage::report_coverage;
+use crate::dashboard::serve_dashboard;
+use crate::deadcode::report_dead_code;
+use crate::doccoverage::report_doc_coverage;
+use crate::docserver::{open_documentation, serve_documentation};
+use crate::errorcodes::explain_error_code;
+use crate::explain::explain_build;
+use crate::externals::update_external_projects;
+use crate::guards::check_include_guards;
+use crate::info::{print_project_info, print_project_metadata};
+use crate::install::{install_project, DEFAULT_INSTALL_PREFIX};
+use crate::languages::LanguageKind;
+use crate::lines::print_line_counts;
+use crate::lockfile::{set_lock_mode, LockMode};
 use crate::makefile::BuildTarget;
+use crate::manpage::print_man_page;
+use crate::matrix::rebuild_configurations;
+use crate::objecthash::ObjectHash;
 use crate::output::*;
-use crate::project::{collect_source_files, CollectSourceFilesMode, Project, ProjectType};
+use crate::ownership::check_root_user;
+use crate::package::{create_package, PackageFormat};
+use crate::project::{
+    append_flags, find_project_file, format_project_file, get_standard_year, is_toml_file,
+    parse_project_value, Profile, Profiles, Project, ProjectType, Sanitizer, DEFAULT_CPP_STANDARD,
+    DEFAULT_C_STANDARD,
+};
 use crate::result::{print_error, BargeError, Result};
-use crate::utilities::{attempt_remove_directory, look_for_project_directory};
+use crate::sarif::parse_sarif_output;
+use crate::scaffold::create_new_file;
+use crate::schema::migrate_project_file;
+use crate::selfprofile::{enable_self_profiling, print_self_profile, ProfileSection};
+use crate::size::report_size;
+use crate::stats::print_stats;
+use crate::tui::build_with_tui;
+use crate::universal::build_universal_binary;
+use crate::userconfig::{apply_color_preference, load_user_config_silently};
+use crate::utilities::{
+    add_project_tools_to_path, attempt_remove_directory, has_uncommitted_changes,
+    look_for_project_directory, print_line_diff,
+};
+use crate::validate::validate_project_file;
+use crate::version::bump_project_version;
+use crate::watch::{watch_project, WatchAction};
+use crate::workspace::for_each_member;
 use std::fs::File;
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::path::Path;
+use std::process::Command;
 
+mod add;
+mod analysis;
+mod annotations;
+mod bench;
+mod bisect;
+mod clangformat;
+mod clangtidy;
+mod commandlog;
+mod compdb;
+mod completions;
+mod configure;
+mod coverage;
+mod dashboard;
+mod deadcode;
+mod dependencies;
+mod doccoverage;
+mod docserver;
+mod errorcodes;
+mod explain;
+mod externals;
+mod fastpath;
+mod guards;
+mod info;
+mod install;
+mod jsonc;
+mod languages;
+mod lines;
+mod lockfile;
 mod makefile;
+mod manpage;
+mod matrix;
+mod memory;
+mod notifications;
+mod objecthash;
+mod observer;
 mod output;
+mod ownership;
+mod package;
 mod project;
+mod records;
+mod resources;
 mod result;
+mod rustcrates;
+mod sarif;
+mod scaffold;
+mod scheduling;
+mod schema;
 mod scripts;
+mod selfprofile;
+mod size;
+mod staging;
+mod stats;
+mod toml;
+mod tui;
+mod universal;
+mod userconfig;
 mod utilities;
+mod validate;
+mod version;
+mod watch;
+mod workspace;
 
-fn init(name: String, project_type: ProjectType, json: bool) -> Result<()> {
+fn init(name: String, project_type: ProjectType, json: bool, toml: bool) -> Result<()> {
     std::fs::create_dir(name.clone())?;
     let project = Project::new(&name, project_type)?;
-    let mut file = File::create(name.clone() + "/barge.json")?;
-    let content = serde_json::to_string_pretty(&project)?;
-    file.write_all(content.as_bytes())?;
-    file.write_all(b"\n")?;
+    let path = match toml {
+        true => name.clone() + "/barge.toml",
+        false => name.clone() + "/barge.json",
+    };
+    let mut file = File::create(&path)?;
+    file.write_all(format_project_file(&path, &project)?.as_bytes())?;
 
     if !json {
         std::fs::create_dir(name.clone() + "/res")?;
         std::fs::create_dir(name.clone() + "/src")?;
         std::fs::create_dir(name.clone() + "/include")?;
         let mut file = File::create(name.clone() + "/.gitignore")?;
-        file.write_all("build/*\n".as_bytes())?;
+        file.write_all("build/*\ndeps/\ncompile_commands.json\nbarge-stats.json\n".as_bytes())?;
         let mut file = File::create(name.clone() + "/README.md")?;
         file.write_all(format!("# `{}`\n", &name).as_bytes())?;
         let mut file = File::create(name.clone() + "/Doxyfile")?;
@@ -36,12 +142,13 @@ fn init(name: String, project_type: ProjectType, json: bool) -> Result<()> {
         file.write_all(include_str!("template-doxygen-style.css").as_bytes())?;
         let mut file = File::create(name.clone() + "/src/main.cpp")?;
         file.write_all(include_str!("template-main.in").as_bytes())?;
+        write_clang_format(&project, Path::new(&name))?;
         Command::new("git").arg("init").arg(&name).output()?;
         color_println!(GREEN, "Project {} successfully created", &name);
     } else {
         color_println!(
             GREEN,
-            "JSON file for project {} successfully created",
+            "Project file for project {} successfully created",
             &name
         );
     }
@@ -55,56 +162,276 @@ fn clean() -> Result<()> {
     Ok(())
 }
 
-fn lines() -> Result<()> {
-    let sources = collect_source_files(CollectSourceFilesMode::All)?;
+fn get_project_file() -> Result<String> {
+    find_project_file(Path::new(""))
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or(BargeError::ProjectNotFound("Project file not found."))
+}
 
-    let cat = Command::new("cat")
-        .args(sources)
-        .stdout(Stdio::piped())
-        .spawn()?;
+fn upgrade(dry_run: bool) -> Result<()> {
+    let path = get_project_file()?;
+    let original = std::fs::read_to_string(&path)?;
+    let mut value = parse_project_value(&path, &original)?;
+    // The file is edited in place, so that its comments, formatting, and the
+    // fields unknown to this version of barge are kept.
+    let (changes, upgraded) = migrate_project_file(&mut value, &original, is_toml_file(&path))?;
+    serde_json::from_value::<Project>(value.clone())?;
+    if changes.is_empty() {
+        color_println!(BLUE, "The project file is already up to date");
+        return Ok(());
+    }
+    if parse_project_value(&path, &upgraded)? != value {
+        return Err(BargeError::FailedOperation(
+            "Could not upgrade the project file in place",
+        ));
+    }
 
-    let wc = Command::new("wc")
-        .arg("-l")
-        .stdin(Stdio::from(
-            cat.stdout
-                .ok_or(BargeError::NoneOption("Could not get file list"))?,
-        ))
-        .output()?
-        .stdout;
-    let mut wc = String::from(std::str::from_utf8(&wc)?);
-    wc.pop();
+    for change in &changes {
+        color_println!(BLUE, "{}", change);
+    }
+    print_line_diff(&original, &upgraded);
 
-    color_println!(BLUE, "The project contains {} lines of code", wc);
+    if dry_run {
+        color_println!(BLUE, "Dry run, the project file was not modified");
+    } else {
+        let backup = format!("{}.bak", path);
+        std::fs::write(&backup, original)?;
+        std::fs::write(&path, upgraded)?;
+        color_println!(
+            GREEN,
+            "The project file was upgraded successfully, the original was saved as {}",
+            backup
+        );
+    }
     Ok(())
 }
 
+fn load_project() -> Result<Project> {
+    let mut project = Project::load(&get_project_file()?)?;
+    apply_environment_overrides(&mut project)?;
+    Ok(project)
+}
+
 fn in_project_directory() -> bool {
-    let metadata = std::fs::metadata("barge.json");
-    if let Ok(metadata) = metadata {
-        metadata.is_file()
-    } else {
-        false
-    }
+    find_project_file(Path::new("")).is_some()
 }
 
 fn parse_build_target(target: Option<&String>) -> Result<BuildTarget> {
     if let Some(target) = target {
         BuildTarget::try_from(target.as_str())
+    } else if let Some(profile) = get_environment_override("BARGE_PROFILE") {
+        BuildTarget::try_from(profile.as_str())
     } else {
         Ok(BuildTarget::Debug)
     }
 }
 
-fn parse_and_run_subcommands() -> Result<()> {
-    let matches = clap::Command::new(env!("CARGO_PKG_NAME"))
+const BUILDING_SUBCOMMANDS: &[&str] = &[
+    "build", "rebuild", "run", "test", "bench", "debug", "coverage", "watch", "install", "package",
+    "bisect",
+];
+
+fn get_environment_override(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn apply_environment_overrides(project: &mut Project) -> Result<()> {
+    if let Some(toolset) = get_environment_override("BARGE_TOOLSET") {
+        project.toolset = Some(
+            serde_json::from_value(serde_json::Value::String(toolset)).map_err(|_| {
+                BargeError::InvalidValue("Invalid BARGE_TOOLSET, valid choices are: gnu, llvm")
+            })?,
+        );
+    }
+    if let Some(triple) = get_environment_override("BARGE_TARGET") {
+        project.target = Some(triple);
+    }
+    if let Some(launcher) = get_environment_override("BARGE_COMPILER_LAUNCHER") {
+        project.compiler_launcher = Some(launcher);
+    }
+    if let Some(jobs) = get_environment_override("BARGE_JOBS") {
+        project.jobs = Some(jobs.parse().ok().filter(|jobs| *jobs > 0).ok_or(
+            BargeError::InvalidValue("Invalid BARGE_JOBS, a positive integer is expected"),
+        )?);
+    }
+    Ok(())
+}
+
+fn jobs_arg() -> clap::Arg {
+    clap::arg!(-j --jobs <JOBS> "Number of parallel jobs used by make")
+        .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+}
+
+fn bits_arg() -> clap::Arg {
+    clap::arg!(--bits <BITS> "Build for the given bitness (16, 32, or 64)")
+        .value_parser(clap::value_parser!(u32))
+}
+
+fn sanitize_arg() -> clap::Arg {
+    clap::arg!(--sanitize <SANITIZER> "Sanitizer to build with (asan, tsan, msan, ubsan, or none)")
+        .value_parser(["asan", "tsan", "msan", "ubsan", "none"])
+}
+
+fn fresh_deps_arg() -> clap::Arg {
+    clap::arg!(--"fresh-deps" "Discard cached dependency information before building")
+}
+
+fn lock_args() -> [clap::Arg; 2] {
+    [
+        clap::arg!(--locked "Fail if the lockfile would need to be updated"),
+        clap::arg!(--frozen "Like --locked, and also fail if anything has to be fetched"),
+    ]
+}
+
+fn bin_arg() -> clap::Arg {
+    clap::arg!(--bin <NAME> "Run one of the additional binaries listed in the project file")
+}
+
+fn member_arg() -> clap::Arg {
+    clap::arg!(--member <NAME> "Only process the workspace member with the given name or directory")
+}
+
+fn multilib_arg() -> clap::Arg {
+    clap::arg!(--multilib "Build every multilib variant listed in the project file")
+}
+
+fn cross_target_arg() -> clap::Arg {
+    clap::arg!(--target <TRIPLE> "Cross-compile for the given target triple (e.g. aarch64-linux-gnu)")
+}
+
+fn annotations_arg() -> clap::Arg {
+    clap::arg!(--annotations <FORMAT> "Also print diagnostics as annotations (github or generic)")
+        .value_parser(["github", "generic"])
+}
+
+fn standard_args() -> [clap::Arg; 2] {
+    [
+        clap::arg!(--std <STD> "Override the C++ standard of the project (e.g. c++20)"),
+        clap::arg!(--"c-std" <STD> "Override the C standard of the project (e.g. c17)"),
+    ]
+}
+
+fn flag_args() -> [clap::Arg; 4] {
+    [
+        clap::arg!(--cflags <FLAGS> "Extra C compiler flags, appended after the ones of the project")
+            .allow_hyphen_values(true),
+        clap::arg!(--cxxflags <FLAGS> "Extra C++ compiler flags, appended after the ones of the project")
+            .allow_hyphen_values(true),
+        clap::arg!(--ldflags <FLAGS> "Extra linker flags, appended after the ones of the project")
+            .allow_hyphen_values(true),
+        clap::arg!(-D --define <DEFINE> "Define a preprocessor macro (NAME or NAME=VALUE) for C and C++")
+            .action(clap::ArgAction::Append),
+    ]
+}
+
+fn apply_standard_override(standard: &mut Option<String>, default: &str, value: &str) {
+    let current = standard.as_deref().unwrap_or(default);
+    match (get_standard_year(current), get_standard_year(value)) {
+        (Some(minimum), Some(requested)) if requested < minimum => color_println!(
+            YELLOW,
+            "Warning: the requested standard {} is older than the project standard {}",
+            value,
+            current
+        ),
+        (_, None) => color_println!(YELLOW, "Warning: unrecognized standard {}", value),
+        _ => {}
+    }
+    *standard = Some(value.to_string());
+}
+
+fn parse_annotation_format(args: &clap::ArgMatches) -> Result<Option<AnnotationFormat>> {
+    match args.try_get_one::<String>("annotations") {
+        Ok(Some(format)) => Ok(Some(AnnotationFormat::try_from(format.as_str())?)),
+        _ => Ok(None),
+    }
+}
+
+fn apply_build_overrides(project: &mut Project, args: &clap::ArgMatches) -> Result<()> {
+    if let Ok(Some(bits)) = args.try_get_one::<u32>("bits") {
+        project.bits = Some(*bits);
+    }
+    if let Ok(Some(jobs)) = args.try_get_one::<usize>("jobs") {
+        project.jobs = Some(*jobs);
+    }
+    if let Ok(Some(triple)) = args.try_get_one::<String>("target") {
+        project.target = Some(triple.clone());
+    }
+    if let Ok(Some(standard)) = args.try_get_one::<String>("std") {
+        apply_standard_override(&mut project.cpp_standard, DEFAULT_CPP_STANDARD, standard);
+    }
+    if let Ok(Some(standard)) = args.try_get_one::<String>("c-std") {
+        apply_standard_override(&mut project.c_standard, DEFAULT_C_STANDARD, standard);
+    }
+    if let Ok(Some(flags)) = args.try_get_one::<String>("cflags") {
+        project.append_custom_flags(LanguageKind::C, flags);
+    }
+    if let Ok(Some(flags)) = args.try_get_one::<String>("cxxflags") {
+        project.append_custom_flags(LanguageKind::Cpp, flags);
+    }
+    if let Ok(Some(flags)) = args.try_get_one::<String>("ldflags") {
+        append_flags(&mut project.custom_ldflags, flags);
+    }
+    if let Ok(Some(defines)) = args.try_get_many::<String>("define") {
+        let defines: Vec<String> = defines.map(|define| format!("-D{}", define)).collect();
+        for kind in [LanguageKind::C, LanguageKind::Cpp] {
+            project.append_custom_flags(kind, &defines.join(" "));
+        }
+    }
+    if let Ok(Some(true)) = args.try_get_one::<bool>("frozen") {
+        set_lock_mode(LockMode::Frozen);
+    } else if let Ok(Some(true)) = args.try_get_one::<bool>("locked") {
+        set_lock_mode(LockMode::Locked);
+    }
+    if let Some(format) = parse_annotation_format(args)? {
+        project.annotations = Some(format);
+    }
+    if let Ok(Some(sanitizer)) = args.try_get_one::<String>("sanitize") {
+        let sanitizer: Sanitizer =
+            serde_json::from_value(serde_json::Value::String(sanitizer.clone()))?;
+        let profiles = project.profiles.get_or_insert_with(Profiles::default);
+        for profile in [&mut profiles.debug, &mut profiles.release] {
+            profile.get_or_insert_with(Profile::default).sanitizer = Some(sanitizer);
+        }
+    }
+    Ok(())
+}
+
+fn select_variants(project: &Project, args: &clap::ArgMatches) -> Result<Vec<Project>> {
+    if args.get_flag("multilib") {
+        project.multilib_variants()
+    } else {
+        Ok(vec![project.clone()])
+    }
+}
+
+fn build_command() -> clap::Command {
+    clap::Command::new(env!("CARGO_PKG_NAME"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .version(env!("CARGO_PKG_VERSION"))
         .about("A simple tool for small assembly/C/C++ projects")
         .subcommand_required(true)
+        .arg(clap::arg!(--"allow-root" "Allow building as the root user").global(true))
+        .arg(
+            clap::arg!(--ascii "Plain output for screen readers, with no colors or graphics")
+                .visible_alias("accessible")
+                .global(true),
+        )
+        .arg(
+            clap::arg!(--"profile-self" "Report where barge itself spent its time after running")
+                .global(true),
+        )
+        .arg(
+            clap::arg!(-C --"project-dir" <DIR> "Run as if barge was started in the given project directory")
+                .global(true),
+        )
         .subcommand(
             clap::Command::new("init")
                 .about("Initializes a new project")
                 .arg(clap::arg!(--json "Create a barge.json file only in the target directory"))
+                .arg(clap::arg!(--toml "Write the project file as barge.toml instead of barge.json"))
                 .arg(clap::arg!(<NAME> "Name of the project"))
                 .arg(clap::arg!([TYPE] "Project type: executable, shared-lib, or static-lib")),
         )
@@ -112,18 +439,63 @@ fn parse_and_run_subcommands() -> Result<()> {
             clap::Command::new("build")
                 .alias("b")
                 .about("Builds the current project")
-                .arg(clap::arg!([TARGET] "Build target (debug or release)")),
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(
+                    clap::arg!(--explain "Print what would be rebuilt and why, without building")
+                        .visible_alias("dry-run"),
+                )
+                .arg(clap::arg!(--"dead-code" "Build with linker garbage collection and report unreferenced code"))
+                .arg(clap::arg!(--tui "Present the progress of the build in a full-screen terminal interface"))
+                .arg(
+                    clap::arg!(--arch <ARCHITECTURES> "Build a universal binary from the given comma-separated architectures (macOS only)")
+                        .value_delimiter(','),
+                )
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .arg(cross_target_arg())
+                .args(standard_args())
+                .args(flag_args())
+                .arg(annotations_arg())
+                .arg(fresh_deps_arg())
+                .args(lock_args())
+                .arg(multilib_arg()),
         )
         .subcommand(
             clap::Command::new("rebuild")
                 .about("Removes build artifacts and builds the current project")
-                .arg(clap::arg!([TARGET] "Build target (debug or release)")),
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .arg(
+                    cross_target_arg()
+                        .value_delimiter(',')
+                        .help("Cross-compile for the given comma-separated target triples"),
+                )
+                .arg(clap::arg!(--"all-profiles" "Rebuild both the debug and the release configuration"))
+                .args(standard_args())
+                .args(flag_args())
+                .arg(annotations_arg())
+                .arg(fresh_deps_arg())
+                .args(lock_args())
+                .arg(multilib_arg()),
         )
         .subcommand(
             clap::Command::new("run")
                 .alias("r")
                 .about("Builds and runs the current project (binary projects only)")
                 .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bin_arg())
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .args(standard_args())
+                .args(flag_args())
+                .arg(
+                    clap::arg!(--"rebuild-window" <SECONDS> "Skip the build if the executable was built within the given number of seconds")
+                        .value_parser(clap::value_parser!(u64)),
+                )
                 .arg(
                     clap::Arg::new("args")
                         .allow_hyphen_values(true)
@@ -136,6 +508,231 @@ fn parse_and_run_subcommands() -> Result<()> {
                 .alias("d")
                 .about("Builds and runs the current project in the debugger (binary projects only)")
                 .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bin_arg())
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .args(standard_args())
+                .arg(
+                    clap::Arg::new("args")
+                        .allow_hyphen_values(true)
+                        .last(true)
+                        .raw(true),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("test")
+                .alias("t")
+                .about("Builds and runs the tests of the current project")
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .args(standard_args())
+                .arg(annotations_arg())
+                .args(lock_args())
+                .arg(clap::arg!(--filter <TEXT> "Only run the tests whose path contains the given text")),
+        )
+        .subcommand(
+            clap::Command::new("bench")
+                .about("Builds and runs the benchmarks of the current project in release mode")
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .args(standard_args())
+                .arg(clap::arg!(--filter <TEXT> "Only run the benchmarks whose path contains the given text"))
+                .arg(
+                    clap::Arg::new("args")
+                        .allow_hyphen_values(true)
+                        .last(true)
+                        .raw(true),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("coverage")
+                .about("Measures the code coverage of the tests, or of the executable")
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(jobs_arg())
+                .arg(
+                    clap::Arg::new("args")
+                        .allow_hyphen_values(true)
+                        .last(true)
+                        .raw(true),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("compdb")
+                .about("Writes a compile_commands.json file for editors and other tools")
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(sanitize_arg())
+                .arg(cross_target_arg())
+                .args(standard_args()),
+        )
+        .subcommand(
+            clap::Command::new("watch")
+                .alias("w")
+                .about("Rebuilds the current project whenever its files change")
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .arg(cross_target_arg())
+                .args(standard_args())
+                .arg(clap::arg!(--run "Run the executable after each successful build"))
+                .arg(clap::arg!(--test "Run the tests after each successful build").conflicts_with("run")),
+        )
+        .subcommand(
+            clap::Command::new("install")
+                .about("Builds the current project and installs its artifacts")
+                .arg(clap::arg!([TARGET] "Build target (debug or release, default: release)"))
+                .arg(
+                    clap::arg!(--prefix <PREFIX> "Installation prefix")
+                        .default_value(DEFAULT_INSTALL_PREFIX),
+                )
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .args(standard_args()),
+        )
+        .subcommand(
+            clap::Command::new("package")
+                .about("Builds the current project and creates a distributable package")
+                .arg(clap::arg!([TARGET] "Build target (debug or release, default: release)"))
+                .arg(
+                    clap::arg!(--format <FORMAT> "Package format")
+                        .value_parser(["tar.gz", "deb", "rpm"])
+                        .default_value("tar.gz"),
+                )
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .arg(cross_target_arg())
+                .args(standard_args())
+                .args(lock_args())
+                .arg(clap::arg!(--output <ARTIFACT> "Copies the package to the path of the given named artifact")),
+        )
+        .subcommand(
+            clap::Command::new("artifact")
+                .about("Prints the path of a named artifact of the current project")
+                .arg(clap::arg!(<NAME> "Name of the artifact in the project file"))
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(cross_target_arg()),
+        )
+        .subcommand(
+            clap::Command::new("clean")
+                .about("Removes build artifacts")
+                .arg(member_arg()),
+        )
+        .subcommand(
+            clap::Command::new("info")
+                .about("Displays information about the project")
+                .visible_alias("metadata")
+                .arg(clap::arg!([TARGET] "Build target of the resolved flags and paths (debug or release)"))
+                .arg(clap::arg!(--json "Print the resolved metadata of the project as JSON"))
+                .arg(bits_arg())
+                .arg(cross_target_arg()),
+        )
+        .subcommand(
+            clap::Command::new("lines")
+                .about("Counts the source code lines in the project")
+                .arg(clap::arg!(--"no-headers" "Leave the header files out of the counts"))
+                .arg(member_arg()),
+        )
+        .subcommand(
+            clap::Command::new("stats")
+                .about("Records and shows the history of the project metrics")
+                .arg(clap::arg!(--lines "Plots the growth of the line count over time")),
+        )
+        .subcommand(
+            clap::Command::new("analyze")
+                .about("Runs static analysis on the project")
+                .arg(member_arg())
+                .arg(clap::arg!(--"include-guards" "Check the include guards of the project headers"))
+                .arg(clap::arg!(--fix "Apply the fixes suggested by clang-tidy, or rewrite the include guards"))
+                .arg(
+                    clap::arg!(--"fix-errors" "Apply the fixes suggested by clang-tidy, even for sources with compile errors")
+                        .conflicts_with_all(["fix", "include-guards"]),
+                )
+                .arg(clap::arg!(--"allow-dirty" "Apply the fixes even if the working tree has uncommitted changes"))
+                .arg(annotations_arg())
+                .arg(clap::arg!(--output <OUTPUT> "Write the diagnostics into a report, given as sarif:<PATH>"))
+                .arg(
+                    clap::arg!(--changed [REF] "Only analyze the sources changed since the git revision (HEAD by default), or including changed headers")
+                        .default_missing_value("HEAD")
+                        .conflicts_with("include-guards"),
+                )
+                .arg(jobs_arg().help("Number of files to analyze in parallel")),
+        )
+        .subcommand(
+            clap::Command::new("format")
+                .about("Formats the source code of the project")
+                .arg(clap::arg!(--check "List the source files which are not formatted, without changing them"))
+                .arg(
+                    clap::arg!(--changed [REF] "Only format the source files changed since the git revision (HEAD by default)")
+                        .default_missing_value("HEAD"),
+                )
+                .arg(member_arg()),
+        )
+        .subcommand(
+            clap::Command::new("doc")
+                .about("Generates HTML documentation for the project")
+                .arg(clap::arg!(--coverage "Report the documentation coverage of the project headers"))
+                .arg(
+                    clap::arg!(--"fail-under" <PERCENT> "Fail if the documentation coverage is below the given percentage")
+                        .value_parser(clap::value_parser!(f64))
+                        .requires("coverage"),
+                )
+                .arg(clap::arg!(--open "Open the generated documentation in the browser").conflicts_with("coverage"))
+                .arg(clap::arg!(--serve "Serve the generated documentation locally").conflicts_with("coverage"))
+                .arg(
+                    clap::arg!(--port <PORT> "Port to serve the documentation on")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("8000")
+                        .requires("serve"),
+                )
+                .arg(clap::arg!(--watch "Generate the documentation again when the sources change").requires("serve")),
+        )
+        .subcommand(
+            clap::Command::new("size")
+                .about("Builds the current project and reports the size of its objects")
+                .arg(clap::arg!([TARGET] "Build target (debug or release)"))
+                .arg(bits_arg())
+                .arg(jobs_arg())
+                .arg(sanitize_arg())
+                .arg(cross_target_arg())
+                .args(standard_args())
+                .arg(clap::arg!(--diff <REFERENCE> "Compare symbol sizes against a size manifest or git revision")),
+        )
+        .subcommand(
+            clap::Command::new("new-file")
+                .about("Creates a new source and header file pair from templates")
+                .arg(clap::arg!(<PATH> "Path of the new files relative to src and include"))
+                .arg(clap::arg!(--class <NAME> "Scaffold a C++ class with the given name"))
+                .arg(clap::arg!(--"header-only" "Only create the header file")),
+        )
+        .subcommand(
+            clap::Command::new("dashboard")
+                .about("Serves a local dashboard of the build results and reports")
+                .arg(
+                    clap::arg!(--port <PORT> "Port to listen on")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("8000"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("replay")
+                .about("Lists or re-runs the commands recorded during the last build")
+                .arg(clap::arg!([COMMAND] "Index of the command, or text to filter the commands by")),
+        )
+        .subcommand(
+            clap::Command::new("record-command")
+                .hide(true)
+                .arg(clap::arg!(--source <SOURCE> "Source file compiled by the command"))
+                .arg(clap::arg!(--message <MESSAGE> "Message printed before the command, unless it is skipped"))
+                .arg(clap::arg!(--output <OUTPUT> "Object file or binary produced by the command"))
+                .arg(clap::arg!(--inputs <INPUTS> "Space-separated files the object depends on"))
+                .arg(clap::arg!(--state <DIRECTORY> "Directory of the object content hashes"))
                 .arg(
                     clap::Arg::new("args")
                         .allow_hyphen_values(true)
@@ -143,22 +740,110 @@ fn parse_and_run_subcommands() -> Result<()> {
                         .raw(true),
                 ),
         )
-        .subcommand(clap::Command::new("clean").about("Removes build artifacts"))
         .subcommand(
-            clap::Command::new("lines").about("Counts the source code lines in the project"),
+            clap::Command::new("add")
+                .about("Adds an external library to the project file")
+                .arg(
+                    clap::arg!([LIBRARY] "Name of the pkg-config package")
+                        .required_unless_present_any(["cflags", "ldflags"])
+                        .conflicts_with_all(["cflags", "ldflags"]),
+                )
+                .arg(clap::arg!(--cflags <FLAGS> "Compiler flags of a manually specified library").allow_hyphen_values(true))
+                .arg(clap::arg!(--ldflags <FLAGS> "Linker flags of a manually specified library").allow_hyphen_values(true)),
+        )
+        .subcommand(
+            clap::Command::new("version")
+                .about("Displays or bumps the version of the project")
+                .subcommand(
+                    clap::Command::new("bump")
+                        .about("Bumps the version in the project file, then commits and tags it")
+                        .arg(clap::arg!(<VERSION> "The part to bump (major, minor, or patch), or the new version"))
+                        .arg(clap::arg!(--"no-git" "Do not create a commit and a tag for the new version")),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("update")
+                .about("Updates the locked versions of the external projects")
+                .arg(clap::arg!([NAME] "Name of the external project to update")),
+        )
+        .subcommand(
+            clap::Command::new("upgrade")
+                .about("Migrates the project file to the current schema version")
+                .visible_alias("migrate")
+                .arg(clap::arg!(--"dry-run" "Only print the changes, do not modify the project file")),
+        )
+        .subcommand(
+            clap::Command::new("validate")
+                .about("Checks the project file, reporting every unknown field and invalid value"),
+        )
+        .subcommand(
+            clap::Command::new("bisect")
+                .about("Finds the commit that broke the build (or the tests) with git bisect")
+                .arg(clap::arg!(<GOOD> "Revision where the project still worked"))
+                .arg(clap::arg!([BAD] "Revision where the project is broken (HEAD by default)"))
+                .arg(clap::arg!(--test "Run the tests at each step, not only the build"))
+                .arg(clap::arg!(--release "Build the release configuration at each step"))
+                .arg(jobs_arg()),
+        )
+        .subcommand(
+            clap::Command::new("bisect-step")
+                .hide(true)
+                .arg(clap::arg!(<TARGET> "Build target (debug or release)"))
+                .arg(clap::arg!(--test "Run the tests after building"))
+                .arg(jobs_arg()),
+        )
+        .subcommand(
+            clap::Command::new("completions")
+                .about("Prints the shell completion script of barge")
+                .arg(
+                    clap::arg!(<SHELL> "Shell to complete barge in")
+                        .value_parser(["bash", "zsh", "fish"]),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("complete-values")
+                .hide(true)
+                .arg(clap::arg!(<KIND> "Kind of names to print").value_parser(PROJECT_VALUE_KINDS)),
+        )
+        .subcommand(clap::Command::new("gen-man").hide(true))
+        .subcommand(
+            clap::Command::new("explain")
+                .about("Explains the causes and fixes of an error code, or lists all error codes")
+                .arg(clap::arg!([CODE] "Error code to explain, for example, B0001")),
         )
-        .subcommand(clap::Command::new("analyze").about("Runs static analysis on the project"))
-        .subcommand(clap::Command::new("format").about("Formats the source code of the project"))
-        .subcommand(clap::Command::new("doc").about("Generates HTML documentation for the project"))
-        .try_get_matches()?;
+}
+
+fn parse_and_run_subcommands() -> Result<()> {
+    // A malformed user configuration is reported when loading the project.
+    if let Ok(config) = load_user_config_silently() {
+        apply_color_preference(&config);
+    }
+
+    let matches = build_command().try_get_matches()?;
+
+    let mut explicit_project_dir = false;
+    if let Some((_, args)) = matches.subcommand() {
+        if args.get_flag("ascii") || get_environment_override("BARGE_ACCESSIBLE").is_some() {
+            enable_accessible_output();
+        }
+        // The directory is used as the project root as is, the parents of it
+        // are not searched for a project file.
+        if let Some(directory) = args.get_one::<String>("project-dir") {
+            if let Err(error) = std::env::set_current_dir(directory) {
+                color_eprintln!("Could not change to the directory {}", directory);
+                return Err(error.into());
+            }
+            explicit_project_dir = true;
+        }
+    }
 
     if let Some(init_args) = matches.subcommand_matches("init") {
         let project_name: &String = init_args
             .get_one("NAME")
             .ok_or(BargeError::NoneOption("Couldn't parse project name"))?;
 
-        let project_type = if let Some(&project_type) = (*init_args).get_one("TYPE") {
-            match project_type {
+        let project_type = if let Some(project_type) = init_args.get_one::<String>("TYPE") {
+            match project_type.as_str() {
                 "executable" => Ok(ProjectType::Executable),
                 "shared-lib" => Ok(ProjectType::SharedLibrary),
                 "shared-library" => Ok(ProjectType::SharedLibrary),
@@ -172,14 +857,87 @@ fn parse_and_run_subcommands() -> Result<()> {
 
         let json = init_args.contains_id("json") && *init_args.get_one("json").unwrap_or(&false);
         return if let Ok(project_type) = project_type {
-            init(project_name.to_string(), project_type, json)?;
+            init(
+                project_name.to_string(),
+                project_type,
+                json,
+                init_args.get_flag("toml"),
+            )?;
             std::process::exit(0);
         } else {
             project_type.map(|_| ())
         };
     }
 
-    let project_dir = look_for_project_directory()?;
+    if let Some(completions_args) = matches.subcommand_matches("completions") {
+        let shell: &String = completions_args
+            .get_one("SHELL")
+            .ok_or(BargeError::NoneOption("Couldn't parse shell name"))?;
+        return print_completions(build_command(), shell);
+    }
+
+    if matches.subcommand_matches("gen-man").is_some() {
+        return print_man_page(build_command());
+    }
+
+    if let Some(values_args) = matches.subcommand_matches("complete-values") {
+        let kind: &String = values_args
+            .get_one("KIND")
+            .ok_or(BargeError::NoneOption("Couldn't parse the kind of values"))?;
+        return print_completion_values(kind);
+    }
+
+    if let Some(explain_args) = matches.subcommand_matches("explain") {
+        return explain_error_code(explain_args.get_one("CODE"));
+    }
+
+    if let Some((subcommand, args)) = matches.subcommand() {
+        if args.get_flag("profile-self") {
+            enable_self_profiling(subcommand);
+        }
+    }
+
+    if let Some(record_args) = matches.subcommand_matches("record-command") {
+        let arguments: Vec<String> = record_args
+            .get_many::<String>("args")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default();
+        let object_hash = match (
+            record_args.get_one::<String>("state"),
+            record_args.get_one::<String>("output"),
+            record_args.get_one::<String>("inputs"),
+        ) {
+            (Some(state), Some(output), Some(inputs)) => {
+                Some(ObjectHash::new(state, output, inputs, &arguments))
+            }
+            _ => None,
+        };
+        std::process::exit(record_and_execute(
+            &arguments,
+            record_args.get_one::<String>("source"),
+            record_args.get_one::<String>("message"),
+            object_hash,
+        )?);
+    }
+
+    // Invoked by git bisect at each step, from the directory of the project,
+    // where an old commit may not even have a project file.
+    if let Some(step_args) = matches.subcommand_matches("bisect-step") {
+        let target = parse_build_target(step_args.get_one::<String>("TARGET"))?;
+        std::process::exit(run_bisect_step(target, step_args.get_flag("test"), || {
+            if !in_project_directory() {
+                return Err(BargeError::ProjectNotFound("Project file not found."));
+            }
+            let mut project = load_project()?;
+            apply_build_overrides(&mut project, step_args)?;
+            Ok(project)
+        }));
+    }
+
+    let project_dir = match explicit_project_dir {
+        true => std::env::current_dir()?,
+        false => look_for_project_directory()?,
+    };
     let previous_dir = std::env::current_dir()?;
     std::env::set_current_dir(project_dir)?;
 
@@ -187,47 +945,326 @@ fn parse_and_run_subcommands() -> Result<()> {
         std::env::set_current_dir(previous_dir)?;
         return Err(BargeError::ProjectNotFound("Project file not found."));
     }
+    add_project_tools_to_path()?;
 
-    let project = Project::load("barge.json")?;
+    if let Some(upgrade_args) = matches.subcommand_matches("upgrade") {
+        upgrade(upgrade_args.get_flag("dry-run"))?;
+        return Ok(std::env::set_current_dir(previous_dir)?);
+    }
+
+    if matches.subcommand_matches("validate").is_some() {
+        validate_project_file(&get_project_file()?)?;
+        return Ok(std::env::set_current_dir(previous_dir)?);
+    }
+
+    let section = ProfileSection::start("project file");
+    let mut project = load_project()?;
+    drop(section);
+
+    if let Some((subcommand, args)) = matches.subcommand() {
+        if BUILDING_SUBCOMMANDS.contains(&subcommand) {
+            check_root_user(
+                args.get_flag("allow-root")
+                    || get_environment_override("BARGE_ALLOW_ROOT").is_some(),
+                subcommand == "install",
+            )?;
+        }
+    }
     if let Some(build_args) = matches.subcommand_matches("build") {
         let target = parse_build_target(build_args.get_one::<String>("TARGET"))?;
-        project.build(target)?;
+        apply_build_overrides(&mut project, build_args)?;
+        for variant in select_variants(&project, build_args)? {
+            if build_args.get_flag("fresh-deps") {
+                variant.discard_dependency_cache(target)?;
+            }
+            if build_args.get_flag("explain") {
+                explain_build(&variant, target)?;
+            } else if build_args.get_flag("dead-code") {
+                report_dead_code(&variant, target)?;
+            } else if let Some(architectures) = build_args.get_many::<String>("arch") {
+                let architectures: Vec<String> = architectures.cloned().collect();
+                build_universal_binary(&variant, target, &architectures)?;
+            } else if build_args.get_flag("tui") {
+                build_with_tui(&variant, target)?;
+            } else {
+                variant.build(target)?;
+            }
+        }
     } else if let Some(rebuild_args) = matches.subcommand_matches("rebuild") {
-        let target = parse_build_target(rebuild_args.get_one::<String>("TARGET"))?;
-        project.rebuild(target)?;
+        let targets = if rebuild_args.get_flag("all-profiles") {
+            vec![BuildTarget::Debug, BuildTarget::Release]
+        } else {
+            vec![parse_build_target(
+                rebuild_args.get_one::<String>("TARGET"),
+            )?]
+        };
+        let triples: Vec<Option<String>> = match rebuild_args.get_many::<String>("target") {
+            Some(triples) => triples.cloned().map(Some).collect(),
+            None => vec![None],
+        };
+        apply_build_overrides(&mut project, rebuild_args)?;
+        rebuild_configurations(
+            &select_variants(&project, rebuild_args)?,
+            &targets,
+            &triples,
+            rebuild_args.get_flag("fresh-deps"),
+        )?;
     } else if let Some(run_args) = matches.subcommand_matches("run") {
         let target = parse_build_target(run_args.get_one::<String>("TARGET"))?;
+        apply_build_overrides(&mut project, run_args)?;
+        if let Some(window) = run_args.get_one::<u64>("rebuild-window") {
+            project.run_rebuild_window = Some(*window);
+        }
         let arguments = if let Some(args) = run_args.get_many::<String>("args") {
             args.cloned().collect()
         } else {
             vec![]
         };
-        project.run(target, arguments)?;
+        project.run(target, arguments, run_args.get_one::<String>("bin"))?;
+    } else if let Some(test_args) = matches.subcommand_matches("test") {
+        let target = parse_build_target(test_args.get_one::<String>("TARGET"))?;
+        apply_build_overrides(&mut project, test_args)?;
+        project.test(target, test_args.get_one::<String>("filter"))?;
+    } else if let Some(bench_args) = matches.subcommand_matches("bench") {
+        apply_build_overrides(&mut project, bench_args)?;
+        let arguments: Vec<String> = bench_args
+            .get_many::<String>("args")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default();
+        run_benchmarks(&project, bench_args.get_one::<String>("filter"), &arguments)?;
     } else if let Some(debug_args) = matches.subcommand_matches("debug") {
         let target = parse_build_target(debug_args.get_one::<String>("TARGET"))?;
+        apply_build_overrides(&mut project, debug_args)?;
         let arguments = if let Some(args) = debug_args.get_many::<String>("args") {
             args.cloned().collect()
         } else {
             vec![]
         };
-        project.debug(target, arguments)?;
-    } else if matches.subcommand_matches("clean").is_some() {
-        clean()?;
-    } else if matches.subcommand_matches("lines").is_some() {
-        lines()?;
-    } else if matches.subcommand_matches("analyze").is_some() {
-        project.analyze()?;
-    } else if matches.subcommand_matches("format").is_some() {
-        project.format()?;
-    } else if matches.subcommand_matches("doc").is_some() {
-        project.document()?;
+        project.debug(target, arguments, debug_args.get_one::<String>("bin"))?;
+    } else if let Some(coverage_args) = matches.subcommand_matches("coverage") {
+        let target = parse_build_target(coverage_args.get_one::<String>("TARGET"))?;
+        let arguments: Vec<String> = coverage_args
+            .get_many::<String>("args")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default();
+        apply_build_overrides(&mut project, coverage_args)?;
+        report_coverage(&project, target, &arguments)?;
+    } else if let Some(compdb_args) = matches.subcommand_matches("compdb") {
+        let target = parse_build_target(compdb_args.get_one::<String>("TARGET"))?;
+        apply_build_overrides(&mut project, compdb_args)?;
+        write_compilation_database(&project, target)?;
+    } else if let Some(watch_args) = matches.subcommand_matches("watch") {
+        let target = parse_build_target(watch_args.get_one::<String>("TARGET"))?;
+        let action = if watch_args.get_flag("run") {
+            WatchAction::Run
+        } else if watch_args.get_flag("test") {
+            WatchAction::Test
+        } else {
+            WatchAction::Build
+        };
+        watch_project(target, action, || {
+            let mut project = load_project()?;
+            apply_build_overrides(&mut project, watch_args)?;
+            Ok(project)
+        })?;
+    } else if let Some(install_args) = matches.subcommand_matches("install") {
+        let target = match install_args.get_one::<String>("TARGET") {
+            None if get_environment_override("BARGE_PROFILE").is_none() => BuildTarget::Release,
+            target => parse_build_target(target)?,
+        };
+        apply_build_overrides(&mut project, install_args)?;
+        let prefix = install_args
+            .get_one::<String>("prefix")
+            .map_or(DEFAULT_INSTALL_PREFIX, |prefix| prefix.as_str());
+        install_project(&project, target, prefix)?;
+    } else if let Some(package_args) = matches.subcommand_matches("package") {
+        let target = match package_args.get_one::<String>("TARGET") {
+            None if get_environment_override("BARGE_PROFILE").is_none() => BuildTarget::Release,
+            target => parse_build_target(target)?,
+        };
+        apply_build_overrides(&mut project, package_args)?;
+        let format = package_args
+            .get_one::<String>("format")
+            .map_or(Ok(PackageFormat::Tarball), |format| {
+                PackageFormat::try_from(format.as_str())
+            })?;
+        create_package(
+            &project,
+            target,
+            format,
+            package_args.get_one::<String>("output"),
+        )?;
+    } else if let Some(artifact_args) = matches.subcommand_matches("artifact") {
+        let target = parse_build_target(artifact_args.get_one::<String>("TARGET"))?;
+        if let Some(bits) = artifact_args.get_one::<u32>("bits") {
+            project.bits = Some(*bits);
+        }
+        if let Some(triple) = artifact_args.get_one::<String>("target") {
+            project.target = Some(triple.clone());
+        }
+        let name = artifact_args
+            .get_one::<String>("NAME")
+            .ok_or(BargeError::NoneOption("No artifact name was given"))?;
+        println!("{}", project.named_artifact(name, target)?);
+    } else if let Some(add_args) = matches.subcommand_matches("add") {
+        add_library(
+            &project,
+            add_args.get_one::<String>("LIBRARY"),
+            add_args.get_one::<String>("cflags"),
+            add_args.get_one::<String>("ldflags"),
+        )?;
+    } else if let Some(version_args) = matches.subcommand_matches("version") {
+        match version_args.subcommand_matches("bump") {
+            Some(bump_args) => bump_project_version(
+                &project,
+                bump_args
+                    .get_one::<String>("VERSION")
+                    .ok_or(BargeError::NoneOption("No version was given"))?,
+                !bump_args.get_flag("no-git"),
+            )?,
+            None => println!("{}", project.version),
+        }
+    } else if let Some(update_args) = matches.subcommand_matches("update") {
+        update_external_projects(&project, update_args.get_one::<String>("NAME"))?;
+    } else if let Some(bisect_args) = matches.subcommand_matches("bisect") {
+        let target = match bisect_args.get_flag("release") {
+            true => BuildTarget::Release,
+            false => parse_build_target(None)?,
+        };
+        let good: &String = bisect_args
+            .get_one("GOOD")
+            .ok_or(BargeError::NoneOption("Couldn't parse the good revision"))?;
+        bisect(
+            good,
+            bisect_args.get_one::<String>("BAD"),
+            target,
+            bisect_args.get_flag("test"),
+            bisect_args.get_one::<usize>("jobs").copied(),
+        )?;
+    } else if let Some(clean_args) = matches.subcommand_matches("clean") {
+        for_each_member(
+            &project,
+            clean_args.get_one::<String>("member"),
+            load_project,
+            |_| clean(),
+        )?;
+    } else if let Some(info_args) = matches.subcommand_matches("info") {
+        let target = parse_build_target(info_args.get_one::<String>("TARGET"))?;
+        if let Some(bits) = info_args.get_one::<u32>("bits") {
+            project.bits = Some(*bits);
+        }
+        if let Some(triple) = info_args.get_one::<String>("target") {
+            project.target = Some(triple.clone());
+        }
+        if info_args.get_flag("json") {
+            print_project_metadata(&project, target)?;
+        } else {
+            print_project_info(&project)?;
+        }
+    } else if let Some(lines_args) = matches.subcommand_matches("lines") {
+        let headers = !lines_args.get_flag("no-headers");
+        for_each_member(
+            &project,
+            lines_args.get_one::<String>("member"),
+            load_project,
+            |project| print_line_counts(project, headers),
+        )?;
+    } else if let Some(stats_args) = matches.subcommand_matches("stats") {
+        print_stats(&project, stats_args.get_flag("lines"))?;
+    } else if let Some(analyze_args) = matches.subcommand_matches("analyze") {
+        let jobs = analyze_args.get_one::<usize>("jobs").copied();
+        let annotations = parse_annotation_format(analyze_args)?;
+        let sarif = analyze_args
+            .get_one::<String>("output")
+            .map(|output| parse_sarif_output(output))
+            .transpose()?;
+        let changed = analyze_args.get_one::<String>("changed");
+        let fix = if analyze_args.get_flag("fix-errors") {
+            Some(FixMode::FixErrors)
+        } else if analyze_args.get_flag("fix") && !analyze_args.get_flag("include-guards") {
+            Some(FixMode::Fix)
+        } else {
+            None
+        };
+        if fix.is_some() && !analyze_args.get_flag("allow-dirty") && has_uncommitted_changes()? {
+            color_eprintln!(
+                "Commit or stash the changes of the working tree before applying fixes, or pass --allow-dirty"
+            );
+            return Err(BargeError::FailedOperation(
+                "The working tree has uncommitted changes",
+            ));
+        }
+        for_each_member(
+            &project,
+            analyze_args.get_one::<String>("member"),
+            load_project,
+            |project| {
+                if analyze_args.get_flag("include-guards") {
+                    check_include_guards(project, analyze_args.get_flag("fix"))
+                } else {
+                    run_static_analysis(
+                        project,
+                        jobs.or(project.jobs),
+                        annotations,
+                        sarif.as_deref(),
+                        fix,
+                        changed.map(String::as_str),
+                    )
+                }
+            },
+        )?;
+    } else if let Some(format_args) = matches.subcommand_matches("format") {
+        let check = format_args.get_flag("check");
+        let changed = format_args.get_one::<String>("changed");
+        for_each_member(
+            &project,
+            format_args.get_one::<String>("member"),
+            load_project,
+            |project| project.format(check, changed.map(String::as_str)),
+        )?;
+    } else if let Some(doc_args) = matches.subcommand_matches("doc") {
+        if doc_args.get_flag("coverage") {
+            let fail_under = doc_args.get_one::<f64>("fail-under").copied();
+            report_doc_coverage(&project, fail_under.or(project.doc_coverage_threshold))?;
+        } else {
+            project.document()?;
+            let open = doc_args.get_flag("open");
+            if doc_args.get_flag("serve") {
+                let port = *doc_args.get_one::<u16>("port").unwrap_or(&8000);
+                serve_documentation(&project, port, open, doc_args.get_flag("watch"))?;
+            } else if open {
+                open_documentation()?;
+            }
+        }
+    } else if let Some(replay_args) = matches.subcommand_matches("replay") {
+        replay_command(replay_args.get_one::<String>("COMMAND"))?;
+    } else if let Some(dashboard_args) = matches.subcommand_matches("dashboard") {
+        let port = *dashboard_args.get_one::<u16>("port").unwrap_or(&8000);
+        serve_dashboard(&project, port)?;
+    } else if let Some(size_args) = matches.subcommand_matches("size") {
+        let target = parse_build_target(size_args.get_one::<String>("TARGET"))?;
+        apply_build_overrides(&mut project, size_args)?;
+        report_size(&project, target, size_args.get_one::<String>("diff"))?;
+    } else if let Some(new_file_args) = matches.subcommand_matches("new-file") {
+        let path: &String = new_file_args
+            .get_one("PATH")
+            .ok_or(BargeError::NoneOption("Couldn't parse file path"))?;
+        create_new_file(
+            &project,
+            path,
+            new_file_args.get_one::<String>("class"),
+            new_file_args.get_flag("header-only"),
+        )?;
     }
 
     Ok(std::env::set_current_dir(previous_dir)?)
 }
 
 fn main() -> Result<()> {
-    if let Err(error) = parse_and_run_subcommands() {
+    let start_time = std::time::Instant::now();
+    let result = parse_and_run_subcommands();
+    print_self_profile(start_time.elapsed());
+    if let Err(error) = result {
         print_error(&error);
         std::process::exit(1);
     }
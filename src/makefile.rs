@@ -1,13 +1,25 @@
+use crate::commandlog::{get_recorder_prefix, record_and_output};
+use crate::configure::{get_config_header, get_generated_directory};
+use crate::externals::get_external_project_flags;
+use crate::languages::{detect_languages, get_language, languages, LanguageKind};
+use crate::memory::{get_generated_linker_script, is_linker_script_input};
 use crate::output::NO_COLOR;
 use crate::project::{
-    collect_source_files, get_toolset_executables, CollectSourceFilesMode, Library, Project,
-    ProjectType, DEFAULT_COBOL_STANDARD, DEFAULT_CPP_STANDARD, DEFAULT_CUSTOM_CFLAGS,
-    DEFAULT_CUSTOM_COBOLFLAGS, DEFAULT_CUSTOM_CXXFLAGS, DEFAULT_CUSTOM_FORTRANFLAGS,
-    DEFAULT_CUSTOM_LDFLAGS, DEFAULT_C_STANDARD, DEFAULT_FORTRAN_STANDARD, DEFAULT_TOOLSET,
+    collect_source_files, get_find_name_arguments, get_launcher_arguments, get_objcopy,
+    CollectSourceFilesMode, CommandTemplates, Library, OutputFormat, Project, ProjectType,
+    Sanitizer, SourceFlags, SourceKind, Toolset, DEFAULT_CUSTOM_LDFLAGS, DEFAULT_TOOLSET,
 };
+use crate::resources::get_version_resource_object;
 use crate::result::{BargeError, Result};
+use crate::rustcrates::get_rust_crate_flags;
+use crate::selfprofile::ProfileSection;
+use crate::size::get_binutils;
+use crate::utilities::{matches_glob, parallel_map};
+use crate::{color_eprintln, RED};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
@@ -42,6 +54,13 @@ impl TryFrom<&str> for BuildTarget {
     }
 }
 
+const SOURCE_FLAGS_DIRECTORY: &str = "sourceflags";
+
+static DEFAULT_COMMAND_TEMPLATES: CommandTemplates = CommandTemplates {
+    compile: BTreeMap::new(),
+    link: None,
+};
+
 macro_rules! get_field_or_default {
     ($field:expr, $default:ident) => {
         if let Some(field) = &$field {
@@ -52,126 +71,557 @@ macro_rules! get_field_or_default {
     };
 }
 
-fn get_cobol_ldflags() -> Result<String> {
-    let result = Command::new("cob-config").arg("--libs").output()?.stdout;
-    Ok(String::from_utf8(result)?)
+const COMMAND_TEMPLATE_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("ldflags", "$(LDFLAGS)"),
+    ("src", "$<"),
+    ("obj", "$@"),
+    ("objects", "$(OBJECTS)"),
+    ("out", "$@"),
+    ("target", "$(TARGET)"),
+];
+
+// Besides the fixed placeholders, the compiler and the flags of each language
+// can be used in the command templates, like {cc} and {cflags}.
+fn get_placeholder_replacement(placeholder: &str) -> Option<String> {
+    if let Some((_, replacement)) = COMMAND_TEMPLATE_PLACEHOLDERS
+        .iter()
+        .find(|(name, _)| *name == placeholder)
+    {
+        return Some(replacement.to_string());
+    }
+    languages().iter().find_map(|language| {
+        if placeholder == language.compiler_variable().to_lowercase() {
+            Some(format!("$({})", language.compiler_variable()))
+        } else if placeholder == language.flags_name() {
+            Some(format!("$({}FLAGS)", language.variable()))
+        } else {
+            None
+        }
+    })
 }
 
-pub(crate) fn generate_build_makefile(project: &Project, target: BuildTarget) -> Result<String> {
-    let common_cflags = "-Wall -Wextra -Wpedantic -Wshadow -Wconversion \
-                         -Wdouble-promotion -Wformat=2 -Iinclude -Isrc";
+fn expand_command_template(template: &str) -> Result<String> {
+    let template = template.replace('$', "$$");
+    let mut result = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or(BargeError::InvalidValue(
+            "Unterminated placeholder in command template",
+        ))?;
+        let placeholder = &rest[start + 1..start + end];
+        let replacement = get_placeholder_replacement(placeholder).ok_or(
+            BargeError::InvalidValue("Unknown placeholder in command template"),
+        )?;
+        result.push_str(&replacement);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
 
-    let (library_cflags, library_ldflags) = build_library_flags(&project.external_libraries)?;
+// Expands the ${NAME} variables in a field of the project file, which can be
+// the built-in ones describing the project and the build, or any environment
+// variable. An escaped $${ is left as it is, as make turns it into a literal
+// ${, like in `-Wl,-rpath,'$${ORIGIN}'`.
+pub(crate) fn expand_project_variables(
+    value: &str,
+    project: &Project,
+    target: BuildTarget,
+) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or(BargeError::InvalidValue(
+            "Unterminated variable in the project file",
+        ))?;
+        let name = &rest[start + 2..start + end];
+        let value = match name {
+            "PROJECT_NAME" => project.name.clone(),
+            "VERSION" => project.version.clone(),
+            "BUILD_TARGET" => target.to_string(),
+            "BUILD_DIR" => project.build_directory(target),
+            "PROJECT_DIR" => std::env::current_dir()?.to_string_lossy().to_string(),
+            _ => std::env::var(name).map_err(|_| {
+                color_eprintln!("The variable {} is not set", name);
+                BargeError::InvalidValue("Undefined variable in the project file")
+            })?,
+        };
+        result.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
 
-    let (target_cflags, target_ldflags) = match target {
-        BuildTarget::Debug => ("-Og -g -fsanitize=undefined -fsanitize-trap", "-ggdb"),
-        BuildTarget::Release => ("-DNDEBUG -O2 -ffast-math", "-s"),
+// The names of the ${NAME} variables referenced in the text, without the
+// escaped ones.
+pub(crate) fn get_referenced_variables(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let escaped = rest[..start].ends_with('$');
+        rest = &rest[start + 2..];
+        if let Some((name, _)) = rest.split_once('}').filter(|_| !escaped) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn expand_library_variables(
+    project: &Project,
+    target: BuildTarget,
+) -> Result<Option<Vec<Library>>> {
+    let Some(libraries) = &project.external_libraries else {
+        return Ok(None);
     };
+    let mut expanded = Vec::new();
+    for library in libraries {
+        expanded.push(match library {
+            Library::Manual { cflags, ldflags } => Library::Manual {
+                cflags: expand_project_variables(cflags, project, target)?,
+                ldflags: expand_project_variables(ldflags, project, target)?,
+            },
+            library => library.clone(),
+        });
+    }
+    Ok(Some(expanded))
+}
 
-    let toolset = if let Some(toolset) = &project.toolset {
-        toolset
+pub(crate) fn get_command(template: Option<&String>, default: &str) -> Result<String> {
+    if let Some(template) = template {
+        expand_command_template(template)
     } else {
-        DEFAULT_TOOLSET
+        Ok(default.to_string())
+    }
+}
+
+// Returns the main objects and the paths of the additional binaries of the
+// project, along with the rules linking them. Each binary is linked from its
+// own main object and the objects shared with the artifact of the project,
+// that is, every other object not defining main. The shared objects are only
+// looked up when linking, after all of them are built.
+fn get_binary_rules(project: &Project, link_command: &str) -> Result<(String, String, String)> {
+    let (mut mains, mut binaries) = (Vec::new(), Vec::new());
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let mut rules = format!(
+        "
+ARTIFACT_OBJECTS:=$(OBJECTS)
+SHARED_OBJECTS=$(shell for object in $(ARTIFACT_OBJECTS); do {} --defined-only -P $$object | grep -qE '^_?main ' || echo $$object; done)
+",
+        project.cross_tool(get_binutils(toolset).1)
+    );
+    for binary in project.bins.iter().flatten() {
+        let Some(main) = binary.main.trim_start_matches("./").strip_prefix("src/") else {
+            return Err(BargeError::InvalidValue(
+                "The main source of a binary must be in the src directory",
+            ));
+        };
+        if binary.name == project.artifact_name() || binary.name.contains('/') {
+            color_eprintln!("Invalid binary name {}", binary.name);
+            return Err(BargeError::InvalidValue("Invalid binary name"));
+        }
+        if !Path::new(&binary.main).is_file() {
+            color_eprintln!("The main source {} does not exist", binary.main);
+            return Err(BargeError::InvalidValue("Missing main source of a binary"));
+        }
+
+        let object = format!("$(BUILDDIR)/obj/{}.o", main);
+        let path = format!("$(BUILDDIR)/{}", binary.name);
+        rules += &format!(
+            "
+{path}: private OBJECTS = {object} $(SHARED_OBJECTS)
+{path}: {object} $(OBJECTS) $(LDSCRIPTS) $(EXTERNALS) $(BUILDDIR)/.ldflags
+\t@mkdir -p $(shell dirname $@)
+\t@$(RECORD_OBJECT) --message \"$(GREEN)Linking executable $@$(RESET)\" -- {link_command}
+\t@printf '%sBuilt target %s%s\\n' $(BLUE) {name} $(RESET)
+",
+            name = binary.name
+        );
+        mains.push(object);
+        binaries.push(path);
+    }
+    if binaries.is_empty() {
+        rules.clear();
+    }
+    Ok((mains.join(" "), binaries.join(" "), rules))
+}
+
+pub(crate) struct BuildFlags {
+    pub compile: BTreeMap<LanguageKind, String>,
+    pub ldflags: String,
+    pub sourceflags: String,
+    pub source_stamps: Vec<(String, String)>,
+    pub external_artifacts: Vec<String>,
+}
+
+impl BuildFlags {
+    pub(crate) fn compile_flags(&self, kind: LanguageKind) -> &String {
+        &self.compile[&kind]
+    }
+
+    pub(crate) fn compile_flags_mut(&mut self, kind: LanguageKind) -> &mut String {
+        self.compile.entry(kind).or_default()
+    }
+
+    pub(crate) fn stamps(&self) -> Vec<(&'static str, &String)> {
+        languages()
+            .iter()
+            .map(|language| (language.stamp(), self.compile_flags(language.kind())))
+            .chain([(".ldflags", &self.ldflags)])
+            .collect()
+    }
+}
+
+// The flags shared by the compilers of the languages, each of them picks the
+// ones it understands.
+pub(crate) struct SharedFlags {
+    // The warnings, the libraries, the optimization, and the sanitizers, which
+    // precede the custom flags.
+    pub leading: String,
+    // The code generation and the target, which follow the custom flags.
+    pub trailing: String,
+    pub bits: String,
+}
+
+fn get_sanitizer_flags(
+    project: &Project,
+    target: BuildTarget,
+) -> Result<(&'static str, &'static str)> {
+    // The sanitizer runtime is usually not available for cross targets, so
+    // only the trapping undefined behavior sanitizer is enabled by default.
+    let sanitizer = match project
+        .profile(target)
+        .and_then(|profile| profile.sanitizer)
+    {
+        Some(sanitizer) => sanitizer,
+        None if target == BuildTarget::Debug && project.target.is_none() => Sanitizer::Undefined,
+        None => Sanitizer::None,
     };
 
-    let c_std = get_field_or_default!(project.c_standard, DEFAULT_C_STANDARD);
-    let cpp_std = get_field_or_default!(project.cpp_standard, DEFAULT_CPP_STANDARD);
-    let fortran_std = get_field_or_default!(project.fortran_standard, DEFAULT_FORTRAN_STANDARD);
-    let cobol_std = get_field_or_default!(project.cobol_standard, DEFAULT_COBOL_STANDARD);
-    let custom_cflags = get_field_or_default!(project.custom_cflags, DEFAULT_CUSTOM_CFLAGS);
-    let custom_cxxflags = get_field_or_default!(project.custom_cxxflags, DEFAULT_CUSTOM_CXXFLAGS);
-    let custom_ldflags = get_field_or_default!(project.custom_ldflags, DEFAULT_CUSTOM_LDFLAGS);
-    let custom_fortranflags =
-        get_field_or_default!(project.custom_fortranflags, DEFAULT_CUSTOM_FORTRANFLAGS);
-    let custom_cobolflags =
-        get_field_or_default!(project.custom_cobolflags, DEFAULT_CUSTOM_COBOLFLAGS);
-
-    let (c_compiler, cpp_compiler, fortran_compiler) = get_toolset_executables(toolset);
-
-    let pic_flag = if project.project_type != ProjectType::Executable {
-        "-fPIC"
-    } else {
-        ""
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    match sanitizer {
+        Sanitizer::Address => Ok((
+            " -fsanitize=address -fno-omit-frame-pointer",
+            " -fsanitize=address",
+        )),
+        Sanitizer::Thread => Ok((" -fsanitize=thread", " -fsanitize=thread")),
+        Sanitizer::Memory if *toolset == Toolset::Llvm => Ok((
+            " -fsanitize=memory -fsanitize-memory-track-origins -fno-omit-frame-pointer",
+            " -fsanitize=memory",
+        )),
+        Sanitizer::Memory => Err(BargeError::InvalidValue(
+            "The memory sanitizer is only supported by the LLVM toolset",
+        )),
+        Sanitizer::Undefined => Ok((" -fsanitize=undefined -fsanitize-trap", "")),
+        Sanitizer::None => Ok(("", "")),
+    }
+}
+
+fn get_link_flags(project: &Project) -> Result<String> {
+    let Some(link) = &project.link else {
+        return Ok(String::new());
     };
 
-    let c_dependencies = get_dependencies_for_project(target, "c")?;
-    let cpp_dependencies = get_dependencies_for_project(target, "cpp")?;
-
-    let cflags = String::from("-std=")
-        + c_std
-        + " "
-        + common_cflags
-        + " "
-        + &library_cflags
-        + " "
-        + target_cflags
-        + " "
-        + custom_cflags
-        + pic_flag;
-
-    let cxxflags = String::from("-std=")
-        + cpp_std
-        + " "
-        + common_cflags
-        + " "
-        + &library_cflags
-        + " "
-        + target_cflags
-        + " "
-        + custom_cxxflags
-        + pic_flag;
-
-    let fortranflags = String::from("-std=") + fortran_std + " " + custom_fortranflags;
-
-    let has_fortran_sources = collect_source_files(CollectSourceFilesMode::All)?
-        .iter()
-        .any(|source| source.ends_with(".f90"));
-    let fortran_ldflags = if has_fortran_sources {
-        "-lgfortran"
-    } else {
-        ""
+    let mut flags = String::new();
+    if let Some(entry) = &link.entry {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$';
+        if entry.is_empty()
+            || entry.starts_with(|c: char| c.is_ascii_digit())
+            || !entry.chars().all(valid)
+        {
+            return Err(BargeError::InvalidValue(
+                "The entry point of the link options is not a valid symbol name",
+            ));
+        }
+        flags += &format!(" -Wl,-e,{}", entry);
+    }
+    if link.no_startup_files == Some(true) {
+        flags += " -nostartfiles";
+    }
+    if let Some(subsystem) = &link.subsystem {
+        if !project.targets_windows() {
+            return Err(BargeError::InvalidValue(
+                "The subsystem of the link options can only be set for Windows targets",
+            ));
+        }
+        let name = serde_json::to_value(subsystem)?;
+        flags += &format!(" -Wl,--subsystem,{}", name.as_str().unwrap_or_default());
+    }
+    Ok(flags)
+}
+
+fn get_cross_flags(project: &Project) -> String {
+    let mut cross_flag = match (
+        &project.target,
+        project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET),
+    ) {
+        (Some(triple), Toolset::Llvm) => format!(" --target={}", triple),
+        _ => String::new(),
+    };
+    if let Some(architecture) = &project.architecture {
+        cross_flag += &format!(" -arch {}", architecture);
+    }
+    cross_flag
+}
+
+// The flags selecting the target of the compiler, without anything else
+// affecting the generated code.
+pub(crate) fn get_target_flags(project: &Project) -> String {
+    let bits_flag = project
+        .bits
+        .map(|bits| format!(" -m{}", bits))
+        .unwrap_or_default();
+    bits_flag + &get_cross_flags(project)
+}
+
+pub(crate) fn resolve_build_flags(project: &Project, target: BuildTarget) -> Result<BuildFlags> {
+    let common_cflags = "-Wall -Wextra -Wpedantic -Wshadow -Wconversion \
+                         -Wdouble-promotion -Wformat=2 -Iinclude -Isrc";
+
+    let (library_cflags, library_ldflags) =
+        build_library_flags(&expand_library_variables(project, target)?)?;
+    let (external_cflags, mut external_ldflags, mut external_artifacts) =
+        get_external_project_flags(project, target)?;
+    let (rust_ldflags, rust_archives) = get_rust_crate_flags(project, target)?;
+    external_ldflags += &rust_ldflags;
+    external_artifacts.extend(rust_archives);
+    if let Some(resource) = get_version_resource_object(project, target) {
+        external_ldflags += &format!(" {}", resource);
+        external_artifacts.push(resource);
+    }
+
+    let generated_cflags = match project.configure_checks {
+        Some(_) => format!(
+            " -I{}",
+            get_generated_directory(&project.build_directory(target))
+        ),
+        None => String::new(),
+    };
+
+    let (sanitizer_cflags, sanitizer_ldflags) = get_sanitizer_flags(project, target)?;
+    let (target_cflags, target_ldflags) = match target {
+        BuildTarget::Debug => ("-Og -g", "-ggdb"),
+        BuildTarget::Release => ("-DNDEBUG -O2 -ffast-math", "-s"),
     };
 
-    let cobolflags = String::from("-std=") + cobol_std + " " + custom_cobolflags;
+    let expand = |value: &str| expand_project_variables(value, project, target);
+    let custom_ldflags = &expand(get_field_or_default!(
+        project.custom_ldflags,
+        DEFAULT_CUSTOM_LDFLAGS
+    ))?;
+
+    let pie = project.profile(target).and_then(|profile| profile.pie);
+    let (pic_flag, pie_ldflag) = match (&project.project_type, pie) {
+        (ProjectType::Executable, Some(true)) => (" -fPIE", " -pie"),
+        (ProjectType::Executable, Some(false)) => (" -fno-PIE", " -no-pie"),
+        (ProjectType::Executable, None) => ("", ""),
+        _ => (" -fPIC", ""),
+    };
 
-    let has_cobol_sources = collect_source_files(CollectSourceFilesMode::All)?
-        .iter()
-        .any(|source| source.ends_with(".cob"));
-    let cobol_ldflags = if has_cobol_sources {
-        get_cobol_ldflags()?
-    } else {
-        String::new()
+    let bits_flag = project
+        .bits
+        .map(|bits| format!(" -m{}", bits))
+        .unwrap_or_default();
+    let cross_flag = get_cross_flags(project);
+
+    let shared = SharedFlags {
+        leading: format!(
+            "{} {} {}{}",
+            common_cflags, library_cflags, target_cflags, sanitizer_cflags
+        ),
+        trailing: format!(
+            "{}{}{}{}{}",
+            pic_flag, bits_flag, cross_flag, external_cflags, generated_cflags
+        ),
+        bits: bits_flag.clone(),
     };
+    let mut compile = BTreeMap::new();
+    for language in languages() {
+        let custom = project
+            .custom_flags
+            .get(&language.kind())
+            .map_or(Ok(String::new()), |custom| expand(custom))?;
+        compile.insert(
+            language.kind(),
+            language.compile_flags(project, &shared, &custom),
+        );
+    }
 
-    let ldscriptflags = collect_source_files(CollectSourceFilesMode::LinkerScriptsOnly)?
-        .iter()
+    let mix = detect_languages(project)?;
+    let mut language_ldflags = Vec::new();
+    for language in languages().iter().filter(|l| mix.contains(l.kind())) {
+        let ldflags = language.extra_ldflags()?;
+        if !ldflags.is_empty() {
+            language_ldflags.push(ldflags);
+        }
+    }
+
+    let generated_ldscript = get_generated_linker_script(project, &project.build_directory(target));
+    let ldscriptflags = collect_source_files(project, CollectSourceFilesMode::LinkerScriptsOnly)?
+        .into_iter()
+        .filter(|f| !is_linker_script_input(project, f))
+        .chain(generated_ldscript)
         .map(|f| format!("-T {}", f))
         .collect::<Vec<_>>()
         .join(" ");
 
+    let link_flags = get_link_flags(project)?;
     let ldflags = format!(
-        "{} {} {} {} {} {}",
+        "{}{}{}{}{}{}{} {} {} {} {}",
         target_ldflags,
+        sanitizer_ldflags,
+        bits_flag,
+        cross_flag,
+        pie_ldflag,
+        link_flags,
+        external_ldflags,
         library_ldflags,
         custom_ldflags,
-        fortran_ldflags,
-        cobol_ldflags.trim(),
+        language_ldflags.join(" "),
         ldscriptflags
     );
 
-    let name = match project.project_type {
-        ProjectType::Executable => project.name.clone(),
-        ProjectType::SharedLibrary => "lib".to_string() + &project.name + ".so",
-        ProjectType::StaticLibrary => "lib".to_string() + &project.name + ".a",
+    let mut flags = BuildFlags {
+        compile,
+        ldflags,
+        sourceflags: String::new(),
+        source_stamps: Vec::new(),
+        external_artifacts,
     };
 
-    let link_command = match project.project_type {
-        ProjectType::Executable => "@$(CXX) $(OBJECTS) -o $@ $(LDFLAGS)",
-        ProjectType::SharedLibrary => "@$(CXX) -shared $(OBJECTS) -o $@ $(LDFLAGS)",
-        ProjectType::StaticLibrary => "@ar rcs $@ $(OBJECTS)",
+    if project.respect_env_flags == Some(true) {
+        append_environment_flags(&mut flags);
+    }
+    (flags.sourceflags, flags.source_stamps) = get_source_flag_rules(project, target, &flags)?;
+
+    Ok(flags)
+}
+
+fn is_removed_flag(flag: &str, removed: &[String]) -> bool {
+    removed
+        .iter()
+        .any(|removed| match removed.strip_suffix('*') {
+            Some(prefix) => flag.starts_with(prefix),
+            None => flag == removed,
+        })
+}
+
+// Applies the source_flags entries matching the source file to the flags of
+// its compiler, in the order they are declared in the project file.
+pub(crate) fn get_source_flags(
+    project: &Project,
+    target: BuildTarget,
+    source: &str,
+    flags: &str,
+) -> Result<Option<String>> {
+    let overrides: Vec<&SourceFlags> = project
+        .source_flags
+        .iter()
+        .flatten()
+        .filter(|entry| matches_glob(&entry.pattern, source))
+        .collect();
+    if overrides.is_empty() {
+        return Ok(None);
+    }
+
+    let mut result: Vec<String> = flags.split_whitespace().map(String::from).collect();
+    for entry in overrides {
+        if let Some(removed) = &entry.remove_flags {
+            result.retain(|flag| !is_removed_flag(flag, removed));
+        }
+        if let Some(added) = &entry.flags {
+            let added = expand_project_variables(added, project, target)?;
+            result.extend(added.split_whitespace().map(String::from));
+        }
+    }
+    Ok(Some(result.join(" ")))
+}
+
+// The stamp of the overridden flags of an object, named after its source like
+// the object itself.
+pub(crate) fn get_source_flag_stamp(build_directory: &str, name: &str) -> String {
+    format!(
+        "{}/{}/{}.flags",
+        build_directory, SOURCE_FLAGS_DIRECTORY, name
+    )
+}
+
+// Overrides the compiler flags of the matching objects with target-specific
+// variables. Each overridden object depends on a stamp of its own flags, so
+// that changing an override only rebuilds the objects it matches. Returns the
+// rules, and the names of the overridden sources with their flags.
+fn get_source_flag_rules(
+    project: &Project,
+    target: BuildTarget,
+    flags: &BuildFlags,
+) -> Result<(String, Vec<(String, String)>)> {
+    if project.source_flags.is_none() {
+        return Ok((String::new(), Vec::new()));
+    }
+
+    let build_directory = project.build_directory(target);
+    let mut rules = String::new();
+    let mut stamps = Vec::new();
+    for source in collect_source_files(project, CollectSourceFilesMode::CCppSourcesOnly)? {
+        let Some(SourceKind::Language(kind)) = project.source_kind(&source) else {
+            continue;
+        };
+        let variable = format!("{}FLAGS", get_language(kind).variable());
+        let base = flags.compile_flags(kind);
+        let Some(source_flags) = get_source_flags(project, target, &source, base)? else {
+            continue;
+        };
+        let name = source.strip_prefix("src/").unwrap_or(&source);
+        let object = format!("{}/obj/{}.o", build_directory, name);
+        let stamp = get_source_flag_stamp(&build_directory, name);
+        rules += &format!("{object}: private {variable} = {source_flags}\n");
+        rules += &format!("{object}: {stamp}\n");
+        stamps.push((name.to_string(), source_flags));
+    }
+    Ok((rules, stamps))
+}
+
+fn append_environment_flags(flags: &mut BuildFlags) {
+    let append = |flags: &mut String, variables: &[&str]| {
+        for variable in variables {
+            let value = std::env::var(variable).unwrap_or_default();
+            if !value.trim().is_empty() {
+                flags.push(' ');
+                flags.push_str(value.trim());
+            }
+        }
     };
+    for language in languages() {
+        append(
+            flags.compile_flags_mut(language.kind()),
+            language.environment_flags(),
+        );
+    }
+    append(&mut flags.ldflags, &["LDFLAGS"]);
+}
 
-    let colorization = if *NO_COLOR {
+pub(crate) fn get_find_expression(extensions: &[String]) -> String {
+    get_find_name_arguments(extensions)
+        .iter()
+        .map(|argument| match argument.as_str() {
+            "(" | ")" => format!("\\{}", argument),
+            "-o" | "-name" => argument.clone(),
+            pattern => format!("'{}'", pattern),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn get_colorization() -> &'static str {
+    if *NO_COLOR {
         "
 GREEN=''
 BLUE=''
@@ -185,106 +635,304 @@ BLUE=`tput setaf 4``tput bold`
 RESET=`tput sgr0`
 DIM=`tput dim`
         "
+    }
+}
+
+pub(crate) fn generate_build_makefile(
+    project: &Project,
+    target: BuildTarget,
+    flags: &BuildFlags,
+) -> Result<String> {
+    let toolset = if let Some(toolset) = &project.toolset {
+        toolset
+    } else {
+        DEFAULT_TOOLSET
+    };
+
+    let build_directory = project.build_directory(target);
+    let templates = project
+        .command_templates
+        .as_ref()
+        .unwrap_or(&DEFAULT_COMMAND_TEMPLATES);
+    let (mut variables, mut sources, mut objects) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut dependencies, mut compile_rules) = (Vec::new(), Vec::new());
+    for language in languages() {
+        variables.push(language.variables(project, flags));
+        sources.push(format!("$({}SRC)", language.variable()));
+        objects.push(format!("$({}OBJ)", language.variable()));
+        let language_dependencies = language.dependencies(project, &build_directory)?;
+        if !language_dependencies.is_empty() {
+            dependencies.push(language_dependencies);
+        }
+        compile_rules.push(language.compile_rule(templates)?);
+    }
+
+    let name = project.artifact_name();
+
+    let (converted, conversion_rule) = match project.output_format {
+        None | Some(OutputFormat::Elf) => (String::new(), String::new()),
+        Some(format) => {
+            if project.project_type != ProjectType::Executable {
+                return Err(BargeError::InvalidValue(
+                    "Output formats other than elf are only supported for executables",
+                ));
+            }
+
+            let bfd_name = match format {
+                OutputFormat::Bin => "binary",
+                OutputFormat::Hex => "ihex",
+                OutputFormat::Srec => "srec",
+                OutputFormat::Elf => unreachable!(),
+            };
+
+            let rule = format!(
+                "
+$(CONVERTED): $(BINARY)
+\t@printf '%sConverting executable to %s%s\\n' $(GREEN) $@ $(RESET)
+\t@$(RECORD) -- {} -O {} $< $@
+",
+                project.cross_tool(get_objcopy(toolset)),
+                bfd_name
+            );
+            let converted = project.converted_artifact_path(target).unwrap_or_default();
+            (converted, rule)
+        }
     };
 
+    let linker = format!(
+        "$({})",
+        get_language(detect_languages(project)?.linker()).compiler_variable()
+    );
+    let default_link_command = match project.project_type {
+        ProjectType::Executable => format!("{} $(OBJECTS) -o $@ $(LDFLAGS)", linker),
+        ProjectType::SharedLibrary => format!("{} -shared $(OBJECTS) -o $@ $(LDFLAGS)", linker),
+        ProjectType::StaticLibrary => format!("{} rcs $@ $(OBJECTS)", project.cross_tool("ar")),
+    };
+
+    let link_command = get_command(templates.link.as_ref(), &default_link_command)?;
+    let (mains, binaries, binary_rules) = match project.project_type {
+        ProjectType::Executable => get_binary_rules(project, &link_command)?,
+        _ => get_binary_rules(project, &format!("{} $(OBJECTS) -o $@ $(LDFLAGS)", linker))?,
+    };
+    let colorization = get_colorization();
+    let launcher = get_launcher_arguments(project.compiler_launcher.as_deref()).join(" ");
+    let generated_ldscript = get_generated_linker_script(project, &build_directory)
+        .map(|path| format!(" {}", path))
+        .unwrap_or_default();
+
     let result = format!(
         include_str!("template-makefile-build.in"),
         target.to_string(),
-        c_compiler,
-        cflags,
-        cpp_compiler,
-        cxxflags,
-        fortran_compiler,
-        fortranflags,
-        cobolflags,
-        ldflags,
+        build_directory,
+        get_recorder_prefix()?,
+        launcher,
+        variables.join("\n"),
+        flags.ldflags,
+        get_find_expression(&project.extensions(SourceKind::LinkerScript)),
+        generated_ldscript,
+        get_config_header(project, &build_directory).unwrap_or_default(),
+        flags.external_artifacts.join(" "),
         name,
+        converted,
+        sources.join(" "),
+        mains,
+        binaries,
+        objects.join(" "),
         colorization,
-        c_dependencies,
-        cpp_dependencies,
-        link_command
+        dependencies.join("\n"),
+        flags.sourceflags,
+        link_command,
+        conversion_rule,
+        binary_rules,
+        compile_rules.join("\n")
     );
 
     Ok(result)
 }
 
-pub(crate) fn generate_analyze_makefile(project: &Project) -> Result<String> {
-    let c_std = get_field_or_default!(project.c_standard, DEFAULT_C_STANDARD);
-    let cpp_std = get_field_or_default!(project.cpp_standard, DEFAULT_CPP_STANDARD);
-    Ok(format!(
-        include_str!("template-makefile-analyze.in"),
-        c_std, cpp_std
-    ))
+fn write_stamp(path: &str, value: &str) -> Result<()> {
+    if std::fs::read_to_string(path).ok().as_deref() != Some(value) {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, value)?;
+    }
+    Ok(())
 }
 
-fn get_dependencies_for_project(target: BuildTarget, extension: &str) -> Result<String> {
-    let sources = Command::new("find")
-        .arg("src")
-        .args(vec!["-type", "f"])
-        .args(vec!["-name", format!("*.{}", extension).as_str()])
-        .output()?
-        .stdout;
-    let mut sources: Vec<&str> = std::str::from_utf8(&sources)?.split('\n').collect();
-    sources.retain(|source| !source.is_empty());
+fn collect_stamps(directory: &Path, stamps: &mut Vec<PathBuf>) {
+    for path in std::fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            collect_stamps(&path, stamps);
+        } else if path.extension().is_some_and(|e| e == "flags") {
+            stamps.push(path);
+        }
+    }
+}
 
-    let dependencies: Vec<_> = sources
-        .iter()
-        .map(|file| {
-            let object = if let Some(name) = file.strip_prefix("src/") {
-                format!("build/{}/obj/{}.o", target, name)
-            } else {
-                String::from("")
-            };
+// Removes the stamps of the objects which are no longer overridden, along with
+// the objects themselves, as they were built with the overridden flags.
+fn remove_stale_source_stamps(build_directory: &str, flags: &BuildFlags) -> Result<()> {
+    let directory = Path::new(build_directory).join(SOURCE_FLAGS_DIRECTORY);
+    let mut stamps = Vec::new();
+    collect_stamps(&directory, &mut stamps);
+    for stamp in stamps {
+        let Some(name) = stamp
+            .strip_prefix(&directory)
+            .ok()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".flags"))
+        else {
+            continue;
+        };
+        if !flags.source_stamps.iter().any(|(source, _)| source == name) {
+            let object = format!("{}/obj/{}.o", build_directory, name);
+            if Path::new(&object).exists() {
+                std::fs::remove_file(object)?;
+            }
+            std::fs::remove_file(stamp)?;
+        }
+    }
+    Ok(())
+}
 
-            Command::new("clang++")
-                .arg("-MM")
-                .arg("-MT")
-                .arg(&object)
-                .arg("-Iinclude")
-                .arg("-Isrc")
-                .arg(file)
-                .output()
-        })
-        .filter_map(|result| result.ok())
-        .map(|result| String::from_utf8(result.stdout))
-        .filter_map(|result| result.ok())
-        .collect::<Vec<_>>();
+pub(crate) fn write_flag_stamps(build_directory: &str, flags: &BuildFlags) -> Result<()> {
+    std::fs::create_dir_all(build_directory)?;
+    for (stamp, value) in flags.stamps() {
+        write_stamp(&format!("{}/{}", build_directory, stamp), value)?;
+    }
+    remove_stale_source_stamps(build_directory, flags)?;
+    for (name, value) in &flags.source_stamps {
+        write_stamp(&get_source_flag_stamp(build_directory, name), value)?;
+    }
+    Ok(())
+}
 
-    Ok(dependencies.join("").trim_end().to_string())
+// The harnesses are built with their own flags (like the benchmarks, which
+// define BARGE_BENCHMARK), so they are stamped in their own directory.
+pub(crate) fn write_harness_flag_stamps(directory: &str, flags: &BuildFlags) -> Result<()> {
+    for (stamp, value) in flags.stamps() {
+        write_stamp(&format!("{}/{}", directory, stamp), value)?;
+    }
+    Ok(())
 }
 
-fn call_pkg_config(name: &str, mode: &str) -> Result<String> {
-    let result = Command::new("pkg-config")
-        .arg(name)
-        .arg(mode)
-        .output()?
-        .stdout;
+pub(crate) fn call_pkg_config(name: &str, mode: &str) -> Result<String> {
+    let result = record_and_output(Command::new("pkg-config").arg(name).arg(mode))?.stdout;
     let mut result = std::str::from_utf8(&result)?.to_string();
     result.pop();
     Ok(result)
 }
 
-fn build_library_flags(libraries: &Option<Vec<Library>>) -> Result<(String, String)> {
+pub(crate) fn build_library_flags(libraries: &Option<Vec<Library>>) -> Result<(String, String)> {
+    let _section = ProfileSection::start("pkg-config");
     let mut library_cflags = String::new();
     let mut library_ldflags = String::new();
 
-    if let Some(libraries) = libraries {
-        for library in libraries {
-            match library {
-                Library::PkgConfig { name } => {
-                    library_cflags.push_str(&call_pkg_config(name, "--cflags")?);
-                    library_ldflags.push_str(&call_pkg_config(name, "--libs")?);
-                }
-                Library::Manual { cflags, ldflags } => {
-                    library_cflags.push_str(cflags);
-                    library_ldflags.push_str(ldflags);
-                }
-            }
+    // Every pkg-config query of every library is run in parallel.
+    let queries: Vec<(&Library, &str)> = libraries
+        .iter()
+        .flatten()
+        .flat_map(|library| [(library, "--cflags"), (library, "--libs")])
+        .collect();
+    let results = parallel_map(&queries, |(library, mode)| match library {
+        Library::PkgConfig { name } => call_pkg_config(name, mode),
+        Library::Manual { cflags, .. } if *mode == "--cflags" => Ok(cflags.clone()),
+        Library::Manual { ldflags, .. } => Ok(ldflags.clone()),
+    });
+
+    let mut results = results.into_iter();
+    while let (Some(cflags), Some(ldflags)) = (results.next(), results.next()) {
+        library_cflags.push_str(&cflags?);
+        library_ldflags.push_str(&ldflags?);
+        library_cflags.push(' ');
+        library_ldflags.push(' ');
+    }
+
+    Ok((library_cflags, library_ldflags))
+}
+
+pub(crate) struct TestBinary {
+    pub source: String,
+    pub binary: String,
+}
 
-            library_cflags.push(' ');
-            library_ldflags.push(' ');
+pub(crate) fn generate_test_makefile(
+    project: &Project,
+    target: BuildTarget,
+    flags: &BuildFlags,
+    directory: &str,
+    tests: &[TestBinary],
+    objects: &[String],
+) -> Result<String> {
+    let build_directory = project.build_directory(target);
+
+    // The tests are written in C or C++, but they are linked with the objects
+    // of the project, which may need the runtime of another language.
+    let test_language = |test: &TestBinary| match project.source_kind(&test.source) {
+        Some(SourceKind::Language(LanguageKind::C)) => LanguageKind::C,
+        _ => LanguageKind::Cpp,
+    };
+    let linker = match detect_languages(project)?.linker() {
+        _ if tests
+            .iter()
+            .any(|test| test_language(test) == LanguageKind::Cpp) =>
+        {
+            project.compiler(LanguageKind::Cpp)
         }
+        kind => project.compiler(kind),
+    };
+
+    let label = match directory {
+        "bench" => "benchmark",
+        _ => "test",
+    };
+    let prefix = format!("{}/", directory);
+    let mut rules = String::new();
+    for test in tests {
+        let name = test.source.strip_prefix(&prefix).unwrap_or(&test.source);
+        let object = format!("$(BUILDDIR)/{}/obj/{}.o", directory, name);
+        let language = get_language(test_language(test));
+        let compiler = format!("$({})", language.compiler_variable());
+        let compiler_flags = format!("$({}FLAGS)", language.variable());
+        let stamp = language.stamp();
+        let language = language.name();
+
+        rules += &format!(
+            "
+{object}: {source} $(BUILDDIR)/{directory}/{stamp}
+\t@mkdir -p $(shell dirname $@)
+\t@$(RECORD_OBJECT) --source $< --message \"$(GREEN)$(DIM)Building {language} {label} object $@.$(RESET)\" -- $(LAUNCHER) {compiler} {compiler_flags} -MMD -MP -c $< -o $@
+
+{binary}: {object} $(PROJECT_OBJECTS) $(BUILDDIR)/{directory}/.ldflags
+\t@$(RECORD_OBJECT) --message \"$(GREEN)Linking {label} $@$(RESET)\" -- $(LINKER) {object} $(PROJECT_OBJECTS) -o $@ $(LDFLAGS)
+",
+            source = test.source,
+            binary = test.binary,
+        );
     }
 
-    Ok((library_cflags, library_ldflags))
+    let binaries: Vec<&str> = tests.iter().map(|test| test.binary.as_str()).collect();
+    Ok(format!(
+        include_str!("template-makefile-test.in"),
+        build_directory,
+        get_recorder_prefix()?,
+        get_launcher_arguments(project.compiler_launcher.as_deref()).join(" "),
+        project.compiler(LanguageKind::C),
+        flags.compile_flags(LanguageKind::C),
+        project.compiler(LanguageKind::Cpp),
+        flags.compile_flags(LanguageKind::Cpp),
+        flags.ldflags,
+        linker,
+        objects.join(" "),
+        binaries.join(" "),
+        get_colorization(),
+        directory,
+        rules
+    ))
 }
@@ -0,0 +1,150 @@
+use crate::completions::{
+    get_about, get_help, get_longs, get_options, get_positionals, get_shorts, get_subcommands,
+    takes_value,
+};
+use crate::result::Result;
+use std::fmt::Write;
+
+// Escapes the text for roff, where backslashes start escape sequences, and
+// lines starting with a dot or an apostrophe are requests.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+fn get_value_name(arg: &clap::Arg) -> String {
+    arg.get_value_names()
+        .and_then(|names| names.first())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| arg.get_id().to_string().to_uppercase())
+}
+
+// For example, `[TARGET]` for an optional positional argument.
+fn get_positional_usage(arg: &clap::Arg) -> String {
+    let name = get_value_name(arg);
+    if arg.is_required_set() {
+        format!("<{}>", name)
+    } else {
+        format!("[{}]", name)
+    }
+}
+
+fn get_synopsis(prefix: &str, command: &clap::Command) -> String {
+    let mut synopsis = format!("\\fB{}{}\\fR", prefix, escape(command.get_name()));
+    if !get_options(command).is_empty() {
+        synopsis += " [\\fIOPTIONS\\fR]";
+    }
+    for positional in get_positionals(command) {
+        let _ = write!(synopsis, " {}", escape(&get_positional_usage(positional)));
+    }
+    if command.get_positionals().any(|arg| arg.is_last_set()) {
+        synopsis += " [\\-\\- \\fIARGS\\fR...]";
+    }
+    synopsis
+}
+
+fn write_option(page: &mut String, option: &clap::Arg) {
+    let mut switches: Vec<String> = get_shorts(option)
+        .into_iter()
+        .map(|short| format!("\\fB\\-{}\\fR", escape(&short.to_string())))
+        .collect();
+    switches.extend(
+        get_longs(option)
+            .into_iter()
+            .map(|long| format!("\\fB\\-\\-{}\\fR", escape(long))),
+    );
+    let mut line = switches.join(", ");
+    if takes_value(option) {
+        let _ = write!(line, " \\fI{}\\fR", escape(&get_value_name(option)));
+    }
+    let possible_values: Vec<String> = option
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    let mut help = get_help(option);
+    if !possible_values.is_empty() {
+        let _ = write!(help, " [possible values: {}]", possible_values.join(", "));
+    }
+    let _ = writeln!(page, ".TP\n{}\n{}", line, escape(&help));
+}
+
+fn write_arguments(page: &mut String, command: &clap::Command, skip_global: bool) {
+    for positional in get_positionals(command) {
+        let _ = writeln!(
+            page,
+            ".TP\n\\fI{}\\fR\n{}",
+            escape(&get_positional_usage(positional)),
+            escape(&get_help(positional))
+        );
+    }
+    for option in get_options(command) {
+        // The global options are only described once, with the main command.
+        if skip_global && option.is_global_set() {
+            continue;
+        }
+        write_option(page, option);
+    }
+}
+
+fn generate_man_page(command: &clap::Command) -> String {
+    let name = command.get_name();
+    let mut page = String::new();
+    let _ = writeln!(
+        page,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        escape(&name.to_uppercase()),
+        escape(name),
+        escape(env!("CARGO_PKG_VERSION"))
+    );
+    let _ = writeln!(page, ".SH NAME");
+    let _ = writeln!(page, "{} \\- {}", escape(name), escape(&get_about(command)));
+    let _ = writeln!(page, ".SH SYNOPSIS");
+    let _ = writeln!(page, "{} \\fICOMMAND\\fR", get_synopsis("", command));
+    let _ = writeln!(page, ".SH OPTIONS");
+    write_arguments(&mut page, command, false);
+
+    let _ = writeln!(page, ".SH COMMANDS");
+    // The help subcommand added by clap is described by the options instead.
+    for subcommand in get_subcommands(command)
+        .into_iter()
+        .filter(|subcommand| subcommand.get_name() != "help")
+    {
+        let _ = writeln!(
+            page,
+            ".SS {}",
+            get_synopsis(&format!("{} ", escape(name)), subcommand)
+        );
+        let _ = writeln!(page, "{}", escape(&get_about(subcommand)));
+        let aliases: Vec<&str> = subcommand.get_visible_aliases().collect();
+        if !aliases.is_empty() {
+            let _ = writeln!(page, ".br\nAlias: {}", escape(&aliases.join(", ")));
+        }
+        write_arguments(&mut page, subcommand, true);
+    }
+
+    let _ = writeln!(page, ".SH SEE ALSO");
+    let _ = writeln!(
+        page,
+        "The README of {} describes the project file and the environment variables \
+         in detail.",
+        escape(name)
+    );
+    if let Some(author) = command.get_author() {
+        let _ = writeln!(page, ".SH AUTHORS\n{}", escape(author));
+    }
+    page
+}
+
+// Prints the man page of the command line interface, generated from the clap
+// definitions, so distributions can install it as barge.1.
+pub(crate) fn print_man_page(mut command: clap::Command) -> Result<()> {
+    command.build();
+    print!("{}", generate_man_page(&command));
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crate::makefile::BuildTarget;
+use crate::project::Project;
+use crate::result::{print_error, BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE};
+use std::time::Instant;
+
+struct ConfigurationResult {
+    name: String,
+    succeeded: bool,
+    duration: f64,
+}
+
+fn get_configuration_name(project: &Project, target: BuildTarget) -> String {
+    let mut name = target.to_string();
+    if let Some(triple) = &project.target {
+        name += &format!(" {}", triple);
+    }
+    if let Some(bits) = project.bits {
+        name += &format!(" {}-bit", bits);
+    }
+    name
+}
+
+fn print_summary(results: &[ConfigurationResult]) {
+    let width = results
+        .iter()
+        .map(|result| result.name.len())
+        .max()
+        .unwrap_or_default()
+        .max("Configuration".len());
+    color_println!(
+        BLUE,
+        "{:<width$}  {:<6}  {:>8}",
+        "Configuration",
+        "Result",
+        "Duration"
+    );
+    for result in results {
+        let line = format!(
+            "{:<width$}  {:<6}  {:>6.2} s",
+            result.name,
+            if result.succeeded { "ok" } else { "failed" },
+            result.duration
+        );
+        if result.succeeded {
+            color_println!(WHITE, "{}", line);
+        } else {
+            color_eprintln!("{}", line);
+        }
+    }
+}
+
+// Rebuilds every combination of the given variants, build targets, and target
+// triples, continuing after failures, then prints a summary of the results.
+pub(crate) fn rebuild_configurations(
+    variants: &[Project],
+    targets: &[BuildTarget],
+    triples: &[Option<String>],
+    fresh_deps: bool,
+) -> Result<()> {
+    let mut results = Vec::new();
+    for variant in variants {
+        for triple in triples {
+            let mut configuration = variant.clone();
+            if triple.is_some() {
+                configuration.target = triple.clone();
+            }
+            for &target in targets {
+                let start_time = Instant::now();
+                let result = if fresh_deps {
+                    configuration
+                        .discard_dependency_cache(target)
+                        .and_then(|_| configuration.rebuild(target))
+                } else {
+                    configuration.rebuild(target)
+                };
+                if let Err(error) = &result {
+                    print_error(error);
+                }
+                results.push(ConfigurationResult {
+                    name: get_configuration_name(&configuration, target),
+                    succeeded: result.is_ok(),
+                    duration: start_time.elapsed().as_secs_f64(),
+                });
+            }
+        }
+    }
+
+    if results.len() > 1 {
+        print_summary(&results);
+    }
+    let failed = results.iter().filter(|result| !result.succeeded).count();
+    if failed == 0 {
+        if results.len() > 1 {
+            color_println!(GREEN, "All {} configurations built", results.len());
+        }
+        Ok(())
+    } else {
+        Err(BargeError::FailedOperation(
+            "One or more configurations failed to build",
+        ))
+    }
+}
@@ -0,0 +1,221 @@
+use crate::makefile::BuildTarget;
+use crate::project::{MemoryLayout, Project, Toolset, DEFAULT_TOOLSET};
+use crate::result::{BargeError, Result};
+use crate::{color_println, GREEN, NO_COLOR, RED, WHITE, YELLOW};
+use std::process::Command;
+
+const GENERATED_LINKER_SCRIPT: &str = "memory.ld";
+const MEMORY_PLACEHOLDER: &str = "{memory}";
+const FRAGMENTS_PLACEHOLDER: &str = "{fragments}";
+
+// Parses an origin or a length in the notation of linker scripts, that is, a
+// decimal or hexadecimal number, optionally followed by a K or M suffix.
+pub(crate) fn parse_memory_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last()? {
+        (index, 'K' | 'k') => (&value[..index], 1024),
+        (index, 'M' | 'm') => (&value[..index], 1024 * 1024),
+        _ => (value, 1),
+    };
+    let number = match number.strip_prefix("0x").or(number.strip_prefix("0X")) {
+        Some(hexadecimal) => u64::from_str_radix(hexadecimal, 16).ok()?,
+        None => number.parse().ok()?,
+    };
+    number.checked_mul(multiplier)
+}
+
+fn generate_memory_block(layout: &MemoryLayout) -> Result<String> {
+    let mut block = String::from("MEMORY\n{\n");
+    for region in &layout.regions {
+        if parse_memory_size(&region.origin).is_none()
+            || parse_memory_size(&region.length).is_none()
+        {
+            return Err(BargeError::InvalidValue(
+                "Invalid origin or length of a memory region",
+            ));
+        }
+        let attributes = match &region.attributes {
+            Some(attributes) => format!(" ({})", attributes),
+            None => String::new(),
+        };
+        block += &format!(
+            "    {}{} : ORIGIN = {}, LENGTH = {}\n",
+            region.name, attributes, region.origin, region.length
+        );
+    }
+    block += "}\n";
+    Ok(block)
+}
+
+// Assembles the linker script from the memory regions and fragments declared
+// in the project file. The template may place them with the {memory} and
+// {fragments} placeholders, without a template they are simply concatenated.
+fn generate_linker_script(layout: &MemoryLayout) -> Result<String> {
+    let memory = generate_memory_block(layout)?;
+    let mut fragments = String::new();
+    for fragment in layout.fragments.iter().flatten() {
+        fragments += &std::fs::read_to_string(fragment)?;
+        if !fragments.ends_with('\n') {
+            fragments.push('\n');
+        }
+    }
+
+    match &layout.template {
+        Some(template) => Ok(std::fs::read_to_string(template)?
+            .replace(MEMORY_PLACEHOLDER, &memory)
+            .replace(FRAGMENTS_PLACEHOLDER, &fragments)),
+        None => Ok(format!("{}\n{}", memory, fragments)),
+    }
+}
+
+pub(crate) fn get_generated_linker_script(
+    project: &Project,
+    build_directory: &str,
+) -> Option<String> {
+    project
+        .memory_layout
+        .as_ref()
+        .map(|_| format!("{}/{}", build_directory, GENERATED_LINKER_SCRIPT))
+}
+
+// Whether the linker script is an input of the generated one, instead of being
+// passed to the linker on its own.
+pub(crate) fn is_linker_script_input(project: &Project, path: &str) -> bool {
+    let Some(layout) = &project.memory_layout else {
+        return false;
+    };
+    let path = path.trim_start_matches("./");
+    layout
+        .template
+        .iter()
+        .chain(layout.fragments.iter().flatten())
+        .any(|input| input.trim_start_matches("./") == path)
+}
+
+// The script is only rewritten when its contents change, so that the artifact
+// is not relinked needlessly.
+pub(crate) fn write_linker_script(project: &Project, build_directory: &str) -> Result<()> {
+    let (Some(layout), Some(path)) = (
+        &project.memory_layout,
+        get_generated_linker_script(project, build_directory),
+    ) else {
+        return Ok(());
+    };
+
+    let script = generate_linker_script(layout)?;
+    let previous = std::fs::read_to_string(&path).unwrap_or_default();
+    if previous != script {
+        std::fs::create_dir_all(build_directory)?;
+        std::fs::write(&path, script)?;
+    }
+    Ok(())
+}
+
+struct LoadSegment {
+    virtual_address: u64,
+    physical_address: u64,
+    file_size: u64,
+    memory_size: u64,
+}
+
+fn parse_hexadecimal(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+fn get_load_segments(project: &Project, artifact: &str) -> Result<Vec<LoadSegment>> {
+    let readelf = match project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET) {
+        Toolset::Gnu => "readelf",
+        Toolset::Llvm => "llvm-readelf",
+    };
+    let output = Command::new(project.cross_tool(readelf))
+        .args(["-l", "-W"])
+        .arg(artifact)
+        .output()?;
+    if !output.status.success() {
+        return Err(BargeError::FailedOperation(
+            "Could not read the program headers of the artifact",
+        ));
+    }
+
+    let segments = String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["LOAD", _, virtual_address, physical_address, file_size, memory_size, ..] => {
+                    Some(LoadSegment {
+                        virtual_address: parse_hexadecimal(virtual_address)?,
+                        physical_address: parse_hexadecimal(physical_address)?,
+                        file_size: parse_hexadecimal(file_size)?,
+                        memory_size: parse_hexadecimal(memory_size)?,
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(segments)
+}
+
+// Reports the usage of each declared memory region, based on the loadable
+// segments of the artifact. Segments loaded from a different address than
+// where they run (like initialized data copied from flash to RAM at startup)
+// are counted in both regions.
+pub(crate) fn report_memory_usage(project: &Project, target: BuildTarget) -> Result<()> {
+    let Some(layout) = &project.memory_layout else {
+        return Ok(());
+    };
+    let segments = get_load_segments(project, &project.linked_artifact_path(target))?;
+
+    println!();
+    color_println!(
+        WHITE,
+        "{:<16} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        "Region",
+        "Origin",
+        "Length",
+        "Used",
+        "Free",
+        "Usage"
+    );
+    for region in &layout.regions {
+        let origin = parse_memory_size(&region.origin).unwrap_or_default();
+        let length = parse_memory_size(&region.length).unwrap_or_default();
+        let contains = |address: u64| address >= origin && address - origin < length;
+        let used: u64 = segments
+            .iter()
+            .map(|segment| {
+                let mut used = 0;
+                if contains(segment.virtual_address) {
+                    used += segment.memory_size;
+                }
+                if segment.physical_address != segment.virtual_address
+                    && contains(segment.physical_address)
+                {
+                    used += segment.file_size;
+                }
+                used
+            })
+            .sum();
+
+        let usage = match length {
+            0 => 0.0,
+            length => used as f64 * 100.0 / length as f64,
+        };
+        let line = format!(
+            "{:<16} {:>#12x} {:>12} {:>12} {:>12} {:>7.1}%",
+            region.name,
+            origin,
+            length,
+            used,
+            length as i64 - used as i64,
+            usage
+        );
+        match usage {
+            usage if usage > 100.0 => color_println!(RED, "{}", line),
+            usage if usage > 90.0 => color_println!(YELLOW, "{}", line),
+            _ => color_println!(GREEN, "{}", line),
+        }
+    }
+    Ok(())
+}
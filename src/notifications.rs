@@ -0,0 +1,106 @@
+use crate::project::{Notification, Project, WebhookKind};
+use crate::records::BuildRecord;
+use crate::result::{BargeError, Result};
+use crate::{color_println, NO_COLOR, YELLOW};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const DEFAULT_MESSAGE: &str =
+    "@project@ @version@: @command@ (@target@) @status@ in @duration@ seconds (@commit@)";
+
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn get_default_payload(kind: WebhookKind) -> String {
+    match kind {
+        WebhookKind::Generic => String::from(
+            r#"{"project": "@project@", "version": "@version@", "command": "@command@", "target": "@target@", "status": "@status@", "duration": @duration@, "commit": "@commit@", "branch": "@branch@"}"#,
+        ),
+        WebhookKind::Slack => format!(r#"{{"text": "{}"}}"#, DEFAULT_MESSAGE),
+        WebhookKind::Discord => format!(r#"{{"content": "{}"}}"#, DEFAULT_MESSAGE),
+    }
+}
+
+fn expand_payload(
+    template: &str,
+    project: &Project,
+    command: &str,
+    record: &BuildRecord,
+) -> String {
+    let status = if record.success {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    let replacements = [
+        ("@project@", project.name.as_str()),
+        ("@version@", project.version.as_str()),
+        ("@command@", command),
+        ("@target@", record.target.as_str()),
+        ("@status@", status),
+        ("@duration@", &format!("{:.2}", record.duration)),
+        ("@commit@", record.commit.as_deref().unwrap_or("unknown")),
+        ("@branch@", record.branch.as_deref().unwrap_or("unknown")),
+        ("@timestamp@", record.timestamp.as_str()),
+    ];
+
+    replacements
+        .iter()
+        .fold(template.to_string(), |payload, (placeholder, value)| {
+            payload.replace(placeholder, &escape_json(value))
+        })
+}
+
+fn post_payload(url: &str, payload: &str) -> Result<()> {
+    let mut curl = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(["-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    curl.stdin
+        .as_mut()
+        .ok_or(BargeError::NoneOption("Could not interact with curl"))?
+        .write_all(payload.as_bytes())?;
+    drop(curl.stdin.take());
+
+    if curl.wait()?.success() {
+        Ok(())
+    } else {
+        Err(BargeError::FailedOperation("Failed to send notification"))
+    }
+}
+
+fn send_notification(
+    notification: &Notification,
+    project: &Project,
+    command: &str,
+    record: &BuildRecord,
+) -> Result<()> {
+    if notification.only_failures == Some(true) && record.success {
+        return Ok(());
+    }
+
+    let template = match &notification.payload {
+        Some(payload) => payload.clone(),
+        None => get_default_payload(notification.kind.unwrap_or(WebhookKind::Generic)),
+    };
+    let payload = expand_payload(&template, project, command, record);
+    post_payload(&notification.url, &payload)
+}
+
+pub(crate) fn send_notifications(project: &Project, command: &str, record: &BuildRecord) {
+    for notification in project.notifications.iter().flatten() {
+        if send_notification(notification, project, command, record).is_err() {
+            color_println!(
+                YELLOW,
+                "Could not send notification to {}",
+                notification.url
+            );
+        }
+    }
+}
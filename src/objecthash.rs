@@ -0,0 +1,79 @@
+use crate::utilities::get_content_hash;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// The content hash of the inputs of an object file or a linked binary, stored
+// in the state directory of the build, so that it is not rebuilt when only the
+// modification times of its inputs changed (for example, after switching git
+// branches). Binaries are hashed from their objects and the link command.
+pub(crate) struct ObjectHash {
+    path: PathBuf,
+    output: PathBuf,
+    hash: Option<String>,
+}
+
+impl ObjectHash {
+    pub(crate) fn new(
+        state_directory: &str,
+        output: &str,
+        inputs: &str,
+        arguments: &[String],
+    ) -> ObjectHash {
+        let build_directory = Path::new(state_directory).parent().unwrap_or(Path::new(""));
+        let relative = Path::new(output)
+            .strip_prefix(build_directory)
+            .unwrap_or(Path::new(output));
+        let mut path = Path::new(state_directory).join(relative).into_os_string();
+        path.push(".hash");
+
+        ObjectHash {
+            path: PathBuf::from(path),
+            output: PathBuf::from(output),
+            hash: get_input_hash(inputs, arguments),
+        }
+    }
+
+    // Returns whether the output was built from the same inputs with the same
+    // command before. If so, the output is touched, so that make considers it
+    // up to date from now on. The dependents of a touched object are relinked,
+    // which is skipped the same way, as the contents of the object are kept.
+    pub(crate) fn is_unchanged(&self) -> bool {
+        let Some(hash) = &self.hash else {
+            return false;
+        };
+        if std::fs::read_to_string(&self.path).ok().as_ref() != Some(hash) {
+            return false;
+        }
+        std::fs::File::options()
+            .write(true)
+            .open(&self.output)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .is_ok()
+    }
+
+    pub(crate) fn store(&self) {
+        if let Some(hash) = &self.hash {
+            if let Some(parent) = self.path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(&self.path, hash);
+        }
+    }
+
+    pub(crate) fn discard(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Hashes the command line and the contents of every input (the source, the
+// headers it includes, and the flag stamps), or returns None if an input can
+// not be read.
+fn get_input_hash(inputs: &str, arguments: &[String]) -> Option<String> {
+    let mut contents = vec![arguments.join("\n").into_bytes()];
+    for input in inputs.split_whitespace() {
+        contents.push(input.as_bytes().to_vec());
+        contents.push(std::fs::read(input).ok()?);
+    }
+    let parts: Vec<&[u8]> = contents.iter().map(|content| content.as_slice()).collect();
+    Some(get_content_hash(&parts))
+}
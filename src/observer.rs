@@ -0,0 +1,192 @@
+use crate::annotations::is_diagnostic;
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// The environment variable naming the file the build events are appended to.
+const EVENT_LOG_VARIABLE: &str = "BARGE_EVENT_LOG";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BuildOutcome {
+    UpToDate,
+    Succeeded(Duration),
+    Failed(Duration),
+}
+
+// Receives the progress of a build, so that frontends other than the console
+// (GUIs, TUIs, test harnesses) can render it in their own way.
+pub(crate) trait BuildObserver: Sync {
+    fn on_step_start(&self, step: &str);
+    fn on_diagnostic(&self, line: &str);
+    fn on_artifact(&self, path: &Path);
+    fn on_finish(&self, outcome: BuildOutcome);
+
+    // Whether the output of the build tools should be captured and reported as
+    // events, instead of being written to the terminal directly.
+    fn captures_output(&self) -> bool {
+        true
+    }
+}
+
+pub(crate) struct ConsoleObserver;
+
+impl BuildObserver for ConsoleObserver {
+    fn on_step_start(&self, step: &str) {
+        println!("{}", step);
+    }
+
+    fn on_diagnostic(&self, line: &str) {
+        eprintln!("{}", line);
+    }
+
+    fn on_artifact(&self, _path: &Path) {}
+
+    fn on_finish(&self, outcome: BuildOutcome) {
+        match outcome {
+            BuildOutcome::UpToDate => color_println!(GREEN, "Everything is up to date"),
+            BuildOutcome::Succeeded(duration) => color_println!(
+                BLUE,
+                "Build finished in {:.2} seconds",
+                duration.as_secs_f64()
+            ),
+            BuildOutcome::Failed(_) => color_eprintln!("Build failed"),
+        }
+    }
+
+    // The compilers only colorize their diagnostics when writing to a terminal.
+    fn captures_output(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Step { message: &'a str },
+    Diagnostic { message: &'a str },
+    Artifact { path: &'a Path },
+    Finish { outcome: &'a str, seconds: f64 },
+}
+
+// Writes the events of the build to a file as JSON lines, while also printing
+// them to the console, so that scripts and test harnesses can check what
+// happened during the build without parsing the console output.
+pub(crate) struct EventLogObserver {
+    file: Mutex<std::fs::File>,
+}
+
+impl EventLogObserver {
+    pub(crate) fn from_environment() -> Result<Option<Self>> {
+        let Some(path) = std::env::var_os(EVENT_LOG_VARIABLE) else {
+            return Ok(None);
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Some(Self {
+            file: Mutex::new(file),
+        }))
+    }
+
+    fn log(&self, event: Event) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Ok(line) = serde_json::to_string(&event) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+impl BuildObserver for EventLogObserver {
+    fn on_step_start(&self, step: &str) {
+        ConsoleObserver.on_step_start(step);
+        self.log(Event::Step { message: step });
+    }
+
+    fn on_diagnostic(&self, line: &str) {
+        ConsoleObserver.on_diagnostic(line);
+        self.log(Event::Diagnostic { message: line });
+    }
+
+    fn on_artifact(&self, path: &Path) {
+        self.log(Event::Artifact { path });
+    }
+
+    fn on_finish(&self, outcome: BuildOutcome) {
+        ConsoleObserver.on_finish(outcome);
+        let (outcome, duration) = match outcome {
+            BuildOutcome::UpToDate => ("up_to_date", Duration::ZERO),
+            BuildOutcome::Succeeded(duration) => ("succeeded", duration),
+            BuildOutcome::Failed(duration) => ("failed", duration),
+        };
+        self.log(Event::Finish {
+            outcome,
+            seconds: duration.as_secs_f64(),
+        });
+    }
+}
+
+fn strip_escape_sequences(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut characters = line.chars();
+    while let Some(character) = characters.next() {
+        if character == '\x1b' {
+            for terminator in characters.by_ref() {
+                if terminator.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(character);
+        }
+    }
+    stripped
+}
+
+fn report_lines(stream: impl Read, mut report: impl FnMut(&str)) {
+    for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+        let line = strip_escape_sequences(&line);
+        if !line.trim().is_empty() {
+            report(line.trim_end());
+        }
+    }
+}
+
+pub(crate) fn configure_output_capture(
+    command: &mut std::process::Command,
+    observer: &dyn BuildObserver,
+) {
+    if observer.captures_output() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+}
+
+// Waits for the child, turning its captured output into events: the progress
+// messages of the makefile are steps, everything else is a diagnostic.
+pub(crate) fn observe_child(child: &mut Child, observer: &dyn BuildObserver) -> Result<bool> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    std::thread::scope(|scope| {
+        if let Some(stderr) = stderr {
+            scope.spawn(|| report_lines(stderr, |line| observer.on_diagnostic(line)));
+        }
+        if let Some(stdout) = stdout {
+            report_lines(stdout, |line| {
+                if line.starts_with("::") || is_diagnostic(line) {
+                    observer.on_diagnostic(line)
+                } else {
+                    observer.on_step_start(line)
+                }
+            });
+        }
+    });
+    child
+        .wait()
+        .map(|status| status.success())
+        .map_err(|_| BargeError::FailedOperation("Could not wait for make"))
+}
@@ -1,21 +1,89 @@
 use ansi_term::{Color, Style};
 use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+// The lines printed while a full-screen interface is shown, which would be
+// drawn over otherwise, so the interface displays them instead.
+static CAPTURED_LINES: Mutex<Option<Vec<String>>> = Mutex::new(None);
 
 lazy_static! {
-    pub static ref NO_COLOR: bool = std::env::var("NO_COLOR").is_ok();
+    pub static ref NO_COLOR: bool =
+        std::env::var("NO_COLOR").is_ok() || ACCESSIBLE.load(Ordering::Relaxed);
     pub static ref BLUE: Style = Style::new().bold().fg(Color::Blue);
     pub static ref GREEN: Style = Style::new().bold().fg(Color::Green);
     pub static ref RED: Style = Style::new().bold().fg(Color::Red);
     pub static ref WHITE: Style = Style::new().bold().fg(Color::White);
+    pub static ref YELLOW: Style = Style::new().bold().fg(Color::Yellow);
+}
+
+// Enables the output mode meant for screen readers and braille displays: no
+// colors, no full-screen interfaces or graphics, and the meaning of colored
+// lines spelled out in words. Child processes are asked to disable colors too.
+pub fn enable_accessible_output() {
+    ACCESSIBLE.store(true, Ordering::Relaxed);
+    std::env::set_var("NO_COLOR", "1");
+}
+
+pub fn is_accessible_output() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+// Returns the line as printed without colors, prefixed with the word standing
+// for its color in accessible mode, unless the line already starts with it.
+pub fn get_plain_line(style: &Style, line: String) -> String {
+    let prefix = match style {
+        _ if !is_accessible_output() => return line,
+        style if *style == *RED => "ERROR",
+        style if *style == *YELLOW => "WARNING",
+        style if *style == *GREEN => "SUCCESS",
+        _ => return line,
+    };
+    let starts_with_prefix = line
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+    if starts_with_prefix {
+        line
+    } else {
+        format!("{}: {}", prefix, line)
+    }
+}
+
+fn lock_captured_lines() -> std::sync::MutexGuard<'static, Option<Vec<String>>> {
+    CAPTURED_LINES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn start_capturing_lines() {
+    lock_captured_lines().get_or_insert_with(Vec::new);
+}
+
+// Returns the lines captured since the last call, and stops capturing them if
+// requested.
+pub fn take_captured_lines(stop: bool) -> Vec<String> {
+    let mut captured = lock_captured_lines();
+    match stop {
+        true => captured.take().unwrap_or_default(),
+        false => captured.as_mut().map(std::mem::take).unwrap_or_default(),
+    }
+}
+
+pub fn print_line(line: String, error: bool) {
+    match lock_captured_lines().as_mut() {
+        Some(captured) => captured.push(line),
+        None if error => eprintln!("{}", line),
+        None => println!("{}", line),
+    }
 }
 
 #[macro_export]
 macro_rules! color_println {
     ($style:tt, $($arg:tt)*) => {
         if *NO_COLOR {
-            println!("{}", format!($($arg)*))
+            $crate::output::print_line($crate::output::get_plain_line(&$style, format!($($arg)*)), false)
         } else {
-            println!("{}", $style.paint(format!($($arg)*)))
+            $crate::output::print_line($style.paint(format!($($arg)*)).to_string(), false)
         }
     }
 }
@@ -24,9 +92,9 @@ macro_rules! color_println {
 macro_rules! color_eprintln {
     ($($arg:tt)*) => {
         if *NO_COLOR {
-            eprintln!("{}", format!($($arg)*))
+            $crate::output::print_line($crate::output::get_plain_line(&RED, format!($($arg)*)), true)
         } else {
-            eprintln!("{}", RED.paint(format!($($arg)*)))
+            $crate::output::print_line(RED.paint(format!($($arg)*)).to_string(), true)
         }
     }
 }
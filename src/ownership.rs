@@ -0,0 +1,95 @@
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, NO_COLOR, RED, YELLOW};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+const REPORTED_FILE_LIMIT: usize = 10;
+
+#[cfg(unix)]
+fn get_effective_user_id() -> Option<u32> {
+    // SAFETY: geteuid always succeeds, and has no side effects.
+    Some(unsafe { libc::geteuid() })
+}
+
+// Other hosts have no user ids, so neither the root check nor the report of
+// the files owned by another user applies there.
+#[cfg(not(unix))]
+fn get_effective_user_id() -> Option<u32> {
+    None
+}
+
+// Refuses to build as root, as the build artifacts would be owned by root and
+// could not be cleaned up later by the user. Installing is allowed with a
+// warning, as it commonly requires elevated privileges.
+pub(crate) fn check_root_user(allow_root: bool, installing: bool) -> Result<()> {
+    if get_effective_user_id() != Some(0) {
+        return Ok(());
+    }
+
+    if allow_root || installing {
+        color_println!(
+            YELLOW,
+            "Running as root, the build artifacts will be owned by root"
+        );
+        Ok(())
+    } else {
+        color_eprintln!(
+            "Building as root leaves build artifacts that other users can not remove, \
+             use --allow-root or set BARGE_ALLOW_ROOT=1 if this is intended"
+        );
+        Err(BargeError::FailedOperation("Refusing to build as root"))
+    }
+}
+
+#[cfg(unix)]
+fn collect_foreign_files(path: &Path, uid: u32, files: &mut Vec<(PathBuf, u32)>) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.uid() != uid {
+        files.push((path.to_path_buf(), metadata.uid()));
+    }
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            collect_foreign_files(&entry.path(), uid, files);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn collect_foreign_files(_path: &Path, _uid: u32, _files: &mut Vec<(PathBuf, u32)>) {}
+
+// Explains a failed removal of a directory, listing the files that are owned
+// by another user (typically root, after a build in a container).
+pub(crate) fn report_removal_failure(path: &Path, error: std::io::Error) -> BargeError {
+    if error.kind() != std::io::ErrorKind::PermissionDenied {
+        return error.into();
+    }
+
+    let mut files = Vec::new();
+    if let Some(uid) = get_effective_user_id() {
+        collect_foreign_files(path, uid, &mut files);
+    }
+    if files.is_empty() {
+        color_eprintln!("Could not remove {}: permission denied", path.display());
+        return error.into();
+    }
+
+    color_eprintln!(
+        "Could not remove {}, {} file(s) are owned by another user:",
+        path.display(),
+        files.len()
+    );
+    for (file, owner) in files.iter().take(REPORTED_FILE_LIMIT) {
+        color_eprintln!("  {} (owned by uid {})", file.display(), owner);
+    }
+    if files.len() > REPORTED_FILE_LIMIT {
+        color_eprintln!("  and {} more", files.len() - REPORTED_FILE_LIMIT);
+    }
+    color_eprintln!(
+        "Fix the ownership with: sudo chown -R $(id -u):$(id -g) {}",
+        path.display()
+    );
+    BargeError::FailedOperation("Could not remove files owned by another user")
+}
@@ -0,0 +1,231 @@
+use crate::install::stage_install_tree;
+use crate::makefile::BuildTarget;
+use crate::project::Project;
+use crate::result::{BargeError, Result};
+use crate::utilities::attempt_remove_directory;
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PACKAGE_DIRECTORY: &str = "build/package";
+const PACKAGE_PREFIX: &str = "/usr";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PackageFormat {
+    Tarball,
+    Deb,
+    Rpm,
+}
+
+impl TryFrom<&str> for PackageFormat {
+    type Error = BargeError;
+
+    fn try_from(string: &str) -> Result<PackageFormat> {
+        match string {
+            "tar.gz" | "tgz" => Ok(PackageFormat::Tarball),
+            "deb" => Ok(PackageFormat::Deb),
+            "rpm" => Ok(PackageFormat::Rpm),
+            _ => Err(BargeError::InvalidValue(
+                "Invalid package format, valid choices are: tar.gz, deb, rpm",
+            )),
+        }
+    }
+}
+
+fn get_package_name(project: &Project) -> String {
+    project.name.to_lowercase().replace('_', "-")
+}
+
+fn get_summary(project: &Project) -> String {
+    if project.description.is_empty() {
+        project.name.clone()
+    } else {
+        project.description.clone()
+    }
+}
+
+// The architecture of the packaged binaries, taken from the target triple of
+// the project, or from its bits on the host architecture.
+fn get_debian_architecture(project: &Project) -> &'static str {
+    let architecture = match (&project.target, project.bits, std::env::consts::ARCH) {
+        (Some(triple), _, _) => triple.split('-').next().unwrap_or_default(),
+        (None, Some(32), "x86_64") => "x86",
+        (None, Some(32), "aarch64") => "arm",
+        (None, _, host) => host,
+    };
+    match architecture {
+        "x86_64" => "amd64",
+        "x86" | "i386" | "i486" | "i586" | "i686" => "i386",
+        "aarch64" => "arm64",
+        "riscv64" => "riscv64",
+        arm if arm.starts_with("arm") => "armhf",
+        _ => "all",
+    }
+}
+
+fn run_packager(command: &mut Command, tool: &'static str) -> Result<()> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(BargeError::FailedOperation("Failed to create the package")),
+        Err(error) => {
+            color_eprintln!("Could not execute {}", tool);
+            Err(error.into())
+        }
+    }
+}
+
+fn create_tarball(project: &Project, target: BuildTarget, root: &Path) -> Result<PathBuf> {
+    let directory = format!("{}-{}", project.name, project.version);
+    stage_install_tree(project, target, &root.join(&directory), "/")?;
+
+    let archive = PathBuf::from(PACKAGE_DIRECTORY).join(format!("{}.tar.gz", directory));
+    run_packager(
+        Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(root)
+            .arg(&directory),
+        "tar",
+    )?;
+    Ok(archive)
+}
+
+fn create_deb(project: &Project, target: BuildTarget, root: &Path) -> Result<PathBuf> {
+    stage_install_tree(project, target, root, PACKAGE_PREFIX)?;
+
+    let name = get_package_name(project);
+    let architecture = get_debian_architecture(project);
+    let control = format!(
+        "Package: {}\nVersion: {}\nArchitecture: {}\nMaintainer: {}\nDescription: {}\n",
+        name,
+        project.version,
+        architecture,
+        project
+            .authors
+            .first()
+            .map_or("Unknown", |author| author.as_str()),
+        get_summary(project)
+    );
+    std::fs::create_dir_all(root.join("DEBIAN"))?;
+    std::fs::write(root.join("DEBIAN/control"), control)?;
+
+    let package = PathBuf::from(PACKAGE_DIRECTORY)
+        .join(format!("{}_{}_{}.deb", name, project.version, architecture));
+    run_packager(
+        Command::new("dpkg-deb")
+            .args(["--build", "--root-owner-group"])
+            .arg(root)
+            .arg(&package),
+        "dpkg-deb",
+    )?;
+    Ok(package)
+}
+
+fn create_rpm(project: &Project, target: BuildTarget, root: &Path) -> Result<PathBuf> {
+    let license = project.license.as_ref().ok_or(BargeError::InvalidValue(
+        "The license field of the project is required for rpm packages",
+    ))?;
+    let installed: Vec<String> = stage_install_tree(project, target, root, PACKAGE_PREFIX)?
+        .iter()
+        .filter_map(|file| file.strip_prefix(root).ok())
+        .map(|file| format!("/{}", file.display()))
+        .collect();
+    let root = std::env::current_dir()?.join(root);
+    let topdir = std::env::current_dir()?.join(PACKAGE_DIRECTORY).join("rpm");
+    attempt_remove_directory(&topdir.to_string_lossy())?;
+    std::fs::create_dir_all(&topdir)?;
+
+    let name = get_package_name(project);
+    let summary = get_summary(project);
+    let spec = [
+        format!("Name: {}", name),
+        format!("Version: {}", project.version.replace('-', "~")),
+        String::from("Release: 1"),
+        format!("Summary: {}", summary),
+        format!("License: {}", license),
+        format!(
+            "Packager: {}",
+            project.authors.first().map_or("Unknown", |a| a.as_str())
+        ),
+        String::new(),
+        String::from("%description"),
+        summary,
+        String::new(),
+        String::from("%install"),
+        String::from("mkdir -p %{buildroot}"),
+        format!("cp -a '{}/.' %{{buildroot}}/", root.display()),
+        String::new(),
+        String::from("%files"),
+        installed.join("\n"),
+    ]
+    .join("\n");
+    let spec_path = topdir.join(format!("{}.spec", name));
+    std::fs::write(&spec_path, spec + "\n")?;
+
+    run_packager(
+        Command::new("rpmbuild")
+            .arg("-bb")
+            .arg("--define")
+            .arg(format!("_topdir {}", topdir.display()))
+            .arg(&spec_path),
+        "rpmbuild",
+    )?;
+
+    // The package is written into the directory of the architecture chosen by
+    // rpmbuild, like RPMS/x86_64.
+    let prefix = format!("{}-", name);
+    std::fs::read_dir(topdir.join("RPMS"))?
+        .flatten()
+        .flat_map(|directory| std::fs::read_dir(directory.path()).into_iter().flatten())
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            file_name.starts_with(&prefix) && file_name.ends_with(".rpm")
+        })
+        .ok_or(BargeError::FailedOperation(
+            "The package created by rpmbuild was not found",
+        ))
+}
+
+pub(crate) fn create_package(
+    project: &Project,
+    target: BuildTarget,
+    format: PackageFormat,
+    output: Option<&String>,
+) -> Result<()> {
+    project.build(target)?;
+    color_println!(
+        BLUE,
+        "Packaging project {} {}",
+        project.name,
+        project.version
+    );
+
+    let root = PathBuf::from(PACKAGE_DIRECTORY).join("root");
+    attempt_remove_directory(&root.to_string_lossy())?;
+    std::fs::create_dir_all(&root)?;
+
+    let package = match format {
+        PackageFormat::Tarball => create_tarball(project, target, &root)?,
+        PackageFormat::Deb => create_deb(project, target, &root)?,
+        PackageFormat::Rpm => create_rpm(project, target, &root)?,
+    };
+    color_println!(GREEN, "Package created at {}", package.display());
+
+    if let Some(output) = output {
+        let destination = project.named_artifact(output, target)?;
+        if !package.is_file() {
+            return Err(BargeError::InvalidValue(
+                "Only single-file packages can be copied to a named artifact",
+            ));
+        }
+        if let Some(parent) = Path::new(&destination).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&package, &destination)?;
+        color_println!(GREEN, "Copied package to {} ({})", destination, output);
+    }
+    Ok(())
+}
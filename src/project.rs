@@ -1,25 +1,63 @@
-use crate::makefile::{generate_analyze_makefile, generate_build_makefile, BuildTarget};
-use crate::result::{BargeError, Result};
+use crate::annotations::{enable_annotations, AnnotationFormat};
+use crate::clangformat::{get_style_arg, update_clang_format};
+use crate::commandlog::{enable_command_recording, start_command_log};
+use crate::configure::{get_target_properties, write_config_header};
+use crate::dependencies::{check_compiler_identity, discard_dependency_cache};
+use crate::externals::{check_external_project_names, prepare_external_projects};
+use crate::fastpath::{
+    discard_build_state, get_input_fingerprint, is_build_up_to_date, write_build_state,
+};
+use crate::jsonc::from_jsonc_str;
+use crate::languages::{
+    compile_fields, custom_flags_fields, detect_languages, get_all_extensions, get_language,
+    language_fields, languages, LanguageKind,
+};
+use crate::makefile::{
+    generate_build_makefile, generate_test_makefile, resolve_build_flags, write_flag_stamps,
+    write_harness_flag_stamps, BuildFlags, BuildTarget, TestBinary,
+};
+use crate::memory::write_linker_script;
+use crate::notifications::send_notifications;
+use crate::observer::{
+    configure_output_capture, observe_child, BuildObserver, BuildOutcome, ConsoleObserver,
+    EventLogObserver,
+};
+use crate::records::{write_build_record, write_test_results, BuildRecord, TestResult};
+use crate::resources::compile_version_resource;
+use crate::result::{print_error, BargeError, Result};
+use crate::rustcrates::prepare_rust_crates;
+use crate::scheduling::{configure_memory_heavy_jobs, update_memory_usage};
+use crate::schema::{migrate_project_value, CURRENT_SCHEMA_VERSION};
 use crate::scripts::{execute_script, BuildScriptKind, ScriptEnvironment};
-use crate::utilities::attempt_remove_directory;
-use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use crate::selfprofile::ProfileSection;
+use crate::size::get_binutils;
+use crate::staging::stage_runtime_resources;
+use crate::toml::{parse_toml, to_toml_string};
+use crate::userconfig::load_user_config;
+use crate::utilities::{attempt_remove_directory, find_executable, get_changed_files};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE, YELLOW};
 use chrono::Local;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
-use std::path::Path;
-use std::process::{Command, Stdio};
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
 
 pub const DEFAULT_C_STANDARD: &str = "c11";
 pub const DEFAULT_CPP_STANDARD: &str = "c++17";
 pub const DEFAULT_FORTRAN_STANDARD: &str = "f2003";
 pub const DEFAULT_COBOL_STANDARD: &str = "cobol2014";
 pub const DEFAULT_TOOLSET: &Toolset = &Toolset::Llvm;
-pub const DEFAULT_CUSTOM_CFLAGS: &str = "";
-pub const DEFAULT_CUSTOM_CXXFLAGS: &str = "";
-pub const DEFAULT_CUSTOM_FORTRANFLAGS: &str = "";
-pub const DEFAULT_CUSTOM_COBOLFLAGS: &str = "";
+pub const HOST_BITS: u32 = usize::BITS;
 pub const DEFAULT_CUSTOM_LDFLAGS: &str = "";
+pub const DEFAULT_HERMETIC_LOCALE: &str = "C";
+pub const DEFAULT_HERMETIC_VARIABLES: &[&str] = &["PATH", "HOME", "TMPDIR", "TERM", "NO_COLOR"];
+pub const DEFAULT_HEADER_EXTENSIONS: &[&str] = &["h", "hpp", "hh", "hxx"];
+pub const DEFAULT_LINKER_SCRIPT_EXTENSIONS: &[&str] = &["ld"];
+// The names of the project file, in the order of preference, if a directory
+// contains more than one of them.
+pub const PROJECT_FILE_NAMES: &[&str] = &["barge.json", "barge.toml"];
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -28,7 +66,41 @@ pub enum Toolset {
     Llvm,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Elf,
+    Bin,
+    Hex,
+    Srec,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Console,
+    Windows,
+    Native,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Sanitizer {
+    #[serde(rename = "asan")]
+    Address,
+    #[serde(rename = "tsan")]
+    Thread,
+    #[serde(rename = "msan")]
+    Memory,
+    #[serde(rename = "ubsan")]
+    Undefined,
+    #[serde(rename = "none")]
+    None,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum Library {
@@ -36,7 +108,261 @@ pub enum Library {
     Manual { cflags: String, ldflags: String },
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Binary {
+    pub name: String,
+    pub main: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExternalProject {
+    pub name: String,
+    pub git: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RustCrate {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exports {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_dirs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defines: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_libraries: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommandTemplates {
+    #[serde(flatten, with = "compile_fields")]
+    pub compile: BTreeMap<LanguageKind, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardStyle {
+    PragmaOnce,
+    IncludeGuard,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scaffolding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guard_style: Option<GuardStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guard_pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_template: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExtraExtensions {
+    #[serde(flatten, with = "language_fields")]
+    pub languages: BTreeMap<LanguageKind, Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linker_script: Option<Vec<String>>,
+}
+
+// The configuration of clang-tidy, written into the .clang-tidy file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClangTidy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_checks: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings_as_errors: Option<Vec<String>>,
+}
+
+// The formatting of a kind of sources. The command is given the sources to
+// rewrite them in-place, instead of the default formatter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SourceFormatter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Formatters {
+    #[serde(flatten, with = "language_fields")]
+    pub languages: BTreeMap<LanguageKind, SourceFormatter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<SourceFormatter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Formatter {
+    ClangFormat,
+    // A formatter of the sources of the language, rewriting them in-place, or
+    // printing a source formatted with --stdout.
+    Program(LanguageKind, &'static str),
+    Custom(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SourceKind {
+    Language(LanguageKind),
+    Header,
+    LinkerScript,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SourceFlags {
+    pub pattern: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove_flags: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryHeavy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_jobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_mb: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookKind {
+    Generic,
+    Slack,
+    Discord,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<WebhookKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_failures: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StaticAnalyzer {
+    ClangTidy,
+    Cppcheck,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pie: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitizer: Option<Sanitizer>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_startup_files: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subsystem: Option<Subsystem>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssemblyOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_dirs: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub origin: String,
+    pub length: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryLayout {
+    pub regions: Vec<MemoryRegion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fragments: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlatformOverrides {
+    #[serde(flatten, with = "custom_flags_fields")]
+    pub custom_flags: BTreeMap<LanguageKind, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_ldflags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_libraries: Option<Vec<Library>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_build_steps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_build_steps: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SymbolCheck {
+    pub name: String,
+    pub header: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigureChecks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<Vec<SymbolCheck>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizeof: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Profiles {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<Profile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HermeticEnvironment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_variables: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ProjectType {
     Executable,
@@ -51,7 +377,7 @@ pub(crate) enum CollectSourceFilesMode {
     LinkerScriptsOnly,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct Project {
     pub name: String,
     pub authors: Vec<String>,
@@ -59,8 +385,32 @@ pub(crate) struct Project {
     pub project_type: ProjectType,
     pub version: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifacts: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bins: Option<Vec<Binary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multilib: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub toolset: Option<Toolset>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiler_launcher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_extensions: Option<ExtraExtensions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub c_standard: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cpp_standard: Option<String>,
@@ -71,23 +421,79 @@ pub(crate) struct Project {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub external_libraries: Option<Vec<Library>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub custom_cflags: Option<String>,
+    pub external_projects: Option<Vec<ExternalProject>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_crates: Option<Vec<RustCrate>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub custom_cxxflags: Option<String>,
+    pub workspace_members: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub custom_fortranflags: Option<String>,
+    pub exports: Option<Exports>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub custom_cobolflags: Option<String>,
+    pub profiles: Option<Profiles>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<LinkOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assembly: Option<AssemblyOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_layout: Option<MemoryLayout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configure_checks: Option<ConfigureChecks>,
+    #[serde(flatten, with = "custom_flags_fields")]
+    pub custom_flags: BTreeMap<LanguageKind, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_ldflags: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_flags: Option<Vec<SourceFlags>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_makeopts: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_heavy: Option<MemoryHeavy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub respect_env_flags: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_rebuild_window: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_resources: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_templates: Option<CommandTemplates>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hermetic_environment: Option<HermeticEnvironment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scaffolding: Option<Scaffolding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_exclude: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_coverage_threshold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_analyzers: Option<Vec<StaticAnalyzer>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clang_tidy: Option<ClangTidy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub format_style: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub format_options: Option<BTreeMap<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatters: Option<Formatters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pre_build_steps: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub post_build_steps: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<Vec<Notification>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linux: Option<PlatformOverrides>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub macos: Option<PlatformOverrides>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows: Option<PlatformOverrides>,
+    #[serde(skip)]
+    pub build_variant: Option<String>,
+    #[serde(skip)]
+    pub architecture: Option<String>,
+    #[serde(skip)]
+    pub annotations: Option<AnnotationFormat>,
 }
 
 impl Project {
@@ -98,31 +504,135 @@ impl Project {
             description: String::from(""),
             project_type,
             version: String::from("0.1.0"),
+            version_header: None,
+            license: None,
+            schema_version: Some(CURRENT_SCHEMA_VERSION),
+            output_format: None,
+            artifact_path: None,
+            artifacts: None,
+            bins: None,
+            bits: None,
+            multilib: None,
             toolset: None,
+            target: None,
+            compiler_launcher: None,
+            extra_extensions: None,
             c_standard: None,
             cpp_standard: None,
             fortran_standard: None,
             cobol_standard: None,
             external_libraries: None,
-            custom_cflags: None,
-            custom_cxxflags: None,
-            custom_fortranflags: None,
-            custom_cobolflags: None,
+            external_projects: None,
+            rust_crates: None,
+            workspace_members: None,
+            exports: None,
+            profiles: None,
+            link: None,
+            assembly: None,
+            memory_layout: None,
+            configure_checks: None,
+            custom_flags: BTreeMap::new(),
             custom_ldflags: None,
+            source_flags: None,
             custom_makeopts: None,
+            jobs: None,
+            memory_heavy: None,
+            respect_env_flags: None,
+            run_rebuild_window: None,
+            runtime_resources: None,
+            command_templates: None,
+            hermetic_environment: None,
+            scaffolding: None,
+            metrics_exclude: None,
+            doc_coverage_threshold: None,
+            static_analyzers: None,
+            clang_tidy: None,
             format_style: None,
+            format_options: None,
+            formatters: None,
             pre_build_steps: None,
             post_build_steps: None,
+            notifications: None,
+            linux: None,
+            macos: None,
+            windows: None,
+            build_variant: None,
+            architecture: None,
+            annotations: None,
         })
     }
 
     pub(crate) fn load(path: &str) -> Result<Project> {
-        let json = std::fs::read_to_string(path)?;
-        let project: Project = serde_json::from_str(&json)?;
+        let mut value = read_project_value(path)?;
+        let changes = migrate_project_value(&mut value)?;
+        if !changes.is_empty() {
+            color_println!(
+                BLUE,
+                "The project file uses an outdated schema, run barge upgrade to update it"
+            );
+        }
+        let mut project: Project = serde_json::from_value(value)?;
+        check_external_project_names(&project)?;
+        project.apply_platform_overrides();
+        load_user_config()?.apply_defaults(&mut project);
         Ok(project)
     }
 
+    pub(crate) fn load_from_directory(directory: &Path) -> Result<Project> {
+        let path = find_project_file(directory)
+            .ok_or(BargeError::ProjectNotFound("Project file not found."))?;
+        Project::load(&path.to_string_lossy())
+    }
+
+    // Merges the section of the host operating system into the project, the
+    // flags are appended to the common ones, and the libraries and the build
+    // steps are added after the common ones.
+    fn apply_platform_overrides(&mut self) {
+        let overrides = match std::env::consts::OS {
+            "linux" => self.linux.take(),
+            "macos" => self.macos.take(),
+            "windows" => self.windows.take(),
+            _ => None,
+        };
+        let Some(overrides) = overrides else {
+            return;
+        };
+
+        for (kind, platform) in &overrides.custom_flags {
+            self.append_custom_flags(*kind, platform);
+        }
+        if let Some(platform) = &overrides.custom_ldflags {
+            append_flags(&mut self.custom_ldflags, platform);
+        }
+
+        let lists = [
+            (&mut self.pre_build_steps, overrides.pre_build_steps),
+            (&mut self.post_build_steps, overrides.post_build_steps),
+        ];
+        for (common, platform) in lists {
+            if let Some(platform) = platform {
+                common.get_or_insert_with(Vec::new).extend(platform);
+            }
+        }
+        if let Some(libraries) = overrides.external_libraries {
+            self.external_libraries
+                .get_or_insert_with(Vec::new)
+                .extend(libraries);
+        }
+    }
+
     pub(crate) fn build(&self, target: BuildTarget) -> Result<()> {
+        match EventLogObserver::from_environment()? {
+            Some(observer) => self.build_with_observer(target, &observer),
+            None => self.build_with_observer(target, &ConsoleObserver),
+        }
+    }
+
+    pub(crate) fn build_with_observer(
+        &self,
+        target: BuildTarget,
+        observer: &dyn BuildObserver,
+    ) -> Result<()> {
         color_println!(
             BLUE,
             "Building project with {} configuration",
@@ -131,13 +641,50 @@ impl Project {
         let start_time = Instant::now();
         let start_timestamp = Local::now();
 
-        let makeopts = if let Some(makeopts) = &self.custom_makeopts {
+        if is_build_up_to_date(self, target)? {
+            self.export_artifact(target)?;
+            observer.on_finish(BuildOutcome::UpToDate);
+            return Ok(());
+        }
+        self.check_source_tree()?;
+        self.check_compiler_launcher()?;
+        let inputs = get_input_fingerprint(self, target)?;
+
+        if let Some(bits) = self.bits {
+            check_multilib_support(self, bits)?;
+        }
+
+        if self.jobs == Some(0) {
+            return Err(BargeError::InvalidValue(
+                "The number of jobs must be a positive integer",
+            ));
+        }
+        let mut makeopts = if let Some(makeopts) = &self.custom_makeopts {
             makeopts.split(' ').map(|str| str.to_string()).collect()
+        } else if self.jobs.is_some() {
+            Vec::new()
         } else {
             generate_default_makeopts()?
         };
+        if let Some(jobs) = self.jobs {
+            makeopts.push(format!("-j{}", jobs));
+        }
 
         let (commit_hash, branch) = get_git_project_info()?;
+        let target_properties = match (&self.pre_build_steps, &self.post_build_steps) {
+            (None, None) => None,
+            _ => match get_target_properties(self) {
+                Ok(properties) => Some(properties),
+                Err(error) => {
+                    color_println!(
+                        YELLOW,
+                        "Warning: could not query the target of the compiler for the build steps"
+                    );
+                    print_error(&error);
+                    None
+                }
+            },
+        };
 
         if let Some(pre_build_steps) = &self.pre_build_steps {
             for step in pre_build_steps {
@@ -153,14 +700,39 @@ impl Project {
                         git_commit_hash: commit_hash.clone(),
                         git_branch: branch.clone(),
                         build_timestamp: start_timestamp,
+                        build_directory: self.build_directory(target),
                         kind: BuildScriptKind::PreBuildStep,
                         toolset: self.toolset.unwrap_or(*DEFAULT_TOOLSET),
+                        artifacts: self.named_artifacts(target),
+                        compiler_launcher: self.compiler_launcher.clone(),
+                        target_properties: target_properties.clone(),
                     },
                 )?;
             }
         }
 
-        let mut make = Command::new("make")
+        start_command_log()?;
+        prepare_external_projects(self, target)?;
+        prepare_rust_crates(self, target)?;
+        let build_directory = self.build_directory(target);
+        check_compiler_identity(self, &build_directory)?;
+        compile_version_resource(self, target)?;
+        let section = ProfileSection::start("makefile generation");
+        let flags = resolve_build_flags(self, target)?;
+        write_flag_stamps(&build_directory, &flags)?;
+        write_linker_script(self, &build_directory)?;
+        write_config_header(self, &build_directory, &flags)?;
+        let makefile = generate_build_makefile(self, target, &flags)?;
+        drop(section);
+
+        let section = ProfileSection::start("make");
+        update_memory_usage()?;
+        let mut make = self.command("make");
+        enable_command_recording(&mut make)?;
+        enable_annotations(&mut make, self.annotations);
+        configure_memory_heavy_jobs(self, &mut make)?;
+        configure_output_capture(&mut make, observer);
+        let mut make = make
             .arg("-s")
             .arg("-f")
             .arg("-")
@@ -169,14 +741,24 @@ impl Project {
             .stdin(Stdio::piped())
             .spawn()?;
 
-        let makefile = generate_build_makefile(self, target)?;
         make.stdin
-            .as_mut()
+            .take()
             .ok_or(BargeError::NoneOption("Could not interact with make"))?
             .write_all(makefile.as_bytes())?;
-        let status = make.wait()?.success();
+        let status = observe_child(&mut make, observer)?;
+        drop(section);
+        let record = BuildRecord::new(
+            target,
+            status,
+            start_time.elapsed().as_secs_f64(),
+            &commit_hash,
+            &branch,
+        );
+        write_build_record(&build_directory, &record)?;
+        send_notifications(self, "build", &record);
 
         if status {
+            self.export_artifact(target)?;
             if let Some(post_build_steps) = &self.post_build_steps {
                 for step in post_build_steps {
                     execute_script(
@@ -191,24 +773,28 @@ impl Project {
                             git_commit_hash: commit_hash.clone(),
                             git_branch: branch.clone(),
                             build_timestamp: start_timestamp,
+                            build_directory: self.build_directory(target),
                             kind: BuildScriptKind::PostBuildStep,
                             toolset: self.toolset.unwrap_or(*DEFAULT_TOOLSET),
+                            artifacts: self.named_artifacts(target),
+                            compiler_launcher: self.compiler_launcher.clone(),
+                            target_properties: target_properties.clone(),
                         },
                     )?;
                 }
             }
 
-            let finish_time = Instant::now();
-            let build_duration = finish_time - start_time;
-            color_println!(
-                BLUE,
-                "Build finished in {:.2} seconds",
-                build_duration.as_secs_f64()
-            );
+            write_build_state(self, target, &inputs)?;
 
+            observer.on_artifact(Path::new(&self.artifact_path(target)));
+            for binary in self.bins.iter().flatten() {
+                observer.on_artifact(&Path::new(&build_directory).join(&binary.name));
+            }
+            observer.on_finish(BuildOutcome::Succeeded(start_time.elapsed()));
             Ok(())
         } else {
-            color_eprintln!("Build failed");
+            discard_build_state(&build_directory)?;
+            observer.on_finish(BuildOutcome::Failed(start_time.elapsed()));
             Err(BargeError::FailedOperation(
                 "One or more dependencies failed to build",
             ))
@@ -217,53 +803,512 @@ impl Project {
 
     pub(crate) fn rebuild(&self, target: BuildTarget) -> Result<()> {
         color_println!(BLUE, "{}", "Removing relevant build artifacts");
-        let path = format!("build/{}", target);
-        attempt_remove_directory(&path)?;
+        attempt_remove_directory(&self.build_directory(target))?;
         self.build(target)
     }
 
-    pub(crate) fn analyze(&self) -> Result<()> {
-        color_println!(BLUE, "Running static analysis on project");
+    pub(crate) fn discard_dependency_cache(&self, target: BuildTarget) -> Result<()> {
+        color_println!(BLUE, "Discarding cached dependency information");
+        discard_build_state(&self.build_directory(target))?;
+        discard_dependency_cache(&self.build_directory(target))
+    }
+
+    // The parts of the build directory telling the variants of the same build
+    // target apart, like the bits, the architecture, and the build variant.
+    fn variant_parts(&self) -> Vec<String> {
+        let bits = self.bits.map(|bits| format!("{}bit", bits));
+        bits.into_iter()
+            .chain(self.architecture.clone())
+            .chain(self.build_variant.clone())
+            .collect()
+    }
+
+    pub(crate) fn build_directory(&self, target: BuildTarget) -> String {
+        let directory = match &self.target {
+            Some(triple) => format!("build/{}/{}", triple, target),
+            None => format!("build/{}", target),
+        };
+        std::iter::once(directory)
+            .chain(self.variant_parts())
+            .collect::<Vec<String>>()
+            .join("-")
+    }
+
+    // Returns the path of the final artifact, either in the build directory, or
+    // at the location given by the artifact_path pattern of the project file.
+    // Executables converted to another output format are delivered converted.
+    pub(crate) fn artifact_path(&self, target: BuildTarget) -> String {
+        let Some(pattern) = &self.artifact_path else {
+            return self
+                .converted_artifact_path(target)
+                .unwrap_or_else(|| self.linked_artifact_path(target));
+        };
+
+        let path = self.expand_artifact_pattern(pattern, target);
+        if path.contains('/') {
+            path
+        } else {
+            format!("./{}", path)
+        }
+    }
+
+    // Returns the path of the artifact as linked, in the build directory.
+    pub(crate) fn linked_artifact_path(&self, target: BuildTarget) -> String {
+        format!("{}/{}", self.build_directory(target), self.artifact_name())
+    }
+
+    // Returns the path of the executable converted to the output format of the
+    // project, in the build directory, if it is converted at all.
+    pub(crate) fn converted_artifact_path(&self, target: BuildTarget) -> Option<String> {
+        let extension = get_output_extension(self.output_format?)?;
+        Some(format!(
+            "{}.{}",
+            self.linked_artifact_path(target),
+            extension
+        ))
+    }
+
+    // Returns the path of the executable to run, either the artifact of the
+    // project, or one of its additional binaries. Converted executables are
+    // run (and debugged) as linked.
+    pub(crate) fn executable_path(
+        &self,
+        target: BuildTarget,
+        bin: Option<&String>,
+    ) -> Result<String> {
+        let Some(name) = bin else {
+            return Ok(match self.converted_artifact_path(target) {
+                Some(_) => self.linked_artifact_path(target),
+                None => self.artifact_path(target),
+            });
+        };
+        match self
+            .bins
+            .iter()
+            .flatten()
+            .find(|binary| binary.name == *name)
+        {
+            Some(binary) => Ok(format!("{}/{}", self.build_directory(target), binary.name)),
+            None => {
+                color_eprintln!("The project has no binary named {}", name);
+                Err(BargeError::InvalidValue("Unknown binary name"))
+            }
+        }
+    }
+
+    fn expand_artifact_pattern(&self, pattern: &str, target: BuildTarget) -> String {
+        let extension = match self.project_type {
+            ProjectType::Executable => self
+                .output_format
+                .and_then(get_output_extension)
+                .map(|extension| format!(".{}", extension))
+                .unwrap_or_default(),
+            ProjectType::SharedLibrary => String::from(".so"),
+            ProjectType::StaticLibrary => String::from(".a"),
+        };
+        pattern
+            .replace("{name}", &self.name)
+            .replace("{version}", &self.version)
+            .replace("{profile}", &target.to_string())
+            .replace("{ext}", &extension)
+            .replace("{artifact}", &self.artifact_name())
+            .replace("{build}", &self.build_directory(target))
+            .replace("{bits}", &self.bits.unwrap_or(HOST_BITS).to_string())
+            .replace("{target}", self.target.as_deref().unwrap_or("native"))
+            .replace("{variant}", &self.variant_parts().join("-"))
+    }
+
+    // Returns the named artifacts of the project file, with their paths
+    // resolved for the given build target.
+    pub(crate) fn named_artifacts(&self, target: BuildTarget) -> Vec<(String, String)> {
+        self.artifacts
+            .iter()
+            .flatten()
+            .map(|(name, pattern)| (name.clone(), self.expand_artifact_pattern(pattern, target)))
+            .collect()
+    }
+
+    pub(crate) fn named_artifact(&self, name: &str, target: BuildTarget) -> Result<String> {
+        let artifacts = self.named_artifacts(target);
+        match artifacts.iter().find(|(artifact, _)| artifact == name) {
+            Some((_, path)) => Ok(path.clone()),
+            None => {
+                let names: Vec<&str> = artifacts.iter().map(|(name, _)| name.as_str()).collect();
+                color_eprintln!(
+                    "No artifact named {} in the project file (defined: {})",
+                    name,
+                    if names.is_empty() {
+                        String::from("none")
+                    } else {
+                        names.join(", ")
+                    }
+                );
+                Err(BargeError::InvalidValue("Unknown artifact name"))
+            }
+        }
+    }
+
+    // Copies the built artifact to the configured artifact path, if it is
+    // missing or older than the one in the build directory.
+    fn export_artifact(&self, target: BuildTarget) -> Result<()> {
+        if self.artifact_path.is_none() {
+            return Ok(());
+        }
+
+        let built = self
+            .converted_artifact_path(target)
+            .unwrap_or_else(|| self.linked_artifact_path(target));
+        let exported = self.artifact_path(target);
+        let modified = |path: &str| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified(&exported) >= modified(&built) {
+            return Ok(());
+        }
+
+        if let Some(parent) = Path::new(&exported).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&built, &exported)?;
+        color_println!(BLUE, "Copied artifact to {}", exported);
+        Ok(())
+    }
+
+    pub(crate) fn compiler(&self, kind: LanguageKind) -> String {
+        get_language(kind).compiler(self)
+    }
+
+    pub(crate) fn append_custom_flags(&mut self, kind: LanguageKind, extra: &str) {
+        let flags = self.custom_flags.entry(kind).or_default();
+        if !flags.is_empty() {
+            flags.push(' ');
+        }
+        flags.push_str(extra);
+    }
+
+    pub(crate) fn targets_windows(&self) -> bool {
+        self.target
+            .as_ref()
+            .is_some_and(|triple| triple.contains("mingw") || triple.contains("windows"))
+    }
+
+    pub(crate) fn cross_tool(&self, tool: &str) -> String {
+        // The LLVM tools handle every target, only GNU binutils are prefixed.
+        match (
+            &self.target,
+            self.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET),
+        ) {
+            (Some(triple), Toolset::Gnu) => format!("{}-{}", triple, tool),
+            _ => tool.to_string(),
+        }
+    }
+
+    fn built_within_rebuild_window(&self, artifact: &str) -> bool {
+        let window = match self.run_rebuild_window {
+            Some(window) if window > 0 => Duration::from_secs(window),
+            _ => return false,
+        };
+        std::fs::metadata(artifact)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < window)
+    }
+
+    fn check_source_tree(&self) -> Result<()> {
+        let missing = !Path::new("src").is_dir();
+        if !missing && !detect_languages(self)?.languages.is_empty() {
+            return Ok(());
+        }
+
+        if missing {
+            color_eprintln!("The src directory is missing from the project directory");
+        } else {
+            color_eprintln!("The src directory contains no recognized source files");
+        }
+        let extensions: Vec<String> = get_all_extensions(self)
+            .iter()
+            .map(|e| format!(".{}", e))
+            .collect();
+        color_println!(
+            WHITE,
+            "Source files are searched for recursively in src, with the extensions {}",
+            extensions.join(", ")
+        );
+        color_println!(
+            WHITE,
+            "Use barge new-file <PATH> to create a source file, or extra_extensions in barge.json to recognize other extensions"
+        );
+        Err(BargeError::FailedOperation("No source files to build"))
+    }
+
+    pub(crate) fn check_host_target(&self) -> Result<()> {
+        if let Some(triple) = &self.target {
+            color_eprintln!(
+                "Executables built for {} can not be run on the host",
+                triple
+            );
+            return Err(BargeError::InvalidValue(
+                "Cross-compiled executables can not be run on the host",
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        if let Some(hermetic) = &self.hermetic_environment {
+            let allowed = hermetic.allowed_variables.iter().flatten();
+            let kept: Vec<(String, String)> = std::env::vars()
+                .filter(|(name, _)| {
+                    DEFAULT_HERMETIC_VARIABLES.contains(&name.as_str())
+                        || allowed.clone().any(|allowed| allowed == name)
+                })
+                .collect();
+            let locale = hermetic
+                .locale
+                .as_deref()
+                .unwrap_or(DEFAULT_HERMETIC_LOCALE);
+
+            command
+                .env_clear()
+                .envs(kept)
+                .env("LANG", locale)
+                .env("LC_ALL", locale);
+        }
+        command
+    }
+
+    fn check_compiler_launcher(&self) -> Result<()> {
+        let launcher = get_launcher_arguments(self.compiler_launcher.as_deref());
+        match launcher.first() {
+            Some(launcher) if find_executable(launcher).is_none() => {
+                color_eprintln!("The compiler launcher {} was not found", launcher);
+                Err(BargeError::InvalidValue("Compiler launcher not found"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn profile(&self, target: BuildTarget) -> Option<&Profile> {
+        let profiles = self.profiles.as_ref()?;
+        match target {
+            BuildTarget::Debug => profiles.debug.as_ref(),
+            BuildTarget::Release => profiles.release.as_ref(),
+        }
+    }
+
+    pub(crate) fn extensions(&self, kind: SourceKind) -> Vec<String> {
+        let extra = self.extra_extensions.as_ref();
+        match kind {
+            SourceKind::Language(language) => get_language(language).extensions(self),
+            SourceKind::Header => merge_extensions(
+                DEFAULT_HEADER_EXTENSIONS,
+                extra.and_then(|e| e.header.as_ref()),
+            ),
+            SourceKind::LinkerScript => merge_extensions(
+                DEFAULT_LINKER_SCRIPT_EXTENSIONS,
+                extra.and_then(|e| e.linker_script.as_ref()),
+            ),
+        }
+    }
 
-        let mut make = Command::new("make")
+    pub(crate) fn source_kind(&self, path: &str) -> Option<SourceKind> {
+        let (_, extension) = path.rsplit_once('.')?;
+        languages()
+            .iter()
+            .map(|language| SourceKind::Language(language.kind()))
+            .chain([SourceKind::Header, SourceKind::LinkerScript])
+            .find(|kind| self.extensions(*kind).iter().any(|e| e == extension))
+    }
+
+    pub(crate) fn artifact_name(&self) -> String {
+        match self.project_type {
+            ProjectType::Executable => self.name.clone(),
+            ProjectType::SharedLibrary => "lib".to_string() + &self.name + ".so",
+            ProjectType::StaticLibrary => "lib".to_string() + &self.name + ".a",
+        }
+    }
+
+    pub(crate) fn multilib_variants(&self) -> Result<Vec<Project>> {
+        let multilib = self.multilib.as_ref().ok_or(BargeError::InvalidValue(
+            "No multilib variants are specified in the project file",
+        ))?;
+
+        Ok(multilib
+            .iter()
+            .map(|bits| {
+                let mut variant = self.clone();
+                variant.bits = Some(*bits);
+                variant
+            })
+            .collect())
+    }
+
+    // Builds the sources of a directory next to the project (such as the tests
+    // or the benchmarks) into separate executables, linked against the objects
+    // of the project. Returns whether the build succeeded.
+    pub(crate) fn build_harness(
+        &self,
+        target: BuildTarget,
+        flags: &BuildFlags,
+        directory: &str,
+        binaries: &[TestBinary],
+    ) -> Result<bool> {
+        let build_directory = self.build_directory(target);
+        let objects = collect_linkable_objects(self, &build_directory)?;
+        write_harness_flag_stamps(&format!("{}/{}", build_directory, directory), flags)?;
+        let makefile = generate_test_makefile(self, target, flags, directory, binaries, &objects)?;
+
+        let mut make = self.command("make");
+        enable_command_recording(&mut make)?;
+        enable_annotations(&mut make, self.annotations);
+        let mut make = make
             .arg("-s")
             .arg("-f")
             .arg("-")
-            .arg("analyze")
+            .arg("all")
             .stdin(Stdio::piped())
             .spawn()?;
-
-        let makefile = generate_analyze_makefile(self)?;
-
         make.stdin
             .as_mut()
             .ok_or(BargeError::NoneOption("Could not interact with make"))?
             .write_all(makefile.as_bytes())?;
-        make.wait()?;
+        Ok(make.wait()?.success())
+    }
+
+    pub(crate) fn test(&self, target: BuildTarget, filter: Option<&String>) -> Result<()> {
+        let mut sources = collect_test_files(self)?;
+        if let Some(filter) = filter {
+            sources.retain(|source| source.contains(filter.as_str()));
+        }
+        if sources.is_empty() {
+            color_println!(BLUE, "No tests were found in the tests directory");
+            return Ok(());
+        }
+
+        self.check_host_target()?;
+        self.build(target)?;
+        color_println!(
+            BLUE,
+            "Building tests with {} configuration",
+            target.to_string()
+        );
+
+        let build_directory = self.build_directory(target);
+        let tests = get_harness_binaries(&build_directory, "tests", &sources);
+        let flags = resolve_build_flags(self, target)?;
+        if !self.build_harness(target, &flags, "tests", &tests)? {
+            color_eprintln!("Failed to build tests");
+            return Err(BargeError::FailedOperation(
+                "One or more tests failed to build",
+            ));
+        }
+
+        let directories: BTreeSet<&Path> = tests
+            .iter()
+            .filter_map(|test| Path::new(&test.binary).parent())
+            .collect();
+        for directory in directories {
+            stage_runtime_resources(self, directory)?;
+        }
 
+        let start_time = Instant::now();
+        let mut results = Vec::new();
+        for test in &tests {
+            let test_start = Instant::now();
+            let output = Command::new(&test.binary).output()?;
+            let duration = test_start.elapsed().as_secs_f64();
+            if output.status.success() {
+                color_println!(GREEN, "PASS {} ({:.2} s)", test.source, duration);
+            } else {
+                color_eprintln!("FAIL {} ({:.2} s)", test.source, duration);
+                std::io::stdout().write_all(&output.stdout)?;
+                std::io::stderr().write_all(&output.stderr)?;
+            }
+            results.push(TestResult {
+                source: test.source.clone(),
+                passed: output.status.success(),
+                duration,
+            });
+        }
+        write_test_results(&build_directory, &results)?;
+        let failed: Vec<&TestResult> = results.iter().filter(|result| !result.passed).collect();
+
+        let (commit_hash, branch) = get_git_project_info()?;
+        let record = BuildRecord::new(
+            target,
+            failed.is_empty(),
+            start_time.elapsed().as_secs_f64(),
+            &commit_hash,
+            &branch,
+        );
+        send_notifications(self, "test", &record);
+
+        let passed = tests.len() - failed.len();
+        if failed.is_empty() {
+            color_println!(GREEN, "All {} test(s) passed", passed);
+            Ok(())
+        } else {
+            color_eprintln!("{} test(s) passed, {} failed", passed, failed.len());
+            Err(BargeError::FailedOperation("One or more tests failed"))
+        }
+    }
+
+    fn stage_runtime_resources_for(&self, artifact: &str) -> Result<()> {
+        match Path::new(artifact).parent() {
+            Some(directory) => stage_runtime_resources(self, directory),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn run(
+        &self,
+        target: BuildTarget,
+        arguments: Vec<String>,
+        bin: Option<&String>,
+    ) -> Result<()> {
+        if let Some(mut child) = self.spawn(target, arguments, bin)? {
+            child.wait()?;
+        }
         Ok(())
     }
 
-    pub(crate) fn run(&self, target: BuildTarget, arguments: Vec<String>) -> Result<()> {
-        if self.project_type != ProjectType::Executable {
+    // Builds the executable if needed, and starts it without waiting for it
+    // to finish.
+    pub(crate) fn spawn(
+        &self,
+        target: BuildTarget,
+        arguments: Vec<String>,
+        bin: Option<&String>,
+    ) -> Result<Option<Child>> {
+        if self.project_type != ProjectType::Executable && bin.is_none() {
             color_eprintln!("Only binary projects can be run");
-            return Ok(());
+            return Ok(None);
         }
 
-        self.build(target)?;
+        self.check_host_target()?;
+        let path = self.executable_path(target, bin)?;
+        if !self.built_within_rebuild_window(&path) {
+            self.build(target)?;
+        }
+        self.stage_runtime_resources_for(&path)?;
 
-        let path = String::from("build/") + &target.to_string() + "/" + &self.name;
         color_println!(BLUE, "Running executable {}", &path);
-        Command::new(&path).args(arguments).spawn()?.wait()?;
-        Ok(())
+        Ok(Some(Command::new(&path).args(arguments).spawn()?))
     }
 
-    pub(crate) fn debug(&self, target: BuildTarget, arguments: Vec<String>) -> Result<()> {
-        if self.project_type != ProjectType::Executable {
+    pub(crate) fn debug(
+        &self,
+        target: BuildTarget,
+        arguments: Vec<String>,
+        bin: Option<&String>,
+    ) -> Result<()> {
+        if self.project_type != ProjectType::Executable && bin.is_none() {
             color_eprintln!("Only binary projects can be run");
             return Ok(());
         }
 
+        self.check_host_target()?;
+        let path = self.executable_path(target, bin)?;
         self.build(target)?;
 
         let toolset = if let Some(toolset) = &self.toolset {
@@ -273,7 +1318,7 @@ impl Project {
         };
         let debugger = get_debugger(toolset);
 
-        let path = String::from("build/") + &target.to_string() + "/" + &self.name;
+        self.stage_runtime_resources_for(&path)?;
         color_println!(BLUE, "Running executable {} in the debugger", &path);
 
         if toolset == &Toolset::Gnu {
@@ -295,20 +1340,111 @@ impl Project {
         Ok(())
     }
 
-    pub(crate) fn format(&self) -> Result<()> {
-        let sources = collect_source_files(CollectSourceFilesMode::CCppSourcesOnly)?;
-        let style_arg = if let Some(format_style) = &self.format_style {
-            "--style=".to_string() + format_style
-        } else {
-            "--style=Google".to_string()
-        };
+    fn get_source_formatter(&self, kind: SourceKind) -> Option<&SourceFormatter> {
+        let formatters = self.formatters.as_ref()?;
+        match kind {
+            SourceKind::Language(language) => formatters.languages.get(&language),
+            SourceKind::Header => formatters.header.as_ref(),
+            SourceKind::LinkerScript => None,
+        }
+    }
 
-        Command::new("clang-format")
-            .arg("-i")
-            .arg(style_arg)
-            .args(sources)
-            .spawn()?
-            .wait()?;
+    fn is_formatting_disabled(&self, kind: SourceKind) -> bool {
+        self.get_source_formatter(kind)
+            .and_then(|settings| settings.enabled)
+            == Some(false)
+    }
+
+    // Returns the formatter of the kind of sources, or None if they are not
+    // formatted, either because it is disabled, or there is no formatter.
+    fn get_formatter(&self, kind: SourceKind) -> Option<Formatter> {
+        if self.is_formatting_disabled(kind) {
+            return None;
+        }
+        let settings = self.get_source_formatter(kind);
+        if let Some(command) = settings.and_then(|settings| settings.command.as_ref()) {
+            return Some(Formatter::Custom(command.clone()));
+        }
+        match kind {
+            SourceKind::Language(language) => get_language(language).formatter(),
+            SourceKind::Header => Some(Formatter::ClangFormat),
+            SourceKind::LinkerScript => None,
+        }
+    }
+
+    // Formats the sources of the project, or only the ones changed since the
+    // given git revision.
+    pub(crate) fn format(&self, check: bool, changed_since: Option<&str>) -> Result<()> {
+        let changed = changed_since.map(get_changed_files).transpose()?;
+        let style_arg = get_style_arg(self);
+
+        // The sources grouped by their formatters, in the order the formatters
+        // are first needed.
+        let mut groups: Vec<(Formatter, Vec<String>)> = Vec::new();
+        let mut unformatted: BTreeMap<LanguageKind, usize> = BTreeMap::new();
+        for source in collect_source_files(self, CollectSourceFilesMode::All)? {
+            if changed
+                .as_ref()
+                .is_some_and(|changed| !changed.contains(&source))
+            {
+                continue;
+            }
+            let Some(kind) = self.source_kind(&source) else {
+                continue;
+            };
+            let Some(formatter) = self.get_formatter(kind) else {
+                if let SourceKind::Language(language) = kind {
+                    if !self.is_formatting_disabled(kind) {
+                        *unformatted.entry(language).or_default() += 1;
+                    }
+                }
+                continue;
+            };
+            match groups.iter_mut().find(|(f, _)| *f == formatter) {
+                Some((_, sources)) => sources.push(source),
+                None => groups.push((formatter, vec![source])),
+            }
+        }
+
+        if let Some(revision) = changed_since {
+            if groups.is_empty() && unformatted.is_empty() {
+                color_println!(BLUE, "No source files were changed since {}", revision);
+                return Ok(());
+            }
+        }
+        if check {
+            return check_formatting(self, &style_arg, &groups);
+        }
+        update_clang_format(self)?;
+
+        for (formatter, sources) in &groups {
+            match formatter {
+                Formatter::ClangFormat => {
+                    Command::new("clang-format")
+                        .arg("-i")
+                        .arg(&style_arg)
+                        .args(sources)
+                        .spawn()?
+                        .wait()?;
+                }
+                Formatter::Program(_, program) => {
+                    run_formatter(self.command(program).args(sources))?;
+                }
+                Formatter::Custom(command) => {
+                    let mut arguments = command.split_whitespace();
+                    let program = arguments.next().unwrap_or_default();
+                    run_formatter(self.command(program).args(arguments).args(sources))?;
+                }
+            }
+        }
+        for (language, count) in unformatted {
+            color_println!(
+                YELLOW,
+                "No formatter is configured for {}, {} source file(s) were left unchanged",
+                language,
+                count
+            );
+        }
 
         color_println!(BLUE, "The project source files were formatted");
         Ok(())
@@ -351,32 +1487,263 @@ fn generate_default_makeopts() -> Result<Vec<String>> {
     Ok(vec![format!("-j{}", parallel_jobs)])
 }
 
-pub(crate) fn collect_source_files(mode: CollectSourceFilesMode) -> Result<Vec<String>> {
-    let arguments = match mode {
-        CollectSourceFilesMode::All => {
-            vec![
-                "-name", "*.f90", // FORTRAN Source
-                "-o", "-name", "*.cob", // Cobol Source
-                "-o", "-name", "*.s", // Assembly Source
-                "-o", "-name", "*.ld", // Linker Script
-                "-o", "-name", "*.c", // C Source
-                "-o", "-name", "*.cpp", // C++ Source
-                "-o", "-name", "*.h", // C Header
-                "-o", "-name", "*.hpp", // C++ Header
-            ]
-        }
-        CollectSourceFilesMode::CCppSourcesOnly => {
-            vec![
-                "-name", "*.c", // C Source
-                "-o", "-name", "*.cpp", // C++ Source
-                "-o", "-name", "*.h", // C Header
-                "-o", "-name", "*.hpp", // C++ Header
-            ]
-        }
-        CollectSourceFilesMode::LinkerScriptsOnly => {
-            vec!["-name", "*.ld"] // Linker Script
+pub(crate) fn collect_test_files(project: &Project) -> Result<Vec<String>> {
+    collect_harness_files(project, "tests")
+}
+
+pub(crate) fn collect_harness_files(project: &Project, directory: &str) -> Result<Vec<String>> {
+    if !Path::new(directory).is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut extensions = project.extensions(SourceKind::Language(LanguageKind::C));
+    extensions.extend(project.extensions(SourceKind::Language(LanguageKind::Cpp)));
+    let find = Command::new("find")
+        .arg(directory)
+        .args(["-type", "f"])
+        .args(get_find_name_arguments(&extensions))
+        .output()?
+        .stdout;
+
+    let mut tests: Vec<String> = std::str::from_utf8(&find)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    tests.sort();
+    Ok(tests)
+}
+
+pub(crate) fn get_harness_binaries(
+    build_directory: &str,
+    directory: &str,
+    sources: &[String],
+) -> Vec<TestBinary> {
+    let prefix = format!("{}/", directory);
+    sources
+        .iter()
+        .map(|source| {
+            let name = source.strip_prefix(&prefix).unwrap_or(source);
+            let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+            TestBinary {
+                source: source.clone(),
+                binary: format!("{}/{}/{}", build_directory, directory, stem),
+            }
+        })
+        .collect()
+}
+
+fn collect_linkable_objects(project: &Project, build_directory: &str) -> Result<Vec<String>> {
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let nm = project.cross_tool(get_binutils(toolset).1);
+    let mut objects = Vec::new();
+    for source in collect_source_files(project, CollectSourceFilesMode::All)? {
+        if matches!(
+            project.source_kind(&source),
+            Some(SourceKind::Header | SourceKind::LinkerScript)
+        ) {
+            continue;
+        }
+
+        let name = source.strip_prefix("src/").unwrap_or(&source);
+        let object = format!("{}/obj/{}.o", build_directory, name);
+        let symbols = Command::new(&nm)
+            .args(["--defined-only", "-P", &object])
+            .output()?
+            .stdout;
+        let defines_main = String::from_utf8_lossy(&symbols)
+            .lines()
+            .any(|line| line.starts_with("main ") || line.starts_with("_main "));
+        if !defines_main {
+            objects.push(object);
+        }
+    }
+    Ok(objects)
+}
+
+pub(crate) fn get_find_name_arguments(extensions: &[String]) -> Vec<String> {
+    let mut arguments = vec![String::from("(")];
+    for (index, extension) in extensions.iter().enumerate() {
+        if index > 0 {
+            arguments.push(String::from("-o"));
+        }
+        arguments.push(String::from("-name"));
+        arguments.push(format!("*.{}", extension));
+    }
+    arguments.push(String::from(")"));
+    arguments
+}
+
+pub(crate) fn find_project_file(directory: &Path) -> Option<PathBuf> {
+    PROJECT_FILE_NAMES
+        .iter()
+        .map(|name| directory.join(name))
+        .find(|path| path.is_file())
+}
+
+pub(crate) fn is_toml_file(path: &str) -> bool {
+    path.ends_with(".toml")
+}
+
+// Appends the flags after the existing ones, so that they take precedence.
+pub(crate) fn append_flags(flags: &mut Option<String>, extra: &str) {
+    *flags = Some(match flags.take() {
+        Some(flags) => format!("{} {}", flags, extra),
+        None => extra.to_string(),
+    });
+}
+
+// Reads a project file of either format into the JSON value of the project.
+pub(crate) fn read_project_value(path: &str) -> Result<serde_json::Value> {
+    parse_project_value(path, &std::fs::read_to_string(path)?)
+}
+
+pub(crate) fn parse_project_value(path: &str, content: &str) -> Result<serde_json::Value> {
+    if is_toml_file(path) {
+        parse_toml(content)
+    } else {
+        from_jsonc_str(content)
+    }
+}
+
+// Parses the edited content of a project file, which is only written if it is
+// still a valid project file.
+pub(crate) fn parse_edited_project(path: &str, content: &str) -> Result<Project> {
+    Ok(serde_json::from_value(parse_project_value(path, content)?)?)
+}
+
+pub(crate) fn format_project_file(path: &str, project: &Project) -> Result<String> {
+    if is_toml_file(path) {
+        to_toml_string(project)
+    } else {
+        Ok(serde_json::to_string_pretty(project)? + "\n")
+    }
+}
+
+// Returns whether the formatter leaves the source unchanged. The formatter
+// prints the formatted source instead of rewriting it.
+fn is_formatted(formatter: &mut Command, source: &str) -> Result<bool> {
+    let output = match formatter.arg(source).output() {
+        Ok(output) => output,
+        Err(error) => {
+            color_eprintln!("Could not execute {:?}", formatter.get_program());
+            return Err(error.into());
         }
     };
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(BargeError::FailedOperation(
+            "Could not format a source file",
+        ));
+    }
+    Ok(output.stdout == std::fs::read(source)?)
+}
+
+// Runs a formatter which modifies the sources in place, and fails if it could
+// not be executed or it did not succeed.
+fn run_formatter(formatter: &mut Command) -> Result<()> {
+    let status = match formatter.status() {
+        Ok(status) => status,
+        Err(error) => {
+            color_eprintln!("Could not execute {:?}", formatter.get_program());
+            return Err(error.into());
+        }
+    };
+    if !status.success() {
+        return Err(BargeError::FailedOperation(
+            "Could not format a source file",
+        ));
+    }
+    Ok(())
+}
+
+// A copy of a source, which is removed when dropped, even if formatting it
+// failed.
+struct FormattedCopy(PathBuf);
+
+impl Drop for FormattedCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Returns whether the formatter command, rewriting the files in-place, leaves
+// the source unchanged. The command is run on a copy of the source in the build
+// directory.
+fn is_formatted_in_place(project: &Project, command: &str, source: &str) -> Result<bool> {
+    let copy = Path::new(&project.build_directory(BuildTarget::Debug))
+        .join("format")
+        .join(source);
+    if let Some(parent) = copy.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(source, &copy)?;
+    let copy = FormattedCopy(copy);
+    let mut arguments = command.split_whitespace();
+    let mut formatter = project.command(arguments.next().unwrap_or_default());
+    run_formatter(formatter.args(arguments).arg(&copy.0))?;
+    Ok(std::fs::read(&copy.0)? == std::fs::read(source)?)
+}
+
+// Lists the sources which would be changed by formatting, without modifying
+// them, and fails if there are any.
+fn check_formatting(
+    project: &Project,
+    style_arg: &str,
+    groups: &[(Formatter, Vec<String>)],
+) -> Result<()> {
+    let mut unformatted = Vec::new();
+    for (formatter, sources) in groups {
+        for source in sources {
+            let formatted = match formatter {
+                Formatter::ClangFormat => {
+                    is_formatted(Command::new("clang-format").arg(style_arg), source)?
+                }
+                Formatter::Program(_, program) => {
+                    is_formatted(project.command(program).arg("--stdout"), source)?
+                }
+                Formatter::Custom(command) => is_formatted_in_place(project, command, source)?,
+            };
+            if !formatted {
+                unformatted.push(source);
+            }
+        }
+    }
+
+    if unformatted.is_empty() {
+        color_println!(GREEN, "The project source files are formatted");
+        return Ok(());
+    }
+    for source in &unformatted {
+        color_println!(YELLOW, "{} is not formatted", source);
+    }
+    color_eprintln!("{} source file(s) are not formatted", unformatted.len());
+    Err(BargeError::FailedOperation(
+        "Some source files are not formatted",
+    ))
+}
+
+pub(crate) fn collect_source_files(
+    project: &Project,
+    mode: CollectSourceFilesMode,
+) -> Result<Vec<String>> {
+    let extensions: Vec<String> = match mode {
+        CollectSourceFilesMode::All => get_all_extensions(project)
+            .into_iter()
+            .chain(project.extensions(SourceKind::Header))
+            .chain(project.extensions(SourceKind::LinkerScript))
+            .collect(),
+        CollectSourceFilesMode::CCppSourcesOnly => [
+            SourceKind::Language(LanguageKind::C),
+            SourceKind::Language(LanguageKind::Cpp),
+            SourceKind::Header,
+        ]
+        .iter()
+        .flat_map(|kind| project.extensions(*kind))
+        .collect(),
+        CollectSourceFilesMode::LinkerScriptsOnly => project.extensions(SourceKind::LinkerScript),
+    };
+    let arguments = get_find_name_arguments(&extensions);
 
     let find_src = Command::new("find")
         .arg("src")
@@ -390,12 +1757,83 @@ pub(crate) fn collect_source_files(mode: CollectSourceFilesMode) -> Result<Vec<S
     Ok(found.iter().map(|s| s.to_string()).collect())
 }
 
-pub(crate) fn get_toolset_executables(
-    toolset: &Toolset,
-) -> (&'static str, &'static str, &'static str) {
-    match toolset {
-        Toolset::Gnu => ("gcc", "g++", "gfortran"),
-        Toolset::Llvm => ("clang", "clang++", "gfortran"),
+// The extension of executables converted to the output format, if they are
+// converted at all.
+pub(crate) fn get_output_extension(format: OutputFormat) -> Option<&'static str> {
+    match format {
+        OutputFormat::Elf => None,
+        OutputFormat::Bin => Some("bin"),
+        OutputFormat::Hex => Some("hex"),
+        OutputFormat::Srec => Some("srec"),
+    }
+}
+
+// The default extensions of a kind of sources, followed by the extra ones of
+// the project file, with or without a leading dot.
+pub(crate) fn merge_extensions(defaults: &[&str], extra: Option<&Vec<String>>) -> Vec<String> {
+    let mut extensions: Vec<String> = defaults.iter().map(|e| e.to_string()).collect();
+    for extension in extra.into_iter().flatten() {
+        let extension = extension.trim_start_matches('.').to_string();
+        if !extensions.contains(&extension) {
+            extensions.push(extension);
+        }
+    }
+    extensions
+}
+
+pub(crate) fn get_standard_year(standard: &str) -> Option<u32> {
+    let version = ["iso9899:", "gnu++", "c++", "gnu", "c"]
+        .iter()
+        .find_map(|prefix| standard.strip_prefix(prefix))?;
+
+    match version {
+        "89" | "90" | "1990" => Some(1989),
+        "98" => Some(1998),
+        "99" | "9x" | "1999" => Some(1999),
+        "03" => Some(2003),
+        "0x" | "11" | "1x" | "2011" => Some(2011),
+        "1y" | "14" => Some(2014),
+        "1z" | "17" | "18" | "2017" | "2018" => Some(2017),
+        "2a" | "20" => Some(2020),
+        "2b" | "2x" | "23" => Some(2023),
+        "2c" | "26" => Some(2026),
+        _ => None,
+    }
+}
+
+fn check_multilib_support(project: &Project, bits: u32) -> Result<()> {
+    if ![16, 32, 64].contains(&bits) {
+        return Err(BargeError::InvalidValue(
+            "Invalid bitness specified, valid choices are: 16, 32, 64",
+        ));
+    }
+
+    let cpp_compiler = project.compiler(LanguageKind::Cpp);
+    let mut probe = Command::new(cpp_compiler)
+        .arg(format!("-m{}", bits))
+        .args(["-x", "c++", "-", "-o", "/dev/null"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    probe
+        .stdin
+        .as_mut()
+        .ok_or(BargeError::NoneOption(
+            "Could not interact with the compiler",
+        ))?
+        .write_all(b"#include <cstdlib>\nint main() { return EXIT_SUCCESS; }\n")?;
+
+    if probe.wait()?.success() {
+        Ok(())
+    } else {
+        color_eprintln!(
+            "The {}-bit libraries are missing (on Debian-based systems, install g++-multilib)",
+            bits
+        );
+        Err(BargeError::FailedOperation(
+            "The libraries required for the selected bitness are missing",
+        ))
     }
 }
 
@@ -417,7 +1855,12 @@ fn get_git_config_field(field: &str) -> Result<String> {
     Ok(std::str::from_utf8(&result)?.to_string())
 }
 
+pub(crate) fn get_launcher_arguments(launcher: Option<&str>) -> Vec<&str> {
+    launcher.map_or(Vec::new(), |launcher| launcher.split_whitespace().collect())
+}
+
 fn get_git_project_info() -> Result<(Option<String>, Option<String>)> {
+    let _section = ProfileSection::start("git");
     let commit_hash = Command::new("git").args(["rev-parse", "HEAD"]).output()?;
     let commit_hash = if commit_hash.status.success() {
         Some(std::str::from_utf8(&commit_hash.stdout)?.to_string())
@@ -437,6 +1880,13 @@ fn get_git_project_info() -> Result<(Option<String>, Option<String>)> {
     Ok((commit_hash, branch))
 }
 
+pub(crate) fn get_objcopy(toolset: &Toolset) -> &'static str {
+    match toolset {
+        Toolset::Gnu => "objcopy",
+        Toolset::Llvm => "llvm-objcopy",
+    }
+}
+
 fn get_debugger(toolset: &Toolset) -> &'static str {
     match toolset {
         Toolset::Gnu => "gdb",
@@ -0,0 +1,71 @@
+use crate::makefile::BuildTarget;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const BUILD_RECORD: &str = "last-build.json";
+pub(crate) const TEST_RESULTS: &str = "tests/results.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct BuildRecord {
+    pub target: String,
+    pub success: bool,
+    pub duration: f64,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl BuildRecord {
+    pub(crate) fn new(
+        target: BuildTarget,
+        success: bool,
+        duration: f64,
+        commit: &Option<String>,
+        branch: &Option<String>,
+    ) -> BuildRecord {
+        BuildRecord {
+            target: target.to_string(),
+            success,
+            duration,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            commit: commit.as_ref().map(|commit| commit.trim().to_string()),
+            branch: branch.as_ref().map(|branch| branch.trim().to_string()),
+        }
+    }
+}
+
+pub(crate) fn write_build_record(build_directory: &str, record: &BuildRecord) -> Result<()> {
+    std::fs::create_dir_all(build_directory)?;
+    let path = format!("{}/{}", build_directory, BUILD_RECORD);
+    std::fs::write(path, serde_json::to_string_pretty(record)?)?;
+    Ok(())
+}
+
+pub(crate) fn load_build_record(build_directory: &str) -> Option<BuildRecord> {
+    let path = format!("{}/{}", build_directory, BUILD_RECORD);
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct TestResult {
+    pub source: String,
+    pub passed: bool,
+    pub duration: f64,
+}
+
+pub(crate) fn write_test_results(build_directory: &str, results: &[TestResult]) -> Result<()> {
+    let path = format!("{}/{}", build_directory, TEST_RESULTS);
+    std::fs::write(path, serde_json::to_string_pretty(results)?)?;
+    Ok(())
+}
+
+pub(crate) fn load_test_results(build_directory: &str) -> Option<Vec<TestResult>> {
+    let path = format!("{}/{}", build_directory, TEST_RESULTS);
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
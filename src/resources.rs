@@ -0,0 +1,124 @@
+use crate::makefile::BuildTarget;
+use crate::project::{Project, ProjectType, Toolset, DEFAULT_TOOLSET};
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED};
+use std::path::Path;
+use std::process::Command;
+
+const VERSION_RESOURCE: &str = "version.rc";
+const VERSION_RESOURCE_OBJECT: &str = "version.rc.o";
+const ICON: &str = "res/icon.ico";
+
+// Static libraries are not linked, so only executables and shared libraries
+// carry a version resource.
+pub(crate) fn get_version_resource_object(
+    project: &Project,
+    target: BuildTarget,
+) -> Option<String> {
+    if !project.targets_windows() || project.project_type == ProjectType::StaticLibrary {
+        return None;
+    }
+    Some(format!(
+        "{}/{}",
+        project.build_directory(target),
+        VERSION_RESOURCE_OBJECT
+    ))
+}
+
+// Converts a version like 1.2.3-beta to the 1,2,3,0 form of resource scripts.
+fn get_numeric_version(version: &str) -> String {
+    let mut parts: Vec<String> = version
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse::<u16>().ok())
+        .take(4)
+        .map(|part| part.to_string())
+        .collect();
+    parts.resize(4, String::from("0"));
+    parts.join(",")
+}
+
+fn escape(value: &str) -> String {
+    value.replace('"', "\"\"")
+}
+
+fn generate_version_resource(project: &Project) -> Result<String> {
+    let icon = if Path::new(ICON).is_file() {
+        let path = std::fs::canonicalize(ICON)?;
+        format!("1 ICON \"{}\"\n\n", escape(&path.to_string_lossy()))
+    } else {
+        String::new()
+    };
+    let file_type = match project.project_type {
+        ProjectType::Executable => "VFT_APP",
+        _ => "VFT_DLL",
+    };
+    let numeric_version = get_numeric_version(&project.version);
+    let company = project.authors.first().cloned().unwrap_or_default();
+
+    Ok(format!(
+        include_str!("template-version-rc.in"),
+        icon,
+        numeric_version,
+        numeric_version,
+        file_type,
+        escape(&company),
+        escape(&project.description),
+        escape(&project.version),
+        escape(&project.name),
+        escape(&project.artifact_name()),
+        escape(&project.name),
+        escape(&project.version)
+    ))
+}
+
+fn is_outdated(object: &Path, inputs: &[&Path]) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let object = modified(object);
+    object.is_none() || inputs.iter().any(|input| modified(input) > object)
+}
+
+// Generates the version resource script from the project metadata, and
+// compiles it with windres if it changed since the last build.
+pub(crate) fn compile_version_resource(project: &Project, target: BuildTarget) -> Result<()> {
+    let Some(object) = get_version_resource_object(project, target) else {
+        return Ok(());
+    };
+
+    let directory = project.build_directory(target);
+    std::fs::create_dir_all(&directory)?;
+    let script = Path::new(&directory).join(VERSION_RESOURCE);
+    let content = generate_version_resource(project)?;
+    if std::fs::read_to_string(&script).ok().as_deref() != Some(content.as_str()) {
+        std::fs::write(&script, content)?;
+    }
+
+    let object = Path::new(&object);
+    if !is_outdated(object, &[&script, Path::new(ICON)]) {
+        return Ok(());
+    }
+
+    color_println!(BLUE, "Compiling version resource {}", script.display());
+    let toolset = project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET);
+    let mut windres = match toolset {
+        Toolset::Gnu => Command::new(project.cross_tool("windres")),
+        Toolset::Llvm => Command::new("llvm-windres"),
+    };
+    if let (Toolset::Llvm, Some(triple)) = (toolset, &project.target) {
+        windres.arg(format!("--target={}", triple));
+    }
+    let status = windres
+        .arg(&script)
+        .args(["-O", "coff", "-o"])
+        .arg(object)
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(BargeError::FailedOperation(
+            "Failed to compile the version resource",
+        )),
+        Err(error) => {
+            color_eprintln!("Could not execute windres");
+            Err(error.into())
+        }
+    }
+}
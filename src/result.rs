@@ -1,4 +1,5 @@
 use crate::color_eprintln;
+use crate::errorcodes::get_error_code;
 use crate::NO_COLOR;
 use crate::RED;
 use std::convert::From;
@@ -60,4 +61,10 @@ pub(crate) fn print_error(error: &BargeError) {
         BargeError::FailedOperation(s) => color_eprintln!("{}", s),
         BargeError::ProjectNotFound(s) => color_eprintln!("{}", s),
     };
+    if let Some(error_code) = get_error_code(error) {
+        eprintln!(
+            "Run `barge explain {}` for the possible causes and fixes.",
+            error_code.code
+        );
+    }
 }
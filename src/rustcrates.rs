@@ -0,0 +1,283 @@
+use crate::commandlog::record_and_output;
+use crate::makefile::BuildTarget;
+use crate::project::{Project, RustCrate, HOST_BITS};
+use crate::result::{BargeError, Result};
+use crate::selfprofile::ProfileSection;
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const RUST_DIRECTORY: &str = "rust";
+const NATIVE_LIBS_NOTE: &str = "native-static-libs:";
+const LIBRARY_KINDS: [&str; 5] = ["lib", "rlib", "staticlib", "cdylib", "dylib"];
+
+// The names of the packages and their library targets, by manifest, as reading
+// the metadata of a crate takes a while.
+static LIBRARY_NAMES: Mutex<BTreeMap<PathBuf, (String, String)>> = Mutex::new(BTreeMap::new());
+
+fn get_manifest_path(rust_crate: &RustCrate) -> PathBuf {
+    Path::new(&rust_crate.path).join("Cargo.toml")
+}
+
+// Every build target has its own cargo target directory, so switching between
+// them does not rebuild the crates.
+fn get_target_directory(project: &Project, target: BuildTarget) -> PathBuf {
+    Path::new(&project.build_directory(target)).join(RUST_DIRECTORY)
+}
+
+// Returns the Rust target triple of a GNU target triple, like
+// aarch64-unknown-linux-gnu for aarch64-linux-gnu. Triples which already name
+// a vendor are passed to cargo unchanged.
+fn get_rust_triple(triple: &str) -> String {
+    match triple.split('-').collect::<Vec<&str>>()[..] {
+        [architecture, "w64", "mingw32"] => format!("{}-pc-windows-gnu", architecture),
+        [architecture, "linux", environment] => {
+            format!("{}-unknown-linux-{}", architecture, environment)
+        }
+        _ => triple.to_string(),
+    }
+}
+
+// The Rust target of the crates, matching the target triple or the bits of the
+// project, so that the archives can be linked into it. None means the host.
+fn get_rust_target(project: &Project) -> Result<Option<String>> {
+    if let Some(triple) = &project.target {
+        return Ok(Some(get_rust_triple(triple)));
+    }
+    match (project.bits, std::env::consts::ARCH, std::env::consts::OS) {
+        (None, _, _) => Ok(None),
+        (Some(HOST_BITS), _, _) => Ok(None),
+        (Some(32), "x86_64", "linux") => Ok(Some(String::from("i686-unknown-linux-gnu"))),
+        (Some(32), "x86_64", "windows") => Ok(Some(String::from("i686-pc-windows-gnu"))),
+        (Some(32), "aarch64", "linux") => Ok(Some(String::from("armv7-unknown-linux-gnueabihf"))),
+        _ => Err(BargeError::InvalidValue(
+            "Rust crates can not be built for the bits of the project on this host",
+        )),
+    }
+}
+
+fn get_cargo_profile(target: BuildTarget) -> &'static str {
+    match target {
+        BuildTarget::Debug => "debug",
+        BuildTarget::Release => "release",
+    }
+}
+
+// Returns the name of the package, and the name of its library target, with
+// dashes replaced by underscores, like in the names of the produced archives.
+fn get_library_name(project: &Project, rust_crate: &RustCrate) -> Result<(String, String)> {
+    let manifest = get_manifest_path(rust_crate);
+    let names = LIBRARY_NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(names) = names.get(&manifest) {
+        return Ok(names.clone());
+    }
+    drop(names);
+
+    let output = record_and_output(
+        project
+            .command("cargo")
+            .args([
+                "metadata",
+                "--no-deps",
+                "--format-version",
+                "1",
+                "--manifest-path",
+            ])
+            .arg(&manifest),
+    );
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            color_eprintln!("{}", String::from_utf8_lossy(&output.stderr).trim());
+            return Err(BargeError::FailedOperation(
+                "Could not read the manifest of a Rust crate",
+            ));
+        }
+        Err(error) => {
+            color_eprintln!("Could not execute cargo");
+            return Err(error.into());
+        }
+    };
+
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let canonical = manifest.canonicalize()?;
+    let package = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|package| {
+            package["manifest_path"]
+                .as_str()
+                .is_some_and(|path| Path::new(path) == canonical)
+        })
+        .ok_or(BargeError::InvalidValue(
+            "The manifest of a Rust crate does not describe a package",
+        ))?;
+    let library = package["targets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|target| {
+            target["kind"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|kind| LIBRARY_KINDS.contains(&kind.as_str().unwrap_or_default()))
+        })
+        .and_then(|target| target["name"].as_str());
+    match (package["name"].as_str(), library) {
+        (Some(package), Some(library)) => {
+            let names = (package.to_string(), library.replace('-', "_"));
+            LIBRARY_NAMES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(manifest, names.clone());
+            Ok(names)
+        }
+        _ => {
+            color_eprintln!(
+                "The Rust crate at {} has no library target",
+                rust_crate.path
+            );
+            Err(BargeError::InvalidValue(
+                "Rust crates must have a library target",
+            ))
+        }
+    }
+}
+
+// The directory of the produced archives, which cargo places under the name of
+// the target triple when one is given.
+fn get_output_directory(project: &Project, target: BuildTarget) -> Result<PathBuf> {
+    let mut directory = get_target_directory(project, target);
+    if let Some(triple) = get_rust_target(project)? {
+        directory = directory.join(triple);
+    }
+    Ok(directory.join(get_cargo_profile(target)))
+}
+
+fn get_archive_path(project: &Project, target: BuildTarget, library: &str) -> Result<PathBuf> {
+    Ok(get_output_directory(project, target)?.join(format!("lib{}.a", library)))
+}
+
+// The system libraries required by the archive are only printed by rustc when
+// it actually builds the crate, so they are saved next to the archive.
+fn get_native_libs_path(project: &Project, target: BuildTarget, library: &str) -> Result<PathBuf> {
+    Ok(get_output_directory(project, target)?.join(format!("lib{}.native-static-libs", library)))
+}
+
+fn run_cargo(
+    project: &Project,
+    target: BuildTarget,
+    rust_crate: &RustCrate,
+    arguments: &[&str],
+    rustc_arguments: &[&str],
+) -> Result<String> {
+    let mut cargo = project.command("cargo");
+    cargo
+        .args(arguments)
+        .arg("--manifest-path")
+        .arg(get_manifest_path(rust_crate))
+        .arg("--target-dir")
+        .arg(get_target_directory(project, target));
+    if let Some(triple) = get_rust_target(project)? {
+        cargo.arg("--target").arg(triple);
+    }
+    if target == BuildTarget::Release {
+        cargo.arg("--release");
+    }
+    if !rustc_arguments.is_empty() {
+        cargo.arg("--").args(rustc_arguments);
+    }
+    let output = match record_and_output(&mut cargo) {
+        Ok(output) => output,
+        Err(error) => {
+            color_eprintln!("Could not execute cargo");
+            return Err(error.into());
+        }
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        eprintln!("{}", stderr.trim_end());
+        return Err(BargeError::FailedOperation("Failed to build a Rust crate"));
+    }
+    Ok(stderr)
+}
+
+fn build_rust_crate(project: &Project, target: BuildTarget, rust_crate: &RustCrate) -> Result<()> {
+    let (package, library) = get_library_name(project, rust_crate)?;
+    color_println!(BLUE, "Building Rust crate {}", package);
+
+    let features = rust_crate
+        .features
+        .as_ref()
+        .map(|features| features.join(","))
+        .unwrap_or_default();
+    let mut arguments = vec!["rustc", "--lib", "--crate-type", "staticlib"];
+    if !features.is_empty() {
+        arguments.extend(["--features", features.as_str()]);
+    }
+    let rustc_arguments = ["--print", "native-static-libs"];
+
+    let native_libs_path = get_native_libs_path(project, target, &library)?;
+    let mut output = run_cargo(project, target, rust_crate, &arguments, &rustc_arguments)?;
+    if !output.contains(NATIVE_LIBS_NOTE) && !native_libs_path.is_file() {
+        // The crate is up to date, but it was not built by barge, so the
+        // required system libraries are unknown.
+        run_cargo(
+            project,
+            target,
+            rust_crate,
+            &["clean", "--package", &package],
+            &[],
+        )?;
+        output = run_cargo(project, target, rust_crate, &arguments, &rustc_arguments)?;
+    }
+
+    if let Some(line) = output.lines().find(|line| line.contains(NATIVE_LIBS_NOTE)) {
+        let (_, libraries) = line.split_once(NATIVE_LIBS_NOTE).unwrap_or_default();
+        std::fs::write(&native_libs_path, libraries.trim())?;
+    }
+    Ok(())
+}
+
+// Builds the Rust crates of the project as static libraries with cargo. The
+// crates track their own sources, so this is done on every build, and cargo
+// decides what needs to be rebuilt.
+pub(crate) fn prepare_rust_crates(project: &Project, target: BuildTarget) -> Result<()> {
+    let _section = ProfileSection::start("rust crates");
+    for rust_crate in project.rust_crates.iter().flatten() {
+        build_rust_crate(project, target, rust_crate)?;
+    }
+    Ok(())
+}
+
+// Returns the linker flags (the archives, followed by the system libraries
+// they require), and the archives of the Rust crates of the project.
+pub(crate) fn get_rust_crate_flags(
+    project: &Project,
+    target: BuildTarget,
+) -> Result<(String, Vec<String>)> {
+    let mut ldflags = String::new();
+    let mut native_libs = Vec::new();
+    let mut archives = Vec::new();
+    for rust_crate in project.rust_crates.iter().flatten() {
+        let (_, library) = get_library_name(project, rust_crate)?;
+        let archive = get_archive_path(project, target, &library)?;
+        ldflags += &format!(" {}", archive.display());
+        archives.push(archive.to_string_lossy().to_string());
+
+        let path = get_native_libs_path(project, target, &library)?;
+        let libraries = std::fs::read_to_string(path).unwrap_or_default();
+        for flag in libraries.split_whitespace() {
+            if !native_libs.iter().any(|existing| existing == flag) {
+                native_libs.push(flag.to_string());
+            }
+        }
+    }
+    for flag in native_libs {
+        ldflags += &format!(" {}", flag);
+    }
+    Ok((ldflags, archives))
+}
@@ -0,0 +1,129 @@
+use crate::annotations::{parse_diagnostic, Diagnostic};
+use crate::result::{BargeError, Result};
+use crate::{color_println, GREEN, NO_COLOR};
+use serde_json::{json, Value};
+use std::path::Path;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_PREFIX: &str = "sarif:";
+
+// Returns the path of the report from an output specification like
+// `sarif:build/analysis.sarif`.
+pub(crate) fn parse_sarif_output(output: &str) -> Result<String> {
+    match output.strip_prefix(SARIF_PREFIX) {
+        Some(path) if !path.is_empty() => Ok(path.to_string()),
+        _ => Err(BargeError::InvalidValue(
+            "Invalid analysis output, the supported format is sarif:<PATH>",
+        )),
+    }
+}
+
+fn get_sarif_level(severity: &str) -> &'static str {
+    match severity {
+        "error" | "fatal error" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+// Splits the check which reported the diagnostic from its message, as given in
+// brackets by clang-tidy and cppcheck, like `[bugprone-use-after-move]`. The
+// markers appended to the name of the check, like `-warnings-as-errors`, are
+// dropped.
+fn split_rule(message: &str) -> (&str, Option<&str>) {
+    let rule = message
+        .strip_suffix(']')
+        .and_then(|message| message.rsplit_once(" ["));
+    match rule {
+        Some((text, rule)) if !rule.is_empty() && !rule.contains(' ') => {
+            (text, rule.split(',').next())
+        }
+        _ => (message, None),
+    }
+}
+
+// Sources in the project are given relative to the project root, so that code
+// scanning dashboards can map them to the files of the repository.
+fn get_location(diagnostic: &Diagnostic) -> Value {
+    let root = std::env::current_dir().unwrap_or_default();
+    let uri = Path::new(diagnostic.file)
+        .strip_prefix(&root)
+        .map_or(diagnostic.file.to_string(), |relative| {
+            relative.to_string_lossy().to_string()
+        });
+    let mut artifact = json!({ "uri": uri });
+    if Path::new(&uri).is_relative() {
+        artifact["uriBaseId"] = json!("%SRCROOT%");
+    }
+
+    let mut region = json!({ "startLine": diagnostic.line.parse::<u64>().unwrap_or(1) });
+    if let Some(column) = diagnostic
+        .column
+        .and_then(|column| column.parse::<u64>().ok())
+    {
+        region["startColumn"] = json!(column);
+    }
+    json!({ "physicalLocation": { "artifactLocation": artifact, "region": region } })
+}
+
+fn get_run(tool: &str, diagnostics: &[&str]) -> Value {
+    let mut rules: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+    for diagnostic in diagnostics
+        .iter()
+        .flat_map(|diagnostics| diagnostics.lines())
+        .filter_map(parse_diagnostic)
+    {
+        let (text, rule) = split_rule(diagnostic.message);
+        let mut result = json!({
+            "level": get_sarif_level(diagnostic.severity),
+            "message": { "text": text },
+            "locations": [get_location(&diagnostic)],
+        });
+        if let Some(rule) = rule {
+            result["ruleId"] = json!(rule);
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        results.push(result);
+    }
+
+    let rules: Vec<Value> = rules.iter().map(|rule| json!({ "id": rule })).collect();
+    json!({
+        "tool": { "driver": { "name": tool, "rules": rules } },
+        "results": results,
+    })
+}
+
+// Writes the diagnostics of the analyzers into a SARIF report, with a run for
+// each analyzer, so that they can be uploaded to code scanning dashboards. The
+// diagnostics are given as the names of the analyzers and their output.
+pub(crate) fn write_sarif_report(path: &str, diagnostics: &[(String, String)]) -> Result<()> {
+    let mut tools: Vec<&str> = Vec::new();
+    for (tool, _) in diagnostics {
+        if !tools.contains(&tool.as_str()) {
+            tools.push(tool);
+        }
+    }
+    let runs: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            let outputs: Vec<&str> = diagnostics
+                .iter()
+                .filter(|(name, _)| name == tool)
+                .map(|(_, output)| output.as_str())
+                .collect();
+            get_run(tool, &outputs)
+        })
+        .collect();
+
+    let report = json!({ "$schema": SARIF_SCHEMA, "version": SARIF_VERSION, "runs": runs });
+    if let Some(parent) = Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&report)? + "\n")?;
+    color_println!(GREEN, "Wrote the SARIF report to {}", path);
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use crate::languages::LanguageKind;
+use crate::project::{GuardStyle, Project, SourceKind};
+use crate::result::{BargeError, Result};
+use crate::{color_println, GREEN, NO_COLOR};
+use std::path::Path;
+
+const DEFAULT_GUARD_PATTERN: &str = "@project@_@path@";
+
+pub(crate) fn get_include_guard(project: &Project, header: &str) -> String {
+    let pattern = project
+        .scaffolding
+        .as_ref()
+        .and_then(|s| s.guard_pattern.as_deref())
+        .unwrap_or(DEFAULT_GUARD_PATTERN);
+    let mut guard: String = pattern
+        .replace("@project@", &project.name)
+        .replace("@path@", header)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if guard.starts_with(|c: char| c.is_ascii_digit()) {
+        guard.insert(0, '_');
+    }
+    guard
+}
+
+fn read_template(path: &Option<String>, default: &str) -> Result<String> {
+    match path {
+        Some(path) => Ok(std::fs::read_to_string(path)?),
+        None => Ok(default.to_string()),
+    }
+}
+
+fn write_new_file(path: &str, content: &str) -> Result<()> {
+    if Path::new(path).exists() {
+        return Err(BargeError::FailedOperation(
+            "Refusing to overwrite an existing file",
+        ));
+    }
+
+    if let Some(parent) = Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut content = content.trim_end().to_string() + "\n";
+    while content.contains("\n\n\n") {
+        content = content.replace("\n\n\n", "\n\n");
+    }
+    std::fs::write(path, content)?;
+    color_println!(GREEN, "Created {}", path);
+    Ok(())
+}
+
+pub(crate) fn create_new_file(
+    project: &Project,
+    path: &str,
+    class: Option<&String>,
+    header_only: bool,
+) -> Result<()> {
+    let (stem, extension) = path.rsplit_once('.').unwrap_or((path, "cpp"));
+    let is_cpp = match project.source_kind(path) {
+        Some(SourceKind::Language(LanguageKind::C)) => false,
+        Some(SourceKind::Language(LanguageKind::Cpp)) => true,
+        _ if !path.contains('.') => true,
+        _ => {
+            return Err(BargeError::InvalidValue(
+                "Only C and C++ source files can be scaffolded",
+            ))
+        }
+    };
+
+    if class.is_some() && !is_cpp {
+        return Err(BargeError::InvalidValue(
+            "Classes can only be scaffolded in C++ files",
+        ));
+    }
+
+    let settings = project.scaffolding.as_ref();
+    let header_extension = match extension {
+        "cc" => "hh",
+        "cxx" => "hxx",
+        _ if is_cpp => "hpp",
+        _ => "h",
+    };
+    let header = format!("{}.{}", stem, header_extension);
+    let source = format!("{}.{}", stem, extension);
+
+    let license = match settings.and_then(|s| s.license_header.as_ref()) {
+        Some(license) => std::fs::read_to_string(license)?.trim_end().to_string() + "\n\n",
+        None => String::new(),
+    };
+
+    let guard_style = settings
+        .and_then(|s| s.guard_style)
+        .unwrap_or(GuardStyle::PragmaOnce);
+    let (guard_begin, guard_end) = match guard_style {
+        GuardStyle::PragmaOnce => (String::from("#pragma once\n\n"), String::new()),
+        GuardStyle::IncludeGuard => {
+            let guard = get_include_guard(project, &header);
+            (
+                format!("#ifndef {}\n#define {}\n\n", guard, guard),
+                format!("\n#endif // {}\n", guard),
+            )
+        }
+    };
+
+    let namespace = settings.and_then(|s| s.namespace.as_ref());
+    let (namespace_begin, namespace_end) = match namespace {
+        Some(namespace) if is_cpp => (
+            format!("namespace {} {{\n\n", namespace),
+            format!("\n}} // namespace {}\n", namespace),
+        ),
+        _ => (String::new(), String::new()),
+    };
+
+    let (class_declaration, class_definition) = match class {
+        Some(class) => (
+            format!(
+                "class {} {{\npublic:\n    {}();\n    ~{}();\n}};\n",
+                class, class, class
+            ),
+            format!(
+                "{}::{}() {{}}\n\n{}::~{}() {{}}\n",
+                class, class, class, class
+            ),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    let expand = |template: String| {
+        template
+            .replace("@license@", &license)
+            .replace("@guard_begin@", &guard_begin)
+            .replace("@guard_end@", &guard_end)
+            .replace("@namespace_begin@", &namespace_begin)
+            .replace("@namespace_end@", &namespace_end)
+            .replace("@class_declaration@", &class_declaration)
+            .replace("@class_definition@", &class_definition)
+            .replace("@header@", &header)
+            .replace("@name@", stem.rsplit('/').next().unwrap_or(stem))
+    };
+
+    let header_template = read_template(
+        &settings.and_then(|s| s.header_template.clone()),
+        include_str!("template-header.in"),
+    )?;
+    write_new_file(&format!("include/{}", header), &expand(header_template))?;
+
+    if !header_only {
+        let source_template = read_template(
+            &settings.and_then(|s| s.source_template.clone()),
+            include_str!("template-source.in"),
+        )?;
+        write_new_file(&format!("src/{}", source), &expand(source_template))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use crate::commandlog::{RecordedCommand, COMMAND_LOG};
+use crate::project::Project;
+use crate::result::Result;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+const MEMORY_USAGE: &str = "build/.memory-usage.json";
+const MEMORY_HEAVY_LOCKS: &str = "build/.memory-heavy";
+const MEMORY_HEAVY_SOURCES_VARIABLE: &str = "BARGE_MEMORY_HEAVY_SOURCES";
+const MEMORY_HEAVY_JOBS_VARIABLE: &str = "BARGE_MEMORY_HEAVY_JOBS";
+const MEMORY_HEAVY_LOCKS_VARIABLE: &str = "BARGE_MEMORY_HEAVY_LOCKS";
+const MEASURE_MEMORY_VARIABLE: &str = "BARGE_MEASURE_MEMORY";
+const DEFAULT_MEMORY_HEAVY_JOBS: u32 = 1;
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+fn load_memory_usage() -> BTreeMap<String, u64> {
+    std::fs::read_to_string(MEMORY_USAGE)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn update_memory_usage() -> Result<()> {
+    let log = match std::fs::read_to_string(COMMAND_LOG) {
+        Ok(log) => log,
+        Err(_) => return Ok(()),
+    };
+
+    let mut usage = load_memory_usage();
+    let mut changed = false;
+    for command in log
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RecordedCommand>(line).ok())
+    {
+        if let (Some(source), Some(peak)) = (command.source, command.peak_rss_kb) {
+            usage.insert(source, peak);
+            changed = true;
+        }
+    }
+
+    if changed {
+        std::fs::write(MEMORY_USAGE, serde_json::to_string_pretty(&usage)?)?;
+    }
+    Ok(())
+}
+
+fn get_memory_heavy_sources(project: &Project) -> Vec<String> {
+    let settings = match &project.memory_heavy {
+        Some(settings) => settings,
+        None => return Vec::new(),
+    };
+
+    let mut sources = settings.sources.clone().unwrap_or_default();
+    if let Some(threshold) = settings.threshold_mb {
+        sources.extend(
+            load_memory_usage()
+                .into_iter()
+                .filter(|(_, peak)| *peak > threshold * 1024)
+                .map(|(source, _)| source),
+        );
+    }
+    sources.sort();
+    sources.dedup();
+    sources
+}
+
+pub(crate) fn configure_memory_heavy_jobs(project: &Project, command: &mut Command) -> Result<()> {
+    let settings = project.memory_heavy.as_ref();
+    if settings.is_some_and(|settings| settings.threshold_mb.is_some()) {
+        command.env(MEASURE_MEMORY_VARIABLE, "1");
+    }
+
+    let sources = get_memory_heavy_sources(project);
+    if sources.is_empty() {
+        return Ok(());
+    }
+
+    let jobs = settings
+        .and_then(|settings| settings.max_jobs)
+        .unwrap_or(DEFAULT_MEMORY_HEAVY_JOBS)
+        .max(1);
+    std::fs::create_dir_all(MEMORY_HEAVY_LOCKS)?;
+    let locks = std::env::current_dir()?.join(MEMORY_HEAVY_LOCKS);
+    command
+        .env(MEMORY_HEAVY_SOURCES_VARIABLE, sources.join("\n"))
+        .env(MEMORY_HEAVY_JOBS_VARIABLE, jobs.to_string())
+        .env(MEMORY_HEAVY_LOCKS_VARIABLE, locks);
+    Ok(())
+}
+
+pub(crate) fn acquire_memory_heavy_slot(source: &str) -> Result<Option<File>> {
+    let sources = std::env::var(MEMORY_HEAVY_SOURCES_VARIABLE).unwrap_or_default();
+    if !sources.lines().any(|heavy| heavy == source) {
+        return Ok(None);
+    }
+
+    let jobs: u32 = std::env::var(MEMORY_HEAVY_JOBS_VARIABLE)
+        .ok()
+        .and_then(|jobs| jobs.parse().ok())
+        .unwrap_or(DEFAULT_MEMORY_HEAVY_JOBS);
+    let locks = std::env::var(MEMORY_HEAVY_LOCKS_VARIABLE)
+        .unwrap_or_else(|_| MEMORY_HEAVY_LOCKS.to_string());
+
+    loop {
+        for slot in 0..jobs {
+            let lock = File::create(format!("{}/slot-{}.lock", locks, slot))?;
+            if lock.try_lock().is_ok() {
+                return Ok(Some(lock));
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn get_peak_rss(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let own = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok());
+
+    // Compiler drivers do the heavy lifting in child processes (like cc1plus).
+    let children =
+        std::fs::read_to_string(format!("/proc/{}/task/{}/children", pid, pid)).unwrap_or_default();
+    children
+        .split_whitespace()
+        .filter_map(|child| child.parse().ok())
+        .filter_map(get_peak_rss)
+        .chain(own)
+        .max()
+}
+
+pub(crate) fn wait_with_peak_rss(child: &mut Child) -> Result<(i32, Option<u64>)> {
+    if std::env::var_os(MEASURE_MEMORY_VARIABLE).is_none() {
+        return Ok((child.wait()?.code().unwrap_or(1), None));
+    }
+
+    let mut peak = None;
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status.code().unwrap_or(1), peak));
+        }
+        if let Some(current) = get_peak_rss(child.id()) {
+            peak = Some(peak.map_or(current, |peak: u64| peak.max(current)));
+        }
+        std::thread::sleep(interval);
+        interval = (interval * 2).min(POLL_INTERVAL);
+    }
+}
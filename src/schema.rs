@@ -0,0 +1,646 @@
+use crate::jsonc::{find_root_field, mask_jsonc};
+use crate::result::{BargeError, Result};
+use crate::toml::{parse_toml_document, set_root_value, to_toml_string};
+use crate::{color_eprintln, NO_COLOR, RED};
+use serde_json::{Map, Value};
+use std::ops::Range;
+use toml_edit::{Item, Key, Value as TomlValue};
+
+// A change of the layout of the project file, which is applied both to the
+// value of the project when loading it, and to the text of the project file
+// when upgrading it, so that its comments and formatting are kept. The kinds
+// of changes are only used by the tests until a migration needs them.
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) enum Change {
+    // Renames a field of the object at the path of parent fields (the root
+    // object if empty), keeping its value, and its place in the file.
+    RenameField {
+        parent: &'static [&'static str],
+        from: &'static str,
+        to: &'static str,
+    },
+    // Replaces the value of a field of the object at the path of parent
+    // fields with the one returned by the function, like turning the names
+    // of libraries into library objects. The function returns None if the
+    // value has the new layout already.
+    ReshapeField {
+        parent: &'static [&'static str],
+        field: &'static str,
+        reshape: fn(&Value) -> Option<Value>,
+    },
+}
+
+// The changes introducing a version of the schema.
+pub(crate) struct Migration {
+    version: u64,
+    changes: &'static [Change],
+}
+
+// Version 1 introduced the schema_version field itself, without changing the
+// others, so the files written before it are only stamped with the version.
+// Changing the schema requires adding a migration with the changes here.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    changes: &[],
+}];
+
+pub(crate) const CURRENT_SCHEMA_VERSION: u64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+pub(crate) fn get_schema_version(value: &Value) -> Result<u64> {
+    match value.get("schema_version") {
+        Some(version) => version.as_u64().ok_or(BargeError::InvalidValue(
+            "The schema_version field must be a non-negative integer",
+        )),
+        None => Ok(0),
+    }
+}
+
+fn get_object_mut<'a>(
+    mut object: &'a mut Map<String, Value>,
+    parent: &[&str],
+) -> Option<&'a mut Map<String, Value>> {
+    for field in parent {
+        object = object.get_mut(*field)?.as_object_mut()?;
+    }
+    Some(object)
+}
+
+impl Change {
+    // Applies the change to the value of the project, and returns its
+    // description, or None if the value is not affected by it.
+    fn apply(&self, object: &mut Map<String, Value>) -> Result<Option<String>> {
+        match self {
+            Change::RenameField { parent, from, to } => {
+                let Some(object) = get_object_mut(object, parent) else {
+                    return Ok(None);
+                };
+                if !object.contains_key(*from) {
+                    return Ok(None);
+                }
+                let path: String = parent.iter().map(|field| format!("{}.", field)).collect();
+                if object.contains_key(*to) {
+                    color_eprintln!(
+                        "The project file contains both {}{} and {}{}",
+                        path,
+                        from,
+                        path,
+                        to
+                    );
+                    return Err(BargeError::InvalidValue(
+                        "The project file contains both a deprecated field and its replacement",
+                    ));
+                }
+                let value = object.remove(*from).unwrap_or_default();
+                object.insert(to.to_string(), value);
+                Ok(Some(format!("Renamed {}{} to {}{}", path, from, path, to)))
+            }
+            Change::ReshapeField {
+                parent,
+                field,
+                reshape,
+            } => {
+                let Some(object) = get_object_mut(object, parent) else {
+                    return Ok(None);
+                };
+                let Some(value) = object.get(*field).and_then(reshape) else {
+                    return Ok(None);
+                };
+                object.insert(field.to_string(), value);
+                let path: String = parent.iter().map(|field| format!("{}.", field)).collect();
+                Ok(Some(format!("Restructured {}{}", path, field)))
+            }
+        }
+    }
+
+    // Applies the change to the text of the project file, along with the value
+    // of the project the change was applied to.
+    fn edit(&self, text: &str, toml: bool, value: &Value) -> Result<String> {
+        match (self, toml) {
+            (Change::RenameField { parent, from, to }, true) => {
+                rename_toml_field(text, parent, from, to)
+            }
+            (Change::RenameField { parent, from, to }, false) => {
+                rename_json_field(text, parent, from, to)
+            }
+            (Change::ReshapeField { parent, field, .. }, toml) => {
+                let malformed =
+                    || BargeError::InvalidValue("Could not locate the fields of the project file");
+                let reshaped = parent
+                    .iter()
+                    .chain([field])
+                    .try_fold(value, |value, field| value.get(field))
+                    .ok_or_else(malformed)?;
+                match toml {
+                    true => replace_toml_value(text, parent, field, reshaped),
+                    false => replace_json_value(text, parent, field, reshaped),
+                }
+            }
+        }
+    }
+}
+
+// Applies the migrations newer than the schema of the project file to its
+// value, and to its text if given, and returns the descriptions of the changes.
+fn migrate(
+    migrations: &[Migration],
+    value: &mut Value,
+    mut text: Option<(&mut String, bool)>,
+) -> Result<Vec<String>> {
+    let version = get_schema_version(value)?;
+    let current = migrations.last().map_or(0, |migration| migration.version);
+    if version > current {
+        return Err(BargeError::InvalidValue(
+            "The project file was written by a newer version of barge",
+        ));
+    }
+
+    let object = value.as_object_mut().ok_or(BargeError::InvalidValue(
+        "The project file must contain an object",
+    ))?;
+
+    let mut descriptions = Vec::new();
+    let pending = migrations
+        .iter()
+        .filter(|migration| migration.version > version);
+    for change in pending.flat_map(|migration| migration.changes) {
+        if let Some(description) = change.apply(object)? {
+            if let Some((text, toml)) = &mut text {
+                let value = Value::Object(object.clone());
+                **text = change.edit(text, *toml, &value)?;
+            }
+            descriptions.push(description);
+        }
+    }
+    if version < current {
+        object.insert(String::from("schema_version"), Value::from(current));
+        if let Some((text, toml)) = text {
+            *text = write_schema_version(text, toml, current)?;
+        }
+        descriptions.push(format!("Set schema_version to {}", current));
+    }
+    Ok(descriptions)
+}
+
+// Migrates the value of the project file to the current schema, and returns
+// the descriptions of the changes.
+pub(crate) fn migrate_project_value(value: &mut Value) -> Result<Vec<String>> {
+    migrate(MIGRATIONS, value, None)
+}
+
+// Migrates both the value and the text of the project file to the current
+// schema, and returns the descriptions of the changes, and the edited text.
+pub(crate) fn migrate_project_file(
+    value: &mut Value,
+    text: &str,
+    toml: bool,
+) -> Result<(Vec<String>, String)> {
+    let mut text = text.to_string();
+    let descriptions = migrate(MIGRATIONS, value, Some((&mut text, toml)))?;
+    Ok((descriptions, text))
+}
+
+// Returns the range of the value of the field of the object at the path in the
+// JSON project file.
+fn locate_json_field(json: &str, parent: &[&str], field: &str) -> Result<Range<usize>> {
+    let malformed = || BargeError::InvalidValue("Could not locate the fields of the project file");
+    let mut object = 0..json.len();
+    for field in parent.iter().chain([&field]) {
+        let range = find_root_field(&json[object.clone()], field).ok_or_else(malformed)?;
+        object = object.start + range.start..object.start + range.end;
+    }
+    Ok(object)
+}
+
+// Renames the field of the object at the path in the JSON project file by
+// replacing its key, leaving the rest of the file untouched.
+fn rename_json_field(json: &str, parent: &[&str], from: &str, to: &str) -> Result<String> {
+    let malformed = || BargeError::InvalidValue("Could not locate the fields of the project file");
+    let value_start = locate_json_field(json, parent, from)?.start;
+    let masked = mask_jsonc(json);
+    let key = serde_json::to_string(from)?;
+    let before_colon = masked[..value_start]
+        .trim_end()
+        .strip_suffix(':')
+        .ok_or_else(malformed)?;
+    let end = before_colon.trim_end().len();
+    let start = end.checked_sub(key.len()).ok_or_else(malformed)?;
+    if masked.get(start..end) != Some(key.as_str()) {
+        return Err(malformed());
+    }
+    Ok(format!(
+        "{}{}{}",
+        &json[..start],
+        serde_json::to_string(to)?,
+        &json[end..]
+    ))
+}
+
+// Replaces the value of the field of the object at the path in the JSON
+// project file, indenting it like the line of the field, and leaving the rest
+// of the file untouched.
+fn replace_json_value(json: &str, parent: &[&str], field: &str, value: &Value) -> Result<String> {
+    let range = locate_json_field(json, parent, field)?;
+    let line_start = json[..range.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = &json[line_start..];
+    let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    let value = serde_json::to_string_pretty(value)?.replace('\n', &format!("\n{}", indentation));
+    Ok(format!(
+        "{}{}{}",
+        &json[..range.start],
+        value,
+        &json[range.end..]
+    ))
+}
+
+fn get_toml_item_mut<'a>(
+    document: &'a mut toml_edit::DocumentMut,
+    parent: &[&str],
+) -> Result<&'a mut Item> {
+    let malformed = || BargeError::InvalidValue("Could not locate the fields of the project file");
+    let mut table = document.as_item_mut();
+    for field in parent {
+        table = table.get_mut(field).ok_or_else(malformed)?;
+    }
+    Ok(table)
+}
+
+// Replaces the value of the key of the table at the path in the TOML project
+// file, keeping its position and the comments around it. The new value is
+// written inline, as the tables would be moved after the other keys.
+fn replace_toml_value(toml: &str, parent: &[&str], field: &str, value: &Value) -> Result<String> {
+    let malformed = || BargeError::InvalidValue("Could not locate the fields of the project file");
+    let replacement = Value::Object(Map::from_iter([(field.to_string(), value.clone())]));
+    let mut value = parse_toml_document(&to_toml_string(&replacement)?)?
+        .remove(field)
+        .and_then(|item| item.into_value().ok())
+        .ok_or_else(malformed)?;
+    let mut document = parse_toml_document(toml)?;
+    let existing = match get_toml_item_mut(&mut document, parent)? {
+        Item::Table(table) => table.get_mut(field).and_then(Item::as_value_mut),
+        Item::Value(TomlValue::InlineTable(table)) => table.get_mut(field),
+        _ => None,
+    }
+    .ok_or_else(malformed)?;
+    *value.decor_mut() = existing.decor().clone();
+    *existing = value;
+    Ok(document.to_string())
+}
+
+// Renames the key of the table at the path in the TOML project file, keeping
+// its position and the comments around it, by inserting the entries of the
+// table again in their order.
+fn rename_toml_field(toml: &str, parent: &[&str], from: &str, to: &str) -> Result<String> {
+    let malformed = || BargeError::InvalidValue("Could not locate the fields of the project file");
+    let mut document = parse_toml_document(toml)?;
+    let table = get_toml_item_mut(&mut document, parent)?;
+    let rename = |key: Key| match key.get() == from {
+        true => Key::new(to).with_leaf_decor(key.leaf_decor().clone()),
+        false => key,
+    };
+    match table {
+        Item::Table(table) => {
+            let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+            for key in keys {
+                let (key, item) = table.remove_entry(&key).ok_or_else(malformed)?;
+                table.insert_formatted(&rename(key), item);
+            }
+        }
+        Item::Value(TomlValue::InlineTable(table)) => {
+            let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+            for key in keys {
+                let (key, value) = table.remove_entry(&key).ok_or_else(malformed)?;
+                table.insert_formatted(&rename(key), value);
+            }
+        }
+        _ => return Err(malformed()),
+    }
+    Ok(document.to_string())
+}
+
+// Sets the schema_version field of the JSON project file, which is added after
+// the version field if missing, leaving the rest of the file untouched.
+fn write_json_schema_version(json: &str, version: u64) -> Option<String> {
+    let version = version.to_string();
+    if let Some(range) = find_root_field(json, "schema_version") {
+        return Some(format!(
+            "{}{}{}",
+            &json[..range.start],
+            version,
+            &json[range.end..]
+        ));
+    }
+
+    let range = find_root_field(json, "version")?;
+    let masked = mask_jsonc(json);
+    let line_start = json[..range.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = &json[line_start..];
+    let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    let field = format!("\"schema_version\": {}", version);
+    let Some(line_end) = masked[range.end..].find('\n').map(|end| range.end + end) else {
+        // The whole object is written on a single line.
+        return Some(format!(
+            "{}, {}{}",
+            &json[..range.end],
+            field,
+            &json[range.end..]
+        ));
+    };
+    if masked[range.end..line_end].trim_start().starts_with(',') {
+        Some(format!(
+            "{}\n{}{},{}",
+            &json[..line_end],
+            indentation,
+            field,
+            &json[line_end..]
+        ))
+    } else {
+        Some(format!(
+            "{},\n{}{}{}",
+            &json[..range.end],
+            indentation,
+            field,
+            &json[range.end..]
+        ))
+    }
+}
+
+// Sets the schema_version key of the TOML project file, which is added after
+// the other top-level keys if missing.
+fn write_toml_schema_version(toml: &str, version: u64) -> Result<String> {
+    let mut document = parse_toml_document(toml)?;
+    set_root_value(&mut document, "schema_version", version as i64);
+    Ok(document.to_string())
+}
+
+// Writes the schema version into the text of the project file.
+fn write_schema_version(text: &str, toml: bool, version: u64) -> Result<String> {
+    match toml {
+        true => write_toml_schema_version(text, version),
+        false => write_json_schema_version(text, version).ok_or(BargeError::InvalidValue(
+            "Could not locate the version field of the project file",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn files_without_a_schema_version_are_stamped() {
+        let mut value = json!({ "name": "old", "version": "0.1.0", "unknown": true });
+        let changes = migrate_project_value(&mut value).unwrap();
+        assert_eq!(changes, ["Set schema_version to 1"]);
+        assert_eq!(
+            value,
+            json!({ "name": "old", "version": "0.1.0", "unknown": true, "schema_version": 1 })
+        );
+
+        assert!(migrate_project_value(&mut value).unwrap().is_empty());
+        assert!(migrate_project_value(&mut json!({ "schema_version": 2 })).is_err());
+        assert!(migrate_project_value(&mut json!({ "schema_version": "1" })).is_err());
+    }
+
+    #[test]
+    fn schema_version_is_written_into_json() {
+        let json = "{\n    \"version\": \"0.1.0\", // Released.\n    \"bits\": 32\n}\n";
+        assert_eq!(
+            write_schema_version(json, false, 1).unwrap(),
+            "{\n    \"version\": \"0.1.0\", // Released.\n    \"schema_version\": 1,\n    \"bits\": 32\n}\n"
+        );
+        let json = "{\n  \"version\": \"0.1.0\"\n}\n";
+        assert_eq!(
+            write_schema_version(json, false, 1).unwrap(),
+            "{\n  \"version\": \"0.1.0\",\n  \"schema_version\": 1\n}\n"
+        );
+        let json = "{\"version\": \"0.1.0\", \"schema_version\": 0}";
+        assert_eq!(
+            write_schema_version(json, false, 1).unwrap(),
+            "{\"version\": \"0.1.0\", \"schema_version\": 1}"
+        );
+    }
+
+    #[test]
+    fn schema_version_is_written_into_toml() {
+        let toml = "# Comment.\nversion = \"0.1.0\"\n\n[[external_libraries]]\nversion = 2\n";
+        assert_eq!(
+            write_schema_version(toml, true, 1).unwrap(),
+            "# Comment.\nversion = \"0.1.0\"\nschema_version = 1\n\n[[external_libraries]]\nversion = 2\n"
+        );
+        let toml = "schema_version = 0\nversion = \"0.1.0\"\n";
+        assert_eq!(
+            write_schema_version(toml, true, 1).unwrap(),
+            "schema_version = 1\nversion = \"0.1.0\"\n"
+        );
+    }
+
+    const RENAMING_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            changes: &[],
+        },
+        Migration {
+            version: 2,
+            changes: &[Change::RenameField {
+                parent: &[],
+                from: "cflags",
+                to: "custom_cflags",
+            }],
+        },
+    ];
+
+    #[test]
+    fn renamed_fields_are_migrated_in_json() {
+        let json = "{\n    \"version\": \"0.1.0\",\n    \"cflags\": \"-O2\", // Tuned.\n    \"nested\": {\"cflags\": 1}\n}\n";
+        let mut value: Value = serde_json::from_str(&mask_jsonc(json)).unwrap();
+        let mut text = json.to_string();
+        let changes = migrate(RENAMING_MIGRATIONS, &mut value, Some((&mut text, false))).unwrap();
+        assert_eq!(
+            changes,
+            ["Renamed cflags to custom_cflags", "Set schema_version to 2"]
+        );
+        assert_eq!(
+            text,
+            "{\n    \"version\": \"0.1.0\",\n    \"schema_version\": 2,\n    \"custom_cflags\": \"-O2\", // Tuned.\n    \"nested\": {\"cflags\": 1}\n}\n"
+        );
+        assert_eq!(
+            value,
+            json!({ "version": "0.1.0", "schema_version": 2, "custom_cflags": "-O2", "nested": { "cflags": 1 } })
+        );
+    }
+
+    #[test]
+    fn renamed_fields_are_migrated_in_toml() {
+        let toml = "version = \"0.1.0\"\nschema_version = 1\n# Tuned.\ncflags = \"-O2\" # Fast.\nbits = 32\n";
+        let mut value = crate::toml::parse_toml(toml).unwrap();
+        let mut text = toml.to_string();
+        let changes = migrate(RENAMING_MIGRATIONS, &mut value, Some((&mut text, true))).unwrap();
+        assert_eq!(
+            changes,
+            ["Renamed cflags to custom_cflags", "Set schema_version to 2"]
+        );
+        assert_eq!(
+            text,
+            "version = \"0.1.0\"\nschema_version = 2\n# Tuned.\ncustom_cflags = \"-O2\" # Fast.\nbits = 32\n"
+        );
+        assert_eq!(crate::toml::parse_toml(&text).unwrap(), value);
+
+        let mut value = json!({ "schema_version": 1, "cflags": "", "custom_cflags": "" });
+        assert!(migrate(RENAMING_MIGRATIONS, &mut value, None).is_err());
+        let mut value = json!({ "schema_version": 2, "cflags": "" });
+        assert!(migrate(RENAMING_MIGRATIONS, &mut value, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    const NESTED_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            changes: &[],
+        },
+        Migration {
+            version: 2,
+            changes: &[Change::RenameField {
+                parent: &["profiles", "release"],
+                from: "sanitize",
+                to: "sanitizer",
+            }],
+        },
+        Migration {
+            version: 3,
+            changes: &[Change::RenameField {
+                parent: &[],
+                from: "profiles",
+                to: "build_profiles",
+            }],
+        },
+    ];
+
+    #[test]
+    fn nested_fields_are_mapped_in_order() {
+        let json = "{\n  \"version\": \"0.1.0\",\n  \"profiles\": {\n    \"debug\": {\"sanitize\": \"asan\"},\n    \"release\": {\n      // Checked.\n      \"sanitize\": \"ubsan\"\n    }\n  }\n}\n";
+        let mut value: Value = serde_json::from_str(&mask_jsonc(json)).unwrap();
+        let mut text = json.to_string();
+        let changes = migrate(NESTED_MIGRATIONS, &mut value, Some((&mut text, false))).unwrap();
+        assert_eq!(
+            changes,
+            [
+                "Renamed profiles.release.sanitize to profiles.release.sanitizer",
+                "Renamed profiles to build_profiles",
+                "Set schema_version to 3",
+            ]
+        );
+        assert_eq!(
+            text,
+            json.replace("\"profiles\"", "\"build_profiles\"")
+                .replace(
+                    "// Checked.\n      \"sanitize\"",
+                    "// Checked.\n      \"sanitizer\""
+                )
+                .replace("0.1.0\",", "0.1.0\",\n  \"schema_version\": 3,")
+        );
+        assert_eq!(
+            value,
+            serde_json::from_str::<Value>(&mask_jsonc(&text)).unwrap()
+        );
+
+        let toml = "version = \"0.1.0\"\nprofiles.debug = { sanitize = \"asan\" }\n\n[profiles.release]\n# Checked.\nsanitize = \"ubsan\"\n";
+        let mut value = crate::toml::parse_toml(toml).unwrap();
+        let mut text = toml.to_string();
+        let changes = migrate(NESTED_MIGRATIONS, &mut value, Some((&mut text, true))).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            text,
+            "version = \"0.1.0\"\nbuild_profiles.debug = { sanitize = \"asan\" }\nschema_version = 3\n\n[build_profiles.release]\n# Checked.\nsanitizer = \"ubsan\"\n"
+        );
+        assert_eq!(crate::toml::parse_toml(&text).unwrap(), value);
+
+        let mut value =
+            json!({ "schema_version": 2, "profiles": { "release": { "sanitize": "asan" } } });
+        let changes = migrate(NESTED_MIGRATIONS, &mut value, None).unwrap();
+        assert_eq!(
+            changes,
+            [
+                "Renamed profiles to build_profiles",
+                "Set schema_version to 3"
+            ]
+        );
+    }
+
+    // Turns the names of pkg-config libraries into library objects.
+    fn get_library_objects(libraries: &Value) -> Option<Value> {
+        let libraries = libraries.as_array()?;
+        if !libraries.iter().any(Value::is_string) {
+            return None;
+        }
+        let objects = libraries.iter().map(|library| match library.as_str() {
+            Some(name) => json!({ "type": "pkg_config", "name": name }),
+            None => library.clone(),
+        });
+        Some(Value::Array(objects.collect()))
+    }
+
+    const RESHAPING_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            changes: &[],
+        },
+        Migration {
+            version: 2,
+            changes: &[Change::ReshapeField {
+                parent: &[],
+                field: "external_libraries",
+                reshape: get_library_objects,
+            }],
+        },
+    ];
+
+    #[test]
+    fn reshaped_fields_are_migrated() {
+        let json = "{\n    \"version\": \"0.1.0\",\n    \"external_libraries\": [\"zlib\", {\"type\": \"manual\", \"cflags\": \"\", \"ldflags\": \"-lm\"}], // Linked.\n    \"bits\": 64\n}\n";
+        let mut value: Value = serde_json::from_str(&mask_jsonc(json)).unwrap();
+        let mut text = json.to_string();
+        let changes = migrate(RESHAPING_MIGRATIONS, &mut value, Some((&mut text, false))).unwrap();
+        assert_eq!(
+            changes,
+            ["Restructured external_libraries", "Set schema_version to 2"]
+        );
+        assert!(text.contains("\n    \"external_libraries\": [\n      {\n        \"name\": \"zlib\",\n        \"type\": \"pkg_config\"\n      },\n"));
+        assert!(text.contains("\n    ], // Linked.\n    \"bits\": 64\n}\n"));
+        assert_eq!(
+            serde_json::from_str::<Value>(&mask_jsonc(&text)).unwrap(),
+            value
+        );
+        assert_eq!(
+            value["external_libraries"],
+            json!([
+                { "type": "pkg_config", "name": "zlib" },
+                { "type": "manual", "cflags": "", "ldflags": "-lm" },
+            ])
+        );
+
+        let toml = "version = \"0.1.0\"\n# Linked.\nexternal_libraries = [\"zlib\", \"ssl\"] # Both.\nbits = 64\n\n[profiles.release]\nlto = true\n";
+        let mut value = crate::toml::parse_toml(toml).unwrap();
+        let mut text = toml.to_string();
+        let changes = migrate(RESHAPING_MIGRATIONS, &mut value, Some((&mut text, true))).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            text,
+            "version = \"0.1.0\"\n# Linked.\nexternal_libraries = [{ name = \"zlib\", type = \"pkg_config\" }, { name = \"ssl\", type = \"pkg_config\" }] # Both.\nbits = 64\nschema_version = 2\n\n[profiles.release]\nlto = true\n"
+        );
+        assert_eq!(crate::toml::parse_toml(&text).unwrap(), value);
+
+        // The libraries of the new layout are left unchanged.
+        let mut value = json!({
+            "schema_version": 1,
+            "external_libraries": [{ "type": "pkg_config", "name": "zlib" }]
+        });
+        let changes = migrate(RESHAPING_MIGRATIONS, &mut value, None).unwrap();
+        assert_eq!(changes, ["Set schema_version to 2"]);
+    }
+}
@@ -1,6 +1,9 @@
+use crate::configure::TargetProperties;
+use crate::languages::{get_language, LanguageKind};
 use crate::makefile::BuildTarget;
-use crate::project::{get_toolset_executables, Toolset};
+use crate::project::{get_launcher_arguments, Toolset};
 use crate::result::{BargeError, Result};
+use crate::selfprofile::ProfileSection;
 use crate::NO_COLOR;
 use chrono::{DateTime, Local};
 use std::collections::HashMap;
@@ -28,8 +31,12 @@ pub(crate) struct ScriptEnvironment<'a> {
     pub git_commit_hash: Option<String>,
     pub git_branch: Option<String>,
     pub build_timestamp: DateTime<Local>,
+    pub build_directory: String,
     pub kind: BuildScriptKind,
     pub toolset: Toolset,
+    pub artifacts: Vec<(String, String)>,
+    pub compiler_launcher: Option<String>,
+    pub target_properties: Option<TargetProperties>,
 }
 
 impl TryFrom<&str> for BuildScriptLanguage {
@@ -53,9 +60,11 @@ impl TryFrom<&str> for BuildScriptLanguage {
 }
 
 pub(crate) fn execute_script(path: &str, name: &str, env: ScriptEnvironment) -> Result<()> {
+    let _section = ProfileSection::start("scripts");
     let kind = BuildScriptLanguage::try_from(get_file_extension(path)?)?;
 
-    let (cc, cxx, _) = get_toolset_executables(&env.toolset);
+    let cc = get_language(LanguageKind::C).executable(&env.toolset);
+    let cxx = get_language(LanguageKind::Cpp).executable(&env.toolset);
 
     match kind {
         BuildScriptLanguage::ShellScript => {
@@ -79,7 +88,7 @@ pub(crate) fn execute_script(path: &str, name: &str, env: ScriptEnvironment) ->
 
 fn get_file_extension(path: &str) -> Result<&str> {
     path.split('.')
-        .last()
+        .next_back()
         .ok_or(BargeError::NoneOption("Failed to parse file name"))
 }
 
@@ -135,13 +144,21 @@ fn execute_c_cpp_source(
         std::fs::remove_file(&target)?;
     }
 
-    let cc = Command::new(compiler)
-        .arg(std_flag)
-        .arg(path)
-        .arg("-o")
-        .arg(&target)
-        .spawn()?
-        .wait()?;
+    let launcher = get_launcher_arguments(env.compiler_launcher.as_deref());
+    let cc = match launcher.split_first() {
+        Some((launcher, arguments)) => {
+            let mut command = Command::new(launcher);
+            command.args(arguments).arg(compiler);
+            command
+        }
+        None => Command::new(compiler),
+    }
+    .arg(std_flag)
+    .arg(path)
+    .arg("-o")
+    .arg(&target)
+    .spawn()?
+    .wait()?;
     if !cc.success() {
         return Err(BargeError::FailedOperation(
             "Failed to compile a custom build step binary",
@@ -175,12 +192,9 @@ fn unpack_script_environment(env: ScriptEnvironment) -> HashMap<String, String>
     result.insert(String::from("BARGE_BUILD_TARGET"), env.target.to_string());
     result.insert(
         String::from("BARGE_OBJECTS_DIR"),
-        format!("build/{}/obj", env.target),
-    );
-    result.insert(
-        String::from("BARGE_BINARY_DIR"),
-        format!("build/{}", env.target),
+        format!("{}/obj", env.build_directory),
     );
+    result.insert(String::from("BARGE_BINARY_DIR"), env.build_directory);
     result.insert(
         String::from("BARGE_GIT_COMMIT"),
         env.git_commit_hash
@@ -217,6 +231,32 @@ fn unpack_script_environment(env: ScriptEnvironment) -> HashMap<String, String>
             Toolset::Gnu => String::from("gnu"),
         },
     );
+    if let Some(properties) = env.target_properties {
+        result.insert(String::from("BARGE_TARGET_ARCH"), properties.architecture);
+        result.insert(String::from("BARGE_TARGET_TRIPLE"), properties.triple);
+        result.insert(
+            String::from("BARGE_TARGET_POINTER_SIZE"),
+            properties.pointer_size.to_string(),
+        );
+        result.insert(
+            String::from("BARGE_TARGET_ENDIANNESS"),
+            String::from(if properties.big_endian {
+                "big"
+            } else {
+                "little"
+            }),
+        );
+    }
+    for (name, path) in env.artifacts {
+        let name: String = name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect();
+        result.insert(format!("BARGE_ARTIFACT_{}", name), path);
+    }
     if *NO_COLOR {
         result.insert(String::from("NO_COLOR"), String::from("1"));
     }
@@ -0,0 +1,107 @@
+use crate::{color_println, BLUE, NO_COLOR, WHITE};
+use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct SectionTotal {
+    count: usize,
+    duration: Duration,
+}
+
+lazy_static! {
+    static ref SUBCOMMAND: Mutex<String> = Mutex::new(String::new());
+    static ref SECTIONS: Mutex<HashMap<&'static str, SectionTotal>> = Mutex::new(HashMap::new());
+}
+
+thread_local! {
+    // The time spent in nested sections of each active section, so that only
+    // the time spent in the section itself is attributed to it.
+    static NESTED: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+// Measures the time spent in a part of barge until it is dropped, if self
+// profiling is enabled.
+pub(crate) struct ProfileSection {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl ProfileSection {
+    pub(crate) fn start(name: &'static str) -> ProfileSection {
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        if enabled {
+            NESTED.with(|nested| nested.borrow_mut().push(Duration::ZERO));
+        }
+        ProfileSection {
+            name,
+            start: enabled.then(Instant::now),
+        }
+    }
+}
+
+impl Drop for ProfileSection {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let elapsed = start.elapsed();
+        let nested = NESTED.with(|nested| {
+            let mut nested = nested.borrow_mut();
+            let inner = nested.pop().unwrap_or_default();
+            if let Some(outer) = nested.last_mut() {
+                *outer += elapsed;
+            }
+            inner
+        });
+
+        let mut sections = SECTIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let total = sections.entry(self.name).or_default();
+        total.count += 1;
+        total.duration += elapsed.saturating_sub(nested);
+    }
+}
+
+pub(crate) fn enable_self_profiling(subcommand: &str) {
+    ENABLED.store(true, Ordering::Relaxed);
+    *SUBCOMMAND.lock().unwrap_or_else(|e| e.into_inner()) = subcommand.to_string();
+}
+
+pub(crate) fn print_self_profile(total: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let sections = SECTIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut sections: Vec<(&str, String, Duration)> = sections
+        .iter()
+        .map(|(name, total)| (*name, format!("{} call(s)", total.count), total.duration))
+        .collect();
+    let measured: Duration = sections.iter().map(|(_, _, duration)| *duration).sum();
+    sections.push(("other", String::new(), total.saturating_sub(measured)));
+    sections.sort_by_key(|(_, _, duration)| std::cmp::Reverse(*duration));
+
+    let subcommand = SUBCOMMAND.lock().unwrap_or_else(|e| e.into_inner());
+    color_println!(
+        BLUE,
+        "Time spent in barge {}: {:.3} seconds",
+        subcommand,
+        total.as_secs_f64()
+    );
+    for (name, count, duration) in sections {
+        let share = 100.0 * duration.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON);
+        color_println!(
+            WHITE,
+            "  {:<20} {:>9.3} s {:>6.1}% {:>14}",
+            name,
+            duration.as_secs_f64(),
+            share,
+            count
+        );
+    }
+}
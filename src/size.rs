@@ -0,0 +1,223 @@
+use crate::makefile::BuildTarget;
+use crate::memory::report_memory_usage;
+use crate::project::{Project, Toolset, DEFAULT_TOOLSET};
+use crate::result::{BargeError, Result};
+use crate::utilities::attempt_remove_directory;
+use crate::{color_println, BLUE, GREEN, NO_COLOR, RED, WHITE};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+pub(crate) const SIZE_MANIFEST: &str = "size.json";
+const SIZE_WORKTREE: &str = "build/size-worktree";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ObjectSize {
+    pub text: u64,
+    pub data: u64,
+    pub bss: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SizeManifest {
+    pub objects: BTreeMap<String, ObjectSize>,
+    pub symbols: BTreeMap<String, u64>,
+}
+
+pub(crate) fn get_binutils(toolset: &Toolset) -> (&'static str, &'static str) {
+    match toolset {
+        Toolset::Gnu => ("size", "nm"),
+        Toolset::Llvm => ("llvm-size", "llvm-nm"),
+    }
+}
+
+pub(crate) fn collect_objects(build_directory: &str) -> Result<Vec<String>> {
+    let find = Command::new("find")
+        .arg(format!("{}/obj", build_directory))
+        .args(["-type", "f", "-name", "*.o"])
+        .output()?
+        .stdout;
+    let mut objects: Vec<String> = std::str::from_utf8(&find)?
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    objects.sort();
+    Ok(objects)
+}
+
+fn measure(project: &Project, target: BuildTarget) -> Result<SizeManifest> {
+    let build_directory = project.build_directory(target);
+    let prefix = format!("{}/obj/", build_directory);
+    let (size, nm) = get_binutils(project.toolset.as_ref().unwrap_or(DEFAULT_TOOLSET));
+    let (size, nm) = (project.cross_tool(size), project.cross_tool(nm));
+
+    let mut manifest = SizeManifest::default();
+    for object in collect_objects(&build_directory)? {
+        let name = object.strip_prefix(&prefix).unwrap_or(&object).to_string();
+
+        let output = Command::new(&size).arg("-B").arg(&object).output()?.stdout;
+        let output = String::from_utf8(output)?;
+        let fields: Vec<u64> = output
+            .lines()
+            .nth(1)
+            .unwrap_or_default()
+            .split_whitespace()
+            .take(3)
+            .filter_map(|field| field.parse().ok())
+            .collect();
+        if let [text, data, bss] = fields[..] {
+            manifest
+                .objects
+                .insert(name.clone(), ObjectSize { text, data, bss });
+        }
+
+        let output = Command::new(&nm)
+            .args(["-S", "--size-sort", "-t", "d", "-C"])
+            .arg(&object)
+            .output()?
+            .stdout;
+        for line in String::from_utf8(output)?.lines() {
+            let mut fields = line.splitn(4, ' ');
+            let (_, size, _, symbol) = (fields.next(), fields.next(), fields.next(), fields.next());
+            if let (Some(size), Some(symbol)) = (size.and_then(|s| s.parse().ok()), symbol) {
+                manifest
+                    .symbols
+                    .insert(format!("{}: {}", name, symbol), size);
+            }
+        }
+    }
+
+    Ok(manifest)
+}
+
+fn load_reference_manifest(reference: &str, target: BuildTarget) -> Result<SizeManifest> {
+    if Path::new(reference).is_file() {
+        let json = std::fs::read_to_string(reference)?;
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    color_println!(BLUE, "Building revision {} for comparison", reference);
+    attempt_remove_directory(SIZE_WORKTREE)?;
+    let worktree = Command::new("git")
+        .args(["worktree", "add", "--detach", SIZE_WORKTREE, reference])
+        .output()?;
+    if !worktree.status.success() {
+        return Err(BargeError::InvalidValue(
+            "The reference is neither a size manifest nor a valid git revision",
+        ));
+    }
+
+    let status = Command::new(std::env::current_exe()?)
+        .arg("size")
+        .arg(target.to_string())
+        .current_dir(SIZE_WORKTREE)
+        .status();
+    let manifest = Project::load_from_directory(Path::new(SIZE_WORKTREE)).and_then(|project| {
+        let path = format!(
+            "{}/{}/{}",
+            SIZE_WORKTREE,
+            project.build_directory(target),
+            SIZE_MANIFEST
+        );
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    });
+
+    Command::new("git")
+        .args(["worktree", "remove", "--force", SIZE_WORKTREE])
+        .output()?;
+
+    if !status?.success() {
+        return Err(BargeError::FailedOperation(
+            "Failed to build the reference revision",
+        ));
+    }
+    manifest
+}
+
+fn print_sizes(manifest: &SizeManifest) {
+    color_println!(
+        WHITE,
+        "{:<40} {:>10} {:>10} {:>10}",
+        "Object",
+        "text",
+        "data",
+        "bss"
+    );
+    let mut total = ObjectSize::default();
+    for (name, size) in &manifest.objects {
+        println!(
+            "{:<40} {:>10} {:>10} {:>10}",
+            name, size.text, size.data, size.bss
+        );
+        total.text += size.text;
+        total.data += size.data;
+        total.bss += size.bss;
+    }
+    color_println!(
+        WHITE,
+        "{:<40} {:>10} {:>10} {:>10}",
+        "Total",
+        total.text,
+        total.data,
+        total.bss
+    );
+}
+
+fn print_size_diff(reference: &SizeManifest, current: &SizeManifest) {
+    let mut changes: Vec<(&String, i64)> = reference
+        .symbols
+        .keys()
+        .chain(current.symbols.keys())
+        .map(|symbol| {
+            let old = *reference.symbols.get(symbol).unwrap_or(&0) as i64;
+            let new = *current.symbols.get(symbol).unwrap_or(&0) as i64;
+            (symbol, new - old)
+        })
+        .filter(|(_, delta)| *delta != 0)
+        .collect();
+    changes.sort_by_key(|(symbol, delta)| (-delta.abs(), *symbol));
+    changes.dedup();
+
+    if changes.is_empty() {
+        color_println!(GREEN, "No symbol sizes changed");
+        return;
+    }
+
+    for (symbol, delta) in &changes {
+        if *delta > 0 {
+            color_println!(RED, "{:>+10} {}", delta, symbol);
+        } else {
+            color_println!(GREEN, "{:>+10} {}", delta, symbol);
+        }
+    }
+    let total: i64 = changes.iter().map(|(_, delta)| delta).sum();
+    color_println!(BLUE, "Total change: {:+} bytes", total);
+}
+
+pub(crate) fn report_size(
+    project: &Project,
+    target: BuildTarget,
+    reference: Option<&String>,
+) -> Result<()> {
+    project.build(target)?;
+
+    let manifest = measure(project, target)?;
+    let path = format!("{}/{}", project.build_directory(target), SIZE_MANIFEST);
+    std::fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+
+    if let Some(reference) = reference {
+        let reference_manifest = load_reference_manifest(reference, target)?;
+        color_println!(BLUE, "Symbol size changes compared to {}", reference);
+        print_size_diff(&reference_manifest, &manifest);
+    } else {
+        print_sizes(&manifest);
+        if let Some(converted) = project.converted_artifact_path(target) {
+            let size = std::fs::metadata(&converted)?.len();
+            color_println!(WHITE, "Converted image {}: {} bytes", converted, size);
+        }
+        report_memory_usage(project, target)?;
+        color_println!(BLUE, "Size manifest written to {}", path);
+    }
+    Ok(())
+}
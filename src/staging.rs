@@ -0,0 +1,132 @@
+use crate::project::Project;
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, NO_COLOR, RED};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+const STAGED_RESOURCES: &str = ".runtime-resources";
+
+fn collect_resource_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.exists() {
+        color_eprintln!("The runtime resource {} does not exist", path.display());
+        return Err(BargeError::InvalidValue("Runtime resource not found"));
+    }
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            collect_resource_files(&entry, files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+// Whether the staged copy has the same size and modification time as the
+// resource, which are copied along with the contents.
+fn is_staged_copy_current(source: &Path, destination: &Path) -> bool {
+    let (Ok(source), Ok(destination)) = (source.metadata(), destination.metadata()) else {
+        return false;
+    };
+    match (source.modified(), destination.modified()) {
+        (Ok(source_time), Ok(destination_time)) => {
+            source.len() == destination.len() && source_time == destination_time
+        }
+        _ => false,
+    }
+}
+
+// Whether the path stays inside the directory it is relative to, so that its
+// staged copy is inside the staging directory.
+fn is_relative_to_project(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+// Removes a previously staged copy. The list of staged files is read from the
+// staging directory, so paths leading outside of it are never removed.
+fn remove_staged_copy(directory: &Path, staged: &str) {
+    if is_relative_to_project(Path::new(staged)) {
+        let _ = std::fs::remove_file(directory.join(staged));
+    }
+}
+
+fn load_staged_resources(directory: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(directory.join(STAGED_RESOURCES))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+// Copies the runtime resources of the project into the directory, keeping
+// their paths relative to the project root. Only the resources that changed
+// since the last staging are copied, and the ones no longer listed in the
+// project file are removed.
+pub(crate) fn stage_runtime_resources(project: &Project, directory: &Path) -> Result<()> {
+    let previous = load_staged_resources(directory);
+    let Some(resources) = &project.runtime_resources else {
+        if previous.is_empty() {
+            return Ok(());
+        }
+        for stale in &previous {
+            remove_staged_copy(directory, stale);
+        }
+        std::fs::remove_file(directory.join(STAGED_RESOURCES))?;
+        return Ok(());
+    };
+
+    let mut files = Vec::new();
+    for resource in resources {
+        if !is_relative_to_project(Path::new(resource)) {
+            color_eprintln!("The runtime resource {} is outside the project", resource);
+            return Err(BargeError::InvalidValue(
+                "Runtime resources must be relative paths inside the project",
+            ));
+        }
+        collect_resource_files(Path::new(resource.trim_start_matches("./")), &mut files)?;
+    }
+
+    let mut copied = 0;
+    let mut staged = BTreeSet::new();
+    for file in &files {
+        let destination = directory.join(file);
+        if !is_staged_copy_current(file, &destination) {
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // The copy keeps the permissions of the resource, so a read-only
+            // copy is replaced instead of being written, and the time of the
+            // change is set without write access.
+            if destination.is_file() {
+                std::fs::remove_file(&destination)?;
+            }
+            std::fs::copy(file, &destination)?;
+            std::fs::File::open(&destination)?.set_modified(file.metadata()?.modified()?)?;
+            copied += 1;
+        }
+        staged.insert(file.to_string_lossy().into_owned());
+    }
+
+    for stale in previous.difference(&staged) {
+        remove_staged_copy(directory, stale);
+    }
+
+    if copied > 0 {
+        color_println!(
+            BLUE,
+            "Staged {} runtime resource(s) in {}",
+            copied,
+            directory.display()
+        );
+    }
+    std::fs::create_dir_all(directory)?;
+    std::fs::write(
+        directory.join(STAGED_RESOURCES),
+        serde_json::to_string(&staged)?,
+    )?;
+    Ok(())
+}
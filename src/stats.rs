@@ -0,0 +1,208 @@
+use crate::languages::get_source_language;
+use crate::lines::is_excluded_from_metrics;
+use crate::output::is_accessible_output;
+use crate::project::{collect_source_files, collect_test_files, CollectSourceFilesMode, Project};
+use crate::result::Result;
+use crate::{color_println, BLUE, GREEN, NO_COLOR, WHITE, YELLOW};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+
+pub(crate) const STATS_HISTORY: &str = "barge-stats.json";
+const SPARKLINE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const BAR_WIDTH: usize = 40;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct StatsSnapshot {
+    pub commit: String,
+    pub timestamp: String,
+    pub files: usize,
+    pub lines: usize,
+    pub test_files: usize,
+    pub test_lines: usize,
+    pub languages: BTreeMap<String, usize>,
+}
+
+fn count_lines(path: &str) -> usize {
+    std::fs::read(path)
+        .map(|content| content.iter().filter(|byte| **byte == b'\n').count())
+        .unwrap_or_default()
+}
+
+fn get_head_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => None,
+    }
+}
+
+fn load_history() -> Result<Vec<StatsSnapshot>> {
+    match std::fs::read_to_string(STATS_HISTORY) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn take_snapshot(project: &Project, commit: String) -> Result<StatsSnapshot> {
+    let mut sources = collect_source_files(project, CollectSourceFilesMode::All)?;
+    sources.retain(|source| !is_excluded_from_metrics(project, source));
+    let mut tests = collect_test_files(project)?;
+    tests.retain(|test| !is_excluded_from_metrics(project, test));
+    let mut languages = BTreeMap::new();
+    let mut lines = 0;
+    for source in &sources {
+        let count = count_lines(source);
+        lines += count;
+        if let Some(language) = get_source_language(project, source) {
+            *languages.entry(language.to_string()).or_insert(0) += count;
+        }
+    }
+
+    Ok(StatsSnapshot {
+        commit,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        files: sources.len(),
+        lines,
+        test_files: tests.len(),
+        test_lines: tests.iter().map(|test| count_lines(test)).sum(),
+        languages,
+    })
+}
+
+// Records the current metrics of the project in the history, keyed by the
+// current git commit. A snapshot of the same commit is replaced.
+pub(crate) fn record_stats_snapshot(project: &Project) -> Result<Option<Vec<StatsSnapshot>>> {
+    let Some(commit) = get_head_commit() else {
+        return Ok(None);
+    };
+
+    let snapshot = take_snapshot(project, commit)?;
+    let mut history = load_history()?;
+    match history
+        .iter_mut()
+        .find(|entry| entry.commit == snapshot.commit)
+    {
+        Some(entry) => *entry = snapshot,
+        None => history.push(snapshot),
+    }
+    std::fs::write(
+        STATS_HISTORY,
+        serde_json::to_string_pretty(&history)? + "\n",
+    )?;
+    Ok(Some(history))
+}
+
+fn sparkline(values: &[usize]) -> String {
+    let minimum = values.iter().copied().min().unwrap_or_default();
+    let maximum = values.iter().copied().max().unwrap_or_default();
+    let range = (maximum - minimum).max(1);
+    values
+        .iter()
+        .map(|value| SPARKLINE_LEVELS[(value - minimum) * (SPARKLINE_LEVELS.len() - 1) / range])
+        .collect()
+}
+
+fn format_delta(current: usize, previous: Option<usize>) -> String {
+    match previous {
+        Some(previous) if current >= previous => format!("+{}", current - previous),
+        Some(previous) => format!("-{}", previous - current),
+        None => String::new(),
+    }
+}
+
+fn print_table(history: &[StatsSnapshot]) {
+    color_println!(
+        BLUE,
+        "{:<10}  {:<10}  {:>6}  {:>8}  {:>8}  {:>6}  {:>10}",
+        "Commit",
+        "Date",
+        "Files",
+        "Lines",
+        "Change",
+        "Tests",
+        "Test lines"
+    );
+    let mut previous = None;
+    for snapshot in history {
+        color_println!(
+            WHITE,
+            "{:<10}  {:<10}  {:>6}  {:>8}  {:>8}  {:>6}  {:>10}",
+            snapshot.commit,
+            snapshot.timestamp.get(..10).unwrap_or(&snapshot.timestamp),
+            snapshot.files,
+            snapshot.lines,
+            format_delta(snapshot.lines, previous),
+            snapshot.test_files,
+            snapshot.test_lines
+        );
+        previous = Some(snapshot.lines);
+    }
+}
+
+fn print_line_growth(history: &[StatsSnapshot]) {
+    let lines: Vec<usize> = history.iter().map(|snapshot| snapshot.lines).collect();
+    color_println!(BLUE, "Lines of code over {} snapshot(s)", history.len());
+    color_println!(GREEN, "{}", sparkline(&lines));
+
+    let maximum = lines.iter().copied().max().unwrap_or_default().max(1);
+    for snapshot in history {
+        let width = snapshot.lines * BAR_WIDTH / maximum;
+        color_println!(
+            WHITE,
+            "{:<10} {:>8} {}",
+            snapshot.commit,
+            snapshot.lines,
+            "#".repeat(width.max(1))
+        );
+    }
+
+    let mut languages: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, snapshot) in history.iter().enumerate() {
+        for language in snapshot.languages.keys() {
+            languages
+                .entry(language)
+                .or_insert_with(|| vec![0; history.len()]);
+        }
+        for (language, values) in languages.iter_mut() {
+            values[index] = snapshot.languages.get(*language).copied().unwrap_or(0);
+        }
+    }
+    for (language, values) in &languages {
+        color_println!(
+            YELLOW,
+            "{:<10} {} {}",
+            language,
+            sparkline(values),
+            values.last().copied().unwrap_or_default()
+        );
+    }
+}
+
+pub(crate) fn print_stats(project: &Project, growth: bool) -> Result<()> {
+    let history = match record_stats_snapshot(project)? {
+        Some(history) => history,
+        None => {
+            color_println!(
+                YELLOW,
+                "The project is not a git repository with commits, showing the recorded history"
+            );
+            load_history()?
+        }
+    };
+    if history.is_empty() {
+        color_println!(BLUE, "No statistics were recorded for the project yet");
+        return Ok(());
+    }
+
+    // The graphs can not be read by screen readers, the table holds the same data.
+    if growth && !is_accessible_output() {
+        print_line_growth(&history);
+    } else {
+        print_table(&history);
+    }
+    Ok(())
+}
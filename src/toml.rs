@@ -0,0 +1,151 @@
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, NO_COLOR, RED};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use toml_edit::{DocumentMut, Item, Value as TomlValue};
+
+// Project files written in TOML are converted from and to the JSON value of
+// the project, so that both formats share the same schema and migrations.
+// Dates and times have no JSON counterpart, and are not used by project files.
+
+fn to_json_value(value: toml::Value) -> Result<Value> {
+    Ok(match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Value::Number(Number::from_f64(float).ok_or(
+            BargeError::InvalidValue("The numbers of the project file must be finite"),
+        )?),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(_) => {
+            return Err(BargeError::InvalidValue(
+                "Dates and times are not supported in the project file",
+            ))
+        }
+        toml::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(to_json_value)
+                .collect::<Result<_>>()?,
+        ),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| Ok((key, to_json_value(value)?)))
+                .collect::<Result<Map<_, _>>>()?,
+        ),
+    })
+}
+
+pub(crate) fn parse_toml(text: &str) -> Result<Value> {
+    let table = text.parse::<toml::Table>().map_err(|error| {
+        color_eprintln!("{}", error.to_string().trim_end());
+        BargeError::InvalidValue("Malformed TOML file")
+    })?;
+    to_json_value(toml::Value::Table(table))
+}
+
+// Parses the TOML text as a document which can be edited, while keeping its
+// comments and formatting.
+pub(crate) fn parse_toml_document(text: &str) -> Result<DocumentMut> {
+    text.parse::<DocumentMut>().map_err(|error| {
+        color_eprintln!("{}", error.to_string().trim_end());
+        BargeError::InvalidValue("Malformed TOML file")
+    })
+}
+
+// Sets the value of a top-level key of the document, keeping the comments
+// around the replaced value. Missing keys are added after the other top-level
+// keys.
+pub(crate) fn set_root_value(document: &mut DocumentMut, key: &str, value: impl Into<TomlValue>) {
+    let mut value = value.into();
+    match document.get_mut(key).and_then(Item::as_value_mut) {
+        Some(existing) => {
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+        None => document[key] = Item::Value(value),
+    }
+}
+
+// Writes the value as TOML, with the top-level fields first, followed by the
+// objects as tables, and the lists of objects as arrays of tables.
+pub(crate) fn to_toml_string<T: Serialize>(value: &T) -> Result<String> {
+    toml::to_string(value).map_err(|error| {
+        color_eprintln!("{}", error);
+        BargeError::InvalidValue("Could not write the project file as TOML")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn documents_are_parsed() {
+        let text = r#"# A project file.
+name = "demo" # Trailing comment.
+"quoted key" = 'C:\path'
+numbers = [1_000, 0x1F, -7, 0b11, +2.5, 1e3]
+flags = { debug = true, release.lto = false }
+description = """
+Multiline \
+  string"""
+
+[profiles.release]
+opt = 3
+
+[[external_libraries]]
+name = "zlib"
+
+[[external_libraries]]
+name = "ssl"
+"#;
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            json!({
+                "name": "demo",
+                "quoted key": "C:\\path",
+                "numbers": [1000, 31, -7, 3, 2.5, 1000.0],
+                "flags": { "debug": true, "release": { "lto": false } },
+                "description": "Multiline string",
+                "profiles": { "release": { "opt": 3 } },
+                "external_libraries": [{ "name": "zlib" }, { "name": "ssl" }],
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        assert!(parse_toml("name = \"demo\"\nname = \"other\"\n").is_err());
+        assert!(parse_toml("name = \"demo\n").is_err());
+        assert!(parse_toml("date = 2024-01-01\n").is_err());
+        assert!(parse_toml("name = \"demo\" extra\n").is_err());
+        assert!(parse_toml("options = { a = 1 }\n[options]\nb = 2\n").is_err());
+        assert!(parse_toml("options.a = 1\n[options]\nb = 2\n").is_err());
+        assert!(parse_toml("number = -0x1\n").is_err());
+        assert!(parse_toml("number = +0o7\n").is_err());
+    }
+
+    #[test]
+    fn written_documents_are_parsed_back() {
+        let value = json!({
+            "name": "demo",
+            "version": "1.0.0",
+            "description": "Quotes \" and \\ and\nlines",
+            "bits": 64,
+            "ratio": 1.0,
+            "authors": ["Someone <someone@example.com>"],
+            "custom.key": { "inline": [1, 2] },
+            "profiles": { "release": { "custom_cflags": "-O3" } },
+            "external_libraries": [
+                { "type": "pkg_config", "name": "zlib" },
+                { "type": "manual", "name": "m", "link_flags": "-lm" },
+            ],
+        });
+        let text = to_toml_string(&value).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), value);
+        assert!(text.find("version = ").unwrap() < text.find("[profiles.release]").unwrap());
+        assert!(text.contains("\n[[external_libraries]]\n"));
+    }
+}
@@ -0,0 +1,299 @@
+use crate::makefile::BuildTarget;
+use crate::observer::{BuildObserver, BuildOutcome, ConsoleObserver};
+use crate::output::{is_accessible_output, start_capturing_lines, take_captured_lines};
+use crate::project::{collect_source_files, CollectSourceFilesMode, Project, SourceKind};
+use crate::result::Result;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const PROGRESS_BAR_WIDTH: usize = 40;
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+struct Step {
+    description: String,
+    output: Option<PathBuf>,
+    started: Instant,
+    started_at: SystemTime,
+    finished: bool,
+}
+
+impl Step {
+    // The progress messages of the makefile end with the path of the file
+    // being produced, the step is finished once that file is updated.
+    fn new(description: &str) -> Step {
+        let output = description
+            .split_whitespace()
+            .last()
+            .map(|word| word.trim_end_matches('.'))
+            .filter(|word| word.contains('/'))
+            .map(PathBuf::from);
+        Step {
+            finished: output.is_none(),
+            description: description.to_string(),
+            output,
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+        }
+    }
+
+    fn update(&mut self) {
+        if let Some(output) = &self.output {
+            let modified = std::fs::metadata(output).and_then(|metadata| metadata.modified());
+            self.finished = modified.is_ok_and(|modified| modified >= self.started_at);
+        }
+    }
+}
+
+struct TuiState {
+    steps: Vec<Step>,
+    diagnostics: Vec<String>,
+    outcome: Option<BuildOutcome>,
+}
+
+struct TuiObserver {
+    title: String,
+    jobs: usize,
+    total_steps: usize,
+    started: Instant,
+    state: Mutex<TuiState>,
+}
+
+fn is_dumb_terminal() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    term.is_empty() || term == "dumb" || !std::io::stdout().is_terminal()
+}
+
+// Returns the number of rows and columns of the terminal.
+#[cfg(unix)]
+fn get_terminal_size() -> (usize, usize) {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes the size into the given structure.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result != 0 || size.ws_row == 0 || size.ws_col == 0 {
+        return (24, 80);
+    }
+    (size.ws_row as usize, size.ws_col as usize)
+}
+
+// The full-screen view is only shown on unix hosts, see build_with_tui.
+#[cfg(not(unix))]
+fn get_terminal_size() -> (usize, usize) {
+    (24, 80)
+}
+
+// Leaves the full-screen view when the build is interrupted, then lets the
+// signal terminate barge as usual.
+#[cfg(unix)]
+extern "C" fn leave_screen_on_interrupt(signal: libc::c_int) {
+    // SAFETY: only async-signal-safe functions are called.
+    unsafe {
+        libc::write(
+            libc::STDOUT_FILENO,
+            LEAVE_SCREEN.as_ptr().cast(),
+            LEAVE_SCREEN.len(),
+        );
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+// Shows the full-screen view while alive, and restores the terminal when
+// dropped, even if the build panics or is interrupted.
+struct AlternateScreen {
+    #[cfg(unix)]
+    interrupt_handler: libc::sighandler_t,
+}
+
+impl AlternateScreen {
+    fn enter() -> AlternateScreen {
+        #[cfg(unix)]
+        let handler = leave_screen_on_interrupt as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only calls async-signal-safe functions.
+        #[cfg(unix)]
+        let interrupt_handler =
+            unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+        start_capturing_lines();
+        print!("{}", ENTER_SCREEN);
+        let _ = std::io::stdout().flush();
+        AlternateScreen {
+            #[cfg(unix)]
+            interrupt_handler,
+        }
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        print!("{}", LEAVE_SCREEN);
+        let _ = std::io::stdout().flush();
+        // SAFETY: restores the handler which was replaced when entering.
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, self.interrupt_handler);
+        }
+        // The lines printed while the view was shown are kept on the screen.
+        for line in take_captured_lines(true) {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    format!("{:02}:{:04.1}", (seconds / 60.0) as u64, seconds % 60.0)
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+impl TuiObserver {
+    fn lock(&self) -> std::sync::MutexGuard<'_, TuiState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn render(&self) -> String {
+        let (rows, columns) = get_terminal_size();
+        let mut state = self.lock();
+        state.diagnostics.extend(take_captured_lines(false));
+        state.steps.iter_mut().for_each(Step::update);
+
+        let finished = state.steps.iter().filter(|step| step.finished).count();
+        let total = self.total_steps.max(state.steps.len());
+        let filled = PROGRESS_BAR_WIDTH * finished / total.max(1);
+        let mut lines = vec![
+            format!(
+                "{}  elapsed {}",
+                self.title,
+                format_duration(self.started.elapsed())
+            ),
+            format!(
+                "[{}{}] {}/{} steps",
+                "#".repeat(filled),
+                "-".repeat(PROGRESS_BAR_WIDTH - filled),
+                finished,
+                total
+            ),
+            String::new(),
+            String::from("Jobs"),
+        ];
+
+        let running: Vec<&Step> = state.steps.iter().filter(|step| !step.finished).collect();
+        for job in 0..self.jobs.min(rows / 3).max(1) {
+            lines.push(match running.get(job) {
+                Some(step) => format!(
+                    " [{}] {} {}",
+                    job + 1,
+                    format_duration(step.started.elapsed()),
+                    step.description
+                ),
+                None => format!(" [{}] idle", job + 1),
+            });
+        }
+
+        lines.push(String::new());
+        let headers = [0, 3, lines.len()];
+        lines.push(format!("Diagnostics ({})", state.diagnostics.len()));
+        let available = rows.saturating_sub(lines.len() + 1);
+        let skipped = state.diagnostics.len().saturating_sub(available);
+        lines.extend(state.diagnostics.iter().skip(skipped).cloned());
+
+        let lines: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| match headers.contains(&index) {
+                true => format!("\x1b[1m{}\x1b[0m", truncate(line, columns)),
+                false => truncate(line, columns),
+            })
+            .collect();
+        format!("\x1b[H\x1b[2J{}", lines.join("\n"))
+    }
+
+    fn redraw(&self) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(self.render().as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+impl BuildObserver for TuiObserver {
+    fn on_step_start(&self, step: &str) {
+        self.lock().steps.push(Step::new(step));
+    }
+
+    fn on_diagnostic(&self, line: &str) {
+        self.lock().diagnostics.push(line.to_string());
+    }
+
+    fn on_artifact(&self, _path: &Path) {}
+
+    fn on_finish(&self, outcome: BuildOutcome) {
+        let mut state = self.lock();
+        state.steps.iter_mut().for_each(|step| step.finished = true);
+        state.outcome = Some(outcome);
+    }
+}
+
+// Builds the project while presenting a full-screen view of the build, or
+// with the usual console output if the terminal can not display it. Only unix
+// hosts tell the size of the terminal, and let the view be left on interrupts.
+pub(crate) fn build_with_tui(project: &Project, target: BuildTarget) -> Result<()> {
+    if !cfg!(unix) || is_dumb_terminal() || is_accessible_output() {
+        return project.build_with_observer(target, &ConsoleObserver);
+    }
+
+    let sources = collect_source_files(project, CollectSourceFilesMode::All)?;
+    let observer = TuiObserver {
+        title: format!("Building {} ({})", project.name, target),
+        jobs: project
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |jobs| jobs.get())),
+        total_steps: sources
+            .iter()
+            .filter(|s| project.source_kind(s) != Some(SourceKind::LinkerScript))
+            .count()
+            + 1,
+        started: Instant::now(),
+        state: Mutex::new(TuiState {
+            steps: Vec::new(),
+            diagnostics: Vec::new(),
+            outcome: None,
+        }),
+    };
+
+    let screen = AlternateScreen::enter();
+    let done = AtomicBool::new(false);
+    let result = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                observer.redraw();
+                std::thread::sleep(REDRAW_INTERVAL);
+            }
+        });
+        let result = project.build_with_observer(target, &observer);
+        done.store(true, Ordering::Relaxed);
+        result
+    });
+    observer.redraw();
+    drop(screen);
+
+    // The diagnostics are kept on the screen after leaving the full-screen view.
+    let state = observer.lock();
+    for diagnostic in &state.diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    if let Some(outcome) = state.outcome {
+        ConsoleObserver.on_finish(outcome);
+    }
+    result
+}
@@ -0,0 +1,71 @@
+use crate::makefile::BuildTarget;
+use crate::project::Project;
+use crate::result::{BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED};
+use std::process::Command;
+
+const ARCHITECTURES: &[&str] = &["x86_64", "x86_64h", "arm64", "arm64e", "i386"];
+
+fn check_architectures(architectures: &[String]) -> Result<()> {
+    if std::env::consts::OS != "macos" {
+        return Err(BargeError::InvalidValue(
+            "Universal binaries can only be built on macOS",
+        ));
+    }
+
+    for architecture in architectures {
+        if !ARCHITECTURES.contains(&architecture.as_str()) {
+            color_eprintln!("Unknown architecture {}", architecture);
+            return Err(BargeError::InvalidValue(
+                "Invalid architecture, valid choices are: x86_64, x86_64h, arm64, arm64e, i386",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Builds a slice of the project per architecture (each in its own build
+// directory), then combines the slices into a universal binary with lipo.
+pub(crate) fn build_universal_binary(
+    project: &Project,
+    target: BuildTarget,
+    architectures: &[String],
+) -> Result<()> {
+    check_architectures(architectures)?;
+
+    let mut slices = Vec::new();
+    for architecture in architectures {
+        let mut slice = project.clone();
+        slice.architecture = Some(architecture.clone());
+        color_println!(BLUE, "Building {} slice", architecture);
+        slice.build(target)?;
+        slices.push(format!(
+            "{}/{}",
+            slice.build_directory(target),
+            slice.artifact_name()
+        ));
+    }
+
+    let directory = project.build_directory(target) + "-universal";
+    std::fs::create_dir_all(&directory)?;
+    let binary = format!("{}/{}", directory, project.artifact_name());
+    let status = Command::new("lipo")
+        .arg("-create")
+        .arg("-output")
+        .arg(&binary)
+        .args(&slices)
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            color_println!(GREEN, "Universal binary created at {}", binary);
+            Ok(())
+        }
+        Ok(_) => Err(BargeError::FailedOperation(
+            "Failed to create the universal binary",
+        )),
+        Err(error) => {
+            color_eprintln!("Could not execute lipo");
+            Err(error.into())
+        }
+    }
+}
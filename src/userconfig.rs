@@ -0,0 +1,81 @@
+use crate::jsonc::from_jsonc_str;
+use crate::project::{Project, Toolset};
+use crate::result::Result;
+use crate::{color_eprintln, NO_COLOR, RED};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const USER_CONFIG_FILE: &str = "barge/config.json";
+
+// The defaults of the user, used for the fields not set in the project file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolset: Option<Toolset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c_standard: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpp_standard: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_makeopts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<bool>,
+}
+
+// Follows the XDG base directory specification, with ~/.config as fallback.
+pub(crate) fn get_user_config_path() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|directory| !directory.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(directory.join(USER_CONFIG_FILE))
+}
+
+// Loads the user configuration without reporting a malformed one, as nothing
+// may be printed before the color preferences are known.
+pub(crate) fn load_user_config_silently() -> Result<UserConfig> {
+    let Some(path) = get_user_config_path() else {
+        return Ok(UserConfig::default());
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(UserConfig::default());
+    };
+    from_jsonc_str(&content)
+}
+
+pub(crate) fn load_user_config() -> Result<UserConfig> {
+    load_user_config_silently().inspect_err(|_| {
+        if let Some(path) = get_user_config_path() {
+            color_eprintln!("Could not load the user configuration {}", path.display());
+        }
+    })
+}
+
+// Disables the colored output if the user prefers it, the same way as the
+// NO_COLOR environment variable does. Has to be called before printing
+// anything.
+pub(crate) fn apply_color_preference(config: &UserConfig) {
+    if config.color == Some(false) {
+        std::env::set_var("NO_COLOR", "1");
+    }
+}
+
+impl UserConfig {
+    // The settings of the project file take precedence over the defaults.
+    pub(crate) fn apply_defaults(&self, project: &mut Project) {
+        project.toolset = project.toolset.or(self.toolset);
+        let defaults = [
+            (&mut project.format_style, &self.format_style),
+            (&mut project.c_standard, &self.c_standard),
+            (&mut project.cpp_standard, &self.cpp_standard),
+            (&mut project.custom_makeopts, &self.custom_makeopts),
+        ];
+        for (field, default) in defaults {
+            if field.is_none() {
+                field.clone_from(default);
+            }
+        }
+    }
+}
@@ -1,10 +1,51 @@
+use crate::ownership::report_removal_failure;
+use crate::project::find_project_file;
 use crate::result::{BargeError, Result};
+use crate::{color_println, GREEN, NO_COLOR, RED};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub(crate) const PROJECT_TOOLS_DIRECTORY: &str = "tools";
 
 pub(crate) fn attempt_remove_directory(path: &str) -> Result<()> {
     let path = Path::new(&path);
     if path.exists() {
-        std::fs::remove_dir_all(path)?;
+        std::fs::remove_dir_all(path).map_err(|error| report_removal_failure(path, error))?;
+    }
+    Ok(())
+}
+
+// Returns the path of the given program, looking it up in PATH, unless it is
+// given with a path.
+pub(crate) fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|directory| directory.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+// Prepends the tools directory of the project to PATH, so that every command
+// spawned by barge (including make and the commands it runs) can use the
+// helper programs vendored by the project.
+pub(crate) fn add_project_tools_to_path() -> Result<()> {
+    let tools = std::env::current_dir()?.join(PROJECT_TOOLS_DIRECTORY);
+    if !tools.is_dir() {
+        return Ok(());
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut directories: Vec<PathBuf> = std::env::split_paths(&path).collect();
+    if directories.first() != Some(&tools) {
+        directories.insert(0, tools);
+        std::env::set_var(
+            "PATH",
+            std::env::join_paths(directories)
+                .map_err(|_| BargeError::InvalidValue("Invalid tools directory path"))?,
+        );
     }
     Ok(())
 }
@@ -12,16 +53,160 @@ pub(crate) fn attempt_remove_directory(path: &str) -> Result<()> {
 pub(crate) fn look_for_project_directory() -> Result<PathBuf> {
     let mut current = std::env::current_dir()?;
     while current.parent().is_some() {
-        current.push("barge.json");
-        if current.exists() {
-            current.pop();
+        if find_project_file(&current).is_some() {
             return Ok(current);
-        } else {
-            current.pop();
-            current.pop();
         }
+        current.pop();
     }
     Err(BargeError::ProjectNotFound(
         "Project file not found before reaching filesystem root.",
     ))
 }
+
+pub(crate) fn print_line_diff(old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            println!("  {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            color_println!(RED, "- {}", old[i]);
+            i += 1;
+        } else {
+            color_println!(GREEN, "+ {}", new[j]);
+            j += 1;
+        }
+    }
+}
+
+pub(crate) fn get_content_hash(parts: &[&[u8]]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain(std::iter::once(&0xff)) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+// Applies the function to every item on a pool of threads (one per available
+// core), returning the results in the order of the items.
+pub(crate) fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .clamp(1, items.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// Matches a path against a glob pattern, where * and ? do not match the path
+// separator, ** matches anything, and **/ matches any number of directories.
+pub(crate) fn matches_glob(pattern: &str, path: &str) -> bool {
+    matches_wildcards(
+        pattern.trim_start_matches("./").as_bytes(),
+        path.trim_start_matches("./").as_bytes(),
+    )
+}
+
+fn matches_wildcards(pattern: &[u8], text: &[u8]) -> bool {
+    if let Some(rest) = pattern.strip_prefix(b"**/") {
+        return matches_wildcards(rest, text)
+            || text
+                .iter()
+                .enumerate()
+                .any(|(index, &c)| c == b'/' && matches_wildcards(rest, &text[index + 1..]));
+    }
+    if let Some(rest) = pattern.strip_prefix(b"**") {
+        return (0..=text.len()).any(|index| matches_wildcards(rest, &text[index..]));
+    }
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches_wildcards(&pattern[1..], text)
+                || text
+                    .first()
+                    .is_some_and(|&c| c != b'/' && matches_wildcards(pattern, &text[1..]))
+        }
+        (Some(b'?'), Some(&c)) if c != b'/' => matches_wildcards(&pattern[1..], &text[1..]),
+        (Some(p), Some(c)) if p == c => matches_wildcards(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+// Returns whether the tracked files of the git repository have changes which
+// are not committed yet.
+pub(crate) fn has_uncommitted_changes() -> Result<bool> {
+    let output = std::process::Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()?;
+    if !output.status.success() {
+        return Err(BargeError::FailedOperation(
+            "Could not query the status of the git repository",
+        ));
+    }
+    Ok(!output.stdout.is_empty())
+}
+
+fn run_git_listing(args: &[&str]) -> Result<Vec<String>> {
+    let output = std::process::Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(BargeError::FailedOperation(
+            "Could not list the changed files using git",
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+// Returns the files (relative to the current directory) which differ from the
+// given git revision, either staged or not, and the untracked files which are
+// not ignored.
+pub(crate) fn get_changed_files(revision: &str) -> Result<Vec<String>> {
+    let mut files = run_git_listing(&["diff", "--name-only", "--relative", revision, "--"])?;
+    files.extend(run_git_listing(&[
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+    ])?);
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
@@ -0,0 +1,618 @@
+use crate::externals::is_valid_external_project_name;
+use crate::jsonc::mask_jsonc;
+use crate::project::{is_toml_file, read_project_value, Project};
+use crate::result::{BargeError, Result};
+use crate::schema::migrate_project_value;
+use crate::{color_eprintln, color_println, GREEN, NO_COLOR, RED};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// The number of errors reported before giving up, each error needs another
+// pass over the project file.
+const MAX_ERRORS: usize = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn format_path(path: &[Segment]) -> String {
+    let mut formatted = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if formatted.is_empty() => formatted += key,
+            Segment::Key(key) => formatted += &format!(".{}", key),
+            Segment::Index(index) => formatted += &format!("[{}]", index),
+        }
+    }
+    formatted
+}
+
+#[derive(Debug)]
+struct ValidationError {
+    path: Option<Vec<Segment>>,
+    message: String,
+}
+
+impl ValidationError {
+    fn at(mut self, path: &[Segment]) -> Self {
+        self.path.get_or_insert_with(|| path.to_vec());
+        self
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl de::Error for ValidationError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self {
+            path: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+struct Issue {
+    path: Vec<Segment>,
+    message: String,
+}
+
+// The structs with flattened fields are deserialized as maps, and their
+// flattened fields are deserialized from a copy of the value, out of the reach
+// of the validator. The deserializer of the flattened fields reports the
+// fields unknown to barge, and the field of its error here while validating.
+#[derive(Default)]
+struct FlattenedFields {
+    leftover: Vec<String>,
+    invalid: Option<String>,
+}
+
+thread_local! {
+    static FLATTENED_FIELDS: RefCell<Option<FlattenedFields>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn report_leftover_fields(fields: impl Iterator<Item = String>) {
+    FLATTENED_FIELDS.with(|flattened| {
+        if let Some(flattened) = flattened.borrow_mut().as_mut() {
+            flattened.leftover.extend(fields);
+        }
+    });
+}
+
+pub(crate) fn report_invalid_field(field: &str) {
+    FLATTENED_FIELDS.with(|flattened| {
+        if let Some(flattened) = flattened.borrow_mut().as_mut() {
+            flattened.invalid = Some(field.to_string());
+        }
+    });
+}
+
+// Deserializes the project from a JSON value like serde_json does, but keeps
+// track of the path of the current value, so that the errors can be located
+// in the project file. The structs pass the list of their fields to the
+// deserializer, so the fields unknown to barge are collected on the way.
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+    path: Vec<Segment>,
+    unknown: &'a RefCell<Vec<Issue>>,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn child(&self, value: &'a Value, segment: Segment) -> Self {
+        let mut path = self.path.clone();
+        path.push(segment);
+        Self {
+            value,
+            path,
+            unknown: self.unknown,
+        }
+    }
+
+    fn visit<'de, V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, ValidationError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(value) => visitor.visit_bool(*value),
+            Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+                (Some(value), _) => visitor.visit_u64(value),
+                (None, Some(value)) => visitor.visit_i64(value),
+                _ => visitor.visit_f64(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(value) => visitor.visit_str(value),
+            Value::Array(elements) => visitor.visit_seq(ElementAccess {
+                parent: &self,
+                elements: elements.iter().enumerate(),
+            }),
+            Value::Object(entries) => visitor.visit_map(EntryAccess {
+                parent: &self,
+                entries: entries.iter(),
+                value: None,
+            }),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = ValidationError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let path = self.path.clone();
+        self.visit(visitor).map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let path = self.path.clone();
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+        .map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        if let Value::Object(entries) = self.value {
+            for key in entries.keys().filter(|key| !fields.contains(&key.as_str())) {
+                let mut path = self.path.clone();
+                path.push(Segment::Key(key.clone()));
+                self.unknown.borrow_mut().push(Issue {
+                    path,
+                    message: String::from("unknown field"),
+                });
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let previous =
+            FLATTENED_FIELDS.with(|flattened| flattened.replace(Some(Default::default())));
+        let path = self.path.clone();
+        let unknown = self.unknown;
+        let result = self.deserialize_any(visitor);
+        let flattened = FLATTENED_FIELDS
+            .with(|flattened| flattened.replace(previous))
+            .unwrap_or_default();
+        for key in flattened.leftover {
+            let mut path = path.clone();
+            path.push(Segment::Key(key));
+            unknown.borrow_mut().push(Issue {
+                path,
+                message: String::from("unknown field"),
+            });
+        }
+        result.map_err(|mut error| {
+            if let Some(field) = flattened.invalid {
+                let prefix = format!("{}: ", field);
+                if let Some(message) = error.message.strip_prefix(&prefix) {
+                    error.message = message.to_string();
+                    error.path = Some([path, vec![Segment::Key(field)]].concat());
+                }
+            }
+            error
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.value {
+            Value::String(variant) => visitor
+                .visit_enum(variant.as_str().into_deserializer())
+                .map_err(|error: ValidationError| error.at(&self.path)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct identifier
+    }
+}
+
+struct ElementAccess<'a, 'b, I> {
+    parent: &'b ValueDeserializer<'a>,
+    elements: I,
+}
+
+impl<'de, 'a, I: Iterator<Item = (usize, &'a Value)>> SeqAccess<'de> for ElementAccess<'a, '_, I> {
+    type Error = ValidationError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> std::result::Result<Option<T::Value>, Self::Error> {
+        match self.elements.next() {
+            Some((index, element)) => seed
+                .deserialize(self.parent.child(element, Segment::Index(index)))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct EntryAccess<'a, 'b, I> {
+    parent: &'b ValueDeserializer<'a>,
+    entries: I,
+    value: Option<(&'a String, &'a Value)>,
+}
+
+impl<'de, 'a, I: Iterator<Item = (&'a String, &'a Value)>> MapAccess<'de>
+    for EntryAccess<'a, '_, I>
+{
+    type Error = ValidationError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, Self::Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(key.as_str().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        seed.deserialize(self.parent.child(value, Segment::Key(key.clone())))
+    }
+}
+
+fn get_value_mut<'a>(value: &'a mut Value, path: &[Segment]) -> Option<&'a mut Value> {
+    match path {
+        [] => Some(value),
+        [Segment::Key(key), rest @ ..] => {
+            let object = value.as_object_mut()?;
+            match rest {
+                // The missing fields are inserted.
+                [] => Some(object.entry(key.clone()).or_insert(Value::Null)),
+                _ => get_value_mut(object.get_mut(key)?, rest),
+            }
+        }
+        [Segment::Index(index), rest @ ..] => get_value_mut(value.get_mut(*index)?, rest),
+    }
+}
+
+fn deserialize_project(
+    value: &Value,
+    unknown: &RefCell<Vec<Issue>>,
+) -> std::result::Result<Project, ValidationError> {
+    Project::deserialize(ValueDeserializer {
+        value,
+        path: Vec::new(),
+        unknown,
+    })
+}
+
+// Replaces the value at the path of the error with the first placeholder that
+// makes the error go away, so that the rest of the file can be checked too.
+fn replace_invalid_value(value: &mut Value, error: &ValidationError) -> bool {
+    let mut path = error.path.clone().unwrap_or_default();
+    if let Some(field) = error
+        .message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        path.push(Segment::Key(field.to_string()));
+    }
+    // The first variant of an enum is a valid placeholder for an unknown one.
+    let variant = error
+        .message
+        .split_once("expected one of `")
+        .or_else(|| error.message.split_once("expected `"))
+        .and_then(|(_, rest)| rest.split_once('`'))
+        .map(|(variant, _)| Value::from(variant));
+
+    // If no placeholder fits the value, its parent is replaced instead.
+    (1..=path.len())
+        .rev()
+        .any(|length| replace_with_placeholder(value, &path[..length], variant.clone()))
+}
+
+fn replace_with_placeholder(value: &mut Value, path: &[Segment], variant: Option<Value>) -> bool {
+    let placeholders = [
+        variant.unwrap_or(Value::Null),
+        Value::Null,
+        Value::from(""),
+        Value::from(false),
+        Value::from(0),
+        Value::Array(Vec::new()),
+        Value::Object(serde_json::Map::new()),
+    ];
+    for placeholder in placeholders {
+        let mut candidate = value.clone();
+        let Some(target) = get_value_mut(&mut candidate, path) else {
+            return false;
+        };
+        *target = placeholder;
+        let fixed = match deserialize_project(&candidate, &RefCell::new(Vec::new())) {
+            Ok(_) => true,
+            Err(next) => !next.path.unwrap_or_default().starts_with(path),
+        };
+        if fixed {
+            *value = candidate;
+            return true;
+        }
+    }
+    false
+}
+
+// Deserializes the project repeatedly, replacing the value causing the error
+// after each failure, so that all of the errors are reported at once, not
+// only the first one.
+fn collect_issues(mut value: Value) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = Vec::new();
+    while issues.len() < MAX_ERRORS {
+        let unknown = RefCell::new(Vec::new());
+        let result = deserialize_project(&value, &unknown);
+        for issue in unknown.into_inner() {
+            if !issues.iter().any(|existing| existing.path == issue.path) {
+                issues.push(issue);
+            }
+        }
+        let Err(error) = result else {
+            break;
+        };
+        let replaced = replace_invalid_value(&mut value, &error);
+        issues.push(Issue {
+            path: error.path.unwrap_or_default(),
+            message: error.message,
+        });
+        if !replaced {
+            break;
+        }
+    }
+    issues
+}
+
+// The names of the external projects are valid strings for serde, but they are
+// also the names of their directories.
+fn collect_name_issues(value: &Value) -> Vec<Issue> {
+    let externals = value.get("external_projects").and_then(Value::as_array);
+    externals
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(index, external)| {
+            let name = external.get("name")?.as_str()?;
+            (!is_valid_external_project_name(name)).then(|| Issue {
+                path: vec![
+                    Segment::Key(String::from("external_projects")),
+                    Segment::Index(index),
+                    Segment::Key(String::from("name")),
+                ],
+                message: String::from("must not be empty, . or .., or contain slashes"),
+            })
+        })
+        .collect()
+}
+
+// Records the position of every value of the JSON text by its path, the
+// position of the fields of objects is the position of their keys.
+struct Locator<'a> {
+    text: &'a str,
+    index: usize,
+    positions: HashMap<String, usize>,
+}
+
+impl Locator<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.index).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Option<()> {
+        self.skip_whitespace();
+        (self.peek() == Some(expected)).then(|| self.index += 1)
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        let start = self.index;
+        self.index += 1;
+        let mut escaped = false;
+        while let Some(byte) = self.peek() {
+            self.index += 1;
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => return serde_json::from_str(&self.text[start..self.index]).ok(),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn parse_value(&mut self, path: &str) -> Option<()> {
+        self.skip_whitespace();
+        self.positions.entry(path.to_string()).or_insert(self.index);
+        match self.peek()? {
+            b'{' => {
+                self.index += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.index += 1;
+                    return Some(());
+                }
+                loop {
+                    self.skip_whitespace();
+                    let start = self.index;
+                    let key = self.parse_string()?;
+                    let child = match path {
+                        "" => key,
+                        _ => format!("{}.{}", path, key),
+                    };
+                    self.positions.insert(child.clone(), start);
+                    self.expect(b':')?;
+                    self.parse_value(&child)?;
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b',' => self.index += 1,
+                        b'}' => break,
+                        _ => return None,
+                    }
+                }
+                self.index += 1;
+            }
+            b'[' => {
+                self.index += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.index += 1;
+                    return Some(());
+                }
+                for index in 0.. {
+                    self.parse_value(&format!("{}[{}]", path, index))?;
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b',' => self.index += 1,
+                        b']' => break,
+                        _ => return None,
+                    }
+                }
+                self.index += 1;
+            }
+            b'"' => {
+                self.parse_string()?;
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|byte| !b",]}".contains(&byte) && !byte.is_ascii_whitespace())
+                {
+                    self.index += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+// Returns the line and the column of every value of the JSON text, by path.
+fn locate_values(text: &str) -> HashMap<String, (usize, usize)> {
+    let masked = mask_jsonc(text);
+    let mut locator = Locator {
+        text: &masked,
+        index: 0,
+        positions: HashMap::new(),
+    };
+    locator.parse_value("");
+    locator
+        .positions
+        .into_iter()
+        .map(|(path, index)| {
+            let line_start = text[..index].rfind('\n').map_or(0, |newline| newline + 1);
+            let line = text[..index].matches('\n').count() + 1;
+            let column = text[line_start..index].chars().count() + 1;
+            (path, (line, column))
+        })
+        .collect()
+}
+
+fn find_position(
+    positions: &HashMap<String, (usize, usize)>,
+    path: &[Segment],
+) -> Option<(usize, usize)> {
+    (0..=path.len())
+        .rev()
+        .find_map(|length| positions.get(&format_path(&path[..length])))
+        .copied()
+}
+
+// Checks the project file against the fields and types barge understands,
+// and reports every problem with its location, instead of stopping at the
+// first one like loading the project does.
+pub(crate) fn validate_project_file(path: &str) -> Result<()> {
+    let mut value = read_project_value(path)?;
+    migrate_project_value(&mut value)?;
+    let mut issues = collect_issues(value.clone());
+    issues.extend(collect_name_issues(&value));
+    if issues.is_empty() {
+        color_println!(GREEN, "The project file {} is valid", path);
+        return Ok(());
+    }
+
+    let positions = match is_toml_file(path) {
+        true => HashMap::new(),
+        false => locate_values(&std::fs::read_to_string(path)?),
+    };
+    let mut issues: Vec<_> = issues
+        .into_iter()
+        .map(|issue| (find_position(&positions, &issue.path), issue))
+        .collect();
+    // The issues without a position (in barge.toml) keep their order.
+    issues.sort_by_key(|(position, _)| position.unwrap_or((usize::MAX, 0)));
+    for (position, issue) in &issues {
+        let location = match position {
+            Some((line, column)) => format!("{}:{}:{}", path, line, column),
+            None => path.to_string(),
+        };
+        match issue.path.is_empty() {
+            true => color_eprintln!("{}: {}", location, issue.message),
+            false => color_eprintln!(
+                "{}: {}: {}",
+                location,
+                format_path(&issue.path),
+                issue.message
+            ),
+        }
+    }
+    color_eprintln!("Found {} problem(s) in the project file", issues.len());
+    Err(BargeError::InvalidValue("The project file is not valid"))
+}
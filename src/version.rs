@@ -0,0 +1,231 @@
+use crate::jsonc::find_root_field;
+use crate::project::{find_project_file, is_toml_file, parse_edited_project, Project};
+use crate::result::{BargeError, Result};
+use crate::toml::{parse_toml_document, set_root_value};
+use crate::utilities::has_uncommitted_changes;
+use crate::{color_println, BLUE, GREEN, NO_COLOR};
+use std::path::Path;
+use std::process::Command;
+use toml_edit::Item;
+
+const TAG_PREFIX: &str = "v";
+
+// Splits a semantic version, like `1.2.3-rc.1+build.5`, into its major, minor,
+// and patch numbers, ignoring the pre-release and build metadata.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let (core, suffix) = version.split_at(version.find(['-', '+']).unwrap_or(version.len()));
+    if suffix.len() == 1
+        || !suffix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ['.', '-', '+'].contains(&c))
+    {
+        return None;
+    }
+    let numbers: Vec<u64> = core
+        .split('.')
+        .map(|number| number.parse().ok())
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [major, minor, patch] => Some((major, minor, patch)),
+        _ => None,
+    }
+}
+
+// Returns the version following the current one, where the part is either
+// `major`, `minor`, `patch`, or an explicit version. Like with other semantic
+// versioning tools, bumping a pre-release, like `2.0.0-rc.1`, releases it as
+// `2.0.0` if the lower parts are zero.
+fn bump_version(current: &str, part: &str) -> Result<String> {
+    let prerelease = current.split('+').next().unwrap_or_default().contains('-');
+    let bumped = match (part, parse_version(current)) {
+        ("major" | "minor" | "patch", None) => return Err(BargeError::InvalidValue(
+            "The version of the project is not a semantic version, give the new version explicitly",
+        )),
+        ("major", Some((major, 0, 0))) if prerelease => format!("{}.0.0", major),
+        ("major", Some((major, _, _))) => format!("{}.0.0", major + 1),
+        ("minor", Some((major, minor, 0))) if prerelease => format!("{}.{}.0", major, minor),
+        ("minor", Some((major, minor, _))) => format!("{}.{}.0", major, minor + 1),
+        ("patch", Some((major, minor, patch))) if prerelease => {
+            format!("{}.{}.{}", major, minor, patch)
+        }
+        ("patch", Some((major, minor, patch))) => format!("{}.{}.{}", major, minor, patch + 1),
+        (version, _) if parse_version(version).is_none() => {
+            return Err(BargeError::InvalidValue(
+                "The version must be major, minor, patch, or a semantic version like 1.2.3",
+            ))
+        }
+        (version, _) => version.to_string(),
+    };
+    if bumped == current {
+        return Err(BargeError::InvalidValue(
+            "The new version is the same as the current one",
+        ));
+    }
+    Ok(bumped)
+}
+
+// Replaces the value of the top-level version field in the JSON project file,
+// leaving the rest of the file (including its comments) untouched.
+fn replace_json_version(json: &str, current: &str, version: &str) -> Option<String> {
+    let range = find_root_field(json, "version")?;
+    if json[range.clone()] != serde_json::to_string(current).ok()? {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        &json[..range.start],
+        serde_json::to_string(version).ok()?,
+        &json[range.end..]
+    ))
+}
+
+// Replaces the value of the top-level version key in the TOML project file,
+// keeping its comments and formatting.
+fn replace_toml_version(toml: &str, current: &str, version: &str) -> Result<Option<String>> {
+    let mut document = parse_toml_document(toml)?;
+    if document.get("version").and_then(Item::as_str) != Some(current) {
+        return Ok(None);
+    }
+    set_root_value(&mut document, "version", version);
+    Ok(Some(document.to_string()))
+}
+
+// Returns the project file with the version replaced.
+fn update_project_file(path: &str, current: &str, version: &str) -> Result<String> {
+    let original = std::fs::read_to_string(path)?;
+    let edited = if is_toml_file(path) {
+        replace_toml_version(&original, current, version)?
+    } else {
+        replace_json_version(&original, current, version)
+    }
+    .ok_or(BargeError::InvalidValue(
+        "Could not locate the version field of the project file",
+    ))?;
+
+    // The top-level field has to be the one changed.
+    if parse_edited_project(path, &edited)?.version != version {
+        return Err(BargeError::InvalidValue(
+            "Could not locate the version field of the project file",
+        ));
+    }
+    Ok(edited)
+}
+
+// Updates the version in the header, both as a string literal, and as the
+// numbers of the macros ending in VERSION_MAJOR, VERSION_MINOR, and
+// VERSION_PATCH. Returns the header with the version replaced.
+fn update_version_header(path: &str, current: &str, version: &str) -> Result<String> {
+    let original = std::fs::read_to_string(path)?;
+    let numbers = parse_version(version);
+    let mut edited = String::new();
+    for line in original.split_inclusive('\n') {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = match (words.as_slice(), numbers) {
+            (["#define", name, _], Some((major, minor, patch))) => {
+                if name.ends_with("VERSION_MAJOR") {
+                    Some(major)
+                } else if name.ends_with("VERSION_MINOR") {
+                    Some(minor)
+                } else if name.ends_with("VERSION_PATCH") {
+                    Some(patch)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        match (number, words.get(2)) {
+            (Some(number), Some(value)) => {
+                let index = line.rfind(value).unwrap_or_default();
+                edited += &format!(
+                    "{}{}{}",
+                    &line[..index],
+                    number,
+                    &line[index + value.len()..]
+                );
+            }
+            _ => edited += &line.replace(&format!("\"{}\"", current), &format!("\"{}\"", version)),
+        }
+    }
+    if edited == original {
+        return Err(BargeError::InvalidValue(
+            "The version header does not contain the version of the project",
+        ));
+    }
+    Ok(edited)
+}
+
+fn run_git(args: &[&str]) -> Result<()> {
+    let status = Command::new("git").args(args).status()?;
+    if !status.success() {
+        return Err(BargeError::FailedOperation("Could not run git"));
+    }
+    Ok(())
+}
+
+fn has_tag(tag: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--quiet", "--verify"])
+        .arg(format!("refs/tags/{}", tag))
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+// Bumps the version of the project in the project file and the version header,
+// then commits the changes and creates an annotated tag for the new version,
+// unless git is not used.
+pub(crate) fn bump_project_version(project: &Project, part: &str, use_git: bool) -> Result<()> {
+    let version = bump_version(&project.version, part)?;
+    let tag = format!("{}{}", TAG_PREFIX, version);
+    if use_git {
+        if has_uncommitted_changes()? {
+            return Err(BargeError::FailedOperation(
+                "Commit or stash the changes before bumping the version, or pass --no-git",
+            ));
+        }
+        if has_tag(&tag) {
+            return Err(BargeError::FailedOperation(
+                "The tag of the new version already exists",
+            ));
+        }
+    }
+
+    let path = find_project_file(Path::new(""))
+        .ok_or(BargeError::ProjectNotFound("Project file not found."))?
+        .to_string_lossy()
+        .to_string();
+    // Both files are edited before writing either, so that a failure does not
+    // leave the version bumped in only one of them.
+    let mut edits = vec![(
+        path.clone(),
+        update_project_file(&path, &project.version, &version)?,
+    )];
+    if let Some(header) = &project.version_header {
+        edits.push((
+            header.clone(),
+            update_version_header(header, &project.version, &version)?,
+        ));
+    }
+    for (file, content) in &edits {
+        std::fs::write(file, content)?;
+    }
+    let files: Vec<String> = edits.into_iter().map(|(file, _)| file).collect();
+    color_println!(
+        BLUE,
+        "Bumped the version of {} from {} to {}",
+        project.name,
+        project.version,
+        version
+    );
+
+    if use_git {
+        let message = format!("Bump version to {}", version);
+        let mut add = vec!["add", "--"];
+        add.extend(files.iter().map(String::as_str));
+        run_git(&add)?;
+        run_git(&["commit", "--quiet", "-m", &message])?;
+        run_git(&["tag", "-a", &tag, "-m", &format!("Version {}", version)])?;
+        color_println!(GREEN, "Committed the changes and created the tag {}", tag);
+    }
+    Ok(())
+}
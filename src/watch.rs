@@ -0,0 +1,157 @@
+use crate::fastpath::{get_source_fingerprint, WATCHED_DIRECTORIES};
+use crate::makefile::BuildTarget;
+use crate::output::is_accessible_output;
+use crate::project::Project;
+use crate::result::{print_error, BargeError, Result};
+use crate::{color_eprintln, color_println, BLUE, GREEN, NO_COLOR, RED, WHITE};
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::Child;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+// Editors often write files in several steps, so the changes are reported
+// once the tree settles.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(200);
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum WatchAction {
+    Build,
+    Run,
+    Test,
+}
+
+impl WatchAction {
+    // The directories watched besides the sources and the project file.
+    fn get_directories(self) -> &'static [&'static str] {
+        match self {
+            WatchAction::Test => &["tests"],
+            _ => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for WatchAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchAction::Build => write!(f, "Build"),
+            WatchAction::Run => write!(f, "Run"),
+            WatchAction::Test => write!(f, "Test"),
+        }
+    }
+}
+
+fn report_watch_error(error: impl std::fmt::Display) -> BargeError {
+    color_eprintln!("{}", error);
+    BargeError::FailedOperation("Could not watch the project for changes")
+}
+
+// Waits for the notifications of the file system about the project file and
+// the sources, and returns the new fingerprint of the tree once it differs
+// from the previous one. The project file is watched through the project
+// directory, as editors replace it instead of writing it in-place.
+pub(crate) fn wait_for_changes(previous: &str, directories: &[&str]) -> Result<String> {
+    let (sender, receiver) = channel();
+    let mut debouncer = new_debouncer(DEBOUNCE_INTERVAL, sender).map_err(report_watch_error)?;
+    let watcher = debouncer.watcher();
+    watcher
+        .watch(Path::new("."), RecursiveMode::NonRecursive)
+        .map_err(report_watch_error)?;
+    for directory in WATCHED_DIRECTORIES.iter().chain(directories) {
+        if Path::new(directory).is_dir() {
+            watcher
+                .watch(Path::new(directory), RecursiveMode::Recursive)
+                .map_err(report_watch_error)?;
+        }
+    }
+
+    // The changes made before the watches were set up are only seen by the
+    // fingerprint, and the notifications may not change any of the files.
+    loop {
+        let current = get_source_fingerprint(directories);
+        if current != previous {
+            return Ok(current);
+        }
+        match receiver.recv() {
+            Ok(Ok(_)) => {}
+            Ok(Err(error)) => return Err(report_watch_error(error)),
+            Err(error) => return Err(report_watch_error(error)),
+        }
+    }
+}
+
+// Returns the executable started by the run action, which is stopped once
+// the project changes.
+fn run_action(
+    project: &Project,
+    target: BuildTarget,
+    action: WatchAction,
+) -> Result<Option<Child>> {
+    match action {
+        WatchAction::Build => project.build(target).map(|_| None),
+        WatchAction::Run => project.spawn(target, vec![], None),
+        WatchAction::Test => project.test(target, None).map(|_| None),
+    }
+}
+
+fn stop_running_executable(child: Option<Child>) {
+    let Some(mut child) = child else {
+        return;
+    };
+    if let Ok(None) = child.try_wait() {
+        color_println!(WHITE, "Stopping the running executable");
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
+
+pub(crate) fn watch_project<F>(target: BuildTarget, action: WatchAction, load: F) -> Result<()>
+where
+    F: Fn() -> Result<Project>,
+{
+    let directories = action.get_directories();
+    let clear_screen = std::io::stdout().is_terminal() && !is_accessible_output();
+    let mut fingerprint = get_source_fingerprint(directories);
+    loop {
+        let time = chrono::Local::now().format("%H:%M:%S");
+        let child = match load().and_then(|project| run_action(&project, target, action)) {
+            Ok(Some(child)) => {
+                color_println!(GREEN, "[{}] {} started", time, action);
+                Some(child)
+            }
+            Ok(None) => {
+                color_println!(GREEN, "[{}] {} succeeded", time, action);
+                None
+            }
+            Err(error) => {
+                print_error(&error);
+                color_eprintln!("[{}] {} failed", time, action);
+                None
+            }
+        };
+
+        color_println!(
+            BLUE,
+            "Watching src, include, {}and the project file for changes (press Ctrl-C to stop)",
+            directories
+                .iter()
+                .map(|directory| format!("{}, ", directory))
+                .collect::<String>()
+        );
+        let changes = wait_for_changes(&fingerprint, directories);
+        stop_running_executable(child);
+        fingerprint = changes?;
+        if clear_screen {
+            print!("{}", CLEAR_SCREEN);
+            let _ = std::io::stdout().flush();
+        }
+        color_println!(
+            WHITE,
+            "[{}] Changes detected",
+            chrono::Local::now().format("%H:%M:%S")
+        );
+    }
+}
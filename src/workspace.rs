@@ -0,0 +1,75 @@
+use crate::project::Project;
+use crate::result::{print_error, BargeError, Result};
+use crate::{color_println, BLUE, NO_COLOR};
+use std::path::Path;
+
+fn is_selected(project: &Project, directory: &str, member: Option<&String>) -> bool {
+    member.is_none_or(|member| {
+        *member == project.name || Path::new(member) == Path::new(directory.trim_end_matches('/'))
+    })
+}
+
+// Runs the action for the project, and for each of its workspace members, in
+// their own directories. With a member name (or directory) given, only that
+// member is processed. The remaining members are processed even if one of
+// them fails, and the failure is reported at the end.
+pub(crate) fn for_each_member(
+    project: &Project,
+    member: Option<&String>,
+    load: impl Fn() -> Result<Project>,
+    action: impl Fn(&Project) -> Result<()>,
+) -> Result<()> {
+    let Some(members) = &project.workspace_members else {
+        if member.is_some() {
+            return Err(BargeError::InvalidValue(
+                "The project does not have workspace members",
+            ));
+        }
+        return action(project);
+    };
+
+    let mut processed = 0;
+    let mut failed = 0;
+    if is_selected(project, ".", member) {
+        color_println!(BLUE, "Workspace root {}", project.name);
+        if let Err(error) = action(project) {
+            print_error(&error);
+            failed += 1;
+        }
+        processed += 1;
+    }
+
+    let root = std::env::current_dir()?;
+    for directory in members {
+        std::env::set_current_dir(root.join(directory))?;
+        let result = load().and_then(|member_project| {
+            if !is_selected(&member_project, directory, member) {
+                return Ok(());
+            }
+            if processed > 0 {
+                println!();
+            }
+            color_println!(
+                BLUE,
+                "Workspace member {} ({})",
+                member_project.name,
+                directory
+            );
+            processed += 1;
+            action(&member_project)
+        });
+        std::env::set_current_dir(&root)?;
+        if let Err(error) = result {
+            print_error(&error);
+            failed += 1;
+        }
+    }
+
+    match (processed, failed) {
+        (0, _) => Err(BargeError::InvalidValue("Unknown workspace member")),
+        (_, 0) => Ok(()),
+        _ => Err(BargeError::FailedOperation(
+            "One or more workspace members failed",
+        )),
+    }
+}
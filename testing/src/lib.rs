@@ -0,0 +1,310 @@
+// Helpers for testing barge (and tools built around it) end to end: temporary
+// projects created by `barge init` or copied from a fixture directory, running
+// subcommands on them, and checking the produced artifacts and build events.
+
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const EVENT_LOG: &str = "events.jsonl";
+
+// The environment variables overriding the project file are cleared, so that
+// the tests behave the same on every machine.
+const OVERRIDE_VARIABLES: [&str; 6] = [
+    "BARGE_PROFILE",
+    "BARGE_TOOLSET",
+    "BARGE_TARGET",
+    "BARGE_COMPILER_LAUNCHER",
+    "BARGE_JOBS",
+    "BARGE_ACCESSIBLE",
+];
+
+static FIXTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// An event of a build, as written to the file named by BARGE_EVENT_LOG.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Step { message: String },
+    Diagnostic { message: String },
+    Artifact { path: PathBuf },
+    Finish { outcome: String, seconds: f64 },
+}
+
+// The result of running a subcommand.
+#[derive(Debug)]
+pub struct Outcome {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    pub fn assert_success(&self) -> &Self {
+        assert!(self.success(), "barge failed unexpectedly\n{}", self);
+        self
+    }
+
+    pub fn assert_failure(&self) -> &Self {
+        assert!(!self.success(), "barge succeeded unexpectedly\n{}", self);
+        self
+    }
+
+    pub fn assert_stdout_contains(&self, text: &str) -> &Self {
+        assert!(
+            self.stdout.contains(text),
+            "The standard output does not contain {:?}\n{}",
+            text,
+            self
+        );
+        self
+    }
+
+    pub fn assert_stderr_contains(&self, text: &str) -> &Self {
+        assert!(
+            self.stderr.contains(text),
+            "The standard error does not contain {:?}\n{}",
+            text,
+            self
+        );
+        self
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "status: {}\nstdout:\n{}\nstderr:\n{}",
+            self.status, self.stdout, self.stderr
+        )
+    }
+}
+
+// Returns whether the program can be found in PATH, so that the tests using
+// optional tools (for example, a toolset) can be skipped without them.
+pub fn has_executable(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|directory| directory.join(program).is_file())
+    })
+}
+
+fn copy_directory(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+// A project in a temporary directory, which is removed when the fixture is
+// dropped.
+pub struct Fixture {
+    root: PathBuf,
+    project: PathBuf,
+    barge: PathBuf,
+    environment: Vec<(String, String)>,
+}
+
+impl Fixture {
+    fn create_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "barge-fixture-{}-{}",
+            std::process::id(),
+            FIXTURE_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).expect("Could not create the fixture directory");
+        root
+    }
+
+    // Creates a new project with `barge init`. The type is the one accepted by
+    // init (executable, shared-lib, or static-lib).
+    pub fn init(barge: impl Into<PathBuf>, name: &str, project_type: &str) -> Self {
+        let root = Self::create_root();
+        let fixture = Self {
+            project: root.join(name),
+            root,
+            barge: barge.into(),
+            environment: Vec::new(),
+        };
+        fixture
+            .run_in(&fixture.root, &["init", name, project_type])
+            .assert_success();
+        fixture
+    }
+
+    // Copies the given directory (containing a project file and its sources)
+    // into a temporary directory.
+    pub fn from_directory(barge: impl Into<PathBuf>, fixture: impl AsRef<Path>) -> Self {
+        let root = Self::create_root();
+        let name = fixture
+            .as_ref()
+            .file_name()
+            .expect("The fixture directory must have a name");
+        let project = root.join(name);
+        copy_directory(fixture.as_ref(), &project).expect("Could not copy the fixture");
+        Self {
+            root,
+            project,
+            barge: barge.into(),
+            environment: Vec::new(),
+        }
+    }
+
+    // Sets an environment variable for every subcommand run on the fixture,
+    // for example, BARGE_TOOLSET.
+    pub fn with_env(mut self, name: &str, value: &str) -> Self {
+        self.environment.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.project
+    }
+
+    pub fn write_file(&self, relative: impl AsRef<Path>, content: &str) {
+        let path = self.project.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Could not create the directory");
+        }
+        std::fs::write(path, content).expect("Could not write the file");
+    }
+
+    // Writes the per-user configuration seen by the subcommands run on the
+    // fixture, instead of the one of the user running the tests.
+    pub fn write_user_config(&self, content: &str) {
+        let path = self.root.join("config/barge/config.json");
+        std::fs::create_dir_all(path.parent().expect("The path has a parent"))
+            .expect("Could not create the directory");
+        std::fs::write(path, content).expect("Could not write the user configuration");
+    }
+
+    pub fn read_project(&self) -> serde_json::Value {
+        let content = std::fs::read_to_string(self.project.join("barge.json"))
+            .expect("Could not read barge.json");
+        serde_json::from_str(&content).expect("Could not parse barge.json")
+    }
+
+    // Changes the fields of barge.json, for example, to set its toolset.
+    pub fn edit_project(&self, edit: impl FnOnce(&mut serde_json::Value)) {
+        let mut project = self.read_project();
+        edit(&mut project);
+        let content = serde_json::to_string_pretty(&project).expect("Could not write barge.json");
+        self.write_file("barge.json", &content);
+    }
+
+    // Runs barge with the given arguments in the given directory, which does
+    // not have to be in the fixture, for example, to test -C.
+    pub fn run_in(&self, directory: &Path, arguments: &[&str]) -> Outcome {
+        let mut command = Command::new(&self.barge);
+        for variable in OVERRIDE_VARIABLES {
+            command.env_remove(variable);
+        }
+        let output = command
+            .args(arguments)
+            .current_dir(directory)
+            .env("BARGE_ALLOW_ROOT", "1")
+            .env("NO_COLOR", "1")
+            .env("BARGE_EVENT_LOG", self.root.join(EVENT_LOG))
+            // The configuration of the user running the tests is not used.
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .envs(self.environment.iter().map(|(name, value)| (name, value)))
+            .output()
+            .expect("Could not execute barge");
+        Outcome {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+
+    // Runs barge with the given arguments in the project directory.
+    pub fn run(&self, arguments: &[&str]) -> Outcome {
+        self.run_in(&self.project, arguments)
+    }
+
+    // Runs git with the given arguments in the project directory, committing as
+    // a fixed user, and returns its standard output.
+    pub fn git(&self, arguments: &[&str]) -> String {
+        let output = Command::new("git")
+            .args([
+                "-c",
+                "user.name=barge",
+                "-c",
+                "user.email=barge@example.com",
+            ])
+            .args(arguments)
+            .current_dir(&self.project)
+            .output()
+            .expect("Could not execute git");
+        assert!(output.status.success(), "git {:?} failed", arguments);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    // Installs the script as an executable into the tools directory of the
+    // project, where barge looks for tools first, to stand in for a real one.
+    pub fn install_tool(&self, name: &str, script: &str) {
+        let relative = format!("tools/{}", name);
+        self.write_file(&relative, script);
+        let path = self.project.join(relative);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("Could not make the tool executable");
+    }
+
+    // Returns the path of a file in the build directory of the given target.
+    pub fn artifact(&self, target: &str, name: &str) -> PathBuf {
+        self.project.join("build").join(target).join(name)
+    }
+
+    pub fn assert_exists(&self, relative: impl AsRef<Path>) {
+        let path = self.project.join(relative);
+        assert!(path.exists(), "{} does not exist", path.display());
+    }
+
+    pub fn assert_missing(&self, relative: impl AsRef<Path>) {
+        let path = self.project.join(relative);
+        assert!(!path.exists(), "{} exists", path.display());
+    }
+
+    // Returns the build events of the subcommands run so far, and forgets them.
+    pub fn take_events(&self) -> Vec<Event> {
+        let path = self.root.join(EVENT_LOG);
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let _ = std::fs::remove_file(path);
+        content
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Malformed build event"))
+            .collect()
+    }
+
+    // Returns the outcomes (up_to_date, succeeded, or failed) of the builds
+    // run so far, and forgets the events.
+    pub fn take_build_outcomes(&self) -> Vec<String> {
+        self.take_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Finish { outcome, .. } => Some(outcome),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,221 @@
+use barge_testing::{has_executable, Fixture};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+// Stands in for clang-tidy in the tools directory of the project, recording its
+// arguments, reporting a warning treated as an error if the configuration asks
+// for it, and replacing std::endl with a newline as a fix.
+const CLANG_TIDY: &str = r#"#!/bin/sh
+echo "$@" >> tidy.log
+fix=0
+for argument in "$@"; do
+    case "$argument" in
+        -fix|-fix-errors) fix=1 ;;
+        --) break ;;
+        -*) ;;
+        *) source="$argument" ;;
+    esac
+done
+if [ $fix -eq 1 ]; then
+    sed -i 's/std::endl/'"'"'\\n'"'"'/' "$source"
+fi
+if grep -q "WarningsAsErrors" .clang-tidy 2>/dev/null; then
+    echo "$source:1:1: error: suspicious code [bugprone-example,-warnings-as-errors]"
+fi
+"#;
+
+fn fixture_with_clang_tidy() -> Fixture {
+    let fixture = Fixture::init(BARGE, "analyzed", "executable");
+    fixture.install_tool("clang-tidy", CLANG_TIDY);
+    fixture
+}
+
+#[test]
+fn clang_tidy_gets_the_compile_flags_of_the_build() {
+    let fixture = fixture_with_clang_tidy();
+    fixture.edit_project(|project| project["custom_cxxflags"] = serde_json::json!("-DANALYZED"));
+    fixture
+        .run(&["analyze"])
+        .assert_success()
+        .assert_stdout_contains("No diagnostics were reported");
+    let log = std::fs::read_to_string(fixture.path().join("tidy.log")).unwrap();
+    assert!(log.contains("--quiet src/main.cpp -- "), "{}", log);
+    assert!(log.contains("-DANALYZED"), "{}", log);
+    assert!(log.contains("-Iinclude"), "{}", log);
+    assert!(log.contains("-std=c++"), "{}", log);
+}
+
+#[test]
+fn cppcheck_runs_only_when_requested() {
+    let fixture = fixture_with_clang_tidy();
+    fixture.install_tool("cppcheck", "#!/bin/sh\necho \"$@\" >> cppcheck.log\n");
+    fixture.run(&["analyze"]).assert_success();
+    fixture.assert_missing("cppcheck.log");
+    std::fs::remove_file(fixture.path().join("tidy.log")).unwrap();
+
+    fixture.edit_project(|project| {
+        project["static_analyzers"] = serde_json::json!(["cppcheck"]);
+        project["custom_cxxflags"] = serde_json::json!("-DANALYZED -Wshadow");
+    });
+    fixture.run(&["analyze"]).assert_success();
+    let log = std::fs::read_to_string(fixture.path().join("cppcheck.log")).unwrap();
+    assert!(log.contains("--std=c++"), "{}", log);
+    assert!(log.contains("-Iinclude -Isrc"), "{}", log);
+    assert!(log.contains("-DANALYZED src/main.cpp"), "{}", log);
+    assert!(!log.contains("-Wshadow"), "{}", log);
+    fixture.assert_missing("tidy.log");
+
+    fixture
+        .run(&["analyze", "--fix"])
+        .assert_failure()
+        .assert_stderr_contains("Fixes can only be applied by clang-tidy");
+}
+
+#[test]
+fn clang_tidy_file_is_generated_from_the_project_file() {
+    let fixture = fixture_with_clang_tidy();
+    fixture.edit_project(|project| {
+        project["clang_tidy"] = serde_json::json!({
+            "checks": ["bugprone-*", "modernize-*"],
+            "disabled_checks": ["modernize-use-trailing-return-type"],
+            "warnings_as_errors": ["bugprone-*"]
+        })
+    });
+    fixture
+        .run(&["analyze"])
+        .assert_failure()
+        .assert_stdout_contains("Updated .clang-tidy from the project file")
+        .assert_stdout_contains("-warnings-as-errors]");
+    let configuration = std::fs::read_to_string(fixture.path().join(".clang-tidy")).unwrap();
+    assert!(configuration
+        .contains("Checks: 'bugprone-*,modernize-*,-modernize-use-trailing-return-type'\n"));
+    assert!(configuration.contains("WarningsAsErrors: 'bugprone-*'\n"));
+
+    // A hand-written file is honored instead.
+    fixture.write_file(".clang-tidy", "Checks: 'bugprone-*'\n");
+    fixture
+        .run(&["analyze"])
+        .assert_success()
+        .assert_stdout_contains("was not generated by barge");
+}
+
+#[test]
+fn diagnostics_are_written_into_a_sarif_report() {
+    let fixture = fixture_with_clang_tidy();
+    fixture.write_file(".clang-tidy", "WarningsAsErrors: 'bugprone-*'\n");
+    fixture
+        .run(&["analyze", "--output", "sarif:build/report.sarif"])
+        .assert_failure()
+        .assert_stdout_contains("Wrote the SARIF report to build/report.sarif");
+    let report = std::fs::read_to_string(fixture.path().join("build/report.sarif")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(report["version"], "2.1.0");
+    let run = &report["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "clang-tidy");
+    assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "bugprone-example");
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "bugprone-example");
+    assert_eq!(result["level"], "error");
+    assert_eq!(result["message"]["text"], "suspicious code");
+    let location = &result["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "src/main.cpp");
+    assert_eq!(location["region"]["startLine"], 1);
+
+    fixture
+        .run(&["analyze", "--output", "report.sarif"])
+        .assert_failure()
+        .assert_stderr_contains("the supported format is sarif:<PATH>");
+}
+
+#[test]
+fn fixes_are_applied_to_a_clean_working_tree() {
+    if !has_executable("git") {
+        return;
+    }
+    let fixture = fixture_with_clang_tidy();
+    let main = fixture.path().join("src/main.cpp");
+    fixture.git(&["add", "-A"]);
+    fixture.git(&["commit", "-q", "-m", "Initial commit"]);
+
+    fixture.run(&["analyze", "--fix"]).assert_success();
+    assert!(std::fs::read_to_string(&main).unwrap().contains("'\\n'"));
+    let log = std::fs::read_to_string(fixture.path().join("tidy.log")).unwrap();
+    assert!(log.contains("--quiet -fix src/main.cpp --"), "{}", log);
+
+    // The fixes of the previous run are not committed yet.
+    fixture
+        .run(&["analyze", "--fix-errors"])
+        .assert_failure()
+        .assert_stderr_contains("before applying fixes, or pass --allow-dirty");
+    fixture
+        .run(&["analyze", "--fix-errors", "--allow-dirty"])
+        .assert_success();
+    let log = std::fs::read_to_string(fixture.path().join("tidy.log")).unwrap();
+    assert!(
+        log.contains("--quiet -fix-errors src/main.cpp --"),
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn changed_analyzes_the_sources_affected_by_the_changes() {
+    if !has_executable("git") {
+        return;
+    }
+    let fixture = fixture_with_clang_tidy();
+    fixture.write_file("include/util.h", "#pragma once\n\nint util();\n");
+    fixture.write_file(
+        "src/util.cpp",
+        "#include \"util.h\"\n\nint util() { return 1; }\n",
+    );
+    fixture.write_file("src/other.cpp", "int other() { return 2; }\n");
+    fixture.git(&["add", "-A"]);
+    fixture.git(&["commit", "-q", "-m", "Initial commit"]);
+    fixture.run(&["analyze"]).assert_success();
+    fixture
+        .run(&["analyze", "--changed"])
+        .assert_success()
+        .assert_stdout_contains("No source files were changed since HEAD");
+
+    fixture.write_file("include/util.h", "#pragma once\n\nint util(void);\n");
+    let outcome = fixture.run(&["analyze", "--changed", "HEAD"]);
+    outcome
+        .assert_success()
+        .assert_stdout_contains("clang-tidy src/util.cpp");
+    assert!(!outcome.stdout.contains("src/other.cpp"));
+    assert!(!outcome.stdout.contains("src/main.cpp"));
+
+    // The results of the sources left out are still cached.
+    fixture
+        .run(&["analyze"])
+        .assert_success()
+        .assert_stdout_contains("clang-tidy src/other.cpp (cached)")
+        .assert_stdout_contains("clang-tidy src/main.cpp (cached)");
+}
+
+#[test]
+fn analysis_jobs_must_be_positive() {
+    let fixture = fixture_with_clang_tidy();
+    fixture
+        .run(&["analyze", "-j", "0"])
+        .assert_failure()
+        .assert_stdout_contains("invalid value '0'");
+    fixture
+        .run(&["analyze", "-j", "2"])
+        .assert_success()
+        .assert_stdout_contains("(1 job(s))");
+}
+
+#[test]
+fn include_guards_after_a_comment_are_detected() {
+    let fixture = Fixture::init(BARGE, "guarded", "executable");
+    fixture.write_file(
+        "include/guarded.h",
+        "/* Copyright. */ #ifndef GUARDED_H\n#define GUARDED_H\nint guarded;\n#endif\n",
+    );
+    fixture
+        .run(&["analyze", "--include-guards"])
+        .assert_failure()
+        .assert_stderr_contains("include/guarded.h: expected #pragma once, found include guard");
+}
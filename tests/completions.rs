@@ -0,0 +1,61 @@
+use barge_testing::{has_executable, Fixture};
+use std::process::Command;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+#[test]
+fn completion_scripts_list_the_subcommands() {
+    let fixture = Fixture::init(BARGE, "completions", "executable");
+    for shell in ["bash", "zsh", "fish"] {
+        let outcome = fixture.run(&["completions", shell]);
+        outcome
+            .assert_success()
+            .assert_stdout_contains("rebuild")
+            .assert_stdout_contains("_values bins");
+        assert!(!outcome.stdout.contains("bisect-step"));
+    }
+    fixture.run(&["completions", "tcsh"]).assert_failure();
+
+    if has_executable("bash") {
+        let script = fixture.path().join("barge.bash");
+        std::fs::write(&script, fixture.run(&["completions", "bash"]).stdout).unwrap();
+        let status = Command::new("bash")
+            .arg("-n")
+            .arg(&script)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
+
+#[test]
+fn names_are_completed_from_the_project_file() {
+    let fixture = Fixture::init(BARGE, "completions", "executable");
+    fixture.edit_project(|project| {
+        project["bins"] = serde_json::json!([{ "name": "tool", "main": "src/tool.cpp" }]);
+        project["artifacts"] = serde_json::json!({ "docs": "build/docs" });
+    });
+    fixture
+        .run(&["complete-values", "bins"])
+        .assert_success()
+        .assert_stdout_contains("tool");
+    let outcome = fixture.run(&["complete-values", "artifacts"]);
+    assert_eq!(outcome.stdout, "docs\n");
+
+    // Outside of a project, there is nothing to complete.
+    let outcome = fixture.run_in(&std::env::temp_dir(), &["complete-values", "bins"]);
+    outcome.assert_success();
+    assert!(outcome.stdout.is_empty());
+}
+
+#[test]
+fn man_page_describes_the_subcommands() {
+    let fixture = Fixture::init(BARGE, "manual", "executable");
+    let outcome = fixture.run(&["gen-man"]);
+    outcome
+        .assert_success()
+        .assert_stdout_contains(".TH BARGE 1")
+        .assert_stdout_contains(".SS \\fBbarge build\\fR")
+        .assert_stdout_contains("\\fB\\-j\\fR, \\fB\\-\\-jobs\\fR \\fIJOBS\\fR");
+    assert!(!outcome.stdout.contains("gen\\-man"));
+}
@@ -0,0 +1,35 @@
+use barge_testing::Fixture;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+// Stands in for doxygen, writing the index of the HTML documentation into the
+// directory configured by the Doxyfile.
+const DOXYGEN: &str = r#"#!/bin/sh
+mkdir -p docs/api
+echo "<html></html>" > docs/api/index.html
+"#;
+
+const BROWSER: &str = "#!/bin/sh\necho \"$1\" > opened.txt\n";
+
+#[test]
+fn open_shows_the_index_of_the_documentation() {
+    let fixture = Fixture::init(BARGE, "documented", "executable").with_env("BROWSER", "browser");
+    fixture.install_tool("doxygen", DOXYGEN);
+    fixture.install_tool("browser", BROWSER);
+    fixture.write_file(
+        "Doxyfile",
+        "OUTPUT_DIRECTORY = build/doc\nOUTPUT_DIRECTORY = docs\nHTML_OUTPUT = \"api\"\n",
+    );
+    fixture
+        .run(&["doc", "--open"])
+        .assert_success()
+        .assert_stdout_contains("Project documentation successfully generated");
+    let opened = std::fs::read_to_string(fixture.path().join("opened.txt")).unwrap();
+    assert!(
+        opened.trim_end().ends_with("docs/api/index.html"),
+        "{}",
+        opened
+    );
+
+    fixture.run(&["doc", "--watch"]).assert_failure();
+}
@@ -0,0 +1,279 @@
+use barge_testing::{has_executable, Event, Fixture};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+// The toolsets with their C++ compilers, the tests are skipped for the ones
+// which are not installed.
+const TOOLSETS: [(&str, &str); 2] = [("gnu", "g++"), ("llvm", "clang++")];
+
+fn available_toolsets() -> impl Iterator<Item = &'static str> {
+    TOOLSETS
+        .into_iter()
+        .filter(|(_, compiler)| has_executable(compiler))
+        .map(|(toolset, _)| toolset)
+}
+
+fn init_with_toolset(name: &str, project_type: &str, toolset: &str) -> Fixture {
+    let fixture = Fixture::init(BARGE, name, project_type);
+    fixture.edit_project(|project| project["toolset"] = toolset.into());
+    fixture
+}
+
+#[test]
+fn init_creates_project() {
+    let fixture = Fixture::init(BARGE, "hello", "executable");
+    fixture.assert_exists("barge.json");
+    fixture.assert_exists("src/main.cpp");
+    let project = fixture.read_project();
+    assert_eq!(project["name"], "hello");
+    assert_eq!(project["project_type"], "executable");
+}
+
+#[test]
+fn build_run_and_clean() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.run(&["build", "release"]).assert_success();
+        assert!(fixture.artifact("release", "hello").is_file());
+
+        let events = fixture.take_events();
+        assert!(events.contains(&Event::Artifact {
+            path: "build/release/hello".into()
+        }));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, Event::Finish { outcome, .. } if outcome == "succeeded")));
+
+        fixture
+            .run(&["run", "release"])
+            .assert_success()
+            .assert_stdout_contains("Hello, world!");
+
+        fixture.run(&["clean"]).assert_success();
+        fixture.assert_missing("build");
+    }
+}
+
+#[test]
+fn second_build_is_up_to_date() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.run(&["build", "release"]).assert_success();
+        fixture.run(&["build", "release"]).assert_success();
+        assert_eq!(fixture.take_build_outcomes(), ["succeeded", "up_to_date"]);
+    }
+}
+
+// Stands in for pkg-config in the tools directory of the project, resolving
+// every package to the fake.pc file next to it.
+const PKG_CONFIG: &str = r#"#!/bin/sh
+pc="$(dirname "$0")/fake.pc"
+case "$*" in
+    --path*) echo "$pc" ;;
+    *--cflags*) sed -n 's/^Cflags: //p' "$pc" ;;
+    *) sed -n 's/^Libs: //p' "$pc" ;;
+esac
+"#;
+
+#[test]
+fn pkg_config_libraries_are_fingerprinted_by_their_pc_files() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.install_tool("pkg-config", PKG_CONFIG);
+        fixture.write_file("tools/fake.pc", "Cflags: -DVERSION=1\nLibs: \n");
+        fixture.edit_project(|project| {
+            project["external_libraries"] =
+                serde_json::json!([{ "type": "pkg_config", "name": "fake" }]);
+        });
+        fixture.run(&["build", "release"]).assert_success();
+        fixture.run(&["build", "release"]).assert_success();
+        fixture.write_file("tools/fake.pc", "Cflags: -DVERSION=22\nLibs: \n");
+        fixture.run(&["build", "release"]).assert_success();
+        assert_eq!(
+            fixture.take_build_outcomes(),
+            ["succeeded", "up_to_date", "succeeded"]
+        );
+    }
+}
+
+#[test]
+fn fresh_deps_keeps_the_objects() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.run(&["build", "release"]).assert_success();
+        let outcome = fixture.run(&["build", "release", "--fresh-deps"]);
+        outcome.assert_success();
+        assert!(!outcome.stdout.contains("Building C++ object"));
+    }
+}
+
+#[test]
+fn touched_sources_are_not_rebuilt() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.run(&["build", "release"]).assert_success();
+        let main = fixture.path().join("src/main.cpp");
+        std::fs::File::options()
+            .write(true)
+            .open(&main)
+            .and_then(|file| file.set_modified(std::time::SystemTime::now()))
+            .unwrap();
+        let outcome = fixture.run(&["build", "release"]);
+        outcome.assert_success();
+        assert!(!outcome.stdout.contains("Building C++ object"));
+        assert!(!outcome.stdout.contains("Linking executable"));
+        fixture.run(&["build", "release"]).assert_success();
+        assert_eq!(
+            fixture.take_build_outcomes(),
+            ["succeeded", "succeeded", "up_to_date"]
+        );
+    }
+}
+
+#[test]
+fn artifact_path_placeholders_are_expanded() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.edit_project(|project| {
+            project["artifact_path"] = "dist/{name}-{bits}-{target}{variant}".into()
+        });
+        fixture.run(&["build", "release"]).assert_success();
+        fixture.assert_exists(format!("dist/hello-{}-native", usize::BITS));
+    }
+}
+
+#[test]
+fn converted_executables_are_the_artifact() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("firmware", "executable", toolset);
+        fixture.edit_project(|project| {
+            project["output_format"] = "bin".into();
+            project["artifact_path"] = "dist/{name}{ext}".into();
+        });
+        fixture.run(&["build", "release"]).assert_success();
+        fixture.assert_exists("dist/firmware.bin");
+        fixture
+            .run(&["size", "release"])
+            .assert_success()
+            .assert_stdout_contains("Converted image build/release/firmware.bin");
+    }
+}
+
+#[test]
+fn libraries_are_built() {
+    for toolset in available_toolsets() {
+        for (project_type, artifact) in [("static-lib", "libmath.a"), ("shared-lib", "libmath.so")]
+        {
+            let fixture = init_with_toolset("math", project_type, toolset);
+            fixture.run(&["build", "release"]).assert_success();
+            assert!(fixture.artifact("release", artifact).is_file());
+        }
+    }
+}
+
+#[test]
+fn compile_errors_fail_the_build() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("broken", "executable", toolset);
+        fixture.write_file("src/main.cpp", "int main() { return undeclared; }\n");
+        fixture.run(&["build", "release"]).assert_failure();
+        let events = fixture.take_events();
+        assert!(events.iter().any(
+            |event| matches!(event, Event::Diagnostic { message } if message.contains("undeclared"))
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(Event::Finish { outcome, .. }) if outcome == "failed"
+        ));
+    }
+}
+
+#[test]
+fn mixed_language_fixture() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    for toolset in available_toolsets() {
+        let fixture = Fixture::from_directory(BARGE, fixtures.join("mixed"))
+            .with_env("BARGE_TOOLSET", toolset);
+        fixture
+            .run(&["run", "release"])
+            .assert_success()
+            .assert_stdout_contains("The answer is 42");
+        fixture.assert_exists("build/release/obj/answer.c.o");
+    }
+}
+
+#[test]
+fn project_dir_option() {
+    let fixture = Fixture::init(BARGE, "elsewhere", "executable");
+    let outside = std::env::temp_dir();
+    let project_dir = fixture.path().to_string_lossy().to_string();
+    fixture
+        .run_in(&outside, &["-C", &project_dir, "info"])
+        .assert_success()
+        .assert_stdout_contains("elsewhere 0.1.0");
+
+    // The parents of the given directory are not searched.
+    let sources = fixture.path().join("src").to_string_lossy().to_string();
+    fixture
+        .run_in(&outside, &["info", "--project-dir", &sources])
+        .assert_failure()
+        .assert_stderr_contains("Project file not found");
+}
+
+// Stands in for rpmbuild, writing the package into the directory of the
+// architecture under the given top directory.
+const RPMBUILD: &str = r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --define) topdir="${2#_topdir }"; shift ;;
+    esac
+    shift
+done
+mkdir -p "$topdir/RPMS/x86_64"
+echo package > "$topdir/RPMS/x86_64/hello-0.1.0-1.x86_64.rpm"
+"#;
+
+#[test]
+fn rpm_packages_are_copied_to_named_artifacts() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.install_tool("rpmbuild", RPMBUILD);
+        fixture.edit_project(|project| {
+            project["license"] = "MIT".into();
+            project["artifacts"] = serde_json::json!({ "package": "dist/hello.rpm" });
+        });
+        fixture
+            .run(&["package", "--format", "rpm", "--output", "package"])
+            .assert_success()
+            .assert_stdout_contains("RPMS/x86_64/hello-0.1.0-1.x86_64.rpm")
+            .assert_stdout_contains("Copied package to dist/hello.rpm");
+        fixture.assert_exists("dist/hello.rpm");
+    }
+}
+
+#[test]
+fn read_only_resources_are_staged() {
+    for toolset in available_toolsets() {
+        let fixture = init_with_toolset("hello", "executable", toolset);
+        fixture.edit_project(|project| {
+            project["runtime_resources"] = serde_json::json!(["data"]);
+        });
+        let resource = fixture.path().join("data/level.map");
+        let set_read_only = |read_only: bool| {
+            let mut permissions = std::fs::metadata(&resource).unwrap().permissions();
+            permissions.set_readonly(read_only);
+            std::fs::set_permissions(&resource, permissions).unwrap();
+        };
+        fixture.write_file("data/level.map", "first\n");
+        set_read_only(true);
+        fixture.run(&["run", "release"]).assert_success();
+        let staged = fixture.artifact("release", "data/level.map");
+        assert_eq!(std::fs::read_to_string(&staged).unwrap(), "first\n");
+
+        set_read_only(false);
+        fixture.write_file("data/level.map", "second\n");
+        set_read_only(true);
+        fixture.run(&["run", "release"]).assert_success();
+        assert_eq!(std::fs::read_to_string(&staged).unwrap(), "second\n");
+    }
+}
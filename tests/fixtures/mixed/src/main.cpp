@@ -0,0 +1,8 @@
+#include <iostream>
+
+extern "C" int answer(void);
+
+int main() {
+    std::cout << "The answer is " << answer() << std::endl;
+    return 0;
+}
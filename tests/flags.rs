@@ -0,0 +1,144 @@
+use barge_testing::{has_executable, Fixture};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+const TRACED_MAIN: &str = r#"#include <cstdio>
+
+int main() {
+#ifdef TRACE
+    std::printf("trace %d\n", TRACE);
+#endif
+    std::printf("done\n");
+    return 0;
+}
+"#;
+
+#[test]
+fn defines_are_passed_to_the_compiler() {
+    if !has_executable("g++") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "flags", "executable").with_env("BARGE_TOOLSET", "gnu");
+    fixture.write_file("src/main.cpp", TRACED_MAIN);
+    fixture
+        .run(&["run", "release", "--define", "TRACE=3"])
+        .assert_success()
+        .assert_stdout_contains("trace 3");
+
+    // Without the define, the sources are rebuilt.
+    let outcome = fixture.run(&["run", "release"]);
+    outcome.assert_success().assert_stdout_contains("done");
+    assert!(!outcome.stdout.contains("trace"));
+}
+
+#[test]
+fn flags_are_appended_to_the_project_flags() {
+    if !has_executable("g++") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "flags", "executable").with_env("BARGE_TOOLSET", "gnu");
+    fixture.edit_project(|project| project["custom_cxxflags"] = "-DTRACE=1".into());
+    fixture.write_file("src/main.cpp", TRACED_MAIN);
+    fixture
+        .run(&[
+            "build",
+            "release",
+            "--cxxflags",
+            "-UTRACE -DTRACE=2",
+            "--ldflags=-lm",
+        ])
+        .assert_success();
+    let cxxflags = std::fs::read_to_string(fixture.artifact("release", ".cxxflags")).unwrap();
+    assert!(cxxflags.trim_end().ends_with("-DTRACE=1 -UTRACE -DTRACE=2"));
+    let ldflags = std::fs::read_to_string(fixture.artifact("release", ".ldflags")).unwrap();
+    assert!(ldflags.trim_end().ends_with("-lm"));
+    fixture
+        .run(&["run", "release"])
+        .assert_success()
+        .assert_stdout_contains("trace 1");
+}
+
+#[test]
+fn jobs_must_be_positive() {
+    if !has_executable("g++") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "jobs", "executable").with_env("BARGE_TOOLSET", "gnu");
+    fixture
+        .run(&["build", "release", "-j", "0"])
+        .assert_failure();
+
+    fixture.edit_project(|project| project["jobs"] = 0.into());
+    fixture
+        .run(&["build", "release"])
+        .assert_failure()
+        .assert_stderr_contains("The number of jobs must be a positive integer");
+
+    // The option takes precedence over the project file.
+    fixture
+        .run(&["build", "release", "--jobs", "1"])
+        .assert_success();
+    fixture.assert_exists("build/release/jobs");
+}
+
+#[test]
+fn source_flags_only_rebuild_the_matching_objects() {
+    if !has_executable("g++") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "flags", "executable").with_env("BARGE_TOOLSET", "gnu");
+    fixture.write_file("src/main.cpp", TRACED_MAIN);
+    fixture.write_file("src/other.cpp", "int other() { return 0; }\n");
+    let set_trace = |value: &str| {
+        let flags = format!("-DTRACE={}", value);
+        fixture.edit_project(|project| {
+            project["source_flags"] =
+                serde_json::json!([{ "pattern": "src/main.cpp", "flags": flags }])
+        });
+    };
+    set_trace("1");
+    fixture
+        .run(&["run", "release"])
+        .assert_success()
+        .assert_stdout_contains("trace 1");
+
+    set_trace("2");
+    let outcome = fixture.run(&["run", "release"]);
+    outcome.assert_success().assert_stdout_contains("trace 2");
+    assert!(outcome.stdout.contains("obj/main.cpp.o"));
+    assert!(!outcome.stdout.contains("obj/other.cpp.o"));
+
+    // Removing the override rebuilds the object it matched.
+    fixture.edit_project(|project| {
+        project.as_object_mut().unwrap().remove("source_flags");
+    });
+    let outcome = fixture.run(&["run", "release"]);
+    outcome.assert_success();
+    assert!(!outcome.stdout.contains("trace"));
+}
+
+#[test]
+fn escaped_variables_are_kept_literally() {
+    if !has_executable("g++") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "flags", "executable").with_env("BARGE_TOOLSET", "gnu");
+    fixture.edit_project(|project| {
+        project["custom_ldflags"] = "-Wl,-rpath,'$${ORIGIN}/${PROJECT_NAME}'".into()
+    });
+    fixture.run(&["build", "release"]).assert_success();
+    let ldflags = std::fs::read_to_string(fixture.artifact("release", ".ldflags")).unwrap();
+    assert!(
+        ldflags.contains("-Wl,-rpath,'$${ORIGIN}/flags'"),
+        "{}",
+        ldflags
+    );
+    if has_executable("readelf") {
+        let output = std::process::Command::new("readelf")
+            .arg("-d")
+            .arg(fixture.artifact("release", "flags"))
+            .output()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("[${ORIGIN}/flags]"));
+    }
+}
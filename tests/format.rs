@@ -0,0 +1,166 @@
+use barge_testing::{has_executable, Fixture};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+// Stands in for clang-format in the tools directory of the project, removing
+// the trailing whitespace of the sources.
+const FORMATTER: &str = r#"#!/bin/sh
+in_place=0
+for argument in "$@"; do
+    case "$argument" in
+        -i) in_place=1 ;;
+        --style=*) ;;
+        *)
+            if [ $in_place -eq 1 ]; then
+                sed -i 's/[[:space:]]*$//' "$argument"
+            else
+                sed 's/[[:space:]]*$//' "$argument"
+            fi
+            ;;
+    esac
+done
+"#;
+
+fn fixture_with_formatter() -> Fixture {
+    let fixture = Fixture::init(BARGE, "formatted", "executable");
+    fixture.install_tool("clang-format", FORMATTER);
+    fixture.write_file("src/main.cpp", "int main() {\n    return 0;\n}\n");
+    fixture.write_file("src/util.cpp", "int util() {   \n    return 1;\n}\n");
+    fixture
+}
+
+#[test]
+fn check_lists_unformatted_files_without_changing_them() {
+    let fixture = fixture_with_formatter();
+    let outcome = fixture.run(&["format", "--check"]);
+    outcome
+        .assert_failure()
+        .assert_stdout_contains("src/util.cpp is not formatted")
+        .assert_stderr_contains("1 source file(s) are not formatted");
+    assert!(!outcome.stdout.contains("src/main.cpp"));
+    let source = std::fs::read_to_string(fixture.path().join("src/util.cpp")).unwrap();
+    assert!(source.contains("{   \n"));
+
+    fixture.run(&["format"]).assert_success();
+    fixture
+        .run(&["format", "--check"])
+        .assert_success()
+        .assert_stdout_contains("The project source files are formatted");
+}
+
+#[test]
+fn changed_only_formats_the_files_changed_since_the_revision() {
+    if !has_executable("git") {
+        return;
+    }
+    let fixture = fixture_with_formatter();
+    fixture.git(&["add", "-A"]);
+    fixture.git(&["commit", "-q", "-m", "Initial commit"]);
+    fixture
+        .run(&["format", "--changed"])
+        .assert_success()
+        .assert_stdout_contains("No source files were changed since HEAD");
+
+    fixture.write_file("src/main.cpp", "int main() {  \n    return 0;\n}\n");
+    fixture.write_file("src/new.cpp", "int new_function() {  \n}\n");
+    let outcome = fixture.run(&["format", "--check", "--changed"]);
+    outcome
+        .assert_failure()
+        .assert_stdout_contains("src/main.cpp is not formatted")
+        .assert_stdout_contains("src/new.cpp is not formatted");
+    assert!(!outcome.stdout.contains("src/util.cpp"));
+
+    fixture
+        .run(&["format", "--changed", "HEAD"])
+        .assert_success();
+    let source = std::fs::read_to_string(fixture.path().join("src/util.cpp")).unwrap();
+    assert!(source.contains("{   \n"));
+    let source = std::fs::read_to_string(fixture.path().join("src/main.cpp")).unwrap();
+    assert!(!source.contains("{  \n"));
+}
+
+#[test]
+fn clang_format_file_follows_the_project_file() {
+    let fixture = fixture_with_formatter();
+    let generated = fixture.path().join(".clang-format");
+    let content = std::fs::read_to_string(&generated).unwrap();
+    assert!(content.contains("BasedOnStyle: Google\n"));
+
+    fixture.edit_project(|project| {
+        project["format_style"] = serde_json::json!("LLVM");
+        project["format_options"] =
+            serde_json::json!({ "IndentWidth": 4, "BraceWrapping": { "AfterClass": true } });
+    });
+    fixture.run(&["format", "--check"]).assert_failure();
+    assert_eq!(std::fs::read_to_string(&generated).unwrap(), content);
+    fixture
+        .run(&["format"])
+        .assert_success()
+        .assert_stdout_contains("Updated .clang-format from the project file");
+    let content = std::fs::read_to_string(&generated).unwrap();
+    assert!(content.contains("BasedOnStyle: LLVM\n"));
+    assert!(content.contains("BraceWrapping: {AfterClass: true}\n"));
+    assert!(content.contains("IndentWidth: 4\n"));
+
+    // A hand-written file is never overwritten.
+    fixture.write_file(".clang-format", "BasedOnStyle: WebKit\n");
+    fixture
+        .run(&["format"])
+        .assert_success()
+        .assert_stdout_contains("was not generated by barge");
+    assert_eq!(
+        std::fs::read_to_string(&generated).unwrap(),
+        "BasedOnStyle: WebKit\n"
+    );
+}
+
+#[test]
+fn formatters_are_configured_per_language() {
+    let fixture = fixture_with_formatter();
+    fixture.install_tool("cobfmt", "#!/bin/sh\nsed -i 's/[[:space:]]*$//' \"$@\"\n");
+    fixture.write_file("src/report.cob", "       IDENTIFICATION DIVISION.  \n");
+    fixture
+        .run(&["format"])
+        .assert_success()
+        .assert_stdout_contains("No formatter is configured for Cobol, 1 source file(s)");
+
+    fixture.edit_project(|project| {
+        project["formatters"] =
+            serde_json::json!({ "cpp": { "enabled": false }, "cobol": { "command": "cobfmt" } })
+    });
+    fixture.write_file("src/util.cpp", "int util() {   \n    return 1;\n}\n");
+    let outcome = fixture.run(&["format", "--check"]);
+    outcome
+        .assert_failure()
+        .assert_stdout_contains("src/report.cob is not formatted");
+    assert!(!outcome.stdout.contains("src/util.cpp"));
+
+    fixture.run(&["format"]).assert_success();
+    fixture.run(&["format", "--check"]).assert_success();
+    let source = std::fs::read_to_string(fixture.path().join("src/report.cob")).unwrap();
+    assert_eq!(source, "       IDENTIFICATION DIVISION.\n");
+    let source = std::fs::read_to_string(fixture.path().join("src/util.cpp")).unwrap();
+    assert!(source.contains("{   \n"));
+}
+
+#[test]
+fn custom_formatters_run_in_the_hermetic_environment_and_fail_the_command() {
+    let fixture = fixture_with_formatter();
+    fixture.install_tool(
+        "cobfmt",
+        "#!/bin/sh\n[ \"$LC_ALL\" = POSIX ] || exit 1\nsed -i 's/[[:space:]]*$//' \"$@\"\n",
+    );
+    fixture.write_file("src/report.cob", "       IDENTIFICATION DIVISION.  \n");
+    fixture.edit_project(|project| {
+        project["formatters"] = serde_json::json!({ "cobol": { "command": "cobfmt" } })
+    });
+    fixture.run(&["format"]).assert_failure();
+    fixture.run(&["format", "--check"]).assert_failure();
+
+    fixture.edit_project(|project| {
+        project["hermetic_environment"] = serde_json::json!({ "locale": "POSIX" })
+    });
+    fixture.run(&["format"]).assert_success();
+    let source = std::fs::read_to_string(fixture.path().join("src/report.cob")).unwrap();
+    assert_eq!(source, "       IDENTIFICATION DIVISION.\n");
+}
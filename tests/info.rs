@@ -0,0 +1,34 @@
+use barge_testing::Fixture;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+#[test]
+fn metadata_is_printed_as_json() {
+    let fixture = Fixture::init(BARGE, "meta", "static-lib");
+    fixture.edit_project(|project| {
+        project["toolset"] = "gnu".into();
+        project["custom_cxxflags"] = "-DMETA=1".into();
+    });
+    fixture.write_file("include/meta.hpp", "#pragma once\n");
+
+    let outcome = fixture.run(&["info", "--json", "release", "--bits", "32"]);
+    outcome.assert_success();
+    let metadata: serde_json::Value = serde_json::from_str(&outcome.stdout).unwrap();
+    assert_eq!(metadata["name"], "meta");
+    assert_eq!(metadata["project_type"], "static_library");
+    assert_eq!(metadata["profile"], "release");
+    assert_eq!(metadata["toolset"], "gnu");
+    assert_eq!(metadata["compilers"]["cpp"], "g++");
+    assert!(metadata["flags"]["cpp"]
+        .as_str()
+        .unwrap()
+        .contains("-DMETA=1"));
+    assert_eq!(metadata["headers"], serde_json::json!(["include/meta.hpp"]));
+    assert_eq!(metadata["build_directory"], "build/release-32bit");
+    assert_eq!(metadata["artifact"], "build/release-32bit/libmeta.a");
+
+    fixture
+        .run(&["metadata", "--json"])
+        .assert_success()
+        .assert_stdout_contains("\"profile\": \"debug\"");
+}
@@ -0,0 +1,101 @@
+use barge_testing::{Fixture, Outcome};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+const MAIN: &str = r#"// Prints a greeting.
+#include <cstdio>
+
+/*
+ * The entry point.
+ */
+int main() {
+    std::puts("// not a comment"); /* trailing comment */
+    return 0;
+}
+"#;
+
+const UTIL: &str = "int util(void) {\n    return 1; // one\n}\n";
+
+const HEADER: &str = "#pragma once\n\nint util(void);\n";
+
+fn rows(outcome: &Outcome) -> Vec<Vec<&str>> {
+    outcome
+        .stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect()
+}
+
+fn assert_rows(outcome: &Outcome, expected: &[&[&str]]) {
+    let rows = rows(outcome);
+    for row in expected {
+        assert!(
+            rows.iter().any(|actual| actual == row),
+            "Missing row {:?}\n{}",
+            row,
+            outcome
+        );
+    }
+}
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::init(BARGE, "counted", "executable");
+    fixture.write_file("src/main.cpp", MAIN);
+    fixture.write_file("src/util/util.c", UTIL);
+    fixture.write_file("include/util.h", HEADER);
+    fixture
+}
+
+#[test]
+fn lines_are_counted_per_language_and_directory() {
+    let fixture = fixture();
+    let outcome = fixture.run(&["lines"]);
+    outcome
+        .assert_success()
+        .assert_stdout_contains("The project contains 10 lines of code, 4 lines of comments");
+    assert_rows(
+        &outcome,
+        &[
+            &["C", "1", "3", "0", "0", "3"],
+            &["C++", "1", "5", "4", "1", "10"],
+            &["headers", "1", "2", "0", "1", "3"],
+            &["include", "1", "2", "0", "1", "3"],
+            &["src/util", "1", "3", "0", "0", "3"],
+            &["Total", "3", "10", "4", "2", "16"],
+        ],
+    );
+}
+
+#[test]
+fn headers_and_excluded_files_are_left_out() {
+    let fixture = fixture();
+    fixture.edit_project(|project| project["metrics_exclude"] = serde_json::json!(["src/util/**"]));
+    let outcome = fixture.run(&["lines", "--no-headers"]);
+    outcome.assert_success();
+    assert_rows(&outcome, &[&["Total", "1", "5", "4", "1", "10"]]);
+}
+
+#[test]
+fn extra_extensions_are_counted_as_their_language() {
+    let fixture = fixture();
+    fixture.edit_project(|project| {
+        project["extra_extensions"] =
+            serde_json::json!({ "fortran": ["f95"], "asm": [".nasm"], "linker_script": ["lds"] })
+    });
+    fixture.write_file(
+        "src/solver.f95",
+        "! Solves.\nprogram solver\nend program solver\n",
+    );
+    fixture.write_file("src/start.nasm", "global _start ; entry\n");
+    fixture.write_file("src/layout.lds", "/* Layout */\nSECTIONS {}\n");
+    let outcome = fixture.run(&["lines", "--no-headers"]);
+    outcome.assert_success();
+    assert_rows(
+        &outcome,
+        &[
+            &["FORTRAN", "1", "2", "1", "0", "3"],
+            &["assembly", "1", "1", "0", "0", "1"],
+            &["linker", "scripts", "1", "1", "1", "0", "2"],
+        ],
+    );
+}
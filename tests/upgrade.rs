@@ -0,0 +1,72 @@
+use barge_testing::Fixture;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+const OLD_PROJECT_FILE: &str = r#"{
+    // Written before the schema was versioned.
+    "name": "old",
+    "authors": [],
+    "description": "",
+    "project_type": "executable",
+    "version": "0.1.0",
+    "toolset": "gnu", // Comments are kept.
+    "unknown_field": true
+}
+"#;
+
+const UPGRADED_PROJECT_FILE: &str = r#"{
+    // Written before the schema was versioned.
+    "name": "old",
+    "authors": [],
+    "description": "",
+    "project_type": "executable",
+    "version": "0.1.0",
+    "schema_version": 1,
+    "toolset": "gnu", // Comments are kept.
+    "unknown_field": true
+}
+"#;
+
+#[test]
+fn migrate_keeps_the_file_and_a_backup() {
+    let fixture = Fixture::init(BARGE, "old", "executable");
+    fixture.write_file("barge.json", OLD_PROJECT_FILE);
+    fixture
+        .run(&["migrate"])
+        .assert_success()
+        .assert_stdout_contains("Set schema_version to 1");
+
+    let content = std::fs::read_to_string(fixture.path().join("barge.json")).unwrap();
+    assert_eq!(content, UPGRADED_PROJECT_FILE);
+    let backup = std::fs::read_to_string(fixture.path().join("barge.json.bak")).unwrap();
+    assert_eq!(backup, OLD_PROJECT_FILE);
+
+    fixture
+        .run(&["upgrade"])
+        .assert_success()
+        .assert_stdout_contains("already up to date");
+}
+
+#[test]
+fn dry_run_leaves_project_file_untouched() {
+    let fixture = Fixture::init(BARGE, "old", "executable");
+    fixture.write_file("barge.json", OLD_PROJECT_FILE);
+    fixture.run(&["upgrade", "--dry-run"]).assert_success();
+    fixture.assert_missing("barge.json.bak");
+    let content = std::fs::read_to_string(fixture.path().join("barge.json")).unwrap();
+    assert_eq!(content, OLD_PROJECT_FILE);
+}
+
+#[test]
+fn migrate_keeps_the_comments_of_toml_files() {
+    let fixture = Fixture::init(BARGE, "old", "executable");
+    std::fs::remove_file(fixture.path().join("barge.json")).unwrap();
+    let old = "# Written by hand.\nname = \"old\"\nauthors = []\ndescription = \"\"\nproject_type = \"executable\"\nversion = \"0.1.0\" # Released.\n\n[profiles.release]\ncustom_cflags = \"-O3\"\n";
+    fixture.write_file("barge.toml", old);
+    fixture.run(&["upgrade"]).assert_success();
+    let content = std::fs::read_to_string(fixture.path().join("barge.toml")).unwrap();
+    assert_eq!(
+        content,
+        old.replace("# Released.\n", "# Released.\nschema_version = 1\n")
+    );
+}
@@ -0,0 +1,51 @@
+use barge_testing::Fixture;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+#[test]
+fn user_defaults_are_used() {
+    let fixture = Fixture::init(BARGE, "defaults", "executable");
+    fixture.write_user_config(r#"{ "toolset": "gnu", "cpp_standard": "c++20" }"#);
+    fixture
+        .run(&["info"])
+        .assert_success()
+        .assert_stdout_contains("Toolset: gnu")
+        .assert_stdout_contains("C++ standard: c++20");
+}
+
+#[test]
+fn project_file_takes_precedence() {
+    let fixture = Fixture::init(BARGE, "defaults", "executable");
+    fixture.write_user_config(r#"{ "cpp_standard": "c++20" }"#);
+    fixture.edit_project(|project| project["cpp_standard"] = "c++14".into());
+    fixture
+        .run(&["info"])
+        .assert_success()
+        .assert_stdout_contains("C++ standard: c++14");
+}
+
+#[test]
+fn malformed_user_config_is_reported() {
+    let fixture = Fixture::init(BARGE, "defaults", "executable");
+    fixture.write_user_config(r#"{ "toolset": "msvc" }"#);
+    fixture
+        .run(&["info"])
+        .assert_failure()
+        .assert_stderr_contains("Could not load the user configuration")
+        .assert_stderr_contains("unknown variant `msvc`");
+}
+
+#[test]
+fn malformed_user_config_is_reported_once() {
+    let fixture = Fixture::init(BARGE, "defaults", "executable");
+    fixture.write_user_config(r#"{ "color": false, "toolset": "msvc" }"#);
+    let outcome = fixture.run(&["info", "--ascii"]);
+    outcome.assert_failure();
+    assert_eq!(
+        outcome
+            .stderr
+            .matches("Could not load the user configuration")
+            .count(),
+        1
+    );
+}
@@ -0,0 +1,77 @@
+use barge_testing::Fixture;
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+#[test]
+fn valid_project_file() {
+    let fixture = Fixture::init(BARGE, "valid", "executable");
+    fixture
+        .run(&["validate"])
+        .assert_success()
+        .assert_stdout_contains("is valid");
+}
+
+#[test]
+fn problems_are_located() {
+    let fixture = Fixture::init(BARGE, "invalid", "executable");
+    fixture.write_file(
+        "barge.json",
+        r#"{
+    "name": "invalid",
+    "authors": [],
+    "description": "",
+    "project_type": "exe",
+    "version": "0.1.0",
+    "colour": true,
+    "profiles": {
+        "debug": { "pie": "yes" }
+    }
+}
+"#,
+    );
+    fixture
+        .run(&["validate"])
+        .assert_failure()
+        .assert_stderr_contains("barge.json:5:5: project_type: unknown variant `exe`")
+        .assert_stderr_contains("barge.json:7:5: colour: unknown field")
+        .assert_stderr_contains("barge.json:9:20: profiles.debug.pie: invalid type")
+        .assert_stderr_contains("Found 3 problem(s)");
+}
+
+#[test]
+fn external_project_names_must_name_a_directory() {
+    let fixture = Fixture::init(BARGE, "externals", "executable");
+    fixture.edit_project(|project| {
+        project["external_projects"] = serde_json::json!([
+            { "name": "..", "git": "https://example.com/parent.git" },
+            { "name": "org/lib", "git": "https://example.com/lib.git" }
+        ]);
+    });
+    fixture
+        .run(&["validate"])
+        .assert_failure()
+        .assert_stderr_contains("external_projects[0].name: must not be empty")
+        .assert_stderr_contains("external_projects[1].name: must not be empty")
+        .assert_stderr_contains("Found 2 problem(s)");
+    fixture
+        .run(&["build"])
+        .assert_failure()
+        .assert_stderr_contains("The names of external projects must not be empty");
+    fixture.assert_exists("barge.json");
+}
+
+#[test]
+fn language_fields_are_located() {
+    let fixture = Fixture::init(BARGE, "languages", "executable");
+    fixture.edit_project(|project| {
+        project["custom_fortranflags"] = serde_json::json!(5);
+        project["extra_extensions"] = serde_json::json!({ "fortran": 3, "bogus": [] });
+    });
+    fixture
+        .run(&["validate"])
+        .assert_failure()
+        .assert_stderr_contains("custom_fortranflags: invalid type: integer `5`")
+        .assert_stderr_contains("extra_extensions.fortran: invalid type: integer `3`")
+        .assert_stderr_contains("extra_extensions.bogus: unknown field")
+        .assert_stderr_contains("Found 3 problem(s)");
+}
@@ -0,0 +1,132 @@
+use barge_testing::{has_executable, Fixture};
+
+const BARGE: &str = env!("CARGO_BIN_EXE_barge");
+
+#[test]
+fn bump_updates_the_project_file_and_the_header() {
+    let fixture = Fixture::init(BARGE, "versioned", "executable");
+    fixture.write_file(
+        "include/version.h",
+        "#pragma once\n\n\
+         #define VERSIONED_VERSION \"0.1.0\"\n\
+         #define VERSIONED_VERSION_MAJOR 0\n\
+         #define VERSIONED_VERSION_MINOR 1\n\
+         #define VERSIONED_VERSION_PATCH 0\n",
+    );
+    fixture
+        .edit_project(|project| project["version_header"] = serde_json::json!("include/version.h"));
+    fixture
+        .run(&["version"])
+        .assert_success()
+        .assert_stdout_contains("0.1.0");
+
+    fixture
+        .run(&["version", "bump", "major", "--no-git"])
+        .assert_success()
+        .assert_stdout_contains("Bumped the version of versioned from 0.1.0 to 1.0.0");
+    let header = std::fs::read_to_string(fixture.path().join("include/version.h")).unwrap();
+    assert!(
+        header.contains("#define VERSIONED_VERSION \"1.0.0\"\n"),
+        "{}",
+        header
+    );
+    assert!(
+        header.contains("#define VERSIONED_VERSION_MAJOR 1\n"),
+        "{}",
+        header
+    );
+    assert!(
+        header.contains("#define VERSIONED_VERSION_MINOR 0\n"),
+        "{}",
+        header
+    );
+    fixture
+        .run(&["version"])
+        .assert_success()
+        .assert_stdout_contains("1.0.0");
+
+    fixture
+        .run(&["version", "bump", "2.0.0-rc.1", "--no-git"])
+        .assert_success();
+    fixture
+        .run(&["version", "bump", "major", "--no-git"])
+        .assert_success()
+        .assert_stdout_contains("from 2.0.0-rc.1 to 2.0.0");
+    fixture
+        .run(&["version", "bump", "next", "--no-git"])
+        .assert_failure()
+        .assert_stderr_contains("a semantic version like 1.2.3");
+}
+
+#[test]
+fn bump_only_replaces_the_top_level_version() {
+    let fixture = Fixture::init(BARGE, "versioned", "executable");
+    let project = r#"{
+    "metadata": { "version": "0.1.0" },
+    "name": "versioned",
+    "authors": [],
+    "description": "",
+    "project_type": "executable",
+    "version": "0.1.0"
+}
+"#;
+    fixture.write_file("barge.json", project);
+    fixture
+        .run(&["version", "bump", "minor", "--no-git"])
+        .assert_success();
+    let content = std::fs::read_to_string(fixture.path().join("barge.json")).unwrap();
+    assert_eq!(
+        content,
+        project.replace("\"version\": \"0.1.0\"\n", "\"version\": \"0.2.0\"\n")
+    );
+}
+
+#[test]
+fn bump_leaves_both_files_untouched_on_failure() {
+    let fixture = Fixture::init(BARGE, "versioned", "executable");
+    fixture.write_file(
+        "include/version.h",
+        "#pragma once
+",
+    );
+    fixture
+        .edit_project(|project| project["version_header"] = serde_json::json!("include/version.h"));
+    let project = std::fs::read_to_string(fixture.path().join("barge.json")).unwrap();
+    fixture
+        .run(&["version", "bump", "patch", "--no-git"])
+        .assert_failure()
+        .assert_stderr_contains("does not contain the version of the project");
+    let content = std::fs::read_to_string(fixture.path().join("barge.json")).unwrap();
+    assert_eq!(content, project);
+}
+
+#[test]
+fn bump_commits_and_tags_the_new_version() {
+    if !has_executable("git") {
+        return;
+    }
+    let fixture = Fixture::init(BARGE, "tagged", "executable")
+        .with_env("GIT_AUTHOR_NAME", "barge")
+        .with_env("GIT_AUTHOR_EMAIL", "barge@example.com")
+        .with_env("GIT_COMMITTER_NAME", "barge")
+        .with_env("GIT_COMMITTER_EMAIL", "barge@example.com");
+    fixture.git(&["add", "-A"]);
+    fixture.git(&["commit", "-q", "-m", "Initial commit"]);
+
+    fixture
+        .run(&["version", "bump", "minor"])
+        .assert_success()
+        .assert_stdout_contains("created the tag v0.2.0");
+    assert!(fixture
+        .git(&["log", "-1", "--format=%s"])
+        .contains("Bump version to 0.2.0"));
+    assert!(fixture.git(&["status", "--porcelain"]).is_empty());
+    let tag = fixture.git(&["cat-file", "-t", "v0.2.0"]);
+    assert_eq!(tag.trim(), "tag");
+
+    fixture.write_file("src/main.cpp", "int main() { return 1; }\n");
+    fixture
+        .run(&["version", "bump", "patch"])
+        .assert_failure()
+        .assert_stderr_contains("or pass --no-git");
+}